@@ -0,0 +1,162 @@
+// Headless entry point: the same queries and exporters as the app, without launching the GUI
+
+use message_insights_core::{
+    chat_title, export, get_chat_stats, load_chats, local_day_bounds, search_messages, tasks, write_chat_export,
+    ExportOptions,
+};
+use std::collections::HashMap;
+use std::path::Path;
+
+const USAGE: &str = "Usage: message-insights <command> [options]
+
+Commands:
+  export --format <txt|markdown|html|json|csv|mbox> --out <path> [--chat <id>] [--since YYYY-MM-DD] [--until YYYY-MM-DD]
+         [--csv-profile <standard|imazing>]
+         Without --chat, every chat is written into the --out directory
+  stats  [--chat <id>] [--since YYYY-MM-DD] [--until YYYY-MM-DD]
+  search <query> [--limit <n>]";
+
+struct Args {
+    positional: Vec<String>,
+    flags: HashMap<String, String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut flags = HashMap::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.strip_prefix("--") {
+            Some(name) => {
+                let value = iter.next().ok_or_else(|| format!("Missing value for --{}", name))?;
+                flags.insert(name.to_string(), value.clone());
+            }
+            None => positional.push(arg.clone()),
+        }
+    }
+    Ok(Args { positional, flags })
+}
+
+fn parse_i64(args: &Args, name: &str) -> Result<Option<i64>, String> {
+    args.flags
+        .get(name)
+        .map(|v| v.parse::<i64>().map_err(|_| format!("--{} expects a number", name)))
+        .transpose()
+}
+
+/// Build filter options from --since/--until (local days) and --chat
+fn filter_options(args: &Args) -> Result<ExportOptions, String> {
+    let day = |name: &str| -> Result<Option<(i64, i64)>, String> {
+        args.flags
+            .get(name)
+            .map(|d| local_day_bounds(d).ok_or_else(|| format!("--{} expects YYYY-MM-DD", name)))
+            .transpose()
+    };
+    Ok(ExportOptions {
+        start_date: day("since")?.map(|(start, _)| start),
+        end_date: day("until")?.map(|(_, end)| end),
+        chat_ids: parse_i64(args, "chat")?.map(|id| vec![id]),
+        ..Default::default()
+    })
+}
+
+/// Keep chat titles usable as file names
+fn safe_file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
+        .take(80)
+        .collect();
+    cleaned.trim().to_string()
+}
+
+fn run_export(args: &Args) -> Result<(), String> {
+    let format_name = args.flags.get("format").ok_or("--format is required")?;
+    let format: export::ExportFormat = serde_json::from_value(serde_json::Value::String(format_name.clone()))
+        .map_err(|_| format!("Unknown format: {}", format_name))?;
+    let out = args.flags.get("out").ok_or("--out is required")?;
+    let mut options = filter_options(args)?;
+    if let Some(profile) = args.flags.get("csv-profile") {
+        options.csv_profile = Some(
+            serde_json::from_value(serde_json::Value::String(profile.clone()))
+                .map_err(|_| format!("Unknown CSV profile: {}", profile))?,
+        );
+    }
+
+    let targets: Vec<(i64, String)> = match parse_i64(args, "chat")? {
+        Some(id) => vec![(id, out.clone())],
+        None => {
+            std::fs::create_dir_all(out).map_err(|e| format!("Cannot create {}: {}", out, e))?;
+            load_chats()?
+                .iter()
+                .map(|chat| {
+                    let name = format!("{}-{}.{}", chat.id, safe_file_name(&chat_title(chat)), format.extension());
+                    (chat.id, Path::new(out).join(name).to_string_lossy().to_string())
+                })
+                .collect()
+        }
+    };
+
+    // The CLI runs outside the app, so its exports only need to be serialized against each other
+    let task_registry = tasks::TaskRegistry::default();
+    for (chat_id, path) in targets {
+        let result = write_chat_export(&task_registry, chat_id, format, path, Some(options.clone()))?;
+        if let Some(file) = result.files.first() {
+            println!("{}\t{} messages", file, result.message_count);
+        }
+    }
+    Ok(())
+}
+
+fn run_stats(args: &Args) -> Result<(), String> {
+    let stats = get_chat_stats(Some(filter_options(args)?))?;
+    let json = serde_json::to_string_pretty(&stats).map_err(|e| format!("Cannot serialize stats: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn run_search(args: &Args) -> Result<(), String> {
+    let query = args.positional.get(1..).map(|words| words.join(" ")).unwrap_or_default();
+    if query.is_empty() {
+        return Err("search needs a query".to_string());
+    }
+    for hit in search_messages(query, parse_i64(args, "limit")?)? {
+        let chat = hit.chat_id.map(|id| id.to_string()).unwrap_or_default();
+        println!("{}\t{}\t{}\t{}", hit.date, chat, hit.source, hit.text.replace('\n', " "));
+    }
+    Ok(())
+}
+
+/// Run a CLI command, returning the process exit code
+fn run(args: &[String]) -> i32 {
+    let parsed = match parse_args(args) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let result = match parsed.positional.first().map(|s| s.as_str()) {
+        Some("export") => run_export(&parsed),
+        Some("stats") => run_stats(&parsed),
+        Some("search") => run_search(&parsed),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(run(&args));
+}
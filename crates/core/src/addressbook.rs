@@ -0,0 +1,127 @@
+use crate::table_columns;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Table/column pairs holding phone numbers and emails, newest layout first
+const PHONE_COLUMNS: &[(&str, &str)] = &[("ZABCDPHONENUMBER", "ZFULLNUMBER"), ("ZABCDPHONENUMBER", "ZNUMBER")];
+const EMAIL_COLUMNS: &[(&str, &str)] = &[("ZABCDEMAILADDRESS", "ZADDRESS"), ("ZABCDEMAILADDRESS", "ZADDRESSNORMALIZED")];
+
+/// Where one AddressBook database keeps the fields we read
+#[derive(Debug, Clone)]
+pub struct AddressBookLayout {
+    pub phone: (&'static str, &'static str),
+    pub email: (&'static str, &'static str),
+}
+
+impl AddressBookLayout {
+    /// Phone then email (table, column) pairs, each joined to ZABCDRECORD through ZOWNER
+    pub fn lookups(&self) -> [(&'static str, &'static str); 2] {
+        [self.phone, self.email]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContactSourceStatus {
+    pub path: String,
+    pub schema_version: Option<u32>,  // From the AddressBook-vNN.abcddb file name
+    pub readable: bool,
+    pub contact_count: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContactsAccess {
+    pub accessible: bool,  // At least one source yielded names
+    pub sources: Vec<ContactSourceStatus>,
+    pub error: Option<String>,
+}
+
+/// Schema version from a file name like AddressBook-v22.abcddb
+fn schema_version(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("AddressBook-v")?
+        .strip_suffix(".abcddb")?
+        .parse()
+        .ok()
+}
+
+/// Newest AddressBook database in a directory, whatever its schema version
+fn newest_in(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter_map(|p| schema_version(&p).map(|v| (v, p)))
+        .max_by_key(|(v, _)| *v)
+        .map(|(_, p)| p)
+}
+
+/// Every AddressBook database: one per source (iCloud, local, Exchange...) plus the
+/// top-level one older macOS versions use
+pub fn find_databases() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else { return Vec::new() };
+    let root = home.join("Library/Application Support/AddressBook");
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(root.join("Sources"))
+        .map(|entries| entries.flatten().filter_map(|e| newest_in(&e.path())).collect())
+        .unwrap_or_default();
+    paths.extend(newest_in(&root));
+    paths
+}
+
+/// Work out which known layout a database uses, or explain what is missing
+pub fn detect_layout(conn: &Connection) -> Result<AddressBookLayout, String> {
+    let records = table_columns(conn, "ZABCDRECORD");
+    if records.is_empty() {
+        return Err("No ZABCDRECORD table (unreadable or unsupported AddressBook schema)".to_string());
+    }
+    for column in ["Z_PK", "ZFIRSTNAME", "ZLASTNAME"] {
+        if !records.contains(column) {
+            return Err(format!("Unsupported AddressBook schema: ZABCDRECORD has no {}", column));
+        }
+    }
+
+    let find = |candidates: &[(&'static str, &'static str)], what: &str| {
+        candidates
+            .iter()
+            .find(|(table, column)| {
+                let columns = table_columns(conn, table);
+                columns.contains(*column) && columns.contains("ZOWNER")
+            })
+            .copied()
+            .ok_or_else(|| format!("Unsupported AddressBook schema: no known {} column", what))
+    };
+    Ok(AddressBookLayout {
+        phone: find(PHONE_COLUMNS, "phone number")?,
+        email: find(EMAIL_COLUMNS, "email")?,
+    })
+}
+
+/// Open a database and detect its layout
+pub fn open(path: &Path) -> Result<(Connection, AddressBookLayout), String> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Cannot open: {}", e))?;
+    let layout = detect_layout(&conn)?;
+    Ok((conn, layout))
+}
+
+/// Per-source readability for check_contacts_access
+pub fn check_sources() -> Vec<ContactSourceStatus> {
+    find_databases()
+        .into_iter()
+        .map(|path| {
+            let result = open(&path).and_then(|(conn, _)| {
+                conn.query_row("SELECT COUNT(*) FROM ZABCDRECORD", [], |row| row.get::<_, i64>(0))
+                    .map_err(|e| format!("Cannot read: {}", e))
+            });
+            ContactSourceStatus {
+                path: path.to_string_lossy().to_string(),
+                schema_version: schema_version(&path),
+                readable: result.is_ok(),
+                contact_count: *result.as_ref().unwrap_or(&0),
+                error: result.err(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,313 @@
+use crate::chatdb::ChatDbSchema;
+use crate::{lookup_contact, lookup_contact_name, store, ContactDetails};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AliasGroup {
+    pub name: String,
+    pub identifiers: Vec<String>,
+    pub source: String,  // "addressbook" (same contact card) or "manual"
+}
+
+/// Load manual handle links (old number -> current identifier) from the store
+pub fn load_handle_links() -> HashMap<String, String> {
+    let conn = match store::open_store_db() {
+        Ok(c) => c,
+        Err(_) => return HashMap::new(),
+    };
+    conn.prepare("SELECT identifier, canonical FROM handle_links")
+        .ok()
+        .map(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+/// Follow manual links to the identifier a handle ultimately points at
+pub fn canonical_identifier(identifier: &str, links: &HashMap<String, String>) -> String {
+    let mut current = identifier.to_string();
+    // Bounded so an accidental cycle can't hang name resolution
+    for _ in 0..8 {
+        match links.get(&current) {
+            Some(next) if *next != current => current = next.clone(),
+            _ => break,
+        }
+    }
+    current
+}
+
+/// Give linked handles the name of the identifier they were linked to, so old numbers in
+/// old group chats show the same name as the contact's current handle
+pub fn apply_links(names: &mut HashMap<String, String>, links: &HashMap<String, String>) {
+    for identifier in links.keys() {
+        if lookup_contact_name(identifier, names).is_some() {
+            continue;
+        }
+        let canonical = canonical_identifier(identifier, links);
+        if let Some(name) = lookup_contact_name(&canonical, names) {
+            names.insert(identifier.clone(), name);
+        }
+    }
+}
+
+/// Key shared by every handle of one person: manual links are followed to the canonical
+/// identifier first, then that identifier's contact card keys it, else the identifier itself.
+/// A linked old number and its target's card siblings thus share one key. The flag is set
+/// when a manual link was followed.
+pub fn person_key(
+    identifier: &str,
+    details: &HashMap<String, ContactDetails>,
+    links: &HashMap<String, String>,
+) -> (String, bool) {
+    let manual = links.contains_key(identifier);
+    let canonical = canonical_identifier(identifier, links);
+    match lookup_contact(&canonical, details) {
+        Some(record) => (format!("card:{}", record.record_key), manual),
+        None => (format!("link:{}", canonical), manual),
+    }
+}
+
+/// Group every handle in chat.db by the person it belongs to: handles on the same AddressBook
+/// card, or handles manually linked together
+pub fn find_alias_groups(
+    conn: &Connection,
+    names: &HashMap<String, String>,
+    details: &HashMap<String, ContactDetails>,
+    links: &HashMap<String, String>,
+) -> Result<Vec<AliasGroup>, String> {
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT id FROM handle")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let identifiers: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut groups: BTreeMap<String, (Vec<String>, bool)> = BTreeMap::new();
+    for identifier in identifiers {
+        let (key, manual) = person_key(&identifier, details, links);
+        let entry = groups.entry(key).or_insert_with(|| (Vec::new(), false));
+        entry.0.push(identifier);
+        entry.1 |= manual;
+    }
+
+    Ok(groups
+        .into_iter()
+        .filter(|(_, (ids, _))| ids.len() > 1)
+        .map(|(key, (mut identifiers, manual))| {
+            identifiers.sort();
+            let canonical = key.split_once(':').map(|(_, id)| id.to_string()).unwrap_or_default();
+            let name = identifiers
+                .iter()
+                .find_map(|id| lookup_contact_name(id, names))
+                .unwrap_or(canonical);
+            AliasGroup {
+                name,
+                identifiers,
+                source: if manual { "manual" } else { "addressbook" }.to_string(),
+            }
+        })
+        .collect())
+}
+
+// A new handle picking up within this long after the old one went quiet reads as continuous
+const CONTINUITY_GAP_DAYS: i64 = 90;
+
+// Activity may overlap this much (a few late messages to the old number) and still count as a switch
+const OVERLAP_TOLERANCE_DAYS: i64 = 14;
+
+// Suggestions below this score are noise
+const MIN_CONFIDENCE: f64 = 0.3;
+
+/// A likely identifier change: `identifier` went quiet and `canonical` took over. Confirming
+/// it is `link_handles(identifier, canonical)`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SuggestedMerge {
+    pub identifier: String,        // Older handle
+    pub canonical: String,         // Newer handle
+    pub name: Option<String>,
+    pub confidence: f64,           // 0-1
+    pub reasons: Vec<String>,      // "same_contact_card", "same_name", "continuity"
+    pub shared_chats: i64,         // Group chats both were in
+    pub old_last_message: i64,     // Unix timestamp
+    pub new_first_message: i64,    // Unix timestamp
+}
+
+struct Activity {
+    first: i64,
+    last: i64,
+    chats: HashSet<i64>,
+}
+
+fn is_phone(identifier: &str) -> bool {
+    !identifier.contains('@')
+}
+
+/// Message span and group chats per identifier (one identifier can have SMS and iMessage handles)
+fn handle_activity(conn: &Connection, schema: &ChatDbSchema) -> Result<HashMap<String, Activity>, String> {
+    let query = format!(
+        "SELECT h.id, MIN({date}), MAX({date})
+         FROM message m
+         JOIN handle h ON m.handle_id = h.ROWID
+         WHERE m.date > 0 AND {content}
+         GROUP BY h.id",
+        date = schema.unix_seconds_sql("m.date"),
+        content = schema.content_filter(),
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let mut activity: HashMap<String, Activity> = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .map(|(id, first, last)| (id, Activity { first, last, chats: HashSet::new() }))
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT h.id, chj.chat_id
+             FROM chat_handle_join chj
+             JOIN handle h ON chj.handle_id = h.ROWID
+             WHERE (SELECT COUNT(*) FROM chat_handle_join c2 WHERE c2.chat_id = chj.chat_id) > 1",
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+    let memberships: Vec<(String, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (id, chat_id) in memberships {
+        if let Some(a) = activity.get_mut(&id) {
+            a.chats.insert(chat_id);
+        }
+    }
+    Ok(activity)
+}
+
+/// Propose identifier changes: pairs of handles where one stops and the other starts, backed
+/// by a shared AddressBook card, an identical contact name, or the new handle turning up in the
+/// old one's group chats soon after it went quiet. Pairs already linked are left out.
+pub fn suggested_merges(
+    conn: &Connection,
+    schema: &ChatDbSchema,
+    names: &HashMap<String, String>,
+    details: &HashMap<String, ContactDetails>,
+    links: &HashMap<String, String>,
+) -> Result<Vec<SuggestedMerge>, String> {
+    let activity = handle_activity(conn, schema)?;
+    let mut identifiers: Vec<&String> = activity.keys().collect();
+    identifiers.sort();
+    let cards: HashMap<&String, Option<&String>> =
+        identifiers.iter().map(|id| (*id, lookup_contact(id, details).map(|d| &d.record_key))).collect();
+    let contact_names: HashMap<&String, Option<String>> =
+        identifiers.iter().map(|id| (*id, lookup_contact_name(id, names))).collect();
+
+    let mut suggestions = Vec::new();
+    for old in &identifiers {
+        for new in &identifiers {
+            let (a, b) = (&activity[*old], &activity[*new]);
+            if old == new || is_phone(old) != is_phone(new) {
+                continue;
+            }
+            // `new` has to take over from `old`, not run alongside it
+            let gap_days = (b.first - a.last) / 86400;
+            if gap_days < -OVERLAP_TOLERANCE_DAYS || b.last <= a.last {
+                continue;
+            }
+            if canonical_identifier(old, links) == canonical_identifier(new, links) {
+                continue;
+            }
+
+            let mut confidence = 0.0;
+            let mut reasons = Vec::new();
+            if cards[*old].is_some() && cards[*old] == cards[*new] {
+                confidence += 0.5;
+                reasons.push("same_contact_card".to_string());
+            }
+            let (old_name, new_name) = (&contact_names[*old], &contact_names[*new]);
+            if old_name.as_ref().is_some_and(|n| Some(n.to_lowercase()) == new_name.as_ref().map(|m| m.to_lowercase())) {
+                confidence += 0.3;
+                reasons.push("same_name".to_string());
+            }
+            let shared_chats = a.chats.intersection(&b.chats).count() as i64;
+            if shared_chats > 0 && gap_days <= CONTINUITY_GAP_DAYS {
+                confidence += 0.3 + 0.05 * (shared_chats.min(4) - 1) as f64;
+                reasons.push("continuity".to_string());
+            }
+
+            if confidence >= MIN_CONFIDENCE {
+                suggestions.push(SuggestedMerge {
+                    identifier: old.to_string(),
+                    canonical: new.to_string(),
+                    name: new_name.clone().or_else(|| old_name.clone()),
+                    confidence: f64::min(confidence, 1.0),
+                    reasons,
+                    shared_chats,
+                    old_last_message: a.last,
+                    new_first_message: b.first,
+                });
+            }
+        }
+    }
+
+    // Keep each old handle's best successor
+    suggestions.sort_by(|a, b| {
+        a.identifier
+            .cmp(&b.identifier)
+            .then(b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal))
+            .then(a.canonical.cmp(&b.canonical))
+    });
+    suggestions.dedup_by(|a, b| a.identifier == b.identifier);
+    suggestions.sort_by(|a, b| {
+        b.confidence
+            .partial_cmp(&a.confidence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.identifier.cmp(&b.identifier))
+    });
+    Ok(suggestions)
+}
+
+/// Name handles whose `id` doesn't resolve, first through `uncanonicalized_id` (the address as
+/// Messages first saw it, often the only readable or AddressBook-matching form), then through
+/// another handle of the same person sharing its `person_centric_id`
+pub fn apply_handle_fallbacks(conn: &Connection, schema: &ChatDbSchema, names: &mut HashMap<String, String>) {
+    let query = format!(
+        "SELECT h.id, {}, {} FROM handle h",
+        schema.handle_column("uncanonicalized_id"),
+        schema.handle_column("person_centric_id"),
+    );
+    let Ok(mut stmt) = conn.prepare(&query) else {
+        return;
+    };
+    let handles: Vec<(String, Option<String>, Option<String>)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default();
+
+    for (id, uncanonicalized, _) in &handles {
+        if lookup_contact_name(id, names).is_some() {
+            continue;
+        }
+        if let Some(name) = uncanonicalized.as_deref().filter(|u| u != id).and_then(|u| lookup_contact_name(u, names)) {
+            names.insert(id.clone(), name);
+        }
+    }
+
+    let mut person_names: HashMap<&str, String> = HashMap::new();
+    for (id, _, person) in &handles {
+        if let (Some(person), Some(name)) = (person.as_deref().filter(|p| !p.is_empty()), lookup_contact_name(id, names)) {
+            person_names.entry(person).or_insert(name);
+        }
+    }
+    for (id, _, person) in &handles {
+        if let Some(name) = person.as_deref().and_then(|p| person_names.get(p)) {
+            if lookup_contact_name(id, names).is_none() {
+                names.insert(id.clone(), name.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,721 @@
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Same word lists as the frontend analysis so numbers line up across views
+const POSITIVE_WORDS: &[&str] = &[
+    "love", "great", "good", "awesome", "amazing", "happy", "excellent", "wonderful", "best",
+    "perfect", "thank", "thanks", "appreciate", "congrats", "yes", "nice", "beautiful",
+    "fantastic", "brilliant", "excited", "fun", "yay", "wow", "cool", "lol", "haha", "hehe",
+];
+
+const NEGATIVE_WORDS: &[&str] = &[
+    "bad", "worse", "worst", "terrible", "awful", "hate", "sad", "angry", "annoyed",
+    "disappointed", "unfortunately", "problem", "issue", "wrong", "error", "sorry", "apologize",
+    "no", "not", "never", "cant", "wont", "dont",
+];
+
+pub const STOP_WORDS: &[&str] = &[
+    "the", "be", "to", "of", "and", "a", "in", "that", "have", "i",
+    "it", "for", "not", "on", "with", "he", "as", "you", "do", "at",
+    "this", "but", "his", "by", "from", "they", "we", "say", "her", "she",
+    "or", "an", "will", "my", "one", "all", "would", "there", "their",
+    "what", "so", "up", "out", "if", "about", "who", "get", "which", "go",
+    "me", "when", "make", "can", "like", "time", "no", "just", "him", "know",
+    "take", "people", "into", "year", "your", "good", "some", "could", "them",
+    "see", "other", "than", "then", "now", "look", "only", "come", "its", "over",
+    "think", "also", "back", "after", "use", "two", "how", "our", "work", "first",
+    "well", "way", "even", "new", "want", "because", "any", "these", "give", "day",
+    "most", "us", "is", "was", "are", "been", "has", "had", "were", "said", "did",
+    "im", "dont", "cant", "wont", "shouldnt", "wouldnt", "couldnt", "isnt", "arent",
+    "wasnt", "werent", "hasnt", "havent", "hadnt", "doesnt", "didnt", "thats",
+    "ill", "id", "ive", "youre", "youve", "youll", "youd", "hes", "shes",
+    "theyre", "theyve", "theyll", "theyd",
+    // Attachment/media related words to filter
+    "image", "attachment", "video", "audio", "content", "downloadmov",
+    "photo", "mov", "mp4", "jpg", "jpeg", "png", "gif", "heic", "pdf",
+    "file", "download", "media", "attachments",
+];
+
+// Replies slower than this are treated as a new conversation rather than a response
+const MAX_RESPONSE_MINUTES: f64 = 1440.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sentiment {
+    Positive,
+    Neutral,
+    Negative,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SentimentCounts {
+    pub positive: i64,
+    pub neutral: i64,
+    pub negative: i64,
+}
+
+impl SentimentCounts {
+    pub fn add(&mut self, sentiment: Sentiment) {
+        match sentiment {
+            Sentiment::Positive => self.positive += 1,
+            Sentiment::Neutral => self.neutral += 1,
+            Sentiment::Negative => self.negative += 1,
+        }
+    }
+}
+
+/// Split message text into lowercase words, dropping stop words and very short tokens
+pub fn tokenize_words(text: &str) -> Vec<String> {
+    let cleaned: String = text
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c.is_whitespace() || *c == '\'' || *c == '-')
+        .collect();
+
+    cleaned
+        .split_whitespace()
+        .filter(|w| w.len() > 2 && !STOP_WORDS.contains(w))
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// Word splitting rules from settings, shared by every word-based analysis so counts agree
+pub struct Tokenizer {
+    extra_stop_words: HashSet<String>,
+    allowed_stop_words: HashSet<String>,
+    keep_urls: bool,
+    split_hyphens: bool,
+    min_word_length: usize,
+}
+
+impl Tokenizer {
+    pub fn new(settings: &crate::settings::TokenizerSettings) -> Self {
+        let normalize = |words: &[String]| words.iter().map(|w| w.trim().to_lowercase()).collect();
+        Tokenizer {
+            extra_stop_words: normalize(&settings.extra_stop_words),
+            allowed_stop_words: normalize(&settings.allowed_stop_words),
+            keep_urls: settings.keep_urls,
+            split_hyphens: settings.split_hyphens,
+            min_word_length: settings.min_word_length.max(1),
+        }
+    }
+
+    fn is_stop_word(&self, word: &str, stop_words: &[&str]) -> bool {
+        self.extra_stop_words.contains(word) || (stop_words.contains(&word) && !self.allowed_stop_words.contains(word))
+    }
+
+    /// Split text into lowercase words (keeping accented letters), dropping stop words, short
+    /// tokens and, unless kept, links
+    pub fn tokenize(&self, text: &str, stop_words: &[&str]) -> Vec<String> {
+        let is_separator = |c: char| !(c.is_alphanumeric() || c == '\'' || (c == '-' && !self.split_hyphens));
+        let mut words = Vec::new();
+        for token in text.to_lowercase().split_whitespace() {
+            if token.contains("://") || token.starts_with("www.") {
+                if self.keep_urls {
+                    words.push(token.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '/').to_string());
+                }
+                continue;
+            }
+            for word in token.split(is_separator) {
+                let word = word.trim_matches(|c| c == '\'' || c == '-');
+                if word.chars().count() >= self.min_word_length && !self.is_stop_word(word, stop_words) {
+                    words.push(word.to_string());
+                }
+            }
+        }
+        words
+    }
+}
+
+/// Classify a message by counting positive vs negative words
+pub fn classify_sentiment(text: &str) -> Sentiment {
+    let words = tokenize_words(text);
+    let positive = words.iter().filter(|w| POSITIVE_WORDS.contains(&w.as_str())).count();
+    let negative = words.iter().filter(|w| NEGATIVE_WORDS.contains(&w.as_str())).count();
+
+    if positive > negative {
+        Sentiment::Positive
+    } else if negative > positive {
+        Sentiment::Negative
+    } else {
+        Sentiment::Neutral
+    }
+}
+
+/// Check if a character falls in the emoji ranges used by the frontend
+pub fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F300..=0x1F9FF | 0x2600..=0x26FF | 0x2700..=0x27BF)
+}
+
+/// Extract emoji characters from text
+pub fn extract_emojis(text: &str) -> Vec<char> {
+    text.chars().filter(|c| is_emoji(*c)).collect()
+}
+
+/// Group messages by chat and sort each chat chronologically
+pub fn messages_by_chat(messages: &[Message]) -> HashMap<i64, Vec<&Message>> {
+    let mut by_chat: HashMap<i64, Vec<&Message>> = HashMap::new();
+    for msg in messages {
+        by_chat.entry(msg.chat_id.unwrap_or(0)).or_default().push(msg);
+    }
+    for chat_messages in by_chat.values_mut() {
+        chat_messages.sort_by_key(|m| (m.date, m.id));
+    }
+    by_chat
+}
+
+/// Response times in minutes, split into (my replies, their replies).
+/// A response is a message whose sender differs from the previous message in the same chat.
+pub fn response_times(messages: &[Message]) -> (Vec<f64>, Vec<f64>) {
+    let mut mine = Vec::new();
+    let mut theirs = Vec::new();
+
+    for chat_messages in messages_by_chat(messages).values() {
+        for pair in chat_messages.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            if prev.is_from_me == next.is_from_me && prev.handle_id == next.handle_id {
+                continue;
+            }
+            let minutes = (next.date - prev.date) as f64 / 60.0;
+            if (0.0..MAX_RESPONSE_MINUTES).contains(&minutes) {
+                if next.is_from_me {
+                    mine.push(minutes);
+                } else {
+                    theirs.push(minutes);
+                }
+            }
+        }
+    }
+
+    (mine, theirs)
+}
+
+/// Average of a slice, or None when empty
+pub fn average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Median of a slice, or None when empty
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    Some(sorted[sorted.len() / 2])
+}
+
+/// Count items and return the top N by frequency (ties broken alphabetically)
+pub fn top_counts<I: IntoIterator<Item = String>>(items: I, n: usize) -> Vec<(String, i64)> {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for item in items {
+        *counts.entry(item).or_insert(0) += 1;
+    }
+    top_of_counts(counts, n)
+}
+
+/// Top N of already-counted items by frequency (ties broken alphabetically)
+pub fn top_of_counts(counts: HashMap<String, i64>, n: usize) -> Vec<(String, i64)> {
+    let mut sorted: Vec<(String, i64)> = counts.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted.truncate(n);
+    sorted
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageMetrics {
+    pub message_count: i64,
+    pub messages_sent: i64,
+    pub messages_received: i64,
+    pub contact_count: i64,
+    pub chat_count: i64,
+    pub avg_my_response_minutes: Option<f64>,
+    pub avg_their_response_minutes: Option<f64>,
+    pub median_my_response_minutes: Option<f64>,
+    pub sentiment: SentimentCounts,
+    pub positive_ratio: f64,            // positive / (positive + negative)
+    pub emoji_per_message: f64,
+    pub top_emojis: Vec<(String, i64)>,
+}
+
+/// Compute the headline metrics shared by comparison reports
+pub fn compute_metrics(messages: &[Message]) -> MessageMetrics {
+    let mut sentiment = SentimentCounts::default();
+    let mut emojis: Vec<String> = Vec::new();
+    let mut contacts: HashSet<i64> = HashSet::new();
+    let mut chats: HashSet<i64> = HashSet::new();
+    let mut sent = 0;
+
+    for msg in messages {
+        if msg.is_from_me {
+            sent += 1;
+        } else if msg.handle_id != 0 {
+            contacts.insert(msg.handle_id);
+        }
+        if let Some(chat_id) = msg.chat_id {
+            chats.insert(chat_id);
+        }
+        if let Some(ref text) = msg.text {
+            sentiment.add(classify_sentiment(text));
+            emojis.extend(extract_emojis(text).into_iter().map(|c| c.to_string()));
+        }
+    }
+
+    let (mine, theirs) = response_times(messages);
+    let polar = sentiment.positive + sentiment.negative;
+    let total = messages.len() as i64;
+
+    MessageMetrics {
+        message_count: total,
+        messages_sent: sent,
+        messages_received: total - sent,
+        contact_count: contacts.len() as i64,
+        chat_count: chats.len() as i64,
+        avg_my_response_minutes: average(&mine),
+        avg_their_response_minutes: average(&theirs),
+        median_my_response_minutes: median(&mine),
+        positive_ratio: if polar > 0 { sentiment.positive as f64 / polar as f64 } else { 0.0 },
+        sentiment,
+        emoji_per_message: if total > 0 { emojis.len() as f64 / total as f64 } else { 0.0 },
+        top_emojis: top_counts(emojis, 10),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricDelta {
+    pub metric: String,
+    pub a: Option<f64>,
+    pub b: Option<f64>,
+    pub delta: Option<f64>,            // b - a
+    pub percent_change: Option<f64>,   // None when a is zero or either side is missing
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContactDelta {
+    pub identifier: String,
+    pub name: String,
+    pub a_count: i64,
+    pub b_count: i64,
+    pub delta: i64,
+    pub percent_change: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodComparison {
+    pub a: MessageMetrics,
+    pub b: MessageMetrics,
+    pub deltas: Vec<MetricDelta>,
+    pub top_contacts: Vec<ContactDelta>,   // Most active contacts across both periods
+}
+
+fn percent_change(a: f64, b: f64) -> Option<f64> {
+    if a == 0.0 {
+        None
+    } else {
+        Some((b - a) / a * 100.0)
+    }
+}
+
+fn metric_delta(metric: &str, a: Option<f64>, b: Option<f64>) -> MetricDelta {
+    let (delta, percent) = match (a, b) {
+        (Some(a), Some(b)) => (Some(b - a), percent_change(a, b)),
+        _ => (None, None),
+    };
+    MetricDelta {
+        metric: metric.to_string(),
+        a,
+        b,
+        delta,
+        percent_change: percent,
+    }
+}
+
+/// Count messages per contact identifier, remembering the resolved name from received messages
+fn contact_counts(messages: &[Message], names: &mut HashMap<String, String>) -> HashMap<String, i64> {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for msg in messages {
+        if msg.contact_identifier.is_empty() {
+            continue;
+        }
+        *counts.entry(msg.contact_identifier.clone()).or_insert(0) += 1;
+        if !msg.is_from_me {
+            names
+                .entry(msg.contact_identifier.clone())
+                .or_insert_with(|| msg.sender_name.clone());
+        }
+    }
+    counts
+}
+
+/// Compare the headline metrics of two periods, with per-metric and per-contact changes (b relative to a)
+pub fn compare_periods(a_messages: &[Message], b_messages: &[Message], top_n: usize) -> PeriodComparison {
+    let a = compute_metrics(a_messages);
+    let b = compute_metrics(b_messages);
+
+    let deltas = vec![
+        metric_delta("message_count", Some(a.message_count as f64), Some(b.message_count as f64)),
+        metric_delta("messages_sent", Some(a.messages_sent as f64), Some(b.messages_sent as f64)),
+        metric_delta("messages_received", Some(a.messages_received as f64), Some(b.messages_received as f64)),
+        metric_delta("contact_count", Some(a.contact_count as f64), Some(b.contact_count as f64)),
+        metric_delta("chat_count", Some(a.chat_count as f64), Some(b.chat_count as f64)),
+        metric_delta("avg_my_response_minutes", a.avg_my_response_minutes, b.avg_my_response_minutes),
+        metric_delta("avg_their_response_minutes", a.avg_their_response_minutes, b.avg_their_response_minutes),
+        metric_delta("median_my_response_minutes", a.median_my_response_minutes, b.median_my_response_minutes),
+        metric_delta("positive_ratio", Some(a.positive_ratio), Some(b.positive_ratio)),
+        metric_delta("emoji_per_message", Some(a.emoji_per_message), Some(b.emoji_per_message)),
+    ];
+
+    let mut names: HashMap<String, String> = HashMap::new();
+    let a_counts = contact_counts(a_messages, &mut names);
+    let b_counts = contact_counts(b_messages, &mut names);
+
+    let identifiers: HashSet<&String> = a_counts.keys().chain(b_counts.keys()).collect();
+    let mut top_contacts: Vec<ContactDelta> = identifiers
+        .into_iter()
+        .map(|identifier| {
+            let a_count = a_counts.get(identifier).copied().unwrap_or(0);
+            let b_count = b_counts.get(identifier).copied().unwrap_or(0);
+            ContactDelta {
+                identifier: identifier.clone(),
+                name: names.get(identifier).cloned().unwrap_or_else(|| identifier.clone()),
+                a_count,
+                b_count,
+                delta: b_count - a_count,
+                percent_change: percent_change(a_count as f64, b_count as f64),
+            }
+        })
+        .collect();
+    top_contacts.sort_by(|x, y| {
+        (y.a_count + y.b_count)
+            .cmp(&(x.a_count + x.b_count))
+            .then_with(|| x.identifier.cmp(&y.identifier))
+    });
+    top_contacts.truncate(top_n);
+
+    PeriodComparison {
+        a,
+        b,
+        deltas,
+        top_contacts,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatSide {
+    pub chat_id: i64,
+    pub title: String,
+    pub metrics: MessageMetrics,
+    pub monthly: Vec<i64>,             // Message counts aligned with `ChatComparison::months`
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatComparison {
+    pub months: Vec<String>,           // YYYY-MM, spanning both chats' first to last message
+    pub a: ChatSide,
+    pub b: ChatSide,
+    pub deltas: Vec<MetricDelta>,      // b relative to a
+}
+
+/// Message counts per local month (YYYY-MM)
+pub fn monthly_counts(messages: &[Message], settings: &crate::settings::AppSettings) -> BTreeMap<String, i64> {
+    let mut counts = BTreeMap::new();
+    for msg in messages {
+        if let Some(dt) = crate::settings::to_local_time(msg.date, settings) {
+            *counts.entry(dt.format("%Y-%m").to_string()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Side-by-side metrics and volume timelines for two chats, on a shared month axis
+pub fn compare_chats(
+    a: (i64, String, &[Message]),
+    b: (i64, String, &[Message]),
+    settings: &crate::settings::AppSettings,
+) -> ChatComparison {
+    let a_months = monthly_counts(a.2, settings);
+    let b_months = monthly_counts(b.2, settings);
+    let first = a_months.keys().chain(b_months.keys()).min().cloned().unwrap_or_default();
+    let last = a_months.keys().chain(b_months.keys()).max().cloned().unwrap_or_default();
+    let months = crate::keywords::month_range(&first, &last);
+
+    let side = |(chat_id, title, messages): (i64, String, &[Message]), counts: &BTreeMap<String, i64>| ChatSide {
+        chat_id,
+        title,
+        metrics: compute_metrics(messages),
+        monthly: months.iter().map(|m| counts.get(m).copied().unwrap_or(0)).collect(),
+    };
+    let a = side(a, &a_months);
+    let b = side(b, &b_months);
+
+    let (am, bm) = (&a.metrics, &b.metrics);
+    let deltas = vec![
+        metric_delta("message_count", Some(am.message_count as f64), Some(bm.message_count as f64)),
+        metric_delta("messages_sent", Some(am.messages_sent as f64), Some(bm.messages_sent as f64)),
+        metric_delta("messages_received", Some(am.messages_received as f64), Some(bm.messages_received as f64)),
+        metric_delta("avg_my_response_minutes", am.avg_my_response_minutes, bm.avg_my_response_minutes),
+        metric_delta("avg_their_response_minutes", am.avg_their_response_minutes, bm.avg_their_response_minutes),
+        metric_delta("median_my_response_minutes", am.median_my_response_minutes, bm.median_my_response_minutes),
+        metric_delta("positive_ratio", Some(am.positive_ratio), Some(bm.positive_ratio)),
+        metric_delta("emoji_per_message", Some(am.emoji_per_message), Some(bm.emoji_per_message)),
+    ];
+
+    ChatComparison { months, a, b, deltas }
+}
+
+/// Check if an hour falls inside quiet hours; the range may wrap past midnight (23 -> 6)
+pub fn in_quiet_hours(hour: u32, start_hour: u32, end_hour: u32) -> bool {
+    if start_hour <= end_hour {
+        hour >= start_hour && hour < end_hour
+    } else {
+        hour >= start_hour || hour < end_hour
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuietMonth {
+    pub month: String,          // YYYY-MM
+    pub sent: i64,
+    pub quiet_sent: i64,
+    pub quiet_percentage: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuietContact {
+    pub identifier: String,
+    pub name: String,
+    pub quiet_messages: i64,    // Late-night messages in either direction
+    pub share_of_quiet: f64,    // Fraction of all late-night messages
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NightOwlReport {
+    pub quiet_start_hour: u32,
+    pub quiet_end_hour: u32,
+    pub timezone: String,
+    pub total_sent: i64,
+    pub quiet_sent: i64,
+    pub quiet_percentage: f64,
+    pub monthly: Vec<QuietMonth>,
+    pub top_contacts: Vec<QuietContact>,
+}
+
+/// Compute how much messaging happens during quiet hours, by month and by contact
+pub fn night_owl_report(
+    messages: &[Message],
+    start_hour: u32,
+    end_hour: u32,
+    settings: &crate::settings::AppSettings,
+) -> NightOwlReport {
+    use chrono::Timelike;
+
+    let mut monthly: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    let mut by_contact: HashMap<String, i64> = HashMap::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut total_sent = 0;
+    let mut quiet_sent = 0;
+    let mut quiet_total = 0;
+
+    for msg in messages {
+        let local = match crate::settings::to_local_time(msg.date, settings) {
+            Some(t) => t,
+            None => continue,
+        };
+        let quiet = in_quiet_hours(local.hour(), start_hour, end_hour);
+
+        if msg.is_from_me {
+            total_sent += 1;
+            let month = monthly.entry(local.format("%Y-%m").to_string()).or_insert((0, 0));
+            month.0 += 1;
+            if quiet {
+                quiet_sent += 1;
+                month.1 += 1;
+            }
+        }
+
+        if quiet {
+            quiet_total += 1;
+            // My late messages in 1:1 chats carry the other side's handle, so both directions count
+            if !msg.contact_identifier.is_empty() {
+                *by_contact.entry(msg.contact_identifier.clone()).or_insert(0) += 1;
+            }
+        }
+        if !msg.is_from_me && !msg.contact_identifier.is_empty() {
+            names
+                .entry(msg.contact_identifier.clone())
+                .or_insert_with(|| msg.sender_name.clone());
+        }
+    }
+
+    let percentage = |part: i64, whole: i64| {
+        if whole > 0 {
+            part as f64 / whole as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    let mut top_contacts: Vec<QuietContact> = by_contact
+        .into_iter()
+        .map(|(identifier, count)| QuietContact {
+            name: names.get(&identifier).cloned().unwrap_or_else(|| identifier.clone()),
+            identifier,
+            quiet_messages: count,
+            share_of_quiet: if quiet_total > 0 { count as f64 / quiet_total as f64 } else { 0.0 },
+        })
+        .collect();
+    top_contacts.sort_by(|a, b| b.quiet_messages.cmp(&a.quiet_messages).then_with(|| a.name.cmp(&b.name)));
+    top_contacts.truncate(20);
+
+    NightOwlReport {
+        quiet_start_hour: start_hour,
+        quiet_end_hour: end_hour,
+        timezone: settings.timezone.clone().unwrap_or_else(|| "system".to_string()),
+        total_sent,
+        quiet_sent,
+        quiet_percentage: percentage(quiet_sent, total_sent),
+        monthly: monthly
+            .into_iter()
+            .map(|(month, (sent, quiet))| QuietMonth {
+                month,
+                sent,
+                quiet_sent: quiet,
+                quiet_percentage: percentage(quiet, sent),
+            })
+            .collect(),
+        top_contacts,
+    }
+}
+
+// Default silence that ends a conversation session
+pub const DEFAULT_SESSION_GAP_MINUTES: i64 = 30;
+
+/// Split a chronologically sorted chat into sessions separated by gaps longer than `gap_secs`
+pub fn split_sessions<'a>(chat_messages: &[&'a Message], gap_secs: i64) -> Vec<Vec<&'a Message>> {
+    let mut sessions: Vec<Vec<&'a Message>> = Vec::new();
+    for &msg in chat_messages {
+        let continues = sessions
+            .last()
+            .and_then(|s| s.last())
+            .map(|m| msg.date - m.date <= gap_secs)
+            .unwrap_or(false);
+        match sessions.last_mut() {
+            Some(current) if continues => current.push(msg),
+            _ => sessions.push(vec![msg]),
+        }
+    }
+    sessions
+}
+
+/// Check if two consecutive messages come from different senders
+fn is_sender_switch(prev: &Message, next: &Message) -> bool {
+    prev.is_from_me != next.is_from_me || prev.handle_id != next.handle_id
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TempoStats {
+    pub sessions: i64,
+    pub messages: i64,
+    pub avg_messages_per_minute: f64,  // Across sessions with more than one message
+    pub avg_session_minutes: f64,
+    pub alternation_rate: f64,         // Sender switches per consecutive message pair
+}
+
+#[derive(Default)]
+struct TempoAccumulator {
+    sessions: i64,
+    messages: i64,
+    rate_sum: f64,
+    rate_sessions: i64,
+    duration_secs: i64,
+    switches: i64,
+    pairs: i64,
+}
+
+impl TempoAccumulator {
+    fn add_session(&mut self, session: &[&Message]) {
+        self.sessions += 1;
+        self.messages += session.len() as i64;
+
+        let (first, last) = (session[0], session[session.len() - 1]);
+        let duration = last.date - first.date;
+        self.duration_secs += duration;
+        if session.len() > 1 {
+            // Sessions shorter than a minute count as one minute so bursts don't explode the rate
+            let minutes = (duration as f64 / 60.0).max(1.0);
+            self.rate_sum += session.len() as f64 / minutes;
+            self.rate_sessions += 1;
+        }
+
+        for pair in session.windows(2) {
+            self.pairs += 1;
+            if is_sender_switch(pair[0], pair[1]) {
+                self.switches += 1;
+            }
+        }
+    }
+
+    fn finish(&self) -> TempoStats {
+        TempoStats {
+            sessions: self.sessions,
+            messages: self.messages,
+            avg_messages_per_minute: if self.rate_sessions > 0 {
+                self.rate_sum / self.rate_sessions as f64
+            } else {
+                0.0
+            },
+            avg_session_minutes: if self.sessions > 0 {
+                self.duration_secs as f64 / 60.0 / self.sessions as f64
+            } else {
+                0.0
+            },
+            alternation_rate: if self.pairs > 0 {
+                self.switches as f64 / self.pairs as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatTempo {
+    pub chat_id: i64,
+    pub overall: TempoStats,
+    pub monthly: Vec<(String, TempoStats)>,  // Keyed by the month each session started (YYYY-MM)
+}
+
+/// Compute one conversation's tempo (pace and back-and-forth within sessions), overall and by
+/// month. A merged conversation's chats are interleaved into one timeline under `chat_id`.
+pub fn conversation_tempo(
+    chat_id: i64,
+    messages: &[Message],
+    gap_minutes: i64,
+    settings: &crate::settings::AppSettings,
+) -> ChatTempo {
+    let mut timeline: Vec<&Message> = messages.iter().collect();
+    timeline.sort_by_key(|m| (m.date, m.id));
+
+    let mut overall = TempoAccumulator::default();
+    let mut monthly: BTreeMap<String, TempoAccumulator> = BTreeMap::new();
+    for session in split_sessions(&timeline, gap_minutes * 60) {
+        overall.add_session(&session);
+        if let Some(start) = crate::settings::to_local_time(session[0].date, settings) {
+            monthly
+                .entry(start.format("%Y-%m").to_string())
+                .or_default()
+                .add_session(&session);
+        }
+    }
+
+    ChatTempo {
+        chat_id,
+        overall: overall.finish(),
+        monthly: monthly.into_iter().map(|(m, acc)| (m, acc.finish())).collect(),
+    }
+}
@@ -0,0 +1,94 @@
+use crate::settings;
+use crate::secrets;
+use crate::{normalize_phone, Chat, Message};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::Read as _;
+
+// Hex digits kept from the keyed hash; 40 bits keeps collisions negligible for an address book
+const PSEUDONYM_HEX_CHARS: usize = 10;
+
+// Secret name of the pseudonym key in the Keychain
+const KEY_SECRET: &str = "pseudonym-key";
+
+/// The user's pseudonym key, generated and stored in the Keychain the first time it is needed.
+/// Keeping it makes the same contact map to the same pseudonym in every export; replacing it
+/// starts afresh.
+pub fn pseudonym_key() -> Result<String, String> {
+    if secrets::has_secret(KEY_SECRET)? {
+        return secrets::get_secret(KEY_SECRET);
+    }
+
+    // Older versions kept the key in settings.json; move it so pseudonyms stay the same
+    let mut settings = settings::load_settings();
+    let key = match settings.pseudonym_key.clone().filter(|k| !k.is_empty()) {
+        Some(key) => key,
+        None => {
+            let mut bytes = [0u8; 32];
+            std::fs::File::open("/dev/urandom")
+                .and_then(|mut f| f.read_exact(&mut bytes))
+                .map_err(|e| format!("Cannot generate pseudonym key: {}", e))?;
+            bytes.iter().map(|b| format!("{:02x}", b)).collect()
+        }
+    };
+    secrets::set_secret(KEY_SECRET, &key)?;
+    if settings.pseudonym_key.take().is_some() {
+        settings::save_settings(&settings)?;
+    }
+    Ok(key)
+}
+
+/// Phone numbers compare by their last 10 digits and emails case-insensitively, so formatting
+/// differences between handles don't produce different pseudonyms
+fn normalize_identifier(identifier: &str) -> String {
+    let trimmed = identifier.trim();
+    if trimmed.contains('@') {
+        trimmed.to_lowercase()
+    } else {
+        let digits = normalize_phone(trimmed);
+        if digits.is_empty() {
+            trimmed.to_lowercase()
+        } else {
+            digits
+        }
+    }
+}
+
+/// Stable pseudonym for a handle: "Person-" plus a truncated HMAC-SHA256 under the user's key
+pub fn pseudonym(key: &str, identifier: &str) -> String {
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(key.as_bytes()) else {
+        return "Person-unknown".to_string();
+    };
+    mac.update(normalize_identifier(identifier).as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("Person-{}", &hex[..PSEUDONYM_HEX_CHARS])
+}
+
+/// Replace every contact handle and name on the messages and their reactions with pseudonyms.
+/// Message text is left as written, so names mentioned in it remain.
+pub fn anonymize_messages(messages: &mut [Message], key: &str) {
+    for msg in messages.iter_mut() {
+        if !msg.is_from_me {
+            let alias = if msg.contact_identifier.is_empty() {
+                "Unknown".to_string()
+            } else {
+                pseudonym(key, &msg.contact_identifier)
+            };
+            msg.sender_name = alias.clone();
+            msg.contact_identifier = alias;
+        }
+        for reaction in msg.reactions.iter_mut().filter(|r| !r.is_from_me) {
+            let alias = pseudonym(key, &reaction.sender_id);
+            reaction.sender = alias.clone();
+            reaction.sender_id = alias;
+        }
+    }
+}
+
+/// Chat title made of its participants' pseudonyms, in place of names or a group's name
+pub fn anonymized_title(chat: &Chat, key: &str) -> String {
+    let mut aliases: Vec<String> = chat.participant_ids.iter().map(|id| pseudonym(key, id)).collect();
+    aliases.sort();
+    aliases.join(", ")
+}
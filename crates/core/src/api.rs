@@ -0,0 +1,215 @@
+use crate::secrets;
+use crate::settings::{self, AppSettings};
+use crate::tasks::TaskRegistry;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+pub const DEFAULT_PORT: u16 = 8765;
+
+// Request bodies are small JSON objects; anything bigger is not a legitimate call
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+struct RunningServer {
+    server: Arc<tiny_http::Server>,
+    thread: JoinHandle<()>,
+    port: u16,
+}
+
+static SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiStatus {
+    pub running: bool,
+    pub url: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+// Keychain secret holding the bearer token
+const TOKEN_SECRET: &str = "api-token";
+
+/// Random hex token from the OS entropy source
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .map_err(|e| format!("Cannot generate API token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The API's bearer token, generated and stored in the Keychain the first time it is needed
+pub fn token() -> Result<String, String> {
+    if secrets::has_secret(TOKEN_SECRET)? {
+        return secrets::get_secret(TOKEN_SECRET);
+    }
+
+    // Older versions kept the token in settings.json; move it so existing clients keep working
+    let mut settings = settings::load_settings();
+    let token = match settings.api_token.clone().filter(|t| !t.is_empty()) {
+        Some(token) => token,
+        None => generate_token()?,
+    };
+    secrets::set_secret(TOKEN_SECRET, &token)?;
+    if settings.api_token.take().is_some() {
+        settings::save_settings(&settings)?;
+    }
+    Ok(token)
+}
+
+/// Read a named parameter; missing parameters deserialize from null so Option<T> works
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, String> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|e| format!("Invalid parameter '{}': {}", name, e))
+}
+
+fn to_value<T: Serialize>(result: Result<T, String>) -> Result<Value, String> {
+    result.and_then(|r| serde_json::to_value(r).map_err(|e| e.to_string()))
+}
+
+/// Route an RPC call to the matching app command
+fn dispatch(task_registry: &TaskRegistry, method: &str, params: &Value) -> Result<Value, String> {
+    match method {
+        "get_chats" => to_value(crate::get_chats(param(params, "filter")?)),
+        "get_contacts" => to_value(crate::get_contacts()),
+        "get_chat_stats" => to_value(crate::get_chat_stats(param(params, "options")?)),
+        "get_messages" => to_value(crate::get_messages(param(params, "options")?, param(params, "limit")?)),
+        "get_message_summaries" => to_value(crate::get_message_summaries(
+            param(params, "options")?,
+            param(params, "limit")?,
+            param(params, "offset")?,
+        )),
+        "count_messages" => to_value(crate::count_messages(param(params, "options")?)),
+        "aggregate_messages" => to_value(crate::aggregate_messages(
+            param(params, "options")?,
+            param(params, "group_by")?,
+        )),
+        "search_messages" => to_value(crate::search_messages(param(params, "query")?, param(params, "limit")?)),
+        "compare_periods" => to_value(crate::compare_periods(
+            param(params, "range_a")?,
+            param(params, "range_b")?,
+            param(params, "options")?,
+            param(params, "top_n")?,
+        )),
+        "export_chat" => {
+            let path: String = param(params, "path")?;
+            let path = settings::approved_export_path(&path, &settings::load_settings())?;
+            to_value(crate::write_chat_export(
+                task_registry,
+                param(params, "chat_id")?,
+                param(params, "format")?,
+                path.to_string_lossy().to_string(),
+                param(params, "options")?,
+            ))
+        }
+        _ => Err(format!("Unknown method: {}", method)),
+    }
+}
+
+fn respond(request: tiny_http::Request, status: u16, body: Value) {
+    let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("static header is valid");
+    let response = tiny_http::Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    let _ = request.respond(response);
+}
+
+/// Compare without stopping at the first difference, so response times don't reveal how much
+/// of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn handle(mut request: tiny_http::Request, token: &str, task_registry: &TaskRegistry) {
+    let authorized = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+        .unwrap_or(false);
+    if !authorized {
+        return respond(request, 401, json!({ "error": "Missing or invalid token" }));
+    }
+    if request.method() != &tiny_http::Method::Post || request.url() != "/rpc" {
+        return respond(request, 404, json!({ "error": "POST /rpc only" }));
+    }
+    if crate::encryption::is_migrating() {
+        return respond(request, 503, json!({ "error": "App data is being re-encrypted; try again in a moment" }));
+    }
+
+    let mut body = String::new();
+    if request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .is_err()
+    {
+        return respond(request, 400, json!({ "error": "Unreadable request body" }));
+    }
+    let rpc: RpcRequest = match serde_json::from_str(&body) {
+        Ok(r) => r,
+        Err(e) => return respond(request, 400, json!({ "error": format!("Invalid request: {}", e) })),
+    };
+
+    match dispatch(task_registry, &rpc.method, &rpc.params) {
+        Ok(result) => respond(request, 200, json!({ "result": result })),
+        Err(e) => respond(request, 200, json!({ "error": e })),
+    }
+}
+
+/// Start the API on 127.0.0.1, generating the token on first use. Exports run through the
+/// app's task registry so they show up and conflict like any other task
+pub fn start(settings: &AppSettings, task_registry: TaskRegistry) -> Result<ApiStatus, String> {
+    let mut guard = SERVER.lock().map_err(|_| "API server state is poisoned")?;
+    if guard.is_some() {
+        drop(guard);
+        return Ok(status());
+    }
+
+    let token = token()?;
+    let port = settings.api_port.unwrap_or(DEFAULT_PORT);
+    let server = Arc::new(
+        tiny_http::Server::http(("127.0.0.1", port)).map_err(|e| format!("Cannot start API server: {}", e))?,
+    );
+
+    let worker = Arc::clone(&server);
+    let thread = std::thread::spawn(move || {
+        for request in worker.incoming_requests() {
+            handle(request, &token, &task_registry);
+        }
+    });
+
+    *guard = Some(RunningServer { server, thread, port });
+    drop(guard);
+    Ok(status())
+}
+
+/// Stop the API if it is running
+pub fn stop() {
+    let running = SERVER.lock().ok().and_then(|mut guard| guard.take());
+    if let Some(running) = running {
+        running.server.unblock();
+        let _ = running.thread.join();
+    }
+}
+
+/// Report whether the API is running and how to reach it
+pub fn status() -> ApiStatus {
+    let port = SERVER.lock().ok().and_then(|guard| guard.as_ref().map(|s| s.port));
+    ApiStatus {
+        running: port.is_some(),
+        url: port.map(|p| format!("http://127.0.0.1:{}/rpc", p)),
+        token: port.and_then(|_| secrets::get_secret(TOKEN_SECRET).ok()),
+    }
+}
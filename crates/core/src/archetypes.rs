@@ -0,0 +1,174 @@
+use crate::analytics::messages_by_chat;
+use crate::{Chat, Message};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Chats with fewer messages have too little behavior to classify
+const MIN_MESSAGES: usize = 20;
+
+// Days of silence after which recency has decayed to about 1/e
+const RECENCY_DAYS: f64 = 90.0;
+
+// k-means rounds after seeding with the archetype prototypes
+const ITERATIONS: usize = 10;
+
+// Driving features reported per chat
+const TOP_FEATURES: usize = 3;
+
+const FEATURE_NAMES: [&str; 6] = ["tempo", "balance", "burstiness", "media_share", "recency", "group"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Archetype {
+    DailyCompanion,     // Talk most days, both sides, recently
+    PlannerGroup,       // Group chat that wakes up in bursts to arrange things
+    DormantFriend,      // Used to talk, has gone quiet
+    MediaSharer,        // Mostly photos, videos and links
+    OneSided,           // One person does nearly all the talking
+    OccasionalCatchUp,  // Balanced, but only now and then
+}
+
+// Prototype feature vectors, in FEATURE_NAMES order; clusters start here and keep these labels
+const PROTOTYPES: [(Archetype, [f64; 6]); 6] = [
+    (Archetype::DailyCompanion, [0.7, 0.8, 0.5, 0.15, 1.0, 0.0]),
+    (Archetype::PlannerGroup, [0.15, 0.6, 0.8, 0.15, 0.6, 1.0]),
+    (Archetype::DormantFriend, [0.1, 0.6, 0.6, 0.15, 0.05, 0.0]),
+    (Archetype::MediaSharer, [0.3, 0.6, 0.6, 0.7, 0.7, 0.0]),
+    (Archetype::OneSided, [0.2, 0.15, 0.6, 0.2, 0.6, 0.0]),
+    (Archetype::OccasionalCatchUp, [0.15, 0.8, 0.75, 0.15, 0.6, 0.0]),
+];
+
+/// A feature value and how far it sits from the average chat
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeatureValue {
+    pub feature: String,
+    pub value: f64,           // 0-1
+    pub vs_average: f64,      // value minus the mean over all classified chats
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatArchetype {
+    pub chat_id: i64,
+    pub title: String,
+    pub archetype: Archetype,
+    pub distance: f64,                   // To the cluster center; lower is a clearer fit
+    pub features: Vec<FeatureValue>,     // All features, in a fixed order
+    pub driving: Vec<String>,            // Features that set this chat apart most
+}
+
+/// Behavioral features of one chat, each scaled to 0-1
+fn features(messages: &[&Message], is_group: bool, now: i64) -> [f64; 6] {
+    let first = messages.first().map(|m| m.date).unwrap_or(now);
+    let last = messages.last().map(|m| m.date).unwrap_or(now);
+
+    let span_days = ((last - first) / 86400 + 1).max(1);
+    let mut days: Vec<i64> = messages.iter().map(|m| m.date.div_euclid(86400)).collect();
+    days.dedup();
+    let tempo = days.len() as f64 / span_days as f64;
+
+    let mine = messages.iter().filter(|m| m.is_from_me).count() as f64 / messages.len() as f64;
+    let balance = 1.0 - 2.0 * (mine - 0.5).abs();
+
+    // Goh-Barabási burstiness of the gaps, mapped from [-1, 1] to [0, 1]
+    let gaps: Vec<f64> = messages.windows(2).map(|w| (w[1].date - w[0].date).max(0) as f64).collect();
+    let burstiness = if gaps.is_empty() {
+        0.5
+    } else {
+        let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+        let sd = (gaps.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / gaps.len() as f64).sqrt();
+        if mean + sd > 0.0 { ((sd - mean) / (sd + mean) + 1.0) / 2.0 } else { 0.5 }
+    };
+
+    let media = messages.iter().filter(|m| m.has_attachment).count() as f64 / messages.len() as f64;
+    let recency = (-((now - last).max(0) as f64 / 86400.0) / RECENCY_DAYS).exp();
+
+    [tempo, balance, burstiness, media, recency, if is_group { 1.0 } else { 0.0 }]
+}
+
+fn distance(a: &[f64; 6], b: &[f64; 6]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+}
+
+fn nearest(point: &[f64; 6], centers: &[[f64; 6]]) -> (usize, f64) {
+    centers
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, distance(point, c)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap_or((0, 0.0))
+}
+
+/// Cluster chats by behavior with k-means seeded from the archetype prototypes, so every
+/// cluster keeps a readable name. Empty clusters stay at their prototype.
+pub fn classify_chats(messages: &[Message], chats: &[Chat], now: i64) -> Vec<ChatArchetype> {
+    let chat_info: HashMap<i64, &Chat> = chats.iter().map(|c| (c.id, c)).collect();
+    let points: Vec<(i64, [f64; 6])> = messages_by_chat(messages)
+        .iter()
+        .filter(|(_, msgs)| msgs.len() >= MIN_MESSAGES)
+        .map(|(&chat_id, msgs)| {
+            let is_group = chat_info.get(&chat_id).is_some_and(|c| c.is_group);
+            (chat_id, features(msgs, is_group, now))
+        })
+        .collect();
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let mut centers: Vec<[f64; 6]> = PROTOTYPES.iter().map(|(_, p)| *p).collect();
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![[0.0; 6]; centers.len()];
+        let mut counts = vec![0usize; centers.len()];
+        for (_, point) in &points {
+            let (cluster, _) = nearest(point, &centers);
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        for ((center, sum), &count) in centers.iter_mut().zip(&sums).zip(&counts) {
+            if count > 0 {
+                *center = sum.map(|s| s / count as f64);
+            }
+        }
+    }
+
+    let mut mean = [0.0; 6];
+    for (_, point) in &points {
+        for (m, value) in mean.iter_mut().zip(point) {
+            *m += value / points.len() as f64;
+        }
+    }
+
+    let mut results: Vec<ChatArchetype> = points
+        .iter()
+        .map(|(chat_id, point)| {
+            let (cluster, dist) = nearest(point, &centers);
+            let features: Vec<FeatureValue> = FEATURE_NAMES
+                .iter()
+                .zip(point.iter().zip(&mean))
+                .map(|(name, (value, avg))| FeatureValue {
+                    feature: name.to_string(),
+                    value: *value,
+                    vs_average: value - avg,
+                })
+                .collect();
+            let mut driving: Vec<&FeatureValue> = features.iter().collect();
+            driving.sort_by(|a, b| {
+                b.vs_average
+                    .abs()
+                    .partial_cmp(&a.vs_average.abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            ChatArchetype {
+                chat_id: *chat_id,
+                title: chat_info.get(chat_id).map(|c| crate::chat_title(c)).unwrap_or_default(),
+                archetype: PROTOTYPES[cluster].0,
+                distance: dist,
+                driving: driving.iter().take(TOP_FEATURES).map(|f| f.feature.clone()).collect(),
+                features,
+            }
+        })
+        .collect();
+    results.sort_by_key(|r| r.chat_id);
+    results
+}
@@ -0,0 +1,582 @@
+use crate::{get_imessage_db_path, tags, AggregateBucket, AggregateBy, ChatStats, ExportOptions, Message, MessageSummary};
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+// Messages are append-only: once archived they survive Apple's auto-deletion in chat.db.
+// Attachments, reactions and delivery metadata are kept as JSON since they only ever grow.
+const ARCHIVE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS archived_messages (
+        guid TEXT PRIMARY KEY,
+        source TEXT NOT NULL,
+        chat_id INTEGER,
+        handle_id INTEGER NOT NULL,
+        contact_identifier TEXT NOT NULL,
+        sender_name TEXT NOT NULL,
+        is_from_me INTEGER NOT NULL,
+        date INTEGER NOT NULL,
+        date_formatted TEXT NOT NULL,
+        text TEXT,
+        attachments_json TEXT NOT NULL,
+        reactions_json TEXT NOT NULL,
+        delivery_json TEXT NOT NULL,
+        archived_at INTEGER NOT NULL,
+        change_seq INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS idx_archived_messages_date ON archived_messages(date);
+    CREATE INDEX IF NOT EXISTS idx_archived_messages_chat ON archived_messages(chat_id);
+";
+
+// Sources of archived rows: this Mac's chat.db, another Mac's chat.db dropped on the window,
+// and imported logs (WhatsApp, SMS backups)
+pub const LIVE_SOURCE: &str = "chat.db";
+pub const COPIED_SOURCE: &str = "copied-chat.db";
+pub const IMPORT_SOURCE: &str = "import";
+
+// Re-scan this far back on each sync so late reactions and edits reach archived messages
+const RESYNC_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSource {
+    #[default]
+    Live,     // Query chat.db directly
+    Archive,  // Query the app-owned archive
+}
+
+/// Reports built on tables the archive doesn't keep (chat membership, attachment rows, handle
+/// history) can't read it; refuse rather than silently answering from the live database
+pub fn require_live(source: Option<DataSource>, report: &str) -> Result<(), String> {
+    if source == Some(DataSource::Archive) {
+        return Err(format!("{} reads chat.db directly and isn't available for the archive", report));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveBucket {
+    pub key: String,             // Chat id ("none" for imported messages) or YYYY
+    pub messages: i64,
+    pub row_bytes: i64,          // Approximate space taken by the archive rows
+    pub attachment_bytes: i64,   // Size of the attachments those rows reference
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveStats {
+    pub total_messages: i64,
+    pub file_bytes: i64,         // archive.db on disk
+    pub by_chat: Vec<ArchiveBucket>,
+    pub by_year: Vec<ArchiveBucket>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompactionResult {
+    pub messages_compacted: i64,   // Rows whose attachments were dropped
+    pub files_deleted: i64,        // App-owned media files removed (imported MMS parts)
+    pub bytes_freed: i64,
+    pub vacuumed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveSyncResult {
+    pub scanned: i64,
+    pub added: i64,
+    pub total: i64,
+}
+
+/// Open (creating if needed) the app-owned long-term message archive
+pub fn open_archive_db() -> Result<Connection, String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Cannot create app data directory: {}", e))?;
+
+    let conn = crate::encryption::open_app_db(&dir.join("archive.db"))
+        .map_err(|e| format!("Cannot open archive database: {}", e))?;
+    conn.execute_batch(ARCHIVE_SCHEMA)
+        .map_err(|e| format!("Cannot initialize archive database: {}", e))?;
+    // Archives created before rows carried a change number
+    if !crate::table_columns(&conn, "archived_messages").contains("change_seq") {
+        conn.execute_batch("ALTER TABLE archived_messages ADD COLUMN change_seq INTEGER NOT NULL DEFAULT 0")
+            .map_err(|e| format!("Cannot initialize archive database: {}", e))?;
+    }
+    Ok(conn)
+}
+
+/// Number for the next write to the archive. Every insert or update stamps its rows with it, so
+/// `MAX(change_seq)` moves whenever archived content changes, even within the same second.
+pub fn next_change(conn: &Connection) -> Result<i64, String> {
+    conn.query_row("SELECT COALESCE(MAX(change_seq), 0) + 1 FROM archived_messages", [], |row| row.get(0))
+        .map_err(|e| format!("Archive error: {}", e))
+}
+
+/// Timestamp to resume syncing from, or None for a first full sync. Only this Mac's own syncs
+/// count: a copied chat.db may run ahead of it.
+pub fn resume_from(conn: &Connection) -> Option<i64> {
+    conn.query_row("SELECT MAX(date) FROM archived_messages WHERE source = ?", [LIVE_SOURCE], |row| {
+        row.get::<_, Option<i64>>(0)
+    })
+    .ok()
+    .flatten()
+    .map(|latest| latest - RESYNC_WINDOW_SECS)
+}
+
+/// Add messages to the archive keyed by GUID. Existing rows are never removed; their
+/// attachments/reactions/delivery are refreshed only with what the source still has.
+pub fn archive_messages(conn: &mut Connection, source: &str, messages: &[Message]) -> Result<i64, String> {
+    let tx = conn.transaction().map_err(|e| format!("Archive error: {}", e))?;
+    let now = Utc::now().timestamp();
+    let change = next_change(&tx)?;
+    let mut added = 0;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO archived_messages
+                    (guid, source, chat_id, handle_id, contact_identifier, sender_name, is_from_me,
+                     date, date_formatted, text, attachments_json, reactions_json, delivery_json, archived_at,
+                     change_seq)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(guid) DO UPDATE SET
+                    text = COALESCE(excluded.text, text),
+                    reactions_json = CASE WHEN json_array_length(excluded.reactions_json) >= json_array_length(reactions_json)
+                                          THEN excluded.reactions_json ELSE reactions_json END,
+                    attachments_json = CASE WHEN json_array_length(excluded.attachments_json) >= json_array_length(attachments_json)
+                                            THEN excluded.attachments_json ELSE attachments_json END,
+                    delivery_json = excluded.delivery_json,
+                    change_seq = excluded.change_seq
+                 -- The resync window re-sends recent messages every time; only real changes count
+                 WHERE COALESCE(excluded.text, text) IS NOT text
+                    OR (json_array_length(excluded.reactions_json) >= json_array_length(reactions_json)
+                        AND excluded.reactions_json IS NOT reactions_json)
+                    OR (json_array_length(excluded.attachments_json) >= json_array_length(attachments_json)
+                        AND excluded.attachments_json IS NOT attachments_json)
+                    OR excluded.delivery_json IS NOT delivery_json",
+            )
+            .map_err(|e| format!("Archive error: {}", e))?;
+        let exists = |guid: &str| -> bool {
+            tx.query_row("SELECT 1 FROM archived_messages WHERE guid = ?", [guid], |_| Ok(()))
+                .is_ok()
+        };
+
+        for msg in messages {
+            let is_new = !exists(&msg.guid);
+            stmt.execute(rusqlite::params![
+                msg.guid,
+                source,
+                msg.chat_id,
+                msg.handle_id,
+                msg.contact_identifier,
+                msg.sender_name,
+                msg.is_from_me,
+                msg.date,
+                msg.date_formatted,
+                msg.text,
+                serde_json::to_string(&msg.attachments).map_err(|e| e.to_string())?,
+                serde_json::to_string(&msg.reactions).map_err(|e| e.to_string())?,
+                serde_json::to_string(&msg.delivery).map_err(|e| e.to_string())?,
+                now,
+                change
+            ])
+            .map_err(|e| format!("Archive error: {}", e))?;
+            if is_new {
+                added += 1;
+            }
+        }
+    }
+    tx.commit().map_err(|e| format!("Archive error: {}", e))?;
+    Ok(added)
+}
+
+/// Count archived messages
+pub fn archived_count(conn: &Connection) -> i64 {
+    conn.query_row("SELECT COUNT(*) FROM archived_messages", [], |row| row.get(0))
+        .unwrap_or(0)
+}
+
+/// Every chat id with archived messages, including chats chat.db no longer has
+pub fn chat_ids() -> Result<Vec<i64>, String> {
+    let conn = open_archive_db()?;
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT chat_id FROM archived_messages WHERE chat_id IS NOT NULL ORDER BY chat_id")
+        .map_err(|e| format!("Archive error: {}", e))?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Archive error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(ids)
+}
+
+/// Load messages from the archive using the same filters as the live query (newest first)
+pub fn load_messages(options: Option<&ExportOptions>, limit: Option<i64>) -> Result<Vec<Message>, String> {
+    load_matching(options, limit, "1")
+}
+
+/// Archived messages that belong to no chat (e.g. imported logs), with the same filters
+pub fn load_messages_without_chat(options: Option<&ExportOptions>) -> Result<Vec<Message>, String> {
+    load_matching(options, None, "chat_id IS NULL")
+}
+
+/// WHERE clauses and parameters matching the live query's filters, ANDed with `clause`
+fn archive_filters(options: Option<&ExportOptions>, clause: &str) -> Result<(Vec<String>, Vec<i64>), String> {
+    let mut where_clauses = vec![clause.to_string()];
+    let mut params: Vec<i64> = Vec::new();
+
+    if let Some(opts) = options {
+        if let Some(start) = opts.start_date {
+            where_clauses.push("date >= ?".to_string());
+            params.push(start);
+        }
+        if let Some(end) = opts.end_date {
+            where_clauses.push("date <= ?".to_string());
+            params.push(end);
+        }
+        if !opts.include_imported.unwrap_or(false) {
+            where_clauses.push(format!("source != '{}'", IMPORT_SOURCE));
+        }
+
+        let mut handle_filter: Vec<Vec<i64>> = Vec::new();
+        if let Some(ref contact_ids) = opts.contact_ids {
+            if !contact_ids.is_empty() {
+                handle_filter.push(contact_ids.clone());
+            }
+        }
+        if let Some(ref tag_filter) = opts.tags {
+            if !tag_filter.is_empty() {
+                // Tag membership lives in AddressBook/chat.db handles; without chat.db nothing matches
+                let handle_ids = get_imessage_db_path()
+                    .and_then(|p| crate::chatdb::connect(&p).ok())
+                    .map(|c| tags::resolve_tag_handle_ids(&c, tag_filter))
+                    .transpose()?
+                    .unwrap_or_default();
+                handle_filter.push(handle_ids);
+            }
+        }
+        for ids in handle_filter {
+            if ids.is_empty() {
+                where_clauses.push("0".to_string());
+            } else {
+                let placeholders: Vec<&str> = ids.iter().map(|_| "?").collect();
+                where_clauses.push(format!("handle_id IN ({})", placeholders.join(",")));
+                params.extend(ids);
+            }
+        }
+        if let Some(ref chat_ids) = opts.chat_ids {
+            if !chat_ids.is_empty() {
+                let placeholders: Vec<&str> = chat_ids.iter().map(|_| "?").collect();
+                where_clauses.push(format!("chat_id IN ({})", placeholders.join(",")));
+                params.extend(chat_ids.iter().cloned());
+            }
+        }
+    } else {
+        where_clauses.push(format!("source != '{}'", IMPORT_SOURCE));
+    }
+
+    Ok((where_clauses, params))
+}
+
+fn load_matching(options: Option<&ExportOptions>, limit: Option<i64>, clause: &str) -> Result<Vec<Message>, String> {
+    let conn = open_archive_db()?;
+    let (where_clauses, params) = archive_filters(options, clause)?;
+
+    let query = format!(
+        "SELECT guid, chat_id, handle_id, contact_identifier, sender_name, is_from_me, date,
+                date_formatted, text, attachments_json, reactions_json, delivery_json, rowid
+         FROM archived_messages
+         WHERE {}
+         ORDER BY date DESC
+         {}",
+        where_clauses.join(" AND "),
+        limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default()
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Archive error: {}", e))?;
+    let messages = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            let attachments_json: String = row.get(9)?;
+            let reactions_json: String = row.get(10)?;
+            let delivery_json: String = row.get(11)?;
+            let attachments: Vec<crate::Attachment> = serde_json::from_str(&attachments_json).unwrap_or_default();
+            Ok(Message {
+                id: row.get(12)?,  // Archive row id; chat.db ROWIDs may be reused after deletion
+                guid: row.get(0)?,
+                chat_id: row.get(1)?,
+                handle_id: row.get(2)?,
+                contact_identifier: row.get(3)?,
+                sender_name: row.get(4)?,
+                is_from_me: row.get::<_, i64>(5)? == 1,
+                date: row.get(6)?,
+                date_formatted: row.get(7)?,
+                text: row.get(8)?,
+                has_attachment: !attachments.is_empty(),
+                attachments,
+                reactions: serde_json::from_str(&reactions_json).unwrap_or_default(),
+                delivery: serde_json::from_str(&delivery_json).unwrap_or_default(),
+            })
+        })
+        .map_err(|e| format!("Archive error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(messages)
+}
+
+/// Chat statistics over the archive, counted in SQL like the live `get_chat_stats`. Delivery
+/// metadata comes from the stored JSON; contacts are the distinct handles archived.
+pub fn chat_stats(options: Option<&ExportOptions>) -> Result<ChatStats, String> {
+    let conn = open_archive_db()?;
+    let (where_clauses, params) = archive_filters(options, "1")?;
+    let count_if = |condition: &str| -> Result<i64, String> {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM archived_messages WHERE {} AND {}", where_clauses.join(" AND "), condition),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Archive error: {}", e))
+    };
+
+    let total_messages = count_if("1")?;
+    let messages_sent = count_if("is_from_me = 1")?;
+    let total_contacts: i64 = conn
+        .query_row(
+            "SELECT COUNT(DISTINCT contact_identifier) FROM archived_messages WHERE contact_identifier != ''",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Archive error: {}", e))?;
+    let (date_range_start, date_range_end): (Option<i64>, Option<i64>) = conn
+        .query_row("SELECT MIN(date), MAX(date) FROM archived_messages", [], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Archive error: {}", e))?;
+
+    Ok(ChatStats {
+        total_messages,
+        messages_sent,
+        messages_received: total_messages - messages_sent,
+        total_contacts,
+        date_range_start,
+        date_range_end,
+        scheduled_messages: count_if("json_extract(delivery_json, '$.is_scheduled') = 1")?,
+        retracted_messages: count_if("json_extract(delivery_json, '$.date_retracted') IS NOT NULL")?,
+        quiet_deliveries: count_if("json_extract(delivery_json, '$.delivered_quietly') = 1")?,
+    })
+}
+
+/// Count archived messages matching the filters
+pub fn count_messages(options: Option<&ExportOptions>) -> Result<i64, String> {
+    let conn = open_archive_db()?;
+    let (where_clauses, params) = archive_filters(options, "1")?;
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM archived_messages WHERE {}", where_clauses.join(" AND ")),
+        rusqlite::params_from_iter(params.iter()),
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Archive error: {}", e))
+}
+
+/// Archived message counts grouped like `aggregate_messages`. Labels are left for the caller:
+/// the archive keeps no chat names.
+pub fn aggregate_messages(options: Option<&ExportOptions>, group_by: AggregateBy) -> Result<Vec<AggregateBucket>, String> {
+    let conn = open_archive_db()?;
+    let (where_clauses, params) = archive_filters(options, "1")?;
+    let local_time = format!(
+        "datetime({}, 'unixepoch')",
+        crate::settings::local_seconds_sql("date", &crate::settings::load_settings())
+    );
+    let (key_sql, order_sql) = match group_by {
+        AggregateBy::Sender => ("CASE WHEN is_from_me = 1 THEN 'me' ELSE contact_identifier END".to_string(), "COUNT(*) DESC"),
+        AggregateBy::Chat => ("CAST(chat_id AS TEXT)".to_string(), "COUNT(*) DESC"),
+        AggregateBy::Day => (format!("strftime('%Y-%m-%d', {})", local_time), "key"),
+        AggregateBy::Month => (format!("strftime('%Y-%m', {})", local_time), "key"),
+        AggregateBy::Hour => (format!("strftime('%H', {})", local_time), "key"),
+        AggregateBy::Weekday => (format!("strftime('%w', {})", local_time), "key"),
+    };
+
+    let query = format!(
+        "SELECT {} AS key, COUNT(*) FROM archived_messages WHERE {} GROUP BY key ORDER BY {}",
+        key_sql,
+        where_clauses.join(" AND "),
+        order_sql
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Archive error: {}", e))?;
+    let buckets = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(AggregateBucket {
+                key: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                label: None,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Archive error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(buckets)
+}
+
+/// Lightweight summaries of archived messages, newest first, for list views
+pub fn message_summaries(
+    options: Option<&ExportOptions>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<MessageSummary>, String> {
+    let conn = open_archive_db()?;
+    let (where_clauses, mut params) = archive_filters(options, "1")?;
+    params.push(limit.unwrap_or(-1));
+    params.push(offset.unwrap_or(0));
+
+    let query = format!(
+        "SELECT rowid, guid, date, is_from_me, sender_name, chat_id, text, attachments_json != '[]'
+         FROM archived_messages
+         WHERE {}
+         ORDER BY date DESC
+         LIMIT ? OFFSET ?",
+        where_clauses.join(" AND ")
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Archive error: {}", e))?;
+    let summaries = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(MessageSummary {
+                id: row.get(0)?,
+                guid: row.get(1)?,
+                date: row.get(2)?,
+                is_from_me: row.get::<_, i64>(3)? == 1,
+                sender_name: row.get(4)?,
+                chat_id: row.get(5)?,
+                snippet: row.get::<_, Option<String>>(6)?.as_deref().and_then(crate::summary_snippet),
+                has_attachment: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("Archive error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(summaries)
+}
+
+/// Archived message counts per (local day, chat) for the busiest days report
+pub fn day_chat_counts(chat_id: Option<i64>) -> Result<Vec<(String, Option<i64>, i64)>, String> {
+    let conn = open_archive_db()?;
+    let options = ExportOptions { chat_ids: chat_id.map(|id| vec![id]), ..Default::default() };
+    let (where_clauses, params) = archive_filters(Some(&options), "1")?;
+    let query = format!(
+        "SELECT strftime('%Y-%m-%d', {}, 'unixepoch') AS day, chat_id, COUNT(*)
+         FROM archived_messages
+         WHERE {}
+         GROUP BY day, chat_id",
+        crate::settings::local_seconds_sql("date", &crate::settings::load_settings()),
+        where_clauses.join(" AND ")
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Archive error: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Archive error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+fn archive_path() -> Option<std::path::PathBuf> {
+    crate::get_data_dir().map(|dir| dir.join("archive.db"))
+}
+
+fn archive_buckets(conn: &Connection, key_sql: &str) -> Result<Vec<ArchiveBucket>, String> {
+    let query = format!(
+        "SELECT {} AS bucket, COUNT(*),
+                SUM(LENGTH(guid) + COALESCE(LENGTH(text), 0) + LENGTH(attachments_json)
+                    + LENGTH(reactions_json) + LENGTH(delivery_json)),
+                SUM((SELECT COALESCE(SUM(json_extract(value, '$.total_bytes')), 0)
+                     FROM json_each(attachments_json)))
+         FROM archived_messages
+         GROUP BY bucket
+         ORDER BY bucket",
+        key_sql
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Archive error: {}", e))?;
+    let buckets = stmt
+        .query_map([], |row| {
+            Ok(ArchiveBucket {
+                key: row.get(0)?,
+                messages: row.get(1)?,
+                row_bytes: row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                attachment_bytes: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+            })
+        })
+        .map_err(|e| format!("Archive error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(buckets)
+}
+
+/// Report archive size broken down by chat and by year
+pub fn archive_stats() -> Result<ArchiveStats, String> {
+    let conn = open_archive_db()?;
+    Ok(ArchiveStats {
+        total_messages: archived_count(&conn),
+        file_bytes: archive_path()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len() as i64)
+            .unwrap_or(0),
+        by_chat: archive_buckets(&conn, "COALESCE(CAST(chat_id AS TEXT), 'none')")?,
+        by_year: archive_buckets(&conn, "strftime('%Y', date, 'unixepoch')")?,
+    })
+}
+
+/// Drop attachments from messages older than the retention period, keeping their text.
+/// Only files the app itself owns (under the app data directory) are deleted from disk.
+pub fn compact_archive(retention_years: Option<u32>, vacuum: bool) -> Result<CompactionResult, String> {
+    let mut conn = open_archive_db()?;
+    let app_dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    let mut result = CompactionResult {
+        messages_compacted: 0,
+        files_deleted: 0,
+        bytes_freed: 0,
+        vacuumed: false,
+    };
+
+    if let Some(years) = retention_years {
+        let cutoff = Utc::now().timestamp() - years as i64 * 365 * 24 * 60 * 60;
+        let tx = conn.transaction().map_err(|e| format!("Archive error: {}", e))?;
+        let change = next_change(&tx)?;
+        let expired: Vec<(String, String)> = {
+            let mut stmt = tx
+                .prepare("SELECT guid, attachments_json FROM archived_messages WHERE date < ? AND attachments_json != '[]'")
+                .map_err(|e| format!("Archive error: {}", e))?;
+            let rows = stmt
+                .query_map([cutoff], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| format!("Archive error: {}", e))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        };
+
+        for (guid, attachments_json) in expired {
+            let attachments: Vec<crate::Attachment> = serde_json::from_str(&attachments_json).unwrap_or_default();
+            for path in attachments.iter().filter_map(|a| a.filename.as_deref()).map(std::path::Path::new) {
+                if !path.starts_with(&app_dir) {
+                    continue;
+                }
+                if let Ok(meta) = std::fs::metadata(path) {
+                    if std::fs::remove_file(path).is_ok() {
+                        result.files_deleted += 1;
+                        result.bytes_freed += meta.len() as i64;
+                    }
+                }
+            }
+            tx.execute(
+                "UPDATE archived_messages SET attachments_json = '[]', change_seq = ? WHERE guid = ?",
+                rusqlite::params![change, guid],
+            )
+                .map_err(|e| format!("Archive error: {}", e))?;
+            result.messages_compacted += 1;
+        }
+        tx.commit().map_err(|e| format!("Archive error: {}", e))?;
+    }
+
+    if vacuum {
+        let before = archive_path().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len() as i64);
+        conn.execute_batch("VACUUM").map_err(|e| format!("Archive error: {}", e))?;
+        let after = archive_path().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len() as i64);
+        if let (Some(before), Some(after)) = (before, after) {
+            result.bytes_freed += (before - after).max(0);
+        }
+        result.vacuumed = true;
+    }
+
+    Ok(result)
+}
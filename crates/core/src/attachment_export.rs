@@ -0,0 +1,169 @@
+use crate::chatdb::ChatDbSchema;
+use crate::filenames::AttachmentNamer;
+use crate::media_filter::{keep_attachment, MediaFilter};
+use crate::media_trends::{attachment_kind, MediaKind};
+use crate::tasks::Task;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Which of a chat's attachments to export; unset fields don't filter
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AttachmentFilter {
+    pub start_date: Option<i64>,         // Unix timestamp, inclusive
+    pub end_date: Option<i64>,           // Unix timestamp, inclusive
+    pub kinds: Option<Vec<MediaKind>>,   // photo, video, audio, sticker, other
+    pub min_bytes: Option<i64>,
+    pub sender_ids: Option<Vec<i64>>,    // Handle ids; 0 for attachments I sent
+    pub exclude_stickers: bool,
+    pub exclude_gifs: bool,
+    pub media_filter: Option<MediaFilter>,  // Same people/documents filter as chat exports
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentExportResult {
+    pub files: Vec<String>,     // Copied attachments, then the manifest
+    pub exported: i64,
+    pub filtered_out: i64,      // Matched the SQL filters but not the kind/sticker/GIF/media ones
+    pub missing: i64,           // Not on disk (e.g. only in iCloud)
+}
+
+struct Row {
+    message_id: i64,
+    filename: Option<String>,
+    mime_type: Option<String>,
+    transfer_name: Option<String>,
+    is_sticker: bool,
+}
+
+fn is_gif(row: &Row) -> bool {
+    row.mime_type.as_deref() == Some("image/gif")
+        || row.transfer_name.as_deref().is_some_and(|n| n.to_lowercase().ends_with(".gif"))
+}
+
+/// Whether a row passes the filters SQL can't express
+fn keep(row: &Row, filter: &AttachmentFilter) -> bool {
+    if (filter.exclude_stickers && row.is_sticker) || (filter.exclude_gifs && is_gif(row)) {
+        return false;
+    }
+    let kind = attachment_kind(row.mime_type.as_deref(), row.is_sticker);
+    if !filter.kinds.as_ref().map_or(true, |kinds| kinds.is_empty() || kinds.contains(&kind)) {
+        return false;
+    }
+    let Some(media_filter) = filter.media_filter else {
+        return true;
+    };
+    let attachment = crate::Attachment {
+        filename: row.filename.clone().map(crate::expand_home_path),
+        mime_type: row.mime_type.clone(),
+        transfer_name: row.transfer_name.clone(),
+        total_bytes: None,
+        video: None,
+    };
+    keep_attachment(&attachment, media_filter)
+}
+
+fn load_rows(conn: &Connection, schema: &ChatDbSchema, chat_id: i64, filter: &AttachmentFilter) -> Result<Vec<Row>, String> {
+    let mut clauses = vec!["cmj.chat_id = ?".to_string(), "a.filename IS NOT NULL".to_string()];
+    let mut params: Vec<i64> = vec![chat_id];
+    if let Some(start) = filter.start_date {
+        clauses.push("m.date >= ?".to_string());
+        params.push(schema.to_mac_time(start));
+    }
+    if let Some(end) = filter.end_date {
+        clauses.push("m.date <= ?".to_string());
+        params.push(schema.to_mac_time(end));
+    }
+    if let Some(min) = filter.min_bytes {
+        clauses.push("a.total_bytes >= ?".to_string());
+        params.push(min);
+    }
+    if let Some(ids) = filter.sender_ids.as_ref().filter(|ids| !ids.is_empty()) {
+        let placeholders: Vec<&str> = ids.iter().map(|_| "?").collect();
+        clauses.push(format!(
+            "(CASE WHEN m.is_from_me = 1 THEN 0 ELSE m.handle_id END) IN ({})",
+            placeholders.join(",")
+        ));
+        params.extend(ids);
+    }
+
+    let query = format!(
+        "SELECT m.ROWID, a.filename, a.mime_type, a.transfer_name, COALESCE({sticker}, 0)
+         FROM attachment a
+         JOIN message_attachment_join maj ON maj.attachment_id = a.ROWID
+         JOIN message m ON m.ROWID = maj.message_id
+         JOIN chat_message_join cmj ON cmj.message_id = m.ROWID
+         WHERE {where_sql}
+         ORDER BY m.date, m.ROWID, a.ROWID",
+        sticker = schema.attachment_column("is_sticker"),
+        where_sql = clauses.join(" AND "),
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(Row {
+                message_id: row.get(0)?,
+                filename: row.get(1)?,
+                mime_type: row.get(2)?,
+                transfer_name: row.get(3)?,
+                is_sticker: row.get::<_, i64>(4)? == 1,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+/// Copy a chat's attachments matching `filter` into `dir`, oldest first, named by the same
+/// scheme as HTML exports and listed in `manifest.json`
+pub fn export_attachments(
+    conn: &Connection,
+    schema: &ChatDbSchema,
+    chat_id: i64,
+    filter: &AttachmentFilter,
+    dir: &Path,
+    task: &Task,
+) -> Result<AttachmentExportResult, String> {
+    let rows = load_rows(conn, schema, chat_id, filter)?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create export folder: {}", e))?;
+
+    let mut namer = AttachmentNamer::default();
+    let mut result = AttachmentExportResult {
+        files: Vec::new(),
+        exported: 0,
+        filtered_out: 0,
+        missing: 0,
+    };
+    for row in &rows {
+        task.check_cancelled()?;
+        if !keep(row, filter) {
+            result.filtered_out += 1;
+            continue;
+        }
+        let source = PathBuf::from(crate::expand_home_path(row.filename.clone().unwrap_or_default()));
+        if !source.is_file() {
+            result.missing += 1;
+            continue;
+        }
+        let original = row
+            .transfer_name
+            .clone()
+            .or_else(|| source.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "attachment".to_string());
+        let file = namer.name(row.message_id, &original);
+        let target = dir.join(&file);
+        match crate::export::copy_atomic(&source, &target) {
+            Ok(()) => {
+                namer.record(&file, row.message_id, &original, &source);
+                result.files.push(target.to_string_lossy().to_string());
+                result.exported += 1;
+            }
+            Err(e) => tracing::warn!("Cannot copy {}: {}", source.display(), e),
+        }
+    }
+
+    result.files.push(crate::filenames::write_manifest(dir, &namer.entries)?);
+    Ok(result)
+}
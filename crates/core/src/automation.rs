@@ -0,0 +1,126 @@
+use crate::tasks::TaskRegistry;
+use crate::{analytics, export, ExportOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use url::Url;
+
+// URLs look like message-insights://export?chat=42&format=markdown&out=chat.md, where `out` is
+// resolved inside the approved export folder
+pub const URL_SCHEME: &str = "message-insights";
+
+#[derive(Debug, Clone)]
+enum AutomationAction {
+    Export {
+        chat_id: i64,
+        format: export::ExportFormat,
+        path: String,
+    },
+    SyncArchive,
+    YearlyReport {
+        year: i32,
+        path: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutomationResult {
+    pub url: String,
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct YearlyReport {
+    year: i32,
+    stats: crate::ChatStats,
+    metrics: analytics::MessageMetrics,
+    top_chats: Vec<crate::AggregateBucket>,
+}
+
+fn required<'a>(query: &'a HashMap<String, String>, name: &str) -> Result<&'a String, String> {
+    query.get(name).ok_or_else(|| format!("Missing '{}' parameter", name))
+}
+
+fn parse_action(url: &Url) -> Result<AutomationAction, String> {
+    if url.scheme() != URL_SCHEME {
+        return Err(format!("Unsupported URL scheme: {}", url.scheme()));
+    }
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    match url.host_str().unwrap_or("") {
+        "export" => {
+            let format_name = query.get("format").map(|f| f.as_str()).unwrap_or("txt");
+            Ok(AutomationAction::Export {
+                chat_id: required(&query, "chat")?
+                    .parse()
+                    .map_err(|_| "'chat' must be a chat id".to_string())?,
+                format: serde_json::from_value(serde_json::Value::String(format_name.to_string()))
+                    .map_err(|_| format!("Unknown format: {}", format_name))?,
+                path: required(&query, "out")?.clone(),
+            })
+        }
+        "sync-archive" => Ok(AutomationAction::SyncArchive),
+        "yearly-report" => Ok(AutomationAction::YearlyReport {
+            year: required(&query, "year")?
+                .parse()
+                .map_err(|_| "'year' must be a number".to_string())?,
+            path: required(&query, "out")?.clone(),
+        }),
+        other => Err(format!("Unknown action: {}", other)),
+    }
+}
+
+/// Write a year's headline stats, metrics and busiest chats as JSON
+fn write_yearly_report(year: i32, path: &str) -> Result<String, String> {
+    let (start, _) = crate::local_day_bounds(&format!("{}-01-01", year)).ok_or("Invalid year")?;
+    let (_, end) = crate::local_day_bounds(&format!("{}-12-31", year)).ok_or("Invalid year")?;
+    let options = ExportOptions {
+        start_date: Some(start),
+        end_date: Some(end),
+        ..Default::default()
+    };
+
+    let report = crate::snapshots::cached("yearly_report", &year, Some(&options), || {
+        let messages = crate::get_messages(Some(options.clone()), None)?;
+        let mut top_chats = crate::aggregate_messages(Some(options.clone()), crate::AggregateBy::Chat)?;
+        top_chats.sort_by_key(|c| std::cmp::Reverse(c.count));
+        top_chats.truncate(10);
+
+        Ok(YearlyReport {
+            year,
+            stats: crate::get_chat_stats(Some(options.clone()))?,
+            metrics: analytics::compute_metrics(&messages),
+            top_chats,
+        })
+    })?;
+    let json = serde_json::to_string_pretty(&report).map_err(|e| format!("Cannot serialize report: {}", e))?;
+    export::write_atomic(Path::new(path), json).map_err(|e| format!("Cannot write report: {}", e))?;
+    Ok(format!("Wrote {} report to {}", year, path))
+}
+
+/// Any page or app can open one of our URLs, so `out` may only point into the export folder
+/// the user approved in settings
+fn approved_path(path: &str) -> Result<String, String> {
+    crate::settings::approved_export_path(path, &crate::settings::load_settings())
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+fn run_action(task_registry: &TaskRegistry, action: AutomationAction) -> Result<String, String> {
+    match action {
+        AutomationAction::Export { chat_id, format, path } => {
+            let result = crate::write_chat_export(task_registry, chat_id, format, approved_path(&path)?, None)?;
+            Ok(format!("Exported {} messages to {}", result.message_count, result.files.join(", ")))
+        }
+        AutomationAction::SyncArchive => {
+            let result = crate::run_archive_sync(task_registry)?;
+            Ok(format!("Archived {} new messages ({} total)", result.added, result.total))
+        }
+        AutomationAction::YearlyReport { year, path } => write_yearly_report(year, &approved_path(&path)?),
+    }
+}
+
+/// Run the action a URL opened by Shortcuts/AppleScript (`open location`) asks for
+pub fn run_url(task_registry: &TaskRegistry, url: &Url) -> Result<String, String> {
+    parse_action(url).and_then(|action| run_action(task_registry, action))
+}
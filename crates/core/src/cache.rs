@@ -0,0 +1,84 @@
+use rusqlite::{Connection, OptionalExtension};
+use std::path::Path;
+
+const CACHE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS ocr_results (
+        attachment_id INTEGER PRIMARY KEY,
+        message_id INTEGER NOT NULL,
+        chat_id INTEGER,
+        text TEXT NOT NULL,
+        scanned_at INTEGER NOT NULL,
+        status TEXT NOT NULL DEFAULT 'ok'  -- 'failed' when the image couldn't be read
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS ocr_text_fts USING fts5(
+        text,
+        message_id UNINDEXED,
+        chat_id UNINDEXED
+    );
+    CREATE TABLE IF NOT EXISTS media_hashes (
+        path TEXT PRIMARY KEY,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        hash TEXT NOT NULL,
+        hashed_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS report_snapshots (
+        report TEXT NOT NULL,
+        filter_hash TEXT NOT NULL,
+        watermark TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (report, filter_hash)
+    );
+";
+
+/// Open (creating if needed) the app-owned cache database
+pub fn open_cache_db() -> Result<Connection, String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Cannot create app data directory: {}", e))?;
+
+    let conn = crate::encryption::open_app_db(&dir.join("cache.db"))
+        .map_err(|e| format!("Cannot open cache database: {}", e))?;
+    conn.execute_batch(CACHE_SCHEMA)
+        .map_err(|e| format!("Cannot initialize cache database: {}", e))?;
+    // Caches created before failed scans were recorded
+    if !crate::table_columns(&conn, "ocr_results").contains("status") {
+        conn.execute_batch("ALTER TABLE ocr_results ADD COLUMN status TEXT NOT NULL DEFAULT 'ok'")
+            .map_err(|e| format!("Cannot initialize cache database: {}", e))?;
+    }
+    Ok(conn)
+}
+
+/// Content hash of a media file, reusing the stored hash while its size and mtime are unchanged
+pub fn media_hash(conn: &Connection, path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(path).ok()?;
+    let size = meta.len() as i64;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let key = path.to_string_lossy();
+
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT hash FROM media_hashes WHERE path = ? AND size = ? AND modified = ?",
+            rusqlite::params![key, size, modified],
+            |row| row.get(0),
+        )
+        .optional()
+        .ok()
+        .flatten();
+    if stored.is_some() {
+        return stored;
+    }
+
+    let hash = crate::media::content_hash(path)?;
+    let _ = conn.execute(
+        "INSERT OR REPLACE INTO media_hashes (path, size, modified, hash, hashed_at) VALUES (?, ?, ?, ?, ?)",
+        rusqlite::params![key, size, modified, hash, chrono::Utc::now().timestamp()],
+    );
+    Some(hash)
+}
@@ -0,0 +1,266 @@
+use crate::settings::{self, AppSettings};
+use crate::{table_columns, MAC_EPOCH_OFFSET};
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+// Columns every chat.db we can read has had, back to the earliest supported macOS versions
+const REQUIRED_MESSAGE_COLUMNS: &[&str] = &["ROWID", "guid", "text", "date", "is_from_me", "handle_id"];
+
+// Nanosecond timestamps pass this within two minutes of 2001-01-01, while second-based ones
+// stay below it for thousands of years, so magnitude alone tells the two apart
+const NANOSECOND_THRESHOLD: i64 = 100_000_000_000;
+
+/// Unit of message.date: seconds before macOS High Sierra, nanoseconds since
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampUnit {
+    Seconds,
+    Nanoseconds,
+}
+
+impl TimestampUnit {
+    /// Unit a single Mac absolute timestamp is stored in
+    pub fn of(mac_ts: i64) -> Self {
+        if mac_ts.abs() < NANOSECOND_THRESHOLD {
+            TimestampUnit::Seconds
+        } else {
+            TimestampUnit::Nanoseconds
+        }
+    }
+
+    pub fn per_second(self) -> i64 {
+        match self {
+            TimestampUnit::Seconds => 1,
+            TimestampUnit::Nanoseconds => 1_000_000_000,
+        }
+    }
+}
+
+/// Which optional chat.db features this database has, for diagnostics
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatDbFeatures {
+    pub reactions: bool,       // associated_message_type / associated_message_guid
+    pub threads: bool,         // thread_originator_guid (inline replies)
+    pub rich_text: bool,       // attributedBody
+    pub edits: bool,           // date_edited / date_retracted
+    pub timestamp_unit: TimestampUnit,
+    pub message_columns: usize,
+}
+
+/// Columns found on a chat.db connection, so queries can fall back instead of erroring
+/// on older databases and backups
+pub struct ChatDbSchema {
+    message: HashSet<String>,
+    handle: HashSet<String>,
+    attachment: HashSet<String>,
+    chat: HashSet<String>,
+    timestamp_unit: TimestampUnit,
+    recoverable: bool,  // "Recently Deleted" (macOS Ventura and later)
+}
+
+impl ChatDbSchema {
+    /// Read the message and handle layouts, rejecting files that aren't a Messages database
+    pub fn probe(conn: &Connection) -> Result<Self, String> {
+        let message = table_columns(conn, "message");
+        if message.is_empty() {
+            return Err("Not a Messages database (no message table)".to_string());
+        }
+        if let Some(missing) = REQUIRED_MESSAGE_COLUMNS.iter().find(|c| !message.contains(**c)) {
+            return Err(format!("Unsupported Messages database: message has no {} column", missing));
+        }
+
+        // Judge by the newest message; an empty database gets the current unit
+        let newest: Option<i64> = conn
+            .query_row("SELECT MAX(date) FROM message WHERE date > 0", [], |row| row.get(0))
+            .map_err(|e| format!("Cannot read database: {}", e))?;
+        let timestamp_unit = newest.map(TimestampUnit::of).unwrap_or(TimestampUnit::Nanoseconds);
+
+        Ok(ChatDbSchema {
+            message,
+            handle: table_columns(conn, "handle"),
+            attachment: table_columns(conn, "attachment"),
+            chat: table_columns(conn, "chat"),
+            timestamp_unit,
+            recoverable: !table_columns(conn, "chat_recoverable_message_join").is_empty(),
+        })
+    }
+
+    pub fn has(&self, column: &str) -> bool {
+        self.message.contains(column)
+    }
+
+    /// `m.<column>` if the message table has it, otherwise NULL so row positions stay stable
+    pub fn column(&self, column: &str) -> String {
+        if self.has(column) {
+            format!("m.{}", column)
+        } else {
+            "NULL".to_string()
+        }
+    }
+
+    /// `h.<column>` if the handle table has it, otherwise NULL
+    pub fn handle_column(&self, column: &str) -> String {
+        if self.handle.contains(column) {
+            format!("h.{}", column)
+        } else {
+            "NULL".to_string()
+        }
+    }
+
+    /// `a.<column>` if the attachment table has it, otherwise NULL
+    pub fn attachment_column(&self, column: &str) -> String {
+        if self.attachment.contains(column) {
+            format!("a.{}", column)
+        } else {
+            "NULL".to_string()
+        }
+    }
+
+    /// `c.<column>` if the chat table has it, otherwise NULL
+    pub fn chat_column(&self, column: &str) -> String {
+        if self.chat.contains(column) {
+            format!("c.{}", column)
+        } else {
+            "NULL".to_string()
+        }
+    }
+
+    /// Condition keeping real messages only: reactions (associated_message_type >= 2000) and
+    /// edits (1000-1999) are excluded. Databases predating tapbacks have nothing to exclude.
+    pub fn content_filter(&self) -> &'static str {
+        if self.has("associated_message_type") {
+            "(m.associated_message_type IS NULL OR m.associated_message_type = 0)"
+        } else {
+            "1 = 1"
+        }
+    }
+
+    /// Like `content_filter`, but keeping tapbacks (2000-2999) as rows of their own
+    pub fn reaction_rows_filter(&self) -> &'static str {
+        if self.has("associated_message_type") {
+            "(m.associated_message_type IS NULL OR m.associated_message_type = 0
+              OR m.associated_message_type BETWEEN 2000 AND 2999)"
+        } else {
+            "1 = 1"
+        }
+    }
+
+    /// Whether deleted messages can still be recovered from chat_recoverable_message_join
+    pub fn has_recoverable(&self) -> bool {
+        self.recoverable
+    }
+
+    /// Convert a Unix timestamp to this database's message.date representation
+    pub fn to_mac_time(&self, unix: i64) -> i64 {
+        (unix - MAC_EPOCH_OFFSET) * self.timestamp_unit.per_second()
+    }
+
+    /// SQL expression converting a date column to Unix seconds
+    pub fn unix_seconds_sql(&self, column: &str) -> String {
+        format!("({} / {} + {})", column, self.timestamp_unit.per_second(), MAC_EPOCH_OFFSET)
+    }
+
+    /// SQL expression converting message.date to a YYYY-MM-DD day in the configured timezone
+    pub fn local_day_sql(&self, settings: &AppSettings) -> String {
+        format!("date({}, 'unixepoch')", settings::local_seconds_sql(&self.unix_seconds_sql("m.date"), settings))
+    }
+
+    pub fn features(&self) -> ChatDbFeatures {
+        ChatDbFeatures {
+            reactions: self.has("associated_message_type") && self.has("associated_message_guid"),
+            threads: self.has("thread_originator_guid"),
+            rich_text: self.has("attributedBody"),
+            edits: self.has("date_edited") || self.has("date_retracted"),
+            timestamp_unit: self.timestamp_unit,
+            message_columns: self.message.len(),
+        }
+    }
+}
+
+// Pragmas the app issues itself; anything else could change connection or file state
+const ALLOWED_PRAGMAS: &[&str] = &["table_info", "quick_check", "query_only"];
+
+// Problems listed by quick_check before we stop reading them
+const MAX_INTEGRITY_ERRORS: usize = 20;
+
+/// Deny every statement that could write. Installed on each chat.db connection as a second line
+/// of defence behind the read-only open flags and `query_only`.
+fn read_only_authorizer(ctx: AuthContext<'_>) -> Authorization {
+    match ctx.action {
+        AuthAction::Select | AuthAction::Read { .. } | AuthAction::Function { .. } | AuthAction::Recursive => {
+            Authorization::Allow
+        }
+        AuthAction::Pragma { pragma_name, .. } if ALLOWED_PRAGMAS.contains(&pragma_name.to_ascii_lowercase().as_str()) => {
+            Authorization::Allow
+        }
+        action => {
+            tracing::warn!("Refused chat.db statement: {:?}", action);
+            Authorization::Deny
+        }
+    }
+}
+
+/// Open a chat.db read-only with writes refused at every level. `immutable=1` is deliberately
+/// not used: it makes SQLite skip the WAL, hiding messages Messages hasn't checkpointed yet.
+pub fn connect(path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Cannot open database: {}", e))?;
+    conn.pragma_update(None, "query_only", true)
+        .map_err(|e| format!("Cannot open database read-only: {}", e))?;
+    conn.authorizer(Some(read_only_authorizer));
+    Ok(conn)
+}
+
+/// Open a chat.db (the live one or a backup) read-only and probe its schema
+pub fn open(path: &Path) -> Result<(Connection, ChatDbSchema), String> {
+    let conn = connect(path)?;
+    let schema = ChatDbSchema::probe(&conn)?;
+    Ok((conn, schema))
+}
+
+/// Result of `PRAGMA quick_check`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub errors: Vec<String>,
+}
+
+/// Run SQLite's quick integrity check. It reads the whole file, so callers run it once at
+/// startup rather than on every open.
+pub fn quick_check(conn: &Connection) -> Result<IntegrityReport, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA quick_check({})", MAX_INTEGRITY_ERRORS))
+        .map_err(|e| format!("Cannot check database integrity: {}", e))?;
+    let rows: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Cannot check database integrity: {}", e))?
+        .flatten()
+        .collect();
+    let ok = rows.len() == 1 && rows[0] == "ok";
+    Ok(IntegrityReport {
+        ok,
+        errors: if ok { Vec::new() } else { rows },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_seconds_from_nanoseconds() {
+        // 2016-06-15 in seconds, and the same moment in nanoseconds
+        assert_eq!(TimestampUnit::of(487_641_600), TimestampUnit::Seconds);
+        assert_eq!(TimestampUnit::of(487_641_600_000_000_000), TimestampUnit::Nanoseconds);
+        assert_eq!(TimestampUnit::of(0), TimestampUnit::Seconds);
+        // Before 2001 the values are negative
+        assert_eq!(TimestampUnit::of(-86_400), TimestampUnit::Seconds);
+        assert_eq!(TimestampUnit::of(-86_400_000_000_000), TimestampUnit::Nanoseconds);
+        // Two minutes into 2001 is the first nanosecond value past the threshold
+        assert_eq!(TimestampUnit::of(NANOSECOND_THRESHOLD - 1), TimestampUnit::Seconds);
+        assert_eq!(TimestampUnit::of(120_000_000_000), TimestampUnit::Nanoseconds);
+    }
+}
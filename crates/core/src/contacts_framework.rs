@@ -0,0 +1,119 @@
+use block2::RcBlock;
+use objc2::runtime::{Bool, ProtocolObject};
+use objc2::ClassType;
+use objc2_contacts::{
+    CNAuthorizationStatus, CNContact, CNContactEmailAddressesKey, CNContactFamilyNameKey, CNContactFetchRequest,
+    CNContactGivenNameKey, CNContactPhoneNumbersKey, CNContactStore, CNEntityType, CNKeyDescriptor,
+};
+use objc2_foundation::{NSArray, NSError};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+// How long to wait for the user to answer the Contacts permission prompt
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Names read from the framework, kept so every name lookup doesn't enumerate Contacts again
+static NAMES: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+fn authorization_status() -> CNAuthorizationStatus {
+    unsafe { CNContactStore::authorizationStatusForEntityType(CNEntityType::Contacts) }
+}
+
+/// Show the system Contacts prompt if the user hasn't answered it yet. Only called from an
+/// explicit UI action, since it can block for as long as the prompt stays open.
+pub fn request_access() -> Result<(), String> {
+    let status = authorization_status();
+    if status == CNAuthorizationStatus::Authorized {
+        return Ok(());
+    }
+    if status != CNAuthorizationStatus::NotDetermined {
+        return Err("Contacts access was denied in System Settings".to_string());
+    }
+
+    let store = unsafe { CNContactStore::new() };
+    let (tx, rx) = mpsc::channel();
+    let handler = RcBlock::new(move |granted: Bool, _error: *mut NSError| {
+        let _ = tx.send(granted.as_bool());
+    });
+    unsafe { store.requestAccessForEntityType_completionHandler(CNEntityType::Contacts, &handler) };
+    match rx.recv_timeout(PROMPT_TIMEOUT) {
+        Ok(true) => {
+            // Read again on the next lookup now that access is granted
+            *NAMES.lock().map_err(|_| "Contacts cache is unavailable")? = None;
+            Ok(())
+        }
+        Ok(false) => Err("Contacts access was denied".to_string()),
+        Err(_) => Err("No answer to the Contacts permission prompt".to_string()),
+    }
+}
+
+/// Add names, phone numbers and emails from the Contacts framework, keyed the same way as the
+/// AddressBook database reader. Never prompts: without access granted through
+/// `request_access` this returns an error straight away.
+pub fn read_contacts(names: &mut HashMap<String, String>) -> Result<(), String> {
+    let mut cached = NAMES.lock().map_err(|_| "Contacts cache is unavailable")?;
+    if cached.is_none() {
+        if authorization_status() != CNAuthorizationStatus::Authorized {
+            return Err("Contacts access hasn't been granted".to_string());
+        }
+        *cached = Some(fetch_contacts()?);
+    }
+    if let Some(cached) = cached.as_ref() {
+        names.extend(cached.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    Ok(())
+}
+
+fn fetch_contacts() -> Result<HashMap<String, String>, String> {
+    let store = unsafe { CNContactStore::new() };
+
+    let keys: Vec<&ProtocolObject<dyn CNKeyDescriptor>> = unsafe {
+        vec![
+            ProtocolObject::from_ref(CNContactGivenNameKey),
+            ProtocolObject::from_ref(CNContactFamilyNameKey),
+            ProtocolObject::from_ref(CNContactPhoneNumbersKey),
+            ProtocolObject::from_ref(CNContactEmailAddressesKey),
+        ]
+    };
+    let keys = NSArray::from_slice(&keys);
+    let request = unsafe { CNContactFetchRequest::initWithKeysToFetch(CNContactFetchRequest::alloc(), &keys) };
+
+    // (name, phones, emails) per contact, collected inside the enumeration block
+    let found: Rc<RefCell<Vec<(String, Vec<String>, Vec<String>)>>> = Rc::default();
+    let sink = found.clone();
+    let block = RcBlock::new(move |contact: NonNull<CNContact>, _stop: NonNull<Bool>| {
+        let contact = unsafe { contact.as_ref() };
+        let name = unsafe { format!("{} {}", contact.givenName(), contact.familyName()) }
+            .trim()
+            .to_string();
+        if name.is_empty() {
+            return;
+        }
+        let phones = unsafe { contact.phoneNumbers() }
+            .iter()
+            .map(|labeled| unsafe { labeled.value().stringValue() }.to_string())
+            .collect();
+        let emails = unsafe { contact.emailAddresses() }
+            .iter()
+            .map(|labeled| unsafe { labeled.value() }.to_string())
+            .collect();
+        sink.borrow_mut().push((name, phones, emails));
+    });
+    unsafe { store.enumerateContactsWithFetchRequest_error_usingBlock(&request, &block) }
+        .map_err(|e| format!("Cannot read contacts: {}", e.localizedDescription()))?;
+
+    let mut names = HashMap::new();
+    for (name, phones, emails) in found.take() {
+        for phone in phones {
+            crate::insert_phone_name(&mut names, phone, name.clone());
+        }
+        for email in emails {
+            names.insert(email.to_lowercase(), name.clone());
+        }
+    }
+    Ok(names)
+}
@@ -0,0 +1,131 @@
+use crate::{aliases, load_chats, Chat};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+
+/// Merge 1:1 chats with the same person (their iMessage, SMS and email threads) into one
+/// conversation, unless the `separate_duplicate_chats` setting keeps them apart. People are
+/// matched the way handle aliases are: manual links, then contact cards. Group chats are
+/// never merged.
+pub fn group_chats(chats: Vec<Chat>) -> Vec<Chat> {
+    if crate::settings::load_settings().separate_duplicate_chats {
+        return chats;
+    }
+    let details = crate::get_contact_details();
+    let links = aliases::load_handle_links();
+
+    let mut grouped: Vec<Chat> = Vec::new();
+    let mut by_person: HashMap<String, Vec<Chat>> = HashMap::new();
+    for chat in chats {
+        match (chat.is_group, chat.participant_ids.as_slice()) {
+            (false, [identifier]) => {
+                let (key, _) = aliases::person_key(identifier, &details, &links);
+                by_person.entry(key).or_default().push(chat);
+            }
+            _ => grouped.push(chat),
+        }
+    }
+    let now = chrono::Utc::now().timestamp();
+    grouped.extend(by_person.into_values().map(|members| merge(members, now)));
+    grouped.sort_by(|a, b| b.message_count.cmp(&a.message_count).then(a.id.cmp(&b.id)));
+    grouped
+}
+
+/// Fold a person's chats into the most recently active one, whose id and title stand for the
+/// conversation
+fn merge(mut members: Vec<Chat>, now: i64) -> Chat {
+    members.sort_by_key(|c| std::cmp::Reverse((c.last_message_date, c.message_count, c.id)));
+    let mut lead = members.remove(0);
+    for chat in members {
+        lead.message_count += chat.message_count;
+        lead.is_archived &= chat.is_archived;
+        for identifier in chat.participant_ids {
+            if !lead.participant_ids.contains(&identifier) {
+                lead.participant_ids.push(identifier);
+            }
+        }
+        lead.tags.extend(chat.tags);
+        if lead.note.is_none() {
+            lead.note = chat.note;
+        }
+        lead.merged_chat_ids.push(chat.id);
+    }
+    lead.tags.sort();
+    lead.tags.dedup();
+    lead.merged_chat_ids.sort();
+    lead.is_dormant = crate::is_dormant(lead.message_count, lead.last_message_date, now);
+    lead
+}
+
+/// Every chat.db chat a conversation covers, its own id first
+pub fn chat_ids(chat: &Chat) -> Vec<i64> {
+    std::iter::once(chat.id).chain(chat.merged_chat_ids.iter().copied()).collect()
+}
+
+/// The conversation a chat belongs to, found by its own id or any chat merged into it
+pub fn conversation_of(chat_id: i64) -> Result<Option<Chat>, String> {
+    Ok(group_chats(load_chats()?)
+        .into_iter()
+        .find(|c| c.id == chat_id || c.merged_chat_ids.contains(&chat_id)))
+}
+
+/// (conversation id, chat ids) for every conversation, or for the conversations holding
+/// `wanted`. Ids chat.db doesn't know (e.g. archive-only chats) stand alone.
+pub fn conversation_units(wanted: Option<&[i64]>) -> Result<Vec<(i64, Vec<i64>)>, String> {
+    let conversations = group_chats(load_chats()?);
+    let Some(wanted) = wanted else {
+        return Ok(conversations.iter().map(|c| (c.id, chat_ids(c))).collect());
+    };
+
+    let mut seen = HashSet::new();
+    let mut units = Vec::new();
+    for &id in wanted {
+        match conversations.iter().find(|c| c.id == id || c.merged_chat_ids.contains(&id)) {
+            Some(c) if seen.insert(c.id) => units.push((c.id, chat_ids(c))),
+            Some(_) => {}
+            None if seen.insert(id) => units.push((id, vec![id])),
+            None => {}
+        }
+    }
+    Ok(units)
+}
+
+/// `ids` plus the other chat.db chats in their conversations, so a chat filter covers a
+/// person's duplicate 1:1 chats the way `group_chats` merges them. Only 1:1 chats' handles are
+/// read, and contact cards only when a wanted chat is 1:1.
+pub fn expand_chat_ids(conn: &Connection, ids: &[i64]) -> Result<Vec<i64>, String> {
+    if crate::settings::load_settings().separate_duplicate_chats {
+        return Ok(ids.to_vec());
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT chj.chat_id, MIN(h.id)
+             FROM chat c
+             JOIN chat_handle_join chj ON chj.chat_id = c.ROWID
+             JOIN handle h ON h.ROWID = chj.handle_id
+             WHERE c.style != 43
+             GROUP BY chj.chat_id
+             HAVING COUNT(*) = 1",
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+    let one_to_one: Vec<(i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let wanted: HashSet<i64> = ids.iter().copied().collect();
+    if !one_to_one.iter().any(|(id, _)| wanted.contains(id)) {
+        return Ok(ids.to_vec());
+    }
+    let details = crate::get_contact_details();
+    let links = aliases::load_handle_links();
+    let keys: Vec<(i64, String)> = one_to_one
+        .into_iter()
+        .map(|(id, identifier)| (id, aliases::person_key(&identifier, &details, &links).0))
+        .collect();
+    let people: HashSet<&String> = keys.iter().filter(|(id, _)| wanted.contains(id)).map(|(_, key)| key).collect();
+
+    let mut expanded = ids.to_vec();
+    expanded.extend(keys.iter().filter(|(id, key)| !wanted.contains(id) && people.contains(key)).map(|(id, _)| *id));
+    Ok(expanded)
+}
@@ -0,0 +1,154 @@
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// A fix for a typo arrives fast; anything later is a new thought
+const CORRECTION_WINDOW_SECS: i64 = 30;
+
+// Below this, "ok" -> "k" or "no" -> "so" would look like corrections
+const MIN_CORRECTION_CHARS: usize = 3;
+
+// Retyping a long paragraph isn't a typo fix, and keeps the edit distance cheap
+const MAX_CORRECTION_CHARS: usize = 500;
+
+/// Character-level Levenshtein distance
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Whether `next` re-sends `prev` with a small fix: same sender and chat, within seconds, a few
+/// characters apart (at most two, or a fifth of the longer text)
+pub fn is_correction(prev: &Message, next: &Message) -> bool {
+    if prev.is_from_me != next.is_from_me
+        || prev.contact_identifier != next.contact_identifier
+        || prev.chat_id != next.chat_id
+        || prev.has_attachment
+        || next.has_attachment
+    {
+        return false;
+    }
+    let gap = next.date - prev.date;
+    if !(0..=CORRECTION_WINDOW_SECS).contains(&gap) {
+        return false;
+    }
+    let (Some(a), Some(b)) = (prev.text.as_deref(), next.text.as_deref()) else {
+        return false;
+    };
+    let a: Vec<char> = a.trim().chars().collect();
+    let b: Vec<char> = b.trim().chars().collect();
+    let (shorter, longer) = (a.len().min(b.len()), a.len().max(b.len()));
+    if shorter < MIN_CORRECTION_CHARS || longer > MAX_CORRECTION_CHARS {
+        return false;
+    }
+    let allowed = (longer / 5).max(2);
+    if longer - shorter > allowed {
+        return false;
+    }
+    let distance = edit_distance(&a, &b);
+    distance > 0 && distance <= allowed
+}
+
+/// Indices of messages superseded by a correction, each paired with the index of the message
+/// that replaced it. Chains ("teh" -> "thw" -> "the") all point forward to the next fix.
+fn superseded(messages: &[Message]) -> Vec<(usize, usize)> {
+    let mut by_chat: HashMap<Option<i64>, Vec<usize>> = HashMap::new();
+    for (i, msg) in messages.iter().enumerate() {
+        by_chat.entry(msg.chat_id).or_default().push(i);
+    }
+    let mut pairs = Vec::new();
+    for indices in by_chat.values_mut() {
+        indices.sort_by_key(|&i| (messages[i].date, messages[i].id));
+        for window in indices.windows(2) {
+            if is_correction(&messages[window[0]], &messages[window[1]]) {
+                pairs.push((window[0], window[1]));
+            }
+        }
+    }
+    pairs
+}
+
+/// Drop messages that were immediately re-sent with a fix, keeping the corrected version. Any
+/// reactions on a dropped message move to its replacement. Returns how many were dropped.
+pub fn collapse_corrections(messages: &mut Vec<Message>) -> usize {
+    let pairs = superseded(messages);
+    if pairs.is_empty() {
+        return 0;
+    }
+
+    // Walk chains newest-first so reactions end up on the final version
+    let mut replaced_by: HashMap<usize, usize> = pairs.iter().copied().collect();
+    let mut order: Vec<usize> = replaced_by.keys().copied().collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((messages[i].date, messages[i].id)));
+    for i in order {
+        let mut target = replaced_by[&i];
+        while let Some(&next) = replaced_by.get(&target) {
+            target = next;
+        }
+        replaced_by.insert(i, target);
+        let reactions = std::mem::take(&mut messages[i].reactions);
+        messages[target].reactions.extend(reactions);
+    }
+
+    let dropped: HashSet<usize> = replaced_by.into_keys().collect();
+    let mut index = 0;
+    messages.retain(|_| {
+        let keep = !dropped.contains(&index);
+        index += 1;
+        keep
+    });
+    dropped.len()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SenderCorrections {
+    pub sender: String,          // "me" or the sender's identifier
+    pub sender_name: String,
+    pub messages: i64,
+    pub corrections: i64,        // Messages immediately re-sent with a fix
+    pub correction_rate: f64,    // Percent of the sender's messages
+}
+
+/// Correction messages per sender, most corrections first
+pub fn correction_report(messages: &[Message]) -> Vec<SenderCorrections> {
+    let corrected: HashSet<usize> = superseded(messages).into_iter().map(|(i, _)| i).collect();
+
+    let mut by_sender: HashMap<String, (String, i64, i64)> = HashMap::new();  // sender -> (name, messages, corrections)
+    for (i, msg) in messages.iter().enumerate() {
+        let sender = if msg.is_from_me { "me".to_string() } else { msg.contact_identifier.clone() };
+        if sender.is_empty() {
+            continue;
+        }
+        let entry = by_sender.entry(sender).or_insert_with(|| {
+            let name = if msg.is_from_me { "Me".to_string() } else { msg.sender_name.clone() };
+            (name, 0, 0)
+        });
+        entry.1 += 1;
+        if corrected.contains(&i) {
+            entry.2 += 1;
+        }
+    }
+
+    let mut results: Vec<SenderCorrections> = by_sender
+        .into_iter()
+        .filter(|(_, (_, _, corrections))| *corrections > 0)
+        .map(|(sender, (sender_name, messages, corrections))| SenderCorrections {
+            sender,
+            sender_name,
+            messages,
+            corrections,
+            correction_rate: corrections as f64 * 100.0 / messages as f64,
+        })
+        .collect();
+    results.sort_by(|a, b| b.corrections.cmp(&a.corrections).then_with(|| a.sender.cmp(&b.sender)));
+    results
+}
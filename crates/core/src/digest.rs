@@ -0,0 +1,184 @@
+use crate::{chat_title, settings, store, webhook, ExportOptions, MessageExtract};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const NOTABLE_MESSAGES: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestChat {
+    pub chat_id: i64,
+    pub title: String,
+    pub message_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeeklyDigest {
+    pub week_start: String,                 // Monday, YYYY-MM-DD
+    pub week_end: String,                   // Sunday, YYYY-MM-DD
+    pub message_count: i64,
+    pub messages_sent: i64,
+    pub previous_week_count: i64,
+    pub change_percent: Option<f64>,
+    pub most_active_chat: Option<DigestChat>,
+    pub notable_messages: Vec<MessageExtract>,  // Most-reacted messages of the week
+    pub created_at: i64,
+}
+
+/// Monday of the most recent fully completed week
+fn last_completed_week(today: NaiveDate) -> NaiveDate {
+    today - Duration::days(today.weekday().num_days_from_monday() as i64 + 7)
+}
+
+fn week_options(monday: NaiveDate) -> Option<ExportOptions> {
+    let (start, _) = crate::local_day_bounds(&monday.format("%Y-%m-%d").to_string())?;
+    let sunday = monday + Duration::days(6);
+    let (_, end) = crate::local_day_bounds(&sunday.format("%Y-%m-%d").to_string())?;
+    Some(ExportOptions {
+        start_date: Some(start),
+        end_date: Some(end),
+        ..Default::default()
+    })
+}
+
+/// Compute the highlights for the week starting on `monday`
+pub fn build_digest(monday: NaiveDate) -> Result<WeeklyDigest, String> {
+    let options = week_options(monday).ok_or("Invalid week")?;
+    let previous = week_options(monday - Duration::days(7)).ok_or("Invalid week")?;
+
+    let messages = crate::get_messages(Some(options), None)?;
+    let previous_week_count = crate::count_messages(Some(previous))?;
+    let message_count = messages.len() as i64;
+
+    let mut per_chat: HashMap<i64, i64> = HashMap::new();
+    for chat_id in messages.iter().filter_map(|m| m.chat_id) {
+        *per_chat.entry(chat_id).or_insert(0) += 1;
+    }
+    let most_active_chat = match per_chat.into_iter().max_by_key(|(id, count)| (*count, -id)) {
+        Some((chat_id, count)) => {
+            let title = crate::load_chats()?
+                .iter()
+                .find(|c| c.id == chat_id)
+                .map(chat_title)
+                .unwrap_or_else(|| format!("Chat {}", chat_id));
+            Some(DigestChat {
+                chat_id,
+                title,
+                message_count: count,
+            })
+        }
+        None => None,
+    };
+
+    let mut notable: Vec<_> = messages
+        .iter()
+        .filter(|m| !m.reactions.is_empty() && m.text.is_some())
+        .collect();
+    notable.sort_by(|a, b| b.reactions.len().cmp(&a.reactions.len()).then(a.date.cmp(&b.date)));
+
+    Ok(WeeklyDigest {
+        week_start: monday.format("%Y-%m-%d").to_string(),
+        week_end: (monday + Duration::days(6)).format("%Y-%m-%d").to_string(),
+        message_count,
+        messages_sent: messages.iter().filter(|m| m.is_from_me).count() as i64,
+        previous_week_count,
+        change_percent: if previous_week_count > 0 {
+            Some((message_count - previous_week_count) as f64 / previous_week_count as f64 * 100.0)
+        } else {
+            None
+        },
+        most_active_chat,
+        notable_messages: notable
+            .into_iter()
+            .take(NOTABLE_MESSAGES)
+            .map(|m| MessageExtract {
+                id: m.id,
+                date: m.date,
+                sender_name: m.sender_name.clone(),
+                text: m.text.clone(),
+            })
+            .collect(),
+        created_at: Utc::now().timestamp(),
+    })
+}
+
+fn has_digest(week_start: &str) -> bool {
+    store::open_store_db()
+        .ok()
+        .and_then(|conn| {
+            conn.query_row("SELECT 1 FROM digests WHERE week_start = ?", [week_start], |_| Ok(()))
+                .ok()
+        })
+        .is_some()
+}
+
+fn save_digest(digest: &WeeklyDigest) -> Result<(), String> {
+    let conn = store::open_store_db()?;
+    let json = serde_json::to_string(digest).map_err(|e| format!("Cannot serialize digest: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO digests (week_start, created_at, digest_json) VALUES (?, ?, ?)",
+        rusqlite::params![digest.week_start, digest.created_at, json],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// Stored digests, newest week first
+pub fn load_digests(limit: i64) -> Result<Vec<WeeklyDigest>, String> {
+    let conn = store::open_store_db()?;
+    let mut stmt = conn
+        .prepare("SELECT digest_json FROM digests ORDER BY week_start DESC LIMIT ?")
+        .map_err(|e| format!("Store error: {}", e))?;
+    let digests = stmt
+        .query_map([limit], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Store error: {}", e))?
+        .filter_map(|r| r.ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    Ok(digests)
+}
+
+fn summary_line(digest: &WeeklyDigest, app_settings: &settings::AppSettings) -> String {
+    let change = digest
+        .change_percent
+        .map(|p| format!(" ({:+.0}% vs the week before)", p))
+        .unwrap_or_default();
+    let chat = digest
+        .most_active_chat
+        .as_ref()
+        .map(|c| format!(" Most active: {} ({}).", c.title, settings::format_count(c.message_count, app_settings)))
+        .unwrap_or_default();
+    format!("{} messages last week{}.{}", settings::format_count(digest.message_count, app_settings), change, chat)
+}
+
+/// Generate, store and announce last week's digest (through `notify(title, body)` and the
+/// digest webhook) if it hasn't been done yet
+pub fn run_if_due(notify: &dyn Fn(&str, &str)) -> Result<(), String> {
+    let app_settings = settings::load_settings();
+    if !app_settings.weekly_digest_enabled.unwrap_or(true) {
+        return Ok(());
+    }
+    let monday = last_completed_week(chrono::Local::now().date_naive());
+    if has_digest(&monday.format("%Y-%m-%d").to_string()) {
+        return Ok(());
+    }
+
+    let digest = build_digest(monday)?;
+    save_digest(&digest)?;
+    let summary = summary_line(&digest, &app_settings);
+
+    notify("Your weekly Messages digest", &summary);
+
+    if let Some(ref name) = app_settings.digest_webhook {
+        let mut context = serde_json::to_value(&digest)
+            .ok()
+            .and_then(|v| v.as_object().cloned())
+            .unwrap_or_default();
+        context.insert("text".into(), summary.into());
+        let delivery = webhook::find(&app_settings.webhooks, name).and_then(|w| webhook::post(w, &context));
+        if let Err(e) = delivery {
+            tracing::warn!("Digest webhook failed: {}", e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::chatdb::ChatDbSchema;
+use crate::{cache, expand_home_path};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// One photo or video sent to several conversations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedMedia {
+    pub content_hash: String,
+    pub mime_type: Option<String>,
+    pub total_bytes: i64,
+    pub filename: String,          // One of the copies on disk
+    pub chat_ids: Vec<i64>,
+    pub chat_titles: Vec<String>,
+    pub times_sent: i64,           // Across all chats, counting repeats within one chat
+    pub first_sent: i64,           // Unix timestamp
+}
+
+struct Copy {
+    filename: String,
+    mime_type: Option<String>,
+    total_bytes: i64,
+    chat_id: i64,
+    date: i64,
+}
+
+/// Images and videos whose content was sent in at least `min_chats` chats, most widespread first.
+/// Only files sharing a byte size with a file in another chat are hashed; hashes are kept in
+/// the cache database so later runs only read new attachments.
+pub fn shared_media(
+    conn: &Connection,
+    schema: &ChatDbSchema,
+    min_chats: usize,
+    titles: &HashMap<i64, String>,
+) -> Result<Vec<SharedMedia>, String> {
+    let query = format!(
+        "SELECT a.filename, a.mime_type, a.total_bytes, cmj.chat_id, {date}
+         FROM attachment a
+         JOIN message_attachment_join maj ON maj.attachment_id = a.ROWID
+         JOIN message m ON m.ROWID = maj.message_id
+         JOIN chat_message_join cmj ON cmj.message_id = m.ROWID
+         WHERE a.filename IS NOT NULL
+           AND a.total_bytes > 0
+           AND (a.mime_type LIKE 'image/%' OR a.mime_type LIKE 'video/%')",
+        date = schema.unix_seconds_sql("m.date"),
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let copies: Vec<Copy> = stmt
+        .query_map([], |row| {
+            Ok(Copy {
+                filename: expand_home_path(row.get(0)?),
+                mime_type: row.get(1)?,
+                total_bytes: row.get(2)?,
+                chat_id: row.get(3)?,
+                date: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Identical content has identical size, so sizes seen in fewer chats can't qualify
+    let mut chats_by_size: HashMap<i64, BTreeSet<i64>> = HashMap::new();
+    for copy in &copies {
+        chats_by_size.entry(copy.total_bytes).or_default().insert(copy.chat_id);
+    }
+
+    let cache_conn = cache::open_cache_db()?;
+    let mut hashes: HashMap<String, Option<String>> = HashMap::new();
+    let mut groups: HashMap<String, Vec<&Copy>> = HashMap::new();
+    for copy in copies.iter().filter(|c| chats_by_size[&c.total_bytes].len() >= min_chats) {
+        let hash = hashes
+            .entry(copy.filename.clone())
+            .or_insert_with(|| cache::media_hash(&cache_conn, Path::new(&copy.filename)))
+            .clone();
+        if let Some(hash) = hash {
+            groups.entry(hash).or_default().push(copy);
+        }
+    }
+
+    let mut shared: Vec<SharedMedia> = groups
+        .into_iter()
+        .filter_map(|(hash, group)| {
+            let chat_ids: BTreeSet<i64> = group.iter().map(|c| c.chat_id).collect();
+            if chat_ids.len() < min_chats {
+                return None;
+            }
+            let first = group.iter().min_by_key(|c| c.date)?;
+            Some(SharedMedia {
+                content_hash: hash,
+                mime_type: first.mime_type.clone(),
+                total_bytes: first.total_bytes,
+                filename: first.filename.clone(),
+                chat_titles: chat_ids.iter().map(|id| titles.get(id).cloned().unwrap_or_default()).collect(),
+                chat_ids: chat_ids.into_iter().collect(),
+                times_sent: group.len() as i64,
+                first_sent: first.date,
+            })
+        })
+        .collect();
+    shared.sort_by(|a, b| {
+        b.chat_ids
+            .len()
+            .cmp(&a.chat_ids.len())
+            .then(b.times_sent.cmp(&a.times_sent))
+            .then_with(|| a.content_hash.cmp(&b.content_hash))
+    });
+    Ok(shared)
+}
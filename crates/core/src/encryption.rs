@@ -0,0 +1,224 @@
+use crate::tasks::{TaskKind, TaskRegistry};
+use crate::{secrets, settings};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// App-owned databases in the app data dir. chat.db and AddressBook are Apple's and never encrypted.
+const APP_DATABASES: &[&str] = &["store.db", "cache.db", "archive.db"];
+
+// Keychain account holding the hex SQLCipher key
+const KEYCHAIN_ACCOUNT: &str = "app-data-key";
+
+// Keychain reads can prompt, so the key is fetched once per run
+static KEY: Mutex<Option<String>> = Mutex::new(None);
+
+// Set while set_encryption rewrites the databases; every other open is refused meanwhile
+static MIGRATING: AtomicBool = AtomicBool::new(false);
+
+// Written once every copy exists and removed after the swap, so an interrupted swap can be
+// finished on the next launch. Holds "on" or "off", the setting being switched to.
+const PENDING_FILE: &str = "encryption-pending";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub databases: Vec<String>,  // App databases present on disk
+}
+
+/// The app data key from the Keychain, creating one if `create` and none exists yet
+fn app_key(create: bool) -> Result<String, String> {
+    let mut cached = KEY.lock().map_err(|_| "Encryption key lock poisoned".to_string())?;
+    if let Some(key) = cached.as_ref() {
+        return Ok(key.clone());
+    }
+    let key = match secrets::keychain_get(KEYCHAIN_ACCOUNT)? {
+        Some(key) => key,
+        None if create => {
+            let mut bytes = [0u8; 32];
+            std::fs::File::open("/dev/urandom")
+                .and_then(|mut f| f.read_exact(&mut bytes))
+                .map_err(|e| format!("Cannot generate encryption key: {}", e))?;
+            let key: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            secrets::keychain_set(KEYCHAIN_ACCOUNT, &key)?;
+            key
+        }
+        None => return Err("App data is encrypted but its key is missing from the Keychain".to_string()),
+    };
+    *cached = Some(key.clone());
+    Ok(key)
+}
+
+/// SQLCipher raw-key literal, so the hex key is used as-is instead of run through PBKDF2
+fn key_literal(key: &str) -> String {
+    format!("\"x'{}'\"", key)
+}
+
+fn unlock(conn: &Connection, key: &str) -> Result<(), String> {
+    conn.execute_batch(&format!("PRAGMA key = {};", key_literal(key)))
+        .map_err(|e| format!("Cannot set database key: {}", e))?;
+    // The key is only checked on first read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .map(|_| ())
+        .map_err(|_| "Cannot unlock database: wrong key or not encrypted".to_string())
+}
+
+/// Open an app-owned database, unlocking it when app data encryption is on
+pub fn open_app_db(path: &Path) -> Result<Connection, String> {
+    if is_migrating() {
+        return Err("App data is being re-encrypted; try again in a moment".to_string());
+    }
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    if settings::load_settings().encrypt_app_data {
+        unlock(&conn, &app_key(false)?)?;
+    }
+    Ok(conn)
+}
+
+/// Whether the app databases are being rewritten; background writers skip their work meanwhile
+pub fn is_migrating() -> bool {
+    MIGRATING.load(Ordering::Relaxed)
+}
+
+/// Clears MIGRATING however set_encryption returns
+struct MigratingGuard;
+
+impl Drop for MigratingGuard {
+    fn drop(&mut self) {
+        MIGRATING.store(false, Ordering::Relaxed);
+    }
+}
+
+fn pending_path() -> Result<PathBuf, String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    Ok(dir.join(PENDING_FILE))
+}
+
+fn existing_databases() -> Result<Vec<PathBuf>, String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    Ok(APP_DATABASES.iter().map(|name| dir.join(name)).filter(|p| p.exists()).collect())
+}
+
+pub fn status() -> Result<EncryptionStatus, String> {
+    Ok(EncryptionStatus {
+        enabled: settings::load_settings().encrypt_app_data,
+        databases: existing_databases()?
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+    })
+}
+
+fn migrating_path(path: &Path) -> PathBuf {
+    path.with_extension("db.migrating")
+}
+
+/// Copy a database into `<name>.migrating` under a new key (None = plaintext) with
+/// sqlcipher_export, leaving the original untouched
+fn export_copy(path: &Path, from: Option<&str>, to: Option<&str>) -> Result<PathBuf, String> {
+    let target = migrating_path(path);
+    let _ = std::fs::remove_file(&target);
+
+    let conn = Connection::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    if let Some(key) = from {
+        unlock(&conn, key)?;
+    }
+    let attach = format!(
+        "ATTACH DATABASE '{}' AS migrated KEY {};",
+        target.to_string_lossy().replace('\'', "''"),
+        to.map(key_literal).unwrap_or_else(|| "''".to_string())
+    );
+    conn.execute_batch(&attach)
+        .and_then(|_| conn.query_row("SELECT sqlcipher_export('migrated')", [], |_| Ok(())))
+        .and_then(|_| conn.execute_batch("DETACH DATABASE migrated;"))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&target);
+            format!("Cannot migrate {}: {}", path.display(), e)
+        })?;
+    Ok(target)
+}
+
+/// Move finished copies over the originals and record the new setting. Safe to repeat: copies
+/// already moved are simply gone.
+fn swap_copies(enabled: bool) -> Result<(), String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    for path in APP_DATABASES.iter().map(|name| dir.join(name)) {
+        let copy = migrating_path(&path);
+        if !copy.exists() {
+            continue;
+        }
+        // Stale WAL/SHM files belong to the old encoding
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.to_string_lossy(), suffix));
+        }
+        std::fs::rename(&copy, &path).map_err(|e| format!("Cannot replace {}: {}", path.display(), e))?;
+    }
+    let mut app_settings = settings::load_settings();
+    app_settings.encrypt_app_data = enabled;
+    settings::save_settings(&app_settings)?;
+    let _ = std::fs::remove_file(pending_path()?);
+    Ok(())
+}
+
+/// Finish a switch interrupted during the swap, or drop copies from one interrupted before it.
+/// Runs at startup, before anything opens the app databases.
+pub fn resume_pending() -> Result<(), String> {
+    let pending = pending_path()?;
+    match std::fs::read_to_string(&pending) {
+        Ok(target) => {
+            let enabled = target.trim() == "on";
+            swap_copies(enabled)?;
+            tracing::info!(enabled, "finished interrupted app data encryption change");
+        }
+        Err(_) => {
+            for path in existing_databases()? {
+                let _ = std::fs::remove_file(migrating_path(&path));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Turn app data encryption on or off, rewriting every existing app database. Waits for running
+/// syncs, imports and cache refreshes, and refuses every other database open until done. All
+/// copies are made first; a failure there leaves the old stores in place. Only then is the
+/// switch recorded and the copies swapped in, so a crash mid-swap is finished by
+/// `resume_pending` on the next launch.
+pub fn set_encryption(task_registry: &TaskRegistry, enabled: bool) -> Result<EncryptionStatus, String> {
+    let label = if enabled { "Encrypting app data" } else { "Decrypting app data" };
+    let _task = task_registry.begin(TaskKind::Encryption, label)?;
+    if settings::load_settings().encrypt_app_data == enabled {
+        return status();
+    }
+    let key = app_key(enabled)?;
+    let (from, to) = if enabled { (None, Some(key.as_str())) } else { (Some(key.as_str()), None) };
+
+    MIGRATING.store(true, Ordering::Relaxed);
+    let _migrating = MigratingGuard;
+    let databases = existing_databases()?;
+    let mut copies = Vec::new();
+    for path in &databases {
+        match export_copy(path, from, to) {
+            Ok(copy) => copies.push(copy),
+            Err(e) => {
+                for copy in &copies {
+                    let _ = std::fs::remove_file(copy);
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    if let Err(e) = std::fs::write(pending_path()?, if enabled { "on" } else { "off" }) {
+        for copy in &copies {
+            let _ = std::fs::remove_file(copy);
+        }
+        return Err(format!("Cannot record encryption change: {}", e));
+    }
+    swap_copies(enabled)?;
+    tracing::info!(enabled, databases = databases.len(), "app data encryption changed");
+    status()
+}
@@ -0,0 +1,698 @@
+use crate::settings::{self, AppSettings};
+use crate::tasks::{TaskKind, TaskRegistry};
+use crate::{Attachment, ExportOptions, Message, Reaction};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Txt,
+    Markdown,
+    Html,
+    Json,
+    Csv,
+    Mbox,
+}
+
+impl ExportFormat {
+    /// File extension for this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Txt => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Mbox => "mbox",
+        }
+    }
+}
+
+/// How reactions are rendered in an export
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReactionStyle {
+    #[default]
+    Inline,     // Badges after each message
+    Footnotes,  // Numbered markers with a reactions section at the end
+    Csv,        // Separate <name>_reactions.csv next to the export
+    Omit,
+}
+
+/// Column layout of CSV exports
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvProfile {
+    #[default]
+    Standard,  // date,sender,is_from_me,text,attachments[,reactions]
+    Imazing,   // iMazing's message CSV columns, so existing spreadsheets keep working
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub files: Vec<String>,
+    pub message_count: i64,
+}
+
+/// Human-readable label for a tapback type
+pub fn reaction_label(reaction_type: i64) -> &'static str {
+    match reaction_type {
+        2000 => "❤️",
+        2001 => "👍",
+        2002 => "👎",
+        2003 => "😂",
+        2004 => "‼️",
+        2005 => "❓",
+        _ => "•",
+    }
+}
+
+/// "❤️ Alice, 👍 Me"
+pub fn format_reactions(reactions: &[Reaction]) -> String {
+    reactions
+        .iter()
+        .map(|r| format!("{} {}", reaction_label(r.reaction_type), r.sender))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Attachment names for a message, preferring the original transfer name
+pub fn attachment_names(msg: &Message) -> Vec<String> {
+    msg.attachments
+        .iter()
+        .map(|a| {
+            a.transfer_name
+                .clone()
+                .or_else(|| a.filename.as_ref().map(|f| f.rsplit('/').next().unwrap_or(f).to_string()))
+                .unwrap_or_else(|| "attachment".to_string())
+        })
+        .collect()
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_csv(text: &str) -> String {
+    if text.contains(',') || text.contains('"') || text.contains('\n') || text.contains('\r') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// In-progress files carry this suffix until they are complete
+const PARTIAL_SUFFIX: &str = ".partial";
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(PARTIAL_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Note a `.partial` file we are about to create, so a crash mid-write can be cleaned up on
+/// the next launch without touching anyone else's files
+fn record_partial(partial: &Path) {
+    if let Ok(conn) = crate::store::open_store_db() {
+        let _ = conn.execute(
+            "INSERT OR IGNORE INTO export_partials (path, created_at) VALUES (?1, ?2)",
+            rusqlite::params![partial.to_string_lossy(), chrono::Utc::now().timestamp()],
+        );
+    }
+}
+
+fn forget_partial(partial: &Path) {
+    if let Ok(conn) = crate::store::open_store_db() {
+        let _ = conn.execute("DELETE FROM export_partials WHERE path = ?1", [partial.to_string_lossy()]);
+    }
+}
+
+/// Create a `.partial` file for `path`, recording it first
+fn create_partial(path: &Path) -> std::io::Result<(PathBuf, std::fs::File)> {
+    let partial = partial_path(path);
+    record_partial(&partial);
+    std::fs::File::create(&partial).map(|file| (partial, file))
+}
+
+/// Move a finished `.partial` file into place, or remove it if writing failed
+fn finish_partial(partial: &Path, path: &Path, written: std::io::Result<()>) -> std::io::Result<()> {
+    let result = written.and_then(|_| std::fs::rename(partial, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(partial);
+    }
+    forget_partial(partial);
+    result
+}
+
+/// Write via a `.partial` file renamed into place, so a crash never leaves a truncated file
+/// under the real name
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    write_atomic_with(path, |out| out.write_all(contents.as_ref()))
+}
+
+/// `write_atomic` for output produced piece by piece, so it never has to sit in memory whole
+pub fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut dyn std::io::Write) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let partial = partial_path(path);
+    let written = create_partial(path).and_then(|(_, file)| {
+        let mut out = std::io::BufWriter::new(file);
+        write(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()
+    });
+    finish_partial(&partial, path, written)
+}
+
+pub fn copy_atomic(source: &Path, path: &Path) -> std::io::Result<()> {
+    let partial = partial_path(path);
+    record_partial(&partial);
+    let written = std::fs::copy(source, &partial).map(|_| ());
+    finish_partial(&partial, path, written)
+}
+
+/// Remove the `.partial` files recorded by exports that never finished. Skipped while an
+/// export is running, since its files are still being written.
+pub fn clean_stale_partials(task_registry: &TaskRegistry) -> usize {
+    if task_registry.active().iter().any(|t| t.kind == TaskKind::Export) {
+        return 0;
+    }
+    let Ok(conn) = crate::store::open_store_db() else { return 0 };
+    let paths: Vec<String> = conn
+        .prepare("SELECT path FROM export_partials")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .unwrap_or_default();
+
+    let mut removed = 0;
+    for path in paths {
+        if std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+        let _ = conn.execute("DELETE FROM export_partials WHERE path = ?1", [&path]);
+    }
+    removed
+}
+
+/// Decides how attachments appear in an HTML export: embedded as data URIs, copied next to
+/// the HTML file with relative links, or just named
+struct HtmlMedia {
+    files_dir: Option<PathBuf>,  // Folder next to the HTML file that receives copied media
+    dir_name: String,            // Relative link prefix for copied media
+    inline_limit: u64,           // Images at or below this size are embedded
+    copied: Vec<String>,
+    copied_by_hash: HashMap<String, String>,  // Content hash -> file already copied, so repeats link to it
+    hash_cache: Option<rusqlite::Connection>,
+    namer: crate::filenames::AttachmentNamer,
+}
+
+/// HEIC/HEIF photos only display in Safari, so they are never inlined and link to the copy
+fn is_heif(mime: &str) -> bool {
+    mime.starts_with("image/heic") || mime.starts_with("image/heif")
+}
+
+impl HtmlMedia {
+    fn render(&mut self, msg: &Message, attachment: &Attachment, fallback_name: &str) -> String {
+        let source = attachment
+            .filename
+            .as_ref()
+            .map(PathBuf::from)
+            .filter(|p| p.exists());
+        let mime = attachment.mime_type.as_deref().unwrap_or("");
+        let label = format!("<div class=\"meta\">📎 {}</div>", escape_html(fallback_name));
+
+        let source = match source {
+            Some(s) => s,
+            None => return label,
+        };
+        let size = std::fs::metadata(&source).map(|m| m.len()).unwrap_or(u64::MAX);
+
+        // Small images travel inside the HTML itself
+        if mime.starts_with("image/") && !is_heif(mime) && size <= self.inline_limit {
+            if let Ok(bytes) = std::fs::read(&source) {
+                let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+                return format!(
+                    "<img class=\"media\" alt=\"{}\" src=\"data:{};base64,{}\">",
+                    escape_html(fallback_name),
+                    mime,
+                    encoded
+                );
+            }
+        }
+
+        let files_dir = match self.files_dir {
+            Some(ref d) => d,
+            None => return label,
+        };
+        let hash = self.hash_cache.as_ref().and_then(|conn| crate::cache::media_hash(conn, &source));
+        let target_name = match hash.as_ref().and_then(|h| self.copied_by_hash.get(h)) {
+            Some(existing) => existing.clone(),
+            None => {
+                let target_name = self.namer.name(msg.id, fallback_name);
+                if std::fs::create_dir_all(files_dir).is_err()
+                    || copy_atomic(&source, &files_dir.join(&target_name)).is_err()
+                {
+                    return label;
+                }
+                self.namer.record(&target_name, msg.id, fallback_name, &source);
+                self.copied.push(files_dir.join(&target_name).to_string_lossy().to_string());
+                if let Some(hash) = hash {
+                    self.copied_by_hash.insert(hash, target_name.clone());
+                }
+                target_name
+            }
+        };
+
+        let href = format!("{}/{}", self.dir_name, target_name);
+        if is_heif(mime) {
+            format!(
+                "<a href=\"{href}\"><img class=\"media\" alt=\"{alt}\" src=\"{href}\"></a>",
+                href = escape_html(&href),
+                alt = escape_html(fallback_name)
+            )
+        } else if mime.starts_with("image/") {
+            format!("<img class=\"media\" alt=\"{}\" src=\"{}\">", escape_html(fallback_name), escape_html(&href))
+        } else if mime.starts_with("video/") {
+            format!("<video class=\"media\" controls src=\"{}\"></video>", escape_html(&href))
+        } else if mime.starts_with("audio/") {
+            format!("<audio controls src=\"{}\"></audio>", escape_html(&href))
+        } else {
+            format!("<div class=\"meta\">📎 <a href=\"{}\">{}</a></div>", escape_html(&href), escape_html(fallback_name))
+        }
+    }
+}
+
+/// Collects footnotes while rendering and prints them at the end
+struct Footnotes {
+    entries: Vec<String>,
+}
+
+impl Footnotes {
+    fn add(&mut self, reactions: &[Reaction]) -> usize {
+        self.entries.push(format_reactions(reactions));
+        self.entries.len()
+    }
+}
+
+pub fn render_txt(
+    title: &str,
+    notes: &[String],
+    messages: &[Message],
+    style: ReactionStyle,
+    settings: &AppSettings,
+) -> String {
+    let mut out = String::new();
+    let mut footnotes = Footnotes { entries: Vec::new() };
+    let _ = writeln!(out, "{}\n{}\n", title, "=".repeat(title.chars().count()));
+    if !notes.is_empty() {
+        for note in notes {
+            let _ = writeln!(out, "Note: {}", note);
+        }
+        out.push('\n');
+    }
+
+    for msg in messages {
+        let _ = write!(out, "[{}] {}: {}", settings::format_datetime(msg.date, settings), msg.sender_name, msg.text.as_deref().unwrap_or(""));
+        for name in attachment_names(msg) {
+            let _ = write!(out, " <attachment: {}>", name);
+        }
+        if !msg.reactions.is_empty() {
+            match style {
+                ReactionStyle::Inline => {
+                    let _ = write!(out, " [{}]", format_reactions(&msg.reactions));
+                }
+                ReactionStyle::Footnotes => {
+                    let _ = write!(out, " [{}]", footnotes.add(&msg.reactions));
+                }
+                ReactionStyle::Csv | ReactionStyle::Omit => {}
+            }
+        }
+        out.push('\n');
+    }
+
+    if !footnotes.entries.is_empty() {
+        out.push_str("\nReactions\n---------\n");
+        for (i, entry) in footnotes.entries.iter().enumerate() {
+            let _ = writeln!(out, "[{}] {}", i + 1, entry);
+        }
+    }
+    out
+}
+
+fn render_markdown(title: &str, notes: &[String], messages: &[Message], style: ReactionStyle, settings: &AppSettings) -> String {
+    let mut out = String::new();
+    let mut footnotes = Footnotes { entries: Vec::new() };
+    let _ = writeln!(out, "# {}\n", title);
+    if !notes.is_empty() {
+        for note in notes {
+            let _ = writeln!(out, "> {}", note);
+        }
+        out.push('\n');
+    }
+
+    for msg in messages {
+        let _ = write!(out, "**{}** _{}_: {}", msg.sender_name, settings::format_datetime(msg.date, settings), msg.text.as_deref().unwrap_or(""));
+        for name in attachment_names(msg) {
+            let _ = write!(out, " `{}`", name);
+        }
+        if !msg.reactions.is_empty() {
+            match style {
+                ReactionStyle::Inline => {
+                    let _ = write!(out, " ({})", format_reactions(&msg.reactions));
+                }
+                ReactionStyle::Footnotes => {
+                    let _ = write!(out, "[^{}]", footnotes.add(&msg.reactions));
+                }
+                ReactionStyle::Csv | ReactionStyle::Omit => {}
+            }
+        }
+        out.push_str("\n\n");
+    }
+
+    for (i, entry) in footnotes.entries.iter().enumerate() {
+        let _ = writeln!(out, "[^{}]: {}", i + 1, entry);
+    }
+    out
+}
+
+fn render_html(
+    title: &str,
+    notes: &[String],
+    messages: &[Message],
+    style: ReactionStyle,
+    media: &mut HtmlMedia,
+    settings: &AppSettings,
+) -> String {
+    let mut out = String::new();
+    let mut footnotes = Footnotes { entries: Vec::new() };
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{title}</title>\n\
+         <style>body{{font-family:-apple-system,sans-serif;max-width:800px;margin:auto}}\
+         .msg{{margin:6px 0}}.me{{text-align:right}}.meta{{color:#888;font-size:12px}}\
+         .reactions{{font-size:12px}}.media{{max-width:320px;border-radius:12px}}</style>\n</head>\n<body>\n<h1>{title}</h1>",
+        title = escape_html(title)
+    );
+    for note in notes {
+        let _ = writeln!(out, "<p class=\"meta\">{}</p>", escape_html(note));
+    }
+
+    for msg in messages {
+        let class = if msg.is_from_me { "msg me" } else { "msg" };
+        let _ = write!(
+            out,
+            "<div class=\"{}\"><div class=\"meta\">{} · {}</div><div>{}</div>",
+            class,
+            escape_html(&msg.sender_name),
+            escape_html(&settings::format_datetime(msg.date, settings)),
+            escape_html(msg.text.as_deref().unwrap_or(""))
+        );
+        for (attachment, name) in msg.attachments.iter().zip(attachment_names(msg)) {
+            out.push_str(&media.render(msg, attachment, &name));
+        }
+        if !msg.reactions.is_empty() {
+            match style {
+                ReactionStyle::Inline => {
+                    let _ = write!(out, "<div class=\"reactions\">{}</div>", escape_html(&format_reactions(&msg.reactions)));
+                }
+                ReactionStyle::Footnotes => {
+                    let n = footnotes.add(&msg.reactions);
+                    let _ = write!(out, "<sup><a href=\"#r{n}\">{n}</a></sup>", n = n);
+                }
+                ReactionStyle::Csv | ReactionStyle::Omit => {}
+            }
+        }
+        out.push_str("</div>\n");
+    }
+
+    if !footnotes.entries.is_empty() {
+        out.push_str("<h2>Reactions</h2>\n<ol>\n");
+        for (i, entry) in footnotes.entries.iter().enumerate() {
+            let _ = writeln!(out, "<li id=\"r{}\">{}</li>", i + 1, escape_html(entry));
+        }
+        out.push_str("</ol>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_csv(messages: &[Message], style: ReactionStyle, settings: &AppSettings) -> String {
+    let mut out = String::from("date,sender,is_from_me,text,attachments");
+    if style != ReactionStyle::Csv && style != ReactionStyle::Omit {
+        out.push_str(",reactions");
+    }
+    out.push('\n');
+
+    for msg in messages {
+        let _ = write!(
+            out,
+            "{},{},{},{},{}",
+            escape_csv(&settings::format_datetime(msg.date, settings)),
+            escape_csv(&msg.sender_name),
+            msg.is_from_me,
+            escape_csv(msg.text.as_deref().unwrap_or("")),
+            escape_csv(&attachment_names(msg).join("; "))
+        );
+        if style != ReactionStyle::Csv && style != ReactionStyle::Omit {
+            let _ = write!(out, ",{}", escape_csv(&format_reactions(&msg.reactions)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// iMazing writes fixed ISO-style dates whatever the system locale
+const IMAZING_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const IMAZING_COLUMNS: &str = "Chat Session,Message Date,Delivered Date,Read Date,Edited Date,Service,Type,\
+Sender ID,Sender Name,Status,Replying to,Subject,Text,Attachment,Attachment type";
+
+/// CSV in iMazing's column layout. Delivery/read receipts, service and replies aren't loaded, so
+/// those columns stay empty; reactions have no column there and follow the reaction style's CSV.
+fn render_imazing_csv(title: &str, messages: &[Message], settings: &AppSettings) -> String {
+    let date = |ts: i64| {
+        settings::to_local_time(ts, settings)
+            .map(|dt| dt.format(IMAZING_DATE_FORMAT).to_string())
+            .unwrap_or_default()
+    };
+    let mut out = String::from(IMAZING_COLUMNS);
+    out.push('\n');
+    for msg in messages {
+        let attachment_types: Vec<&str> = msg
+            .attachments
+            .iter()
+            .map(|a| a.mime_type.as_deref().unwrap_or(""))
+            .collect();
+        let _ = writeln!(
+            out,
+            "{},{},,,{},,{},{},{},{},,,{},{},{}",
+            escape_csv(title),
+            date(msg.date),
+            msg.delivery.date_edited.map(date).unwrap_or_default(),
+            if msg.is_from_me { "Outgoing" } else { "Incoming" },
+            escape_csv(if msg.is_from_me { "" } else { &msg.contact_identifier }),
+            escape_csv(if msg.is_from_me { "" } else { &msg.sender_name }),
+            if msg.is_from_me { "Sent" } else { "" },
+            escape_csv(msg.text.as_deref().unwrap_or("")),
+            escape_csv(&attachment_names(msg).join("; ")),
+            escape_csv(&attachment_types.join("; "))
+        );
+    }
+    out
+}
+
+/// One row per reaction, for the separate reactions CSV
+fn render_reactions_csv(messages: &[Message], settings: &AppSettings) -> String {
+    let mut out = String::from("message_guid,message_date,message_sender,reaction,reactor\n");
+    for msg in messages {
+        for reaction in &msg.reactions {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                escape_csv(&msg.guid),
+                escape_csv(&settings::format_datetime(msg.date, settings)),
+                escape_csv(&msg.sender_name),
+                reaction_label(reaction.reaction_type),
+                escape_csv(&reaction.sender)
+            );
+        }
+    }
+    out
+}
+
+/// Pinned messages (with notes) listed after the transcript; CSV, JSON and mbox exports have no appendix
+fn render_pinned_appendix(format: ExportFormat, pinned: &[(&Message, Option<&str>)], settings: &AppSettings) -> String {
+    let mut out = String::new();
+    if pinned.is_empty() {
+        return out;
+    }
+    match format {
+        ExportFormat::Txt => out.push_str("\nPinned\n------\n"),
+        ExportFormat::Markdown => out.push_str("\n## Pinned\n\n"),
+        ExportFormat::Html => out.push_str("<h2>Pinned</h2>\n<ul>\n"),
+        ExportFormat::Csv | ExportFormat::Json | ExportFormat::Mbox => return out,
+    }
+    for (msg, note) in pinned {
+        let date = settings::format_datetime(msg.date, settings);
+        let text = msg.text.as_deref().unwrap_or("");
+        match format {
+            ExportFormat::Txt => {
+                let _ = writeln!(out, "[{}] {}: {}", date, msg.sender_name, text);
+                if let Some(note) = note {
+                    let _ = writeln!(out, "    Note: {}", note);
+                }
+            }
+            ExportFormat::Markdown => {
+                let _ = writeln!(out, "- **{}** _{}_: {}", msg.sender_name, date, text);
+                if let Some(note) = note {
+                    let _ = writeln!(out, "  > {}", note);
+                }
+            }
+            _ => {
+                let _ = write!(
+                    out,
+                    "<li><div class=\"meta\">{} · {}</div><div>{}</div>",
+                    escape_html(&msg.sender_name),
+                    escape_html(&date),
+                    escape_html(text)
+                );
+                if let Some(note) = note {
+                    let _ = write!(out, "<div class=\"meta\">{}</div>", escape_html(note));
+                }
+                out.push_str("</li>\n");
+            }
+        }
+    }
+    if format == ExportFormat::Html {
+        out.push_str("</ul>\n");
+    }
+    out
+}
+
+/// Path for the companion reactions CSV: chat.txt -> chat_reactions.csv
+fn reactions_csv_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "export".to_string());
+    path.with_file_name(format!("{}_reactions.csv", stem))
+}
+
+/// Render messages (chronological order) in the requested format and write them to `path`
+pub fn write_export(
+    title: &str,
+    notes: &[String],
+    messages: &[Message],
+    format: ExportFormat,
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<ExportResult, String> {
+    let _span = tracing::info_span!("write_export", ?format, messages = messages.len()).entered();
+    let style = options.reaction_style.unwrap_or_default();
+    let settings = settings::load_settings();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "export".to_string());
+    let dir_name = format!("{}_files", stem);
+    let mut media = HtmlMedia {
+        files_dir: if options.copy_attachments.unwrap_or(false) {
+            Some(path.with_file_name(&dir_name))
+        } else {
+            None
+        },
+        dir_name,
+        inline_limit: options.inline_images_below_bytes.unwrap_or(0),
+        copied: Vec::new(),
+        copied_by_hash: HashMap::new(),
+        hash_cache: None,
+        namer: Default::default(),
+    };
+    if media.files_dir.is_some() {
+        media.hash_cache = crate::cache::open_cache_db().ok();
+    }
+
+    // A template replaces the built-in text/Markdown/HTML renderers
+    let template = match (&options.template, format) {
+        (Some(t), ExportFormat::Txt | ExportFormat::Markdown | ExportFormat::Html) => Some(t),
+        _ => None,
+    };
+
+    let mut content = match format {
+        _ if template.is_some() => {
+            let template = template.map(String::as_str).unwrap_or_default();
+            let grouping = options.template_grouping.unwrap_or_default();
+            crate::templates::render(template, format, title, notes, messages, grouping, &settings)?
+        }
+        ExportFormat::Txt => render_txt(title, notes, messages, style, &settings),
+        ExportFormat::Markdown => render_markdown(title, notes, messages, style, &settings),
+        ExportFormat::Html => render_html(title, notes, messages, style, &mut media, &settings),
+        ExportFormat::Csv => match options.csv_profile.unwrap_or_default() {
+            CsvProfile::Standard => render_csv(messages, style, &settings),
+            CsvProfile::Imazing => render_imazing_csv(title, messages, &settings),
+        },
+        // Streamed to disk below, one email at a time: attachments make mboxes too big to buffer
+        ExportFormat::Mbox => String::new(),
+        ExportFormat::Json => {
+            let json = if style == ReactionStyle::Omit || style == ReactionStyle::Csv {
+                let stripped: Vec<Message> = messages
+                    .iter()
+                    .cloned()
+                    .map(|mut m| {
+                        m.reactions.clear();
+                        m
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&stripped)
+            } else {
+                serde_json::to_string_pretty(messages)
+            };
+            json.map_err(|e| format!("Cannot serialize messages: {}", e))?
+        }
+    };
+
+    if options.include_pinned.unwrap_or(false) && template.is_none() {
+        let notes = crate::pins::load_notes()?;
+        let pinned: Vec<(&Message, Option<&str>)> = messages
+            .iter()
+            .filter_map(|m| notes.get(&m.guid).map(|note| (m, note.as_deref())))
+            .collect();
+        let appendix = render_pinned_appendix(format, &pinned, &settings);
+        match content.rfind("</body>") {
+            Some(pos) if format == ExportFormat::Html => content.insert_str(pos, &appendix),
+            _ => content.push_str(&appendix),
+        }
+    }
+
+    let written = if format == ExportFormat::Mbox {
+        let grouping = options.mbox_grouping.unwrap_or_default();
+        write_atomic_with(path, |out| crate::mbox::write_mbox(out, title, messages, grouping, style, &settings))
+    } else {
+        write_atomic(path, content)
+    };
+    written.map_err(|e| format!("Cannot write export: {}", e))?;
+    let mut files = vec![path.to_string_lossy().to_string()];
+
+    if style == ReactionStyle::Csv {
+        let csv_path = reactions_csv_path(path);
+        write_atomic(&csv_path, render_reactions_csv(messages, &settings))
+            .map_err(|e| format!("Cannot write reactions CSV: {}", e))?;
+        files.push(csv_path.to_string_lossy().to_string());
+    }
+
+    files.extend(media.copied);
+    if let (Some(dir), false) = (&media.files_dir, media.namer.entries.is_empty()) {
+        files.push(crate::filenames::write_manifest(dir, &media.namer.entries)?);
+    }
+
+    Ok(ExportResult {
+        files,
+        message_count: messages.len() as i64,
+    })
+}
@@ -0,0 +1,106 @@
+use crate::export::{ExportFormat, ExportResult};
+use crate::{store, DateRange, ExportOptions};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A completed export, with everything needed to run it again
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportJob {
+    pub id: i64,
+    pub chat_id: i64,
+    pub format: ExportFormat,
+    pub path: String,
+    pub options: ExportOptions,
+    pub message_count: i64,
+    pub files: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Record a finished export in the audit log
+pub fn record(
+    chat_id: i64,
+    format: ExportFormat,
+    path: &str,
+    options: &ExportOptions,
+    result: &ExportResult,
+) -> Result<i64, String> {
+    let conn = store::open_store_db()?;
+    let format_json = serde_json::to_string(&format).map_err(|e| format!("Cannot serialize format: {}", e))?;
+    let options_json = serde_json::to_string(options).map_err(|e| format!("Cannot serialize options: {}", e))?;
+    let files_json = serde_json::to_string(&result.files).map_err(|e| format!("Cannot serialize files: {}", e))?;
+    conn.execute(
+        "INSERT INTO export_jobs (chat_id, format, path, options_json, message_count, files_json, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![chat_id, format_json, path, options_json, result.message_count, files_json, Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn job_from_row(row: &rusqlite::Row) -> rusqlite::Result<ExportJob> {
+    let format: String = row.get(2)?;
+    let options: String = row.get(4)?;
+    let files: String = row.get(6)?;
+    Ok(ExportJob {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        format: serde_json::from_str(&format).unwrap_or(ExportFormat::Txt),
+        path: row.get(3)?,
+        options: serde_json::from_str(&options).unwrap_or_default(),
+        message_count: row.get(5)?,
+        files: serde_json::from_str(&files).unwrap_or_default(),
+        created_at: row.get(7)?,
+    })
+}
+
+const JOB_COLUMNS: &str = "id, chat_id, format, path, options_json, message_count, files_json, created_at";
+
+/// Most recent exports first
+pub fn list(limit: usize) -> Result<Vec<ExportJob>, String> {
+    let conn = store::open_store_db()?;
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM export_jobs ORDER BY id DESC LIMIT ?", JOB_COLUMNS))
+        .map_err(|e| format!("Store error: {}", e))?;
+    let jobs = stmt
+        .query_map([limit as i64], job_from_row)
+        .map_err(|e| format!("Store error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(jobs)
+}
+
+pub fn load(job_id: i64) -> Result<ExportJob, String> {
+    let conn = store::open_store_db()?;
+    conn.query_row(
+        &format!("SELECT {} FROM export_jobs WHERE id = ?", JOB_COLUMNS),
+        [job_id],
+        job_from_row,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Export job {} not found", job_id),
+        e => format!("Store error: {}", e),
+    })
+}
+
+/// Output path for a re-run over a new range: chat.txt -> chat_2024-06-01_2024-06-30.txt,
+/// so monthly re-runs sit next to each other instead of overwriting the original
+pub fn path_for_range(path: &str, range: &DateRange) -> String {
+    let day = |ts: i64| {
+        Utc.timestamp_opt(ts, 0)
+            .single()
+            .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "export".to_string());
+    let mut name = format!("{}_{}_{}", stem, day(range.start), day(range.end));
+    if let Some(ext) = path.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    path.with_file_name(name).to_string_lossy().to_string()
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+// Well under the 255-byte limit of APFS/ext4/NTFS, leaving room for the id prefix and a counter
+const MAX_NAME_BYTES: usize = 150;
+
+// Reserved on Windows, where exports are often copied to
+const RESERVED_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// One exported attachment file and where it came from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestEntry {
+    pub file: String,            // Name inside the export's media folder
+    pub message_id: i64,
+    pub original_name: String,   // transfer_name, or the stored file name
+    pub source: String,          // Path in ~/Library/Messages/Attachments
+    pub renamed: bool,           // `file` differs from `{message_id}_{original_name}`
+}
+
+/// Make a name safe on every common filesystem: reserved and control characters become `_`,
+/// leading/trailing dots and spaces go, and long names are shortened keeping the extension
+pub fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if RESERVED_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if cleaned.is_empty() {
+        return "attachment".to_string();
+    }
+    truncate(cleaned, MAX_NAME_BYTES)
+}
+
+/// Split off the extension, if it is a plausible one
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 && name.len() - i <= 16 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    }
+}
+
+/// Shorten to at most `max` bytes on a char boundary, keeping the extension
+fn truncate(name: &str, max: usize) -> String {
+    if name.len() <= max {
+        return name.to_string();
+    }
+    let (stem, ext) = split_extension(name);
+    let mut end = max.saturating_sub(ext.len()).min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", stem[..end].trim_end(), ext)
+}
+
+/// Assigns each attachment of an export a unique, safe file name: `{message_id}_{name}`, then
+/// `-2`, `-3`, ... before the extension when that is taken. Names are compared ignoring case,
+/// as on default macOS volumes. The same messages in the same order always get the same names.
+#[derive(Default)]
+pub struct AttachmentNamer {
+    used: HashSet<String>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl AttachmentNamer {
+    /// Reserve the file name for an attachment
+    pub fn name(&mut self, message_id: i64, original_name: &str) -> String {
+        let base = sanitize(&format!("{}_{}", message_id, original_name));
+        let (stem, ext) = split_extension(&base);
+        let mut file = base.clone();
+        let mut n = 2;
+        while !self.used.insert(file.to_lowercase()) {
+            file = format!("{}-{}{}", stem, n, ext);
+            n += 1;
+        }
+        file
+    }
+
+    /// Add a file to the manifest once it has been written
+    pub fn record(&mut self, file: &str, message_id: i64, original_name: &str, source: &Path) {
+        self.entries.push(ManifestEntry {
+            file: file.to_string(),
+            message_id,
+            original_name: original_name.to_string(),
+            source: source.to_string_lossy().to_string(),
+            renamed: file != format!("{}_{}", message_id, original_name),
+        });
+    }
+}
+
+/// Write `manifest.json` listing every exported attachment into `dir`, returning its path
+pub fn write_manifest(dir: &Path, entries: &[ManifestEntry]) -> Result<String, String> {
+    let path = dir.join("manifest.json");
+    let json = serde_json::to_string_pretty(entries).map_err(|e| format!("Cannot serialize manifest: {}", e))?;
+    crate::export::write_atomic(&path, json).map_err(|e| format!("Cannot write manifest: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_reserved_and_control_characters() {
+        assert_eq!(sanitize("a/b\\c:d*e?f\"g<h>i|j\tk"), "a_b_c_d_e_f_g_h_i_j_k");
+        assert_eq!(sanitize("  ..hidden.txt. "), "hidden.txt");
+        assert_eq!(sanitize(" ... "), "attachment");
+    }
+
+    #[test]
+    fn shortens_long_names_keeping_the_extension() {
+        let name = format!("{}.jpeg", "é".repeat(200));
+        let short = sanitize(&name);
+        assert!(short.len() <= MAX_NAME_BYTES);
+        assert!(short.ends_with(".jpeg"));
+        assert!(short.starts_with('é'));
+    }
+
+    #[test]
+    fn numbers_clashing_names_ignoring_case() {
+        let mut namer = AttachmentNamer::default();
+        assert_eq!(namer.name(7, "IMG.jpg"), "7_IMG.jpg");
+        assert_eq!(namer.name(7, "img.JPG"), "7_img-2.JPG");
+        assert_eq!(namer.name(7, "IMG.jpg"), "7_IMG-3.jpg");
+        assert_eq!(namer.name(8, "IMG.jpg"), "8_IMG.jpg");
+        assert_eq!(namer.name(9, "a:b"), "9_a_b");
+    }
+
+    #[test]
+    fn marks_renamed_files_in_the_manifest() {
+        let mut namer = AttachmentNamer::default();
+        let first = namer.name(1, "photo.heic");
+        let second = namer.name(1, "photo.heic");
+        namer.record(&first, 1, "photo.heic", Path::new("/a/photo.heic"));
+        namer.record(&second, 1, "photo.heic", Path::new("/b/photo.heic"));
+        assert!(!namer.entries[0].renamed);
+        assert!(namer.entries[1].renamed);
+        assert_eq!(namer.entries[1].file, "1_photo-2.heic");
+    }
+}
@@ -0,0 +1,463 @@
+use crate::MAC_EPOCH_OFFSET;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const CHAT_DB_FILE: &str = "chat.db";
+const ADDRESSBOOK_FILE: &str = "AddressBook-v22.abcddb";
+
+// Set while the generated databases stand in for chat.db and AddressBook; never persisted, so a
+// restart always returns to the real data
+static DEMO_MODE: AtomicBool = AtomicBool::new(false);
+
+// The subset of the real chat.db layout the app queries
+const CHAT_DB_SCHEMA: &str = "
+    CREATE TABLE handle (
+        ROWID INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL,
+        service TEXT NOT NULL DEFAULT 'iMessage',
+        uncanonicalized_id TEXT
+    );
+    CREATE TABLE chat (
+        ROWID INTEGER PRIMARY KEY AUTOINCREMENT,
+        guid TEXT NOT NULL UNIQUE,
+        style INTEGER,
+        chat_identifier TEXT,
+        service_name TEXT DEFAULT 'iMessage',
+        display_name TEXT
+    );
+    CREATE TABLE message (
+        ROWID INTEGER PRIMARY KEY AUTOINCREMENT,
+        guid TEXT NOT NULL UNIQUE,
+        text TEXT,
+        handle_id INTEGER DEFAULT 0,
+        service TEXT DEFAULT 'iMessage',
+        date INTEGER,
+        is_from_me INTEGER DEFAULT 0,
+        cache_has_attachments INTEGER DEFAULT 0,
+        attributedBody BLOB,
+        associated_message_guid TEXT,
+        associated_message_type INTEGER DEFAULT 0,
+        thread_originator_guid TEXT,
+        schedule_type INTEGER DEFAULT 0,
+        date_retracted INTEGER DEFAULT 0,
+        date_edited INTEGER DEFAULT 0,
+        was_delivered_quietly INTEGER DEFAULT 0,
+        did_notify_recipient INTEGER DEFAULT 0
+    );
+    CREATE INDEX message_idx_date ON message(date);
+    CREATE TABLE attachment (
+        ROWID INTEGER PRIMARY KEY AUTOINCREMENT,
+        guid TEXT NOT NULL UNIQUE,
+        created_date INTEGER DEFAULT 0,
+        filename TEXT,
+        mime_type TEXT,
+        transfer_name TEXT,
+        total_bytes INTEGER DEFAULT 0
+    );
+    CREATE TABLE chat_handle_join (chat_id INTEGER, handle_id INTEGER, UNIQUE(chat_id, handle_id));
+    CREATE TABLE chat_message_join (
+        chat_id INTEGER,
+        message_id INTEGER,
+        message_date INTEGER DEFAULT 0,
+        PRIMARY KEY (chat_id, message_id)
+    );
+    CREATE TABLE message_attachment_join (message_id INTEGER, attachment_id INTEGER, UNIQUE(message_id, attachment_id));
+";
+
+// AddressBook v22 tables, including a group join table with version-specific entity numbers
+const ADDRESSBOOK_SCHEMA: &str = "
+    CREATE TABLE ZABCDRECORD (
+        Z_PK INTEGER PRIMARY KEY,
+        ZFIRSTNAME VARCHAR,
+        ZLASTNAME VARCHAR,
+        ZNICKNAME VARCHAR,
+        ZORGANIZATION VARCHAR,
+        ZJOBTITLE VARCHAR,
+        ZBIRTHDAY TIMESTAMP,
+        ZNAME VARCHAR
+    );
+    CREATE TABLE ZABCDPHONENUMBER (Z_PK INTEGER PRIMARY KEY, ZOWNER INTEGER, ZFULLNUMBER VARCHAR);
+    CREATE TABLE ZABCDEMAILADDRESS (Z_PK INTEGER PRIMARY KEY, ZOWNER INTEGER, ZADDRESS VARCHAR);
+    CREATE TABLE Z_22PARENTGROUPS (Z_22CONTACTS INTEGER, Z_19PARENTGROUPS1 INTEGER);
+";
+
+const FIRST_NAMES: &[&str] = &["Avery", "Jordan", "Riley", "Morgan", "Casey", "Quinn", "Rowan", "Sage", "Emerson", "Harper"];
+const LAST_NAMES: &[&str] = &["Nguyen", "Okafor", "Lindqvist", "Moreau", "Tanaka", "Alvarez", "Kowalski", "Brennan"];
+const PHRASES: &[&str] = &[
+    "Are we still on for tonight?",
+    "Running 10 minutes late, sorry!",
+    "Did you see the game last night?",
+    "Can you send me that recipe?",
+    "Happy birthday!! 🎉",
+    "Just landed ✈️",
+    "lol that's amazing",
+    "Let me check and get back to you",
+    "Thanks so much, really appreciate it",
+    "Want to grab coffee this week?",
+    "On my way",
+    "That sounds perfect 👍",
+];
+const GROUPS: &[&str] = &["Family", "Work", "Book Club"];
+
+/// Shape of the generated databases; unset fields use small demo-friendly defaults
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FixtureOptions {
+    pub chats: Option<usize>,              // One-to-one chats (a group chat is always added)
+    pub messages_per_chat: Option<usize>,
+    pub days: Option<i64>,                 // Messages are spread over this many days before now
+    pub reactions: Option<bool>,
+    pub attachments: Option<bool>,
+    pub edge_cases: Option<bool>,          // attributedBody-only text, edits, unsends, unknown senders
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FixtureSummary {
+    pub chat_db: String,
+    pub addressbook_db: String,
+    pub chats: usize,
+    pub handles: usize,
+    pub messages: usize,
+    pub reactions: usize,  // Tapbacks still standing, after any that were taken back
+    pub attachments: usize,
+}
+
+/// Small deterministic generator so the same seed always produces the same databases
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// A minimal typedstream blob the attributedBody decoder can read, as macOS Ventura+ writes
+/// for messages whose text column is empty
+fn attributed_body(text: &str) -> Vec<u8> {
+    let mut blob = b"\x04\x0bstreamtyped\x81\xe8\x03\x84\x01@\x84\x84\x84\x12NSAttributedString\x00\x84\x84\x08NSObject\x00\x85\x92\x84\x84\x84\x08NSString\x01\x94\x84\x01+".to_vec();
+    blob.push(text.len().min(255) as u8);
+    blob.extend_from_slice(&text.as_bytes()[..text.len().min(255)]);
+    blob.extend_from_slice(b"\x86\x84\x02iI\x01\x05\x92\x84\x84\x84\x0cNSDictionary\x00\x94\x84\x01i\x00\x86\x86");
+    blob
+}
+
+fn mac_nanos(unix: i64) -> i64 {
+    (unix - MAC_EPOCH_OFFSET) * 1_000_000_000
+}
+
+struct Person {
+    first: &'static str,
+    last: &'static str,
+    identifier: String,
+}
+
+/// Where demo mode keeps its generated databases
+pub fn demo_dir() -> Option<PathBuf> {
+    crate::get_app_data_dir().map(|dir| dir.join("demo"))
+}
+
+pub fn is_demo_mode() -> bool {
+    DEMO_MODE.load(Ordering::Relaxed)
+}
+
+/// The generated chat.db that replaces the real one in demo mode
+pub fn demo_chat_db() -> Option<PathBuf> {
+    demo_dir().map(|dir| dir.join(CHAT_DB_FILE))
+}
+
+/// The generated AddressBook database that replaces every real source in demo mode
+pub fn demo_addressbook_db() -> Option<PathBuf> {
+    demo_dir().map(|dir| dir.join(ADDRESSBOOK_FILE))
+}
+
+/// Switch the data source to the generated databases, generating them first if needed
+pub fn enable_demo_mode() -> Result<(), String> {
+    let dir = demo_dir().ok_or("Could not determine app data directory")?;
+    if !dir.join(CHAT_DB_FILE).exists() || !dir.join(ADDRESSBOOK_FILE).exists() {
+        generate(&dir, &FixtureOptions::default())?;
+    }
+    DEMO_MODE.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn disable_demo_mode() {
+    DEMO_MODE.store(false, Ordering::Relaxed);
+}
+
+/// Write a synthetic chat.db and AddressBook database into `dir`, replacing any previous ones
+pub fn generate(dir: &Path, options: &FixtureOptions) -> Result<FixtureSummary, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create fixture directory: {}", e))?;
+    let chat_path = dir.join(CHAT_DB_FILE);
+    let addressbook_path = dir.join(ADDRESSBOOK_FILE);
+    for path in [&chat_path, &addressbook_path] {
+        if path.exists() {
+            std::fs::remove_file(path).map_err(|e| format!("Cannot replace {}: {}", path.display(), e))?;
+        }
+    }
+
+    let chat_count = options.chats.unwrap_or(6).max(1);
+    let per_chat = options.messages_per_chat.unwrap_or(200).max(1);
+    let days = options.days.unwrap_or(365).max(1);
+    let with_reactions = options.reactions.unwrap_or(true);
+    let with_attachments = options.attachments.unwrap_or(true);
+    let with_edge_cases = options.edge_cases.unwrap_or(true);
+    let mut rng = Rng(options.seed.unwrap_or(0x5EED).max(1));
+
+    let people: Vec<Person> = (0..chat_count)
+        .map(|i| Person {
+            first: FIRST_NAMES[i % FIRST_NAMES.len()],
+            last: LAST_NAMES[(i * 3 + 1) % LAST_NAMES.len()],
+            // Every third contact messages from an email address
+            identifier: if i % 3 == 2 {
+                format!("{}.{}@example.com", FIRST_NAMES[i % FIRST_NAMES.len()], i).to_lowercase()
+            } else {
+                format!("+1555010{:04}", i)
+            },
+        })
+        .collect();
+
+    let mut chat_db = Connection::open(&chat_path).map_err(|e| format!("Cannot create chat.db: {}", e))?;
+    chat_db
+        .execute_batch(CHAT_DB_SCHEMA)
+        .map_err(|e| format!("Cannot initialize chat.db: {}", e))?;
+    let tx = chat_db.transaction().map_err(|e| format!("Cannot write chat.db: {}", e))?;
+    let sql_err = |e: rusqlite::Error| format!("Cannot write chat.db: {}", e);
+
+    for person in &people {
+        tx.execute(
+            "INSERT INTO handle (id, uncanonicalized_id) VALUES (?, ?)",
+            params![person.identifier, person.identifier],
+        )
+        .map_err(sql_err)?;
+    }
+    // A sender with no AddressBook entry, so unresolved names show up in the UI
+    let unknown_handle = if with_edge_cases {
+        tx.execute("INSERT INTO handle (id) VALUES ('+15550199999')", []).map_err(sql_err)?;
+        Some(tx.last_insert_rowid())
+    } else {
+        None
+    };
+
+    // 1:1 chats use handle ROWIDs 1..=n; the group chat includes the first three people
+    let mut chats: Vec<(i64, Vec<i64>)> = Vec::new();
+    for (i, person) in people.iter().enumerate() {
+        let handle_id = i as i64 + 1;
+        tx.execute(
+            "INSERT INTO chat (guid, style, chat_identifier) VALUES (?, 45, ?)",
+            params![format!("iMessage;-;{}", person.identifier), person.identifier],
+        )
+        .map_err(sql_err)?;
+        chats.push((tx.last_insert_rowid(), vec![handle_id]));
+    }
+    let mut group_members: Vec<i64> = (1..=people.len().min(3) as i64).collect();
+    group_members.extend(unknown_handle);
+    tx.execute(
+        "INSERT INTO chat (guid, style, chat_identifier, display_name) VALUES ('iMessage;+;chat-demo-group', 43, 'chat-demo-group', 'Weekend Plans')",
+        [],
+    )
+    .map_err(sql_err)?;
+    chats.push((tx.last_insert_rowid(), group_members));
+    for (chat_id, members) in &chats {
+        for handle_id in members {
+            tx.execute("INSERT INTO chat_handle_join VALUES (?, ?)", params![chat_id, handle_id])
+                .map_err(sql_err)?;
+        }
+    }
+
+    let now = Utc::now().timestamp();
+    let mut message_count = 0;
+    let mut reaction_count = 0;
+    let mut attachment_count = 0;
+    for (chat_id, members) in &chats {
+        let mut date = now - days * 86_400;
+        let step = (days * 86_400 / per_chat as i64).max(1);
+        for n in 0..per_chat {
+            date += step / 2 + (rng.next() % step as u64) as i64;
+            let date = date.min(now - 60);
+            let from_me = rng.chance(45);
+            let handle_id = if from_me { 0 } else { members[rng.below(members.len())] };
+            let guid = format!("DEMO-{:08}-{:04}", chat_id, n);
+            let text = rng.pick(PHRASES);
+            let has_attachment = with_attachments && rng.chance(6);
+
+            // Ventura+ stores some messages only in attributedBody
+            let body_only = with_edge_cases && rng.chance(5);
+            tx.execute(
+                "INSERT INTO message (guid, text, handle_id, date, is_from_me, cache_has_attachments, attributedBody)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    guid,
+                    if body_only { None } else { Some(text) },
+                    handle_id,
+                    mac_nanos(date),
+                    from_me as i64,
+                    has_attachment as i64,
+                    attributed_body(text)
+                ],
+            )
+            .map_err(sql_err)?;
+            let message_id = tx.last_insert_rowid();
+            tx.execute(
+                "INSERT INTO chat_message_join VALUES (?, ?, ?)",
+                params![chat_id, message_id, mac_nanos(date)],
+            )
+            .map_err(sql_err)?;
+            message_count += 1;
+
+            if has_attachment {
+                let name = format!("IMG_{:04}.jpeg", attachment_count + 1);
+                tx.execute(
+                    "INSERT INTO attachment (guid, created_date, filename, mime_type, transfer_name, total_bytes)
+                     VALUES (?, ?, ?, 'image/jpeg', ?, ?)",
+                    params![
+                        format!("DEMO-ATT-{:06}", attachment_count + 1),
+                        date - MAC_EPOCH_OFFSET,
+                        format!("~/Library/Messages/Attachments/demo/{}", name),
+                        name,
+                        200_000 + rng.below(3_000_000) as i64
+                    ],
+                )
+                .map_err(sql_err)?;
+                tx.execute(
+                    "INSERT INTO message_attachment_join VALUES (?, ?)",
+                    params![message_id, tx.last_insert_rowid()],
+                )
+                .map_err(sql_err)?;
+                attachment_count += 1;
+            }
+
+            if with_reactions && rng.chance(10) {
+                let reactor = if from_me { members[0] } else { 0 };
+                let kind = 2000 + rng.below(6) as i64;
+                // Some tapbacks are taken back a minute later (3000-3005), leaving no reaction
+                let removed = with_edge_cases && rng.chance(20);
+                let rows = [(format!("{}-R", guid), kind, 30)]
+                    .into_iter()
+                    .chain(removed.then(|| (format!("{}-U", guid), kind + 1000, 90)));
+                for (reaction_guid, reaction_type, delay) in rows {
+                    tx.execute(
+                        "INSERT INTO message (guid, text, handle_id, date, is_from_me, associated_message_guid, associated_message_type)
+                         VALUES (?, NULL, ?, ?, ?, ?, ?)",
+                        params![
+                            reaction_guid,
+                            reactor,
+                            mac_nanos(date + delay),
+                            (reactor == 0) as i64,
+                            format!("p:0/{}", guid),
+                            reaction_type
+                        ],
+                    )
+                    .map_err(sql_err)?;
+                    tx.execute(
+                        "INSERT INTO chat_message_join VALUES (?, ?, ?)",
+                        params![chat_id, tx.last_insert_rowid(), mac_nanos(date + delay)],
+                    )
+                    .map_err(sql_err)?;
+                }
+                if !removed {
+                    reaction_count += 1;
+                }
+            }
+        }
+    }
+
+    if with_edge_cases {
+        let (chat_id, members) = &chats[0];
+        let edge_cases: [(&str, Option<&str>, &str); 4] = [
+            ("DEMO-EDGE-EDITED", Some("Meet at 7, not 6"), "date_edited"),
+            ("DEMO-EDGE-UNSENT", None, "date_retracted"),
+            ("DEMO-EDGE-SCHEDULED", Some("Reminder: dinner reservation tomorrow"), "schedule_type"),
+            ("DEMO-EDGE-QUIET", Some("Sent with Focus on"), "was_delivered_quietly"),
+        ];
+        for (i, (guid, text, column)) in edge_cases.iter().enumerate() {
+            let date = now - 3_600 * (i as i64 + 1);
+            let value = match *column {
+                "schedule_type" => 2,
+                "was_delivered_quietly" => 1,
+                _ => mac_nanos(date + 120),
+            };
+            tx.execute(
+                &format!(
+                    "INSERT INTO message (guid, text, handle_id, date, is_from_me, {}) VALUES (?, ?, ?, ?, 1, ?)",
+                    column
+                ),
+                params![guid, text, members[0], mac_nanos(date), value],
+            )
+            .map_err(sql_err)?;
+            tx.execute(
+                "INSERT INTO chat_message_join VALUES (?, ?, ?)",
+                params![chat_id, tx.last_insert_rowid(), mac_nanos(date)],
+            )
+            .map_err(sql_err)?;
+            message_count += 1;
+        }
+    }
+    tx.commit().map_err(sql_err)?;
+
+    write_addressbook(&addressbook_path, &people)?;
+
+    Ok(FixtureSummary {
+        chat_db: chat_path.to_string_lossy().to_string(),
+        addressbook_db: addressbook_path.to_string_lossy().to_string(),
+        chats: chats.len(),
+        handles: people.len() + unknown_handle.iter().count(),
+        messages: message_count,
+        reactions: reaction_count,
+        attachments: attachment_count,
+    })
+}
+
+fn write_addressbook(path: &Path, people: &[Person]) -> Result<(), String> {
+    let mut conn = Connection::open(path).map_err(|e| format!("Cannot create AddressBook database: {}", e))?;
+    conn.execute_batch(ADDRESSBOOK_SCHEMA)
+        .map_err(|e| format!("Cannot initialize AddressBook database: {}", e))?;
+    let tx = conn.transaction().map_err(|e| format!("Cannot write AddressBook database: {}", e))?;
+    let sql_err = |e: rusqlite::Error| format!("Cannot write AddressBook database: {}", e);
+
+    // Groups are records too; contacts start after them
+    for (i, group) in GROUPS.iter().enumerate() {
+        tx.execute("INSERT INTO ZABCDRECORD (Z_PK, ZNAME) VALUES (?, ?)", params![i as i64 + 1, group])
+            .map_err(sql_err)?;
+    }
+    for (i, person) in people.iter().enumerate() {
+        let pk = (GROUPS.len() + i) as i64 + 1;
+        // Birthdays are seconds since 2001; every other contact has one
+        let birthday = (i % 2 == 0).then_some((i as f64 + 1.0) * 86_400.0 * 200.0);
+        tx.execute(
+            "INSERT INTO ZABCDRECORD (Z_PK, ZFIRSTNAME, ZLASTNAME, ZORGANIZATION, ZBIRTHDAY) VALUES (?, ?, ?, ?, ?)",
+            params![pk, person.first, person.last, (i % 4 == 1).then_some("Example Corp"), birthday],
+        )
+        .map_err(sql_err)?;
+        let table = if person.identifier.contains('@') {
+            "INSERT INTO ZABCDEMAILADDRESS (ZOWNER, ZADDRESS) VALUES (?, ?)"
+        } else {
+            "INSERT INTO ZABCDPHONENUMBER (ZOWNER, ZFULLNUMBER) VALUES (?, ?)"
+        };
+        tx.execute(table, params![pk, person.identifier]).map_err(sql_err)?;
+        tx.execute(
+            "INSERT INTO Z_22PARENTGROUPS VALUES (?, ?)",
+            params![pk, (i % GROUPS.len()) as i64 + 1],
+        )
+        .map_err(sql_err)?;
+    }
+    tx.commit().map_err(sql_err)
+}
@@ -0,0 +1,170 @@
+use crate::export::{attachment_names, write_atomic, ExportResult};
+use crate::Message;
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+// Fixed layout so page numbers cited in a filing match every printout of the same export
+const LINES_PER_PAGE: usize = 54;
+const LINE_WIDTH: usize = 100;
+
+// Order of the fields hashed per message, recorded in the manifest so hashes can be recomputed
+const HASHED_FIELDS: &str = "rowid|guid|date_unix|is_from_me|handle|text";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForensicManifest {
+    pub title: String,
+    pub chat_id: i64,
+    pub exported_at: String,          // RFC 3339, UTC
+    pub app_version: String,
+    pub database_path: String,
+    pub database_sha256: String,      // chat.db at export time
+    pub wal_sha256: Option<String>,   // chat.db-wal, which holds messages not yet checkpointed
+    pub message_count: i64,
+    pub page_count: usize,
+    pub lines_per_page: usize,
+    pub hashed_fields: String,        // Field order of each message's SHA-256
+    pub export_file: String,
+    pub export_sha256: String,
+}
+
+fn hex(digest: impl AsRef<[u8]>) -> String {
+    digest.as_ref().iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{:02x}", b);
+        out
+    })
+}
+
+/// SHA-256 of a file, streamed so multi-gigabyte databases aren't read into memory
+fn file_sha256(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| format!("Cannot hash {}: {}", path.display(), e))?;
+    Ok(hex(hasher.finalize()))
+}
+
+/// SHA-256 of a message's source row fields, joined in `HASHED_FIELDS` order
+pub fn message_sha256(msg: &Message) -> String {
+    let canonical = format!(
+        "{}|{}|{}|{}|{}|{}",
+        msg.id,
+        msg.guid,
+        msg.date,
+        u8::from(msg.is_from_me),
+        msg.contact_identifier,
+        msg.text.as_deref().unwrap_or("")
+    );
+    hex(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Hard-wrap a line to the page width, counting characters rather than bytes
+fn wrap(line: &str, indent: &str) -> Vec<String> {
+    let width = LINE_WIDTH - indent.chars().count();
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![indent.to_string()];
+    }
+    chars
+        .chunks(width)
+        .map(|chunk| format!("{}{}", indent, chunk.iter().collect::<String>()))
+        .collect()
+}
+
+/// Every message exactly as stored: UTC timestamps, raw text, no grouping, reactions or templates
+fn layout(messages: &[Message]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, msg) in messages.iter().enumerate() {
+        let date = Utc
+            .timestamp_opt(msg.date, 0)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| msg.date.to_string());
+        let sender = if msg.is_from_me {
+            "Me".to_string()
+        } else {
+            format!("{} <{}>", msg.sender_name, msg.contact_identifier)
+        };
+        lines.extend(wrap(&format!("#{} [ROWID {}] {} {}", i + 1, msg.id, date, sender), ""));
+        for text_line in msg.text.as_deref().unwrap_or("").split('\n') {
+            lines.extend(wrap(text_line, "    "));
+        }
+        let attachments = attachment_names(msg);
+        if !attachments.is_empty() {
+            lines.extend(wrap(&format!("Attachments: {}", attachments.join("; ")), "    "));
+        }
+        lines.push(format!("    GUID: {}", msg.guid));
+        lines.push(format!("    SHA-256: {}", message_sha256(msg)));
+        lines.push(String::new());
+    }
+    lines
+}
+
+fn manifest_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "export".to_string());
+    path.with_file_name(format!("{}_manifest.json", stem))
+}
+
+/// Write a paginated plain-text record of `messages` (oldest first) and a manifest with the
+/// database checksum, export time and the export file's own hash
+pub fn write_forensic_export(
+    title: &str,
+    chat_id: i64,
+    messages: &[Message],
+    db_path: &Path,
+    path: &Path,
+) -> Result<ExportResult, String> {
+    let exported_at = Utc::now();
+    let lines = layout(messages);
+    let page_count = lines.len().div_ceil(LINES_PER_PAGE).max(1);
+    let stamp = exported_at.format("%Y-%m-%d %H:%M:%S UTC");
+
+    let mut content = String::new();
+    for (page, page_lines) in lines.chunks(LINES_PER_PAGE).enumerate() {
+        if page > 0 {
+            content.push('\u{c}');  // Form feed starts a new printed page
+        }
+        let _ = writeln!(content, "{} | Exported {} | Page {} of {}", title, stamp, page + 1, page_count);
+        content.push('\n');
+        for line in page_lines {
+            content.push_str(line);
+            content.push('\n');
+        }
+    }
+    if lines.is_empty() {
+        let _ = writeln!(content, "{} | Exported {} | Page 1 of 1\n\nNo messages.", title, stamp);
+    }
+    write_atomic(path, &content).map_err(|e| format!("Cannot write export: {}", e))?;
+
+    let wal = db_path.with_file_name(format!(
+        "{}-wal",
+        db_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    ));
+    let manifest = ForensicManifest {
+        title: title.to_string(),
+        chat_id,
+        exported_at: exported_at.to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        database_path: db_path.to_string_lossy().to_string(),
+        database_sha256: file_sha256(db_path)?,
+        wal_sha256: if wal.exists() { Some(file_sha256(&wal)?) } else { None },
+        message_count: messages.len() as i64,
+        page_count,
+        lines_per_page: LINES_PER_PAGE,
+        hashed_fields: HASHED_FIELDS.to_string(),
+        export_file: path.to_string_lossy().to_string(),
+        export_sha256: hex(Sha256::digest(content.as_bytes())),
+    };
+    let manifest_file = manifest_path(path);
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| format!("Cannot serialize manifest: {}", e))?;
+    write_atomic(&manifest_file, json).map_err(|e| format!("Cannot write manifest: {}", e))?;
+
+    Ok(ExportResult {
+        files: vec![path.to_string_lossy().to_string(), manifest_file.to_string_lossy().to_string()],
+        message_count: messages.len() as i64,
+    })
+}
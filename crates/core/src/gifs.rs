@@ -0,0 +1,205 @@
+use crate::chatdb::ChatDbSchema;
+use crate::{cache, expand_home_path, lookup_contact_name, media};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Hosts whose links are GIFs or memes, matched in message text and link-preview payloads
+const MEME_HOSTS: &[&str] = &["giphy.com", "tenor.com", "gfycat.com", "imgflip.com", "knowyourmeme.com"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SenderGifCounts {
+    pub sender: String,             // "me" or the contact handle
+    pub sender_name: String,
+    pub gif_attachments: i64,
+    pub meme_links: i64,
+    pub by_host: BTreeMap<String, i64>,
+}
+
+/// A GIF file sent more than once in a chat, recognized by content hash
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReusedGif {
+    pub content_hash: String,
+    pub times_sent: i64,
+    pub filename: String,           // One of the copies on disk
+    pub first_sent: i64,            // Unix timestamp
+    pub last_sent: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatGifReport {
+    pub chat_id: i64,
+    pub senders: Vec<SenderGifCounts>,  // Most GIFs and meme links first
+    pub most_reused: Option<ReusedGif>,
+}
+
+struct Row {
+    chat_id: i64,
+    date: i64,
+    is_from_me: bool,
+    handle: Option<String>,
+    filename: Option<String>,
+    mime_type: Option<String>,
+    transfer_name: Option<String>,
+    text: Option<String>,
+    payload: Option<Vec<u8>>,
+}
+
+fn is_gif(mime_type: Option<&str>, transfer_name: Option<&str>) -> bool {
+    mime_type == Some("image/gif") || transfer_name.is_some_and(|n| n.to_lowercase().ends_with(".gif"))
+}
+
+/// Meme hosts mentioned in the text or the link preview's plist payload
+fn meme_hosts(text: Option<&str>, payload: Option<&[u8]>) -> Vec<&'static str> {
+    MEME_HOSTS
+        .iter()
+        .copied()
+        .filter(|host| {
+            text.is_some_and(|t| t.contains(host))
+                || payload.is_some_and(|p| p.windows(host.len()).any(|w| w == host.as_bytes()))
+        })
+        .collect()
+}
+
+/// GIF attachments and meme-site links per sender in each chat, plus each chat's most reused
+/// GIF. GIF files are hashed so copies saved under different names still match.
+pub fn gif_report(
+    conn: &Connection,
+    schema: &ChatDbSchema,
+    chat_id: Option<i64>,
+    contacts: &HashMap<String, String>,
+) -> Result<Vec<ChatGifReport>, String> {
+    let payload = schema.column("payload_data");
+    let host_filter = MEME_HOSTS
+        .iter()
+        .map(|h| format!("m.text LIKE '%{0}%' OR instr({1}, CAST('{0}' AS BLOB)) > 0", h, payload))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let query = format!(
+        "SELECT cmj.chat_id, {date}, m.is_from_me, h.id, a.filename, a.mime_type, a.transfer_name, m.text, {payload}, m.ROWID
+         FROM message m
+         JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         LEFT JOIN handle h ON m.handle_id = h.ROWID
+         LEFT JOIN message_attachment_join maj ON m.ROWID = maj.message_id
+         LEFT JOIN attachment a ON maj.attachment_id = a.ROWID
+         WHERE m.date > 0
+           AND {content}
+           AND (a.mime_type = 'image/gif' OR LOWER(a.transfer_name) LIKE '%.gif' OR {hosts})
+           {chat}
+         ORDER BY m.date",
+        date = schema.unix_seconds_sql("m.date"),
+        payload = payload,
+        content = schema.content_filter(),
+        hosts = host_filter,
+        chat = if chat_id.is_some() { "AND cmj.chat_id = ?" } else { "" },
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let rows: Vec<(i64, Row)> = stmt
+        .query_map(rusqlite::params_from_iter(chat_id.iter()), |row| {
+            Ok((
+                row.get(9)?,
+                Row {
+                    chat_id: row.get(0)?,
+                    date: row.get(1)?,
+                    is_from_me: row.get(2)?,
+                    handle: row.get(3)?,
+                    filename: row.get(4)?,
+                    mime_type: row.get(5)?,
+                    transfer_name: row.get(6)?,
+                    text: row.get(7)?,
+                    payload: row.get(8).ok().flatten(),
+                },
+            ))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut senders: HashMap<(i64, String), SenderGifCounts> = HashMap::new();
+    let mut reuse: HashMap<(i64, String), ReusedGif> = HashMap::new();
+    let mut links_counted: HashSet<i64> = HashSet::new();
+    let mut hashes: HashMap<String, Option<String>> = HashMap::new();
+    let cache_conn = cache::open_cache_db().ok();
+
+    for (message_id, row) in rows {
+        let sender = if row.is_from_me { "me".to_string() } else { row.handle.clone().unwrap_or_default() };
+        let entry = senders.entry((row.chat_id, sender.clone())).or_insert_with(|| SenderGifCounts {
+            sender_name: if row.is_from_me {
+                "Me".to_string()
+            } else {
+                lookup_contact_name(&sender, contacts).unwrap_or_else(|| sender.clone())
+            },
+            sender: sender.clone(),
+            ..Default::default()
+        });
+
+        // A message joins once per attachment; count its links only once
+        if links_counted.insert(message_id) {
+            let hosts = meme_hosts(row.text.as_deref(), row.payload.as_deref());
+            if !hosts.is_empty() {
+                entry.meme_links += 1;
+            }
+            for host in hosts {
+                *entry.by_host.entry(host.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        if !is_gif(row.mime_type.as_deref(), row.transfer_name.as_deref()) {
+            continue;
+        }
+        entry.gif_attachments += 1;
+        let Some(filename) = row.filename.map(expand_home_path) else {
+            continue;
+        };
+        let hash = hashes
+            .entry(filename.clone())
+            .or_insert_with(|| {
+                let path = std::path::Path::new(&filename);
+                match cache_conn {
+                    Some(ref conn) => cache::media_hash(conn, path),
+                    None => media::content_hash(path),
+                }
+            })
+            .clone();
+        if let Some(hash) = hash {
+            let gif = reuse.entry((row.chat_id, hash.clone())).or_insert(ReusedGif {
+                content_hash: hash,
+                times_sent: 0,
+                filename,
+                first_sent: row.date,
+                last_sent: row.date,
+            });
+            gif.times_sent += 1;
+            gif.last_sent = row.date;
+        }
+    }
+
+    let mut by_chat: BTreeMap<i64, ChatGifReport> = BTreeMap::new();
+    for ((chat, _), counts) in senders {
+        by_chat
+            .entry(chat)
+            .or_insert_with(|| ChatGifReport { chat_id: chat, senders: Vec::new(), most_reused: None })
+            .senders
+            .push(counts);
+    }
+    for ((chat, _), gif) in reuse.into_iter().filter(|(_, g)| g.times_sent > 1) {
+        if let Some(report) = by_chat.get_mut(&chat) {
+            let better = report.most_reused.as_ref().map_or(true, |best| {
+                (gif.times_sent, std::cmp::Reverse(gif.first_sent)) > (best.times_sent, std::cmp::Reverse(best.first_sent))
+            });
+            if better {
+                report.most_reused = Some(gif);
+            }
+        }
+    }
+
+    let mut reports: Vec<ChatGifReport> = by_chat.into_values().collect();
+    for report in &mut reports {
+        report.senders.sort_by(|a, b| {
+            (b.gif_attachments + b.meme_links)
+                .cmp(&(a.gif_attachments + a.meme_links))
+                .then_with(|| a.sender.cmp(&b.sender))
+        });
+    }
+    Ok(reports)
+}
@@ -0,0 +1,143 @@
+use crate::analytics::{median, messages_by_chat, response_times, split_sessions, DEFAULT_SESSION_GAP_MINUTES};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+
+// Default and maximum number of buckets
+pub const DEFAULT_BUCKETS: usize = 20;
+const MAX_BUCKETS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistogramMetric {
+    MessageLength,   // Characters per text message
+    ReplyLatency,    // Minutes until the other side replied, both directions
+    SessionLength,   // Minutes from first to last message of a conversation session
+    AttachmentSize,  // Bytes per attachment
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketScale {
+    Linear,
+    Log,  // Bucket edges grow geometrically, for long-tailed values
+}
+
+impl HistogramMetric {
+    pub fn unit(self) -> &'static str {
+        match self {
+            HistogramMetric::MessageLength => "characters",
+            HistogramMetric::ReplyLatency | HistogramMetric::SessionLength => "minutes",
+            HistogramMetric::AttachmentSize => "bytes",
+        }
+    }
+
+    fn scale(self) -> BucketScale {
+        match self {
+            HistogramMetric::MessageLength => BucketScale::Linear,
+            _ => BucketScale::Log,
+        }
+    }
+
+    /// One value per item being measured
+    fn values(self, messages: &[Message]) -> Vec<f64> {
+        match self {
+            HistogramMetric::MessageLength => messages
+                .iter()
+                .filter_map(|m| m.text.as_deref())
+                .filter(|t| !t.is_empty())
+                .map(|t| t.chars().count() as f64)
+                .collect(),
+            HistogramMetric::ReplyLatency => {
+                let (mut mine, theirs) = response_times(messages);
+                mine.extend(theirs);
+                mine
+            }
+            HistogramMetric::SessionLength => messages_by_chat(messages)
+                .values()
+                .flat_map(|chat| split_sessions(chat, DEFAULT_SESSION_GAP_MINUTES * 60))
+                .filter(|s| s.len() > 1)
+                .map(|s| (s[s.len() - 1].date - s[0].date) as f64 / 60.0)
+                .collect(),
+            HistogramMetric::AttachmentSize => messages
+                .iter()
+                .flat_map(|m| &m.attachments)
+                .filter_map(|a| a.total_bytes)
+                .filter(|&b| b > 0)
+                .map(|b| b as f64)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistogramBucket {
+    pub lower: f64,  // Inclusive
+    pub upper: f64,  // Exclusive, except for the last bucket
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Histogram {
+    pub metric: HistogramMetric,
+    pub unit: String,
+    pub scale: BucketScale,
+    pub total: i64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub median: Option<f64>,
+    pub buckets: Vec<HistogramBucket>,
+}
+
+/// Bucket edges spanning [min, max]; log edges start at 1 so zero-minute replies land in the first bucket
+fn bucket_edges(min: f64, max: f64, buckets: usize, scale: BucketScale) -> Vec<f64> {
+    match scale {
+        BucketScale::Linear => {
+            let width = ((max - min) / buckets as f64).max(f64::EPSILON);
+            (0..=buckets).map(|i| min + width * i as f64).collect()
+        }
+        BucketScale::Log => {
+            let (low, high) = (min.max(1.0).ln(), max.max(1.0).ln());
+            let step = ((high - low) / buckets as f64).max(f64::EPSILON);
+            let mut edges: Vec<f64> = (0..=buckets).map(|i| (low + step * i as f64).exp()).collect();
+            edges[0] = min.min(edges[0]);
+            edges
+        }
+    }
+}
+
+/// Distribution of a metric over the given messages in `buckets` buckets
+pub fn histogram(messages: &[Message], metric: HistogramMetric, buckets: usize) -> Histogram {
+    let values = metric.values(messages);
+    let scale = metric.scale();
+    let min = values.iter().copied().reduce(f64::min);
+    let max = values.iter().copied().reduce(f64::max);
+
+    let buckets = match (min, max) {
+        (Some(min), Some(max)) => {
+            let edges = bucket_edges(min, max, buckets.clamp(1, MAX_BUCKETS), scale);
+            let mut counts = vec![0i64; edges.len() - 1];
+            for value in &values {
+                // Index of the last edge at or below the value, with max folded into the final bucket
+                let index = edges.partition_point(|e| e <= value).saturating_sub(1).min(counts.len() - 1);
+                counts[index] += 1;
+            }
+            edges
+                .windows(2)
+                .zip(counts)
+                .map(|(edge, count)| HistogramBucket { lower: edge[0], upper: edge[1], count })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    Histogram {
+        metric,
+        unit: metric.unit().to_string(),
+        scale,
+        total: values.len() as i64,
+        min,
+        max,
+        median: median(&values),
+        buckets,
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{store, Attachment, ExportOptions, Message};
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+// Keep the error list short; a bad file would otherwise return one entry per row
+pub const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Which CSV header columns hold each message field
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColumnMapping {
+    pub date: String,
+    pub text: String,
+    pub sender: Option<String>,
+    pub is_from_me: Option<String>,     // Truthy values: 1/true/yes/sent/outgoing/me
+    pub conversation: Option<String>,   // Thread/contact the message belongs to
+    pub date_format: Option<String>,    // chrono strftime; defaults to Unix/RFC 3339/ISO-like guesses
+    pub source: Option<String>,         // Label stored with the rows, defaults to "csv"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportResult {
+    pub source: String,
+    pub imported: i64,
+    pub skipped: i64,     // Duplicates of rows imported earlier
+    pub errors: Vec<String>,
+}
+
+/// A message from an external log, normalized before it goes into the store
+#[derive(Debug, Clone)]
+pub struct ImportedMessage {
+    pub conversation: Option<String>,
+    pub sender: Option<String>,
+    pub is_from_me: bool,
+    pub date: i64,  // Unix timestamp
+    pub text: Option<String>,
+    pub attachments: Vec<ImportedAttachment>,
+}
+
+/// A media file an importer has already written to disk
+#[derive(Debug, Clone)]
+pub struct ImportedAttachment {
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub total_bytes: Option<i64>,
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "sent" | "outgoing" | "me"
+    )
+}
+
+/// Parse a timestamp cell, treating bare numbers as Unix seconds (or milliseconds when large)
+fn parse_date(value: &str, format: Option<&str>) -> Option<i64> {
+    let value = value.trim();
+    if let Some(fmt) = format {
+        return NaiveDateTime::parse_from_str(value, fmt)
+            .ok()
+            .map(|dt| dt.and_utc().timestamp());
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        return Some(if n > 100_000_000_000 { n / 1000 } else { n });
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp());
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%m/%d/%Y %H:%M", "%d/%m/%Y %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+/// Insert normalized messages, skipping rows already imported from the same source
+pub fn insert_imported(
+    conn: &mut Connection,
+    source: &str,
+    messages: &[ImportedMessage],
+) -> Result<(i64, i64), String> {
+    let tx = conn.transaction().map_err(|e| format!("Store error: {}", e))?;
+    let now = Utc::now().timestamp();
+    let mut imported = 0;
+    let mut skipped = 0;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT OR IGNORE INTO imported_messages
+                    (source, dedupe_key, conversation, sender, is_from_me, date, text, imported_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .map_err(|e| format!("Store error: {}", e))?;
+        for msg in messages {
+            let dedupe_key = format!(
+                "{}|{}|{}|{}",
+                msg.date,
+                msg.conversation.as_deref().unwrap_or(""),
+                if msg.is_from_me { "me" } else { msg.sender.as_deref().unwrap_or("") },
+                msg.text.as_deref().unwrap_or("")
+            );
+            let changed = stmt
+                .execute(rusqlite::params![
+                    source,
+                    dedupe_key,
+                    msg.conversation,
+                    msg.sender,
+                    msg.is_from_me,
+                    msg.date,
+                    msg.text,
+                    now
+                ])
+                .map_err(|e| format!("Store error: {}", e))?;
+            if changed > 0 {
+                let message_id = tx.last_insert_rowid();
+                for attachment in &msg.attachments {
+                    tx.execute(
+                        "INSERT INTO imported_attachments (message_id, filename, mime_type, total_bytes)
+                         VALUES (?, ?, ?, ?)",
+                        rusqlite::params![
+                            message_id,
+                            attachment.filename,
+                            attachment.mime_type,
+                            attachment.total_bytes
+                        ],
+                    )
+                    .map_err(|e| format!("Store error: {}", e))?;
+                }
+                imported += 1;
+            } else {
+                skipped += 1;
+            }
+        }
+    }
+    tx.commit().map_err(|e| format!("Store error: {}", e))?;
+    Ok((imported, skipped))
+}
+
+/// Parse CSV rows into messages using the column mapping; returns the messages and a note for
+/// each row that could not be read
+fn read_csv<R: Read>(input: R, mapping: &ColumnMapping) -> Result<(Vec<ImportedMessage>, Vec<String>), String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+
+    let headers = reader.headers().map_err(|e| format!("Cannot read CSV header: {}", e))?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name.trim()));
+    let optional_column = |name: &Option<String>| -> Result<Option<usize>, String> {
+        match name {
+            Some(n) => column(n).map(Some).ok_or_else(|| format!("Column '{}' not found", n)),
+            None => Ok(None),
+        }
+    };
+
+    let date_col = column(&mapping.date).ok_or_else(|| format!("Column '{}' not found", mapping.date))?;
+    let text_col = column(&mapping.text).ok_or_else(|| format!("Column '{}' not found", mapping.text))?;
+    let sender_col = optional_column(&mapping.sender)?;
+    let from_me_col = optional_column(&mapping.is_from_me)?;
+    let conversation_col = optional_column(&mapping.conversation)?;
+
+    let mut messages = Vec::new();
+    let mut errors = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let line = i + 2; // 1-based, after the header
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(format!("Line {}: {}", line, e));
+                continue;
+            }
+        };
+        let cell = |idx: Option<usize>| {
+            idx.and_then(|i| record.get(i))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let date = match record.get(date_col).and_then(|v| parse_date(v, mapping.date_format.as_deref())) {
+            Some(d) => d,
+            None => {
+                errors.push(format!("Line {}: unrecognized date", line));
+                continue;
+            }
+        };
+        messages.push(ImportedMessage {
+            conversation: cell(conversation_col),
+            sender: cell(sender_col),
+            is_from_me: cell(from_me_col).map(|v| is_truthy(&v)).unwrap_or(false),
+            date,
+            text: cell(Some(text_col)),
+            attachments: Vec::new(),
+        });
+    }
+    Ok((messages, errors))
+}
+
+/// Read a CSV export from another tool using the given column mapping
+pub fn import_csv(path: &Path, mapping: &ColumnMapping) -> Result<ImportResult, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open CSV: {}", e))?;
+    let (messages, mut errors) = read_csv(file, mapping)?;
+
+    let source = mapping.source.clone().unwrap_or_else(|| "csv".to_string());
+    let mut conn = store::open_store_db()?;
+    let (imported, skipped) = insert_imported(&mut conn, &source, &messages)?;
+
+    errors.truncate(MAX_REPORTED_ERRORS);
+    Ok(ImportResult {
+        source,
+        imported,
+        skipped,
+        errors,
+    })
+}
+
+/// Load imported messages as regular Messages (negative ids, no chat) honoring the date filters.
+/// Contact/chat/tag filters refer to chat.db rows, so imported messages never match them.
+pub fn load_imported_messages(options: Option<&ExportOptions>) -> Result<Vec<Message>, String> {
+    if let Some(opts) = options {
+        let has_filter = |ids: &Option<Vec<i64>>| ids.as_ref().map(|v| !v.is_empty()).unwrap_or(false);
+        if has_filter(&opts.contact_ids)
+            || has_filter(&opts.chat_ids)
+            || opts.tags.as_ref().map(|t| !t.is_empty()).unwrap_or(false)
+        {
+            return Ok(Vec::new());
+        }
+    }
+    let start = options.and_then(|o| o.start_date).unwrap_or(i64::MIN);
+    let end = options.and_then(|o| o.end_date).unwrap_or(i64::MAX);
+
+    let conn = store::open_store_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, source, conversation, sender, is_from_me, date, text
+             FROM imported_messages
+             WHERE date >= ? AND date <= ?
+             ORDER BY date DESC",
+        )
+        .map_err(|e| format!("Store error: {}", e))?;
+
+    let mut messages: Vec<Message> = stmt
+        .query_map([start, end], |row| {
+            let id: i64 = row.get(0)?;
+            let source: String = row.get(1)?;
+            let conversation: Option<String> = row.get(2)?;
+            let sender: Option<String> = row.get(3)?;
+            let is_from_me = row.get::<_, i64>(4)? == 1;
+            let date: i64 = row.get(5)?;
+
+            Ok(Message {
+                id: -id,
+                guid: format!("import:{}:{}", source, id),
+                text: row.get(6)?,
+                date,
+                date_formatted: Utc
+                    .timestamp_opt(date, 0)
+                    .single()
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                is_from_me,
+                handle_id: 0,
+                contact_identifier: conversation.clone().or_else(|| sender.clone()).unwrap_or_default(),
+                sender_name: if is_from_me {
+                    "Me".to_string()
+                } else {
+                    sender.or(conversation).unwrap_or_else(|| "Unknown".to_string())
+                },
+                chat_id: None,
+                has_attachment: false,
+                attachments: Vec::new(),
+                reactions: Vec::new(),
+                delivery: Default::default(),
+            })
+        })
+        .map_err(|e| format!("Store error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut attach_stmt = conn
+        .prepare("SELECT filename, mime_type, total_bytes FROM imported_attachments WHERE message_id = ?")
+        .map_err(|e| format!("Store error: {}", e))?;
+    for msg in &mut messages {
+        msg.attachments = attach_stmt
+            .query_map([-msg.id], |row| {
+                Ok(Attachment {
+                    filename: row.get(0)?,
+                    mime_type: row.get(1)?,
+                    transfer_name: None,
+                    total_bytes: row.get(2)?,
+                    video: None,
+                })
+            })
+            .map_err(|e| format!("Store error: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        msg.has_attachment = !msg.attachments.is_empty();
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping() -> ColumnMapping {
+        ColumnMapping {
+            date: "Timestamp".to_string(),
+            text: "Body".to_string(),
+            sender: Some("From".to_string()),
+            is_from_me: Some("Direction".to_string()),
+            conversation: Some("Thread".to_string()),
+            date_format: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn guesses_common_date_formats() {
+        assert_eq!(parse_date("1700000000", None), Some(1_700_000_000));
+        assert_eq!(parse_date("1700000000123", None), Some(1_700_000_000));
+        assert_eq!(parse_date("2023-11-14T22:13:20+00:00", None), Some(1_700_000_000));
+        assert_eq!(parse_date("2023-11-14 22:13:20", None), Some(1_700_000_000));
+        assert_eq!(parse_date("11/14/2023 22:13", None), Some(1_699_999_980));
+        assert_eq!(parse_date("14.11.2023 22:13", Some("%d.%m.%Y %H:%M")), Some(1_699_999_980));
+        assert_eq!(parse_date("yesterday", None), None);
+    }
+
+    #[test]
+    fn maps_columns_case_insensitively() {
+        let csv = "timestamp, body ,FROM,Direction,Thread\n\
+                   1700000000,Hello,Ana,incoming,Family\n\
+                   2023-11-14 22:14:00,Hi back,,Outgoing,Family\n\
+                   whenever,Lost,Ben,incoming,Family\n\
+                   1700000100,,Ben,no,\n";
+        let (messages, errors) = read_csv(csv.as_bytes(), &mapping()).unwrap();
+        assert_eq!(errors, vec!["Line 4: unrecognized date".to_string()]);
+        assert_eq!(messages.len(), 3);
+
+        assert_eq!(messages[0].sender.as_deref(), Some("Ana"));
+        assert_eq!(messages[0].text.as_deref(), Some("Hello"));
+        assert!(!messages[0].is_from_me);
+        assert!(messages[1].is_from_me);
+        assert_eq!(messages[1].sender, None);
+        assert_eq!(messages[1].date, 1_700_000_040);
+        assert_eq!(messages[2].text, None);
+        assert_eq!(messages[2].conversation, None);
+    }
+
+    #[test]
+    fn reports_missing_columns() {
+        let mut mapping = mapping();
+        mapping.sender = Some("Author".to_string());
+        let err = read_csv("Timestamp,Body\n1,x\n".as_bytes(), &mapping).unwrap_err();
+        assert_eq!(err, "Column 'Author' not found");
+    }
+}
@@ -0,0 +1,120 @@
+use crate::tasks::{TaskKind, TaskRegistry};
+use crate::{archive, chatdb, imports, sms_backup, vcard, whatsapp};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IngestReport {
+    pub path: String,
+    pub kind: String,     // "chat.db", "whatsapp", "sms-backup", "vcard" or "unknown"
+    pub success: bool,
+    pub message: String,
+}
+
+fn kind_for(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .as_deref()
+    {
+        Some("db") => "chat.db",
+        Some("txt") => "whatsapp",
+        Some("xml") => "sms-backup",
+        Some("vcf") => "vcard",
+        _ => "unknown",
+    }
+}
+
+fn describe(result: &imports::ImportResult) -> String {
+    format!("Imported {} messages ({} already present)", result.imported, result.skipped)
+}
+
+/// Chat GUIDs by ROWID
+fn chat_guids(conn: &Connection) -> Result<HashMap<i64, String>, String> {
+    let mut stmt = conn.prepare("SELECT ROWID, guid FROM chat").map_err(|e| format!("Query error: {}", e))?;
+    let guids = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(guids)
+}
+
+/// Handle ROWIDs by phone number/email
+fn handle_ids(conn: &Connection) -> Result<HashMap<String, i64>, String> {
+    let mut stmt = conn.prepare("SELECT id, ROWID FROM handle").map_err(|e| format!("Query error: {}", e))?;
+    let ids = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(ids)
+}
+
+/// Stable id for a chat this Mac never had: negative, so it can't collide with a chat.db ROWID
+fn copied_chat_id(guid: &str) -> i64 {
+    let digest = Sha256::digest(guid.as_bytes());
+    let bits = digest[..6].iter().fold(0i64, |acc, b| (acc << 8) | *b as i64);
+    -(bits + 1)
+}
+
+/// Copy the messages of another chat.db (old Mac, backup) into the archive. Its ROWIDs mean
+/// nothing here, so chats are matched to this Mac's by GUID and handles by phone number/email.
+fn ingest_chat_db(path: &Path) -> Result<String, String> {
+    let mut messages = crate::query_messages(path, None, None)?;
+    let copied_chats = chat_guids(&chatdb::connect(path)?)?;
+    let live = crate::get_imessage_db_path().and_then(|p| chatdb::connect(&p).ok());
+    let live_chats: HashMap<String, i64> = match &live {
+        Some(conn) => chat_guids(conn)?.into_iter().map(|(id, guid)| (guid, id)).collect(),
+        None => HashMap::new(),
+    };
+    let live_handles = match &live {
+        Some(conn) => handle_ids(conn)?,
+        None => HashMap::new(),
+    };
+
+    for msg in &mut messages {
+        msg.chat_id = msg
+            .chat_id
+            .and_then(|id| copied_chats.get(&id))
+            .map(|guid| live_chats.get(guid).copied().unwrap_or_else(|| copied_chat_id(guid)));
+        // Unknown handles keep their identifier; 0 is chat.db's own "no handle"
+        msg.handle_id = live_handles.get(&msg.contact_identifier).copied().unwrap_or(0);
+    }
+
+    let mut conn = archive::open_archive_db()?;
+    let added = archive::archive_messages(&mut conn, archive::COPIED_SOURCE, &messages)?;
+    Ok(format!("Archived {} new messages out of {}", added, messages.len()))
+}
+
+fn ingest(task_registry: &TaskRegistry, path: &Path, kind: &str) -> Result<String, String> {
+    let task_kind = if kind == "chat.db" { TaskKind::ArchiveSync } else { TaskKind::Import };
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let _task = task_registry.begin(task_kind, format!("Importing {}", name))?;
+    match kind {
+        "chat.db" => ingest_chat_db(path),
+        "whatsapp" => whatsapp::import_whatsapp_chat(path).map(|r| describe(&r)),
+        "sms-backup" => sms_backup::import_sms_backup(path).map(|r| describe(&r)),
+        "vcard" => vcard::import_vcard(path)
+            .map(|r| format!("Imported {} contacts ({} phone numbers/emails)", r.contacts, r.identifiers)),
+        _ => Err("Unsupported file type; drop a .db, .txt, .xml or .vcf file".to_string()),
+    }
+}
+
+/// Route a dropped file to the matching importer and report how it went
+pub fn ingest_file(task_registry: &TaskRegistry, path: &Path) -> IngestReport {
+    let kind = kind_for(path);
+    let (success, message) = match ingest(task_registry, path, kind) {
+        Ok(m) => (true, m),
+        Err(e) => (false, e),
+    };
+    IngestReport {
+        path: path.to_string_lossy().to_string(),
+        kind: kind.to_string(),
+        success,
+        message,
+    }
+}
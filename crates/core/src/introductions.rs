@@ -0,0 +1,172 @@
+use crate::chatdb::ChatDbSchema;
+use crate::{lookup_contact_name, Chat};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntroducerBasis {
+    AddedToGroup,         // A membership event shows who added them
+    EarliestKnownMember,  // No event; the member I'd known longest is credited
+    Me,                   // I added them myself
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Person {
+    pub contact_id: i64,
+    pub identifier: String,
+    pub name: String,
+}
+
+/// A contact I first met in a group chat rather than one-on-one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Introduction {
+    pub person: Person,
+    pub chat_id: i64,
+    pub chat_title: String,
+    pub first_seen: i64,                     // Unix timestamp they joined or first spoke in the group
+    pub introduced_by: Option<Person>,
+    pub basis: IntroducerBasis,
+    pub first_direct_message: Option<i64>,   // First 1:1 message with them, either way
+    pub days_until_direct: Option<i64>,
+}
+
+/// First (chat, handle) appearances: first message (in direct chats, mine count too since they
+/// carry the other person's handle) and, where recorded, the "added to group" event
+struct Chronology {
+    first_message: HashMap<(i64, i64), i64>,
+    added: HashMap<(i64, i64), (i64, i64)>,  // (chat, handle) -> (date, adder handle; 0 = me)
+}
+
+fn load_chronology(conn: &Connection, schema: &ChatDbSchema) -> Result<Chronology, String> {
+    let date = schema.unix_seconds_sql("m.date");
+    let query = format!(
+        "SELECT cmj.chat_id, m.handle_id, MIN({date})
+         FROM message m
+         JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         WHERE m.handle_id != 0 AND m.date > 0 AND {content}
+         GROUP BY cmj.chat_id, m.handle_id",
+        date = date,
+        content = schema.content_filter(),
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let first_message = stmt
+        .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut added: HashMap<(i64, i64), (i64, i64)> = HashMap::new();
+    if schema.has("item_type") && schema.has("group_action_type") && schema.has("other_handle") {
+        // item_type 1 / group_action_type 0 is "X added Y to the conversation"
+        let query = format!(
+            "SELECT cmj.chat_id, m.other_handle, {date}, CASE WHEN m.is_from_me = 1 THEN 0 ELSE m.handle_id END
+             FROM message m
+             JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+             WHERE m.item_type = 1 AND m.group_action_type = 0 AND m.other_handle != 0 AND m.date > 0
+             ORDER BY m.date",
+            date = date,
+        );
+        let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+        let rows: Vec<(i64, i64, i64, i64)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(|e| format!("Query error: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        for (chat_id, handle, when, adder) in rows {
+            added.entry((chat_id, handle)).or_insert((when, adder));
+        }
+    }
+    Ok(Chronology { first_message, added })
+}
+
+fn handle_identifiers(conn: &Connection) -> Result<HashMap<i64, String>, String> {
+    let mut stmt = conn.prepare("SELECT ROWID, id FROM handle").map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+/// Who introduced whom: for every contact whose first appearance was in a group chat, the
+/// group, when they showed up, who brought them in, and when we first talked one-on-one
+pub fn introductions(
+    conn: &Connection,
+    schema: &ChatDbSchema,
+    chats: &[Chat],
+    contacts: &HashMap<String, String>,
+) -> Result<Vec<Introduction>, String> {
+    let chronology = load_chronology(conn, schema)?;
+    let identifiers = handle_identifiers(conn)?;
+    let chat_info: HashMap<i64, &Chat> = chats.iter().map(|c| (c.id, c)).collect();
+    let is_group = |chat_id: &i64| chat_info.get(chat_id).is_some_and(|c| c.is_group);
+
+    // When each handle entered each chat: joined or first spoke, whichever came first
+    let mut appearances: HashMap<(i64, i64), i64> = chronology.first_message.clone();
+    for (&key, &(when, _)) in &chronology.added {
+        let entry = appearances.entry(key).or_insert(when);
+        *entry = (*entry).min(when);
+    }
+
+    let mut first_group: HashMap<i64, (i64, i64)> = HashMap::new();   // handle -> (date, chat)
+    let mut first_direct: HashMap<i64, i64> = HashMap::new();         // handle -> date
+    let mut first_anywhere: HashMap<i64, i64> = HashMap::new();
+    for (&(chat_id, handle), &when) in &appearances {
+        let anywhere = first_anywhere.entry(handle).or_insert(when);
+        *anywhere = (*anywhere).min(when);
+        if is_group(&chat_id) {
+            let entry = first_group.entry(handle).or_insert((when, chat_id));
+            if (when, chat_id) < *entry {
+                *entry = (when, chat_id);
+            }
+        } else {
+            let entry = first_direct.entry(handle).or_insert(when);
+            *entry = (*entry).min(when);
+        }
+    }
+
+    let person = |handle: i64| {
+        let identifier = identifiers.get(&handle).cloned().unwrap_or_default();
+        Person {
+            contact_id: handle,
+            name: lookup_contact_name(&identifier, contacts).unwrap_or_else(|| identifier.clone()),
+            identifier,
+        }
+    };
+
+    let mut results: Vec<Introduction> = first_group
+        .iter()
+        .filter(|(handle, (when, _))| first_direct.get(handle).map_or(true, |direct| direct > when))
+        .map(|(&handle, &(first_seen, chat_id))| {
+            let (introduced_by, basis) = match chronology.added.get(&(chat_id, handle)) {
+                Some(&(_, 0)) => (None, IntroducerBasis::Me),
+                Some(&(_, adder)) => (Some(person(adder)), IntroducerBasis::AddedToGroup),
+                None => {
+                    // Members present before them, credited to the one I've known longest
+                    let known = appearances
+                        .iter()
+                        .filter(|(&(c, h), &w)| c == chat_id && h != handle && w < first_seen)
+                        .map(|(&(_, h), _)| h)
+                        .min_by_key(|h| (first_anywhere.get(h).copied().unwrap_or(i64::MAX), *h));
+                    (known.map(person), IntroducerBasis::EarliestKnownMember)
+                }
+            };
+            let first_direct_message = first_direct.get(&handle).copied();
+            Introduction {
+                person: person(handle),
+                chat_id,
+                chat_title: chat_info.get(&chat_id).map(|c| crate::chat_title(c)).unwrap_or_default(),
+                first_seen,
+                introduced_by,
+                basis,
+                days_until_direct: first_direct_message.map(|d| (d - first_seen) / 86400),
+                first_direct_message,
+            }
+        })
+        .collect();
+    results.sort_by_key(|i| (i.first_seen, i.person.contact_id));
+    Ok(results)
+}
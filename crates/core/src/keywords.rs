@@ -0,0 +1,231 @@
+use crate::analytics::messages_by_chat;
+use crate::settings::{to_local_time, AppSettings};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A named group of words or phrases tracked together, e.g. "gym": ["gym", "workout", "leg day"]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeywordCategory {
+    pub name: String,
+    pub keywords: Vec<String>,  // Matched case-insensitively as whole words or phrases
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CategorySeries {
+    pub chat_id: i64,
+    pub category: String,
+    pub total: i64,                   // Messages mentioning the category
+    pub monthly: Vec<(String, i64)>,  // Every month of the chat (YYYY-MM), including zeros
+}
+
+pub fn validate(category: &KeywordCategory) -> Result<(), String> {
+    if category.name.trim().is_empty() {
+        return Err("Keyword category name cannot be empty".to_string());
+    }
+    if category.keywords.iter().all(|k| k.trim().is_empty()) {
+        return Err(format!("Keyword category '{}' has no keywords", category.name));
+    }
+    Ok(())
+}
+
+/// Whether `phrase` occurs in `text` as whole words. Both must already be lowercase.
+pub fn contains_phrase(text: &str, phrase: &str) -> bool {
+    if phrase.is_empty() {
+        return false;
+    }
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Lowercased, trimmed keywords with blanks dropped
+pub fn normalize_keywords(keywords: &[String]) -> Vec<String> {
+    keywords
+        .iter()
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+/// Months from `first` to `last` inclusive, as YYYY-MM
+pub fn month_range(first: &str, last: &str) -> Vec<String> {
+    let parse = |m: &str| -> Option<(i32, u32)> {
+        let (y, mo) = m.split_once('-')?;
+        Some((y.parse().ok()?, mo.parse().ok()?))
+    };
+    let (Some((mut year, mut month)), Some(end)) = (parse(first), parse(last)) else {
+        return Vec::new();
+    };
+    let mut months = Vec::new();
+    while (year, month) <= end {
+        months.push(format!("{:04}-{:02}", year, month));
+        month += 1;
+        if month > 12 {
+            month = 1;
+            year += 1;
+        }
+    }
+    months
+}
+
+/// Count, per chat and month, the messages mentioning each category
+pub fn category_trends(
+    messages: &[Message],
+    categories: &[KeywordCategory],
+    settings: &AppSettings,
+) -> Vec<CategorySeries> {
+    let categories: Vec<(&str, Vec<String>)> = categories
+        .iter()
+        .map(|c| (c.name.as_str(), normalize_keywords(&c.keywords)))
+        .collect();
+
+    let mut results = Vec::new();
+    for (chat_id, chat_messages) in messages_by_chat(messages) {
+        let month_of = |m: &Message| to_local_time(m.date, settings).map(|dt| dt.format("%Y-%m").to_string());
+        // Messages are sorted, so the first and last bound the chat's months
+        let months = match (
+            chat_messages.first().and_then(|m| month_of(m)),
+            chat_messages.last().and_then(|m| month_of(m)),
+        ) {
+            (Some(first), Some(last)) => month_range(&first, &last),
+            _ => continue,
+        };
+
+        for (name, keywords) in &categories {
+            let mut monthly: BTreeMap<String, i64> = months.iter().map(|m| (m.clone(), 0)).collect();
+            let mut total = 0;
+            for msg in &chat_messages {
+                let Some(text) = msg.text.as_deref() else { continue };
+                let text = text.to_lowercase();
+                if keywords.iter().any(|k| contains_phrase(&text, k)) {
+                    total += 1;
+                    if let Some(month) = month_of(msg) {
+                        *monthly.entry(month).or_insert(0) += 1;
+                    }
+                }
+            }
+            if total > 0 {
+                results.push(CategorySeries {
+                    chat_id,
+                    category: name.to_string(),
+                    total,
+                    monthly: monthly.into_iter().collect(),
+                });
+            }
+        }
+    }
+
+    results.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.chat_id.cmp(&b.chat_id)));
+    results
+}
+
+/// Built-in phrase categories used until the user saves their own
+pub fn default_phrase_categories() -> Vec<KeywordCategory> {
+    let category = |name: &str, phrases: &[&str]| KeywordCategory {
+        name: name.to_string(),
+        keywords: phrases.iter().map(|p| p.to_string()).collect(),
+    };
+    vec![
+        category("apology", &["sorry", "soz", "sry", "my bad", "apologies", "i apologize", "forgive me"]),
+        category("thanks", &["thanks", "thank you", "thank u", "thx", "ty", "tysm", "appreciate it", "grateful"]),
+        category("affection", &["love you", "love u", "luv you", "ily", "miss you", "miss u", "xoxo", "<3", "❤️"]),
+    ]
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SenderPhraseCount {
+    pub sender: String,               // "me" or the sender's identifier
+    pub sender_name: String,
+    pub total: i64,                   // Messages containing a phrase from the category
+    pub per_100_messages: f64,        // Normalized by how much this sender writes in the chat
+    pub monthly: Vec<(String, i64)>,  // Every month of the chat (YYYY-MM), including zeros
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhraseCategoryCounts {
+    pub chat_id: i64,
+    pub category: String,
+    pub senders: Vec<SenderPhraseCount>,  // Highest rate first: who apologizes (or thanks) more
+}
+
+/// Count, per chat and sender, the messages containing each phrase category, with a monthly series
+pub fn phrase_counts(
+    messages: &[Message],
+    categories: &[KeywordCategory],
+    settings: &AppSettings,
+) -> Vec<PhraseCategoryCounts> {
+    let categories: Vec<(&str, Vec<String>)> = categories
+        .iter()
+        .map(|c| (c.name.as_str(), normalize_keywords(&c.keywords)))
+        .collect();
+
+    let mut results = Vec::new();
+    for (chat_id, chat_messages) in messages_by_chat(messages) {
+        let month_of = |m: &Message| to_local_time(m.date, settings).map(|dt| dt.format("%Y-%m").to_string());
+        let months = match (
+            chat_messages.first().and_then(|m| month_of(m)),
+            chat_messages.last().and_then(|m| month_of(m)),
+        ) {
+            (Some(first), Some(last)) => month_range(&first, &last),
+            _ => continue,
+        };
+        let sender_of = |m: &Message| if m.is_from_me { "me".to_string() } else { m.contact_identifier.clone() };
+
+        let mut written: BTreeMap<String, (String, i64)> = BTreeMap::new();  // sender -> (name, messages)
+        for msg in &chat_messages {
+            written.entry(sender_of(msg)).or_insert_with(|| (msg.sender_name.clone(), 0)).1 += 1;
+        }
+
+        for (name, phrases) in &categories {
+            let mut by_sender: BTreeMap<String, (i64, BTreeMap<String, i64>)> = BTreeMap::new();
+            for msg in &chat_messages {
+                let Some(text) = msg.text.as_deref() else { continue };
+                let text = text.to_lowercase();
+                if !phrases.iter().any(|p| contains_phrase(&text, p)) {
+                    continue;
+                }
+                let (total, monthly) = by_sender
+                    .entry(sender_of(msg))
+                    .or_insert_with(|| (0, months.iter().map(|m| (m.clone(), 0)).collect()));
+                *total += 1;
+                if let Some(month) = month_of(msg) {
+                    *monthly.entry(month).or_insert(0) += 1;
+                }
+            }
+            if by_sender.is_empty() {
+                continue;
+            }
+
+            let mut senders: Vec<SenderPhraseCount> = by_sender
+                .into_iter()
+                .map(|(sender, (total, monthly))| {
+                    let (sender_name, sent) = written.get(&sender).cloned().unwrap_or_default();
+                    SenderPhraseCount {
+                        sender,
+                        sender_name,
+                        total,
+                        per_100_messages: if sent > 0 { total as f64 * 100.0 / sent as f64 } else { 0.0 },
+                        monthly: monthly.into_iter().collect(),
+                    }
+                })
+                .collect();
+            senders.sort_by(|a, b| {
+                b.per_100_messages
+                    .partial_cmp(&a.per_100_messages)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.sender.cmp(&b.sender))
+            });
+            results.push(PhraseCategoryCounts {
+                chat_id,
+                category: name.to_string(),
+                senders,
+            });
+        }
+    }
+
+    results.sort_by_key(|r| r.chat_id);
+    results
+}
@@ -0,0 +1,171 @@
+use crate::analytics::{top_of_counts, Tokenizer, STOP_WORDS};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+// Shorter texts ("ok", "lol", "😂") don't carry enough signal to guess a language
+const MIN_DETECT_CHARS: usize = 12;
+
+const SPANISH_STOP_WORDS: &[&str] = &[
+    "de", "la", "que", "el", "en", "y", "a", "los", "se", "del", "las", "un", "por", "con", "no",
+    "una", "su", "para", "es", "al", "lo", "como", "más", "pero", "sus", "le", "ya", "o", "este",
+    "sí", "porque", "esta", "entre", "cuando", "muy", "sin", "sobre", "también", "me", "hasta",
+    "hay", "donde", "quien", "desde", "todo", "nos", "durante", "todos", "uno", "les", "ni",
+    "contra", "otros", "ese", "eso", "ante", "ellos", "e", "esto", "mí", "antes", "algunos",
+    "qué", "unos", "yo", "otro", "otras", "otra", "él", "tanto", "esa", "estos", "mucho",
+    "quienes", "nada", "muchos", "cual", "poco", "ella", "estar", "estas", "algunas", "algo",
+    "nosotros", "mi", "mis", "tú", "te", "ti", "tu", "tus", "ellas", "vosotros", "está", "estoy",
+    "son", "fue", "era", "bien", "pues", "jaja", "jajaja",
+];
+
+const FRENCH_STOP_WORDS: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et", "eux", "il",
+    "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "même", "mes", "moi", "mon", "ne",
+    "nos", "notre", "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui", "sa", "se",
+    "ses", "son", "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre",
+    "vous", "c'est", "est", "suis", "es", "sont", "était", "été", "être", "avoir", "ai", "as",
+    "a", "ont", "fait", "bien", "très", "oui", "non", "ça", "cette", "tout", "plus", "mdr",
+];
+
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "aber", "alle", "als", "also", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "bist",
+    "da", "dann", "das", "dass", "dein", "dem", "den", "der", "des", "die", "dir", "doch", "du",
+    "ein", "eine", "einen", "einem", "einer", "er", "es", "für", "hab", "habe", "hast", "hat",
+    "ich", "ihr", "im", "in", "ist", "ja", "jetzt", "kann", "mal", "man", "mein", "mich", "mir",
+    "mit", "nach", "nicht", "noch", "nur", "oder", "schon", "sein", "sich", "sie", "sind", "so",
+    "um", "und", "uns", "von", "war", "was", "wenn", "wie", "wir", "wird", "zu", "zum", "zur",
+];
+
+const PORTUGUESE_STOP_WORDS: &[&str] = &[
+    "a", "ao", "aos", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "ela", "ele",
+    "em", "entre", "era", "essa", "esse", "esta", "está", "estou", "eu", "foi", "isso", "já",
+    "lhe", "mais", "mas", "me", "meu", "minha", "muito", "na", "não", "nas", "no", "nos", "o",
+    "os", "ou", "para", "pela", "pelo", "por", "que", "se", "sem", "seu", "sua", "também", "te",
+    "tem", "tu", "um", "uma", "você", "vc", "sim", "tá", "kkk", "kkkk",
+];
+
+const ITALIAN_STOP_WORDS: &[&str] = &[
+    "a", "ad", "al", "alla", "anche", "che", "chi", "ci", "come", "con", "da", "dal", "del",
+    "della", "di", "e", "è", "gli", "ha", "hai", "ho", "i", "il", "in", "io", "la", "le", "lo",
+    "ma", "mi", "mio", "ne", "nel", "no", "non", "per", "più", "quello", "questo", "se", "si",
+    "sono", "su", "sei", "ti", "tu", "tutto", "un", "una", "uno", "va", "bene", "sì",
+];
+
+/// Stop words for an ISO 639-3 language code; English for anything without its own list
+pub fn stop_words(language: Option<&str>) -> &'static [&'static str] {
+    match language {
+        Some("spa") => SPANISH_STOP_WORDS,
+        Some("fra") => FRENCH_STOP_WORDS,
+        Some("deu") => GERMAN_STOP_WORDS,
+        Some("por") => PORTUGUESE_STOP_WORDS,
+        Some("ita") => ITALIAN_STOP_WORDS,
+        _ => STOP_WORDS,
+    }
+}
+
+/// ISO 639-3 code of a message's language, when the text is long enough to tell reliably
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_DETECT_CHARS {
+        return None;
+    }
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code())
+}
+
+fn language_name(code: &str) -> String {
+    whatlang::Lang::from_code(code)
+        .map(|lang| lang.eng_name().to_string())
+        .unwrap_or_else(|| code.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LanguageShare {
+    pub language: String,  // ISO 639-3 code, e.g. "spa"
+    pub name: String,      // English name, e.g. "Spanish"
+    pub messages: i64,
+    pub percent: f64,      // Share of this chat's messages with a detected language
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatLanguages {
+    pub chat_id: i64,
+    pub detected_messages: i64,
+    pub undetected_messages: i64,  // Too short or too mixed to classify
+    pub languages: Vec<LanguageShare>,
+}
+
+/// Per-chat language mix, most active chats first
+pub fn language_report<M: Borrow<Message>>(messages: impl IntoIterator<Item = M>) -> Vec<ChatLanguages> {
+    // chat id -> (messages per language, undetected messages)
+    let mut by_chat: HashMap<i64, (HashMap<&'static str, i64>, i64)> = HashMap::new();
+    for msg in messages {
+        let msg: &Message = msg.borrow();
+        let (counts, undetected) = by_chat.entry(msg.chat_id.unwrap_or(0)).or_default();
+        match msg.text.as_deref().and_then(detect_language) {
+            Some(code) => *counts.entry(code).or_insert(0) += 1,
+            None => *undetected += 1,
+        }
+    }
+
+    let mut results: Vec<ChatLanguages> = by_chat
+        .into_iter()
+        .map(|(chat_id, (counts, undetected))| {
+            let detected: i64 = counts.values().sum();
+            let mut languages: Vec<LanguageShare> = counts
+                .into_iter()
+                .map(|(code, n)| LanguageShare {
+                    language: code.to_string(),
+                    name: language_name(code),
+                    messages: n,
+                    percent: n as f64 * 100.0 / detected as f64,
+                })
+                .collect();
+            languages.sort_by(|a, b| b.messages.cmp(&a.messages).then_with(|| a.language.cmp(&b.language)));
+
+            ChatLanguages {
+                chat_id,
+                detected_messages: detected,
+                undetected_messages: undetected,
+                languages,
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        (b.detected_messages + b.undetected_messages).cmp(&(a.detected_messages + a.undetected_messages))
+    });
+    results
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WordCount {
+    pub word: String,
+    pub count: i64,
+}
+
+/// Every word's count, filtering each message with the stop words of its own language
+pub fn word_counts(messages: &[Message], tokenizer: &Tokenizer) -> HashMap<String, i64> {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for text in messages.iter().filter_map(|m| m.text.as_deref()) {
+        for word in tokenizer.tokenize(text, stop_words(detect_language(text))) {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Merge per-chat word counts into the top `limit` words
+pub fn merge_word_counts<I: IntoIterator<Item = HashMap<String, i64>>>(parts: I, limit: usize) -> Vec<WordCount> {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for part in parts {
+        for (word, count) in part {
+            *counts.entry(word).or_insert(0) += count;
+        }
+    }
+    top_of_counts(counts, limit)
+        .into_iter()
+        .map(|(word, count)| WordCount { word, count })
+        .collect()
+}
@@ -0,0 +1,3956 @@
+use chrono::{TimeZone, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub mod addressbook;
+pub mod aliases;
+pub mod analytics;
+pub mod anonymize;
+pub mod api;
+pub mod archetypes;
+pub mod archive;
+pub mod attachment_export;
+pub mod automation;
+pub mod cache;
+pub mod chatdb;
+#[cfg(target_os = "macos")]
+pub mod contacts_framework;
+pub mod conversations;
+pub mod corrections;
+pub mod digest;
+pub mod duplicates;
+pub mod encryption;
+pub mod export;
+pub mod export_history;
+pub mod filenames;
+pub mod fixtures;
+pub mod forensic;
+pub mod gifs;
+pub mod histogram;
+pub mod imports;
+pub mod ingest;
+pub mod introductions;
+pub mod keywords;
+pub mod language;
+pub mod logging;
+pub mod mbox;
+pub mod media;
+pub mod media_filter;
+pub mod media_trends;
+pub mod metrics;
+pub mod notes;
+pub mod obsidian;
+pub mod ocr;
+pub mod parallel;
+pub mod permissions;
+pub mod pins;
+pub mod privacy;
+pub mod ranking;
+pub mod scheduler;
+pub mod search;
+pub mod secrets;
+pub mod settings;
+pub mod social;
+pub mod split;
+pub mod sms_backup;
+pub mod snapshots;
+pub mod storage;
+pub mod store;
+pub mod style;
+pub mod tags;
+pub mod tasks;
+pub mod templates;
+#[doc(hidden)]
+pub mod test_support;
+pub mod trends;
+pub mod triggers;
+pub mod vcard;
+pub mod vocabulary;
+pub mod webhook;
+pub mod whatsapp;
+
+// Mac Absolute Time epoch: 2001-01-01 00:00:00 UTC
+const MAC_EPOCH_OFFSET: i64 = 978307200;
+
+/// Convert macOS timestamp (nanoseconds since 2001-01-01, or seconds before High Sierra)
+/// to Unix timestamp
+fn mac_timestamp_to_unix(mac_ts: i64) -> i64 {
+    let seconds = mac_ts / chatdb::TimestampUnit::of(mac_ts).per_second();
+    seconds + MAC_EPOCH_OFFSET
+}
+
+/// Get the path to the iMessage database (the generated one in demo mode)
+pub fn get_imessage_db_path() -> Option<PathBuf> {
+    if fixtures::is_demo_mode() {
+        return fixtures::demo_chat_db();
+    }
+    dirs::home_dir().map(|home| home.join("Library/Messages/chat.db"))
+}
+
+/// Get the column names of a table, used to adapt queries to older or newer chat.db layouts
+fn table_columns(conn: &Connection, table: &str) -> std::collections::HashSet<String> {
+    conn.prepare(&format!("PRAGMA table_info({})", table))
+        .ok()
+        .map(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(1))
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the directory for app-owned data (caches, indexes, settings)
+fn get_app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("com.messageinsights.app"))
+}
+
+/// Where settings and the store, archive and cache databases live. Demo mode keeps its own set
+/// next to the generated chat.db, so nothing done while exploring reaches the real ones.
+fn get_data_dir() -> Option<PathBuf> {
+    if fixtures::is_demo_mode() {
+        return fixtures::demo_dir();
+    }
+    get_app_data_dir()
+}
+
+/// Get ALL paths to AddressBook databases (iCloud, local, Exchange, etc.)
+fn get_all_addressbook_db_paths() -> Vec<PathBuf> {
+    if fixtures::is_demo_mode() {
+        return fixtures::demo_addressbook_db().into_iter().collect();
+    }
+    addressbook::find_databases()
+}
+
+/// Normalize phone number for comparison (remove formatting)
+fn normalize_phone(phone: &str) -> String {
+    phone.chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .chars()
+        .rev()
+        .take(10) // Last 10 digits
+        .collect::<String>()
+        .chars()
+        .rev()
+        .collect()
+}
+
+/// Expand a leading ~ in an attachment path to the actual home directory
+fn expand_home_path(path: String) -> String {
+    if path.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return path.replacen("~", &home.to_string_lossy(), 1);
+        }
+    }
+    path
+}
+
+/// Check if text looks like a UUID (attachment reference)
+fn is_uuid_like(text: &str) -> bool {
+    let trimmed = text.trim();
+    // UUID format: 8-4-4-4-12 hex characters with dashes
+    // Also match without dashes or with newlines
+    let clean: String = trimmed.chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect();
+
+    // A UUID has exactly 32 hex characters
+    // Allow some variance for partial UUIDs or UUIDs with extra chars
+    if clean.len() >= 32 && clean.len() <= 40 {
+        // Check if most of the original string was hex + dashes/whitespace
+        let valid_chars = trimmed.chars()
+            .filter(|c| c.is_ascii_hexdigit() || *c == '-' || c.is_whitespace())
+            .count();
+        return valid_chars as f32 / trimmed.len() as f32 > 0.9;
+    }
+    false
+}
+
+/// Map a phone number to a name under the raw, normalized and +1-prefixed forms handles use
+fn insert_phone_name(names: &mut HashMap<String, String>, phone: String, name: String) {
+    // Store both normalized and original
+    let normalized = normalize_phone(&phone);
+    if !normalized.is_empty() {
+        names.insert(normalized.clone(), name.clone());
+        // Also store with +1 prefix variations
+        names.insert(format!("+1{}", normalized), name.clone());
+    }
+    names.insert(phone, name);
+}
+
+/// Read names from one AddressBook database; false when it can't be read (e.g. no Full Disk Access
+/// or a schema version we don't recognize)
+fn read_contacts_from_db(db_path: &Path, names: &mut HashMap<String, String>) -> bool {
+    let (conn, layout) = match addressbook::open(db_path) {
+        Ok(opened) => opened,
+        Err(e) => {
+            tracing::warn!("Skipping AddressBook {}: {}", db_path.display(), e);
+            return false;
+        }
+    };
+
+    for (table, column) in layout.lookups() {
+        let query = format!(
+            "SELECT r.ZFIRSTNAME, r.ZLASTNAME, v.{column}
+             FROM ZABCDRECORD r
+             JOIN {table} v ON r.Z_PK = v.ZOWNER
+             WHERE v.{column} IS NOT NULL",
+            table = table,
+            column = column
+        );
+        let rows: Vec<(Option<String>, Option<String>, String)> = conn
+            .prepare(&query)
+            .ok()
+            .map(|mut stmt| {
+                stmt.query_map([], |row| {
+                    let first: Option<String> = row.get(0).ok();
+                    let last: Option<String> = row.get(1).ok();
+                    let identifier: String = row.get(2)?;
+                    Ok((first, last, identifier))
+                })
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        for (first, last, identifier) in rows {
+            let name = match (first, last) {
+                (Some(f), Some(l)) => format!("{} {}", f, l),
+                (Some(f), None) => f,
+                (None, Some(l)) => l,
+                (None, None) => continue,
+            };
+            if (table, column) == layout.email {
+                names.insert(identifier.to_lowercase(), name);
+            } else {
+                insert_phone_name(names, identifier, name);
+            }
+        }
+    }
+    true
+}
+
+/// Get contact name mappings from ALL AddressBook databases
+fn get_contact_names() -> HashMap<String, String> {
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    let db_paths = get_all_addressbook_db_paths();
+
+    // Read from ALL AddressBook databases (iCloud, local, Exchange, etc.)
+    let mut any_readable = false;
+    for db_path in &db_paths {
+        any_readable |= read_contacts_from_db(db_path, &mut names);
+    }
+
+    // Without access to the database files, use the Contacts framework if the user granted it
+    // through `request_contacts_access`
+    #[cfg(target_os = "macos")]
+    if !any_readable && !fixtures::is_demo_mode() {
+        if let Err(e) = contacts_framework::read_contacts(&mut names) {
+            tracing::warn!("Contacts framework unavailable: {}", e);
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = any_readable;
+
+    // Contacts imported from vCard files fill in handles AddressBook doesn't know
+    vcard::merge_imported_contacts(&mut names);
+
+    // Old numbers the user linked to a current contact resolve to that contact's name
+    aliases::apply_links(&mut names, &aliases::load_handle_links());
+
+    // Handles whose id doesn't match a card, but whose typed form or sibling handles do
+    if let Some((conn, schema)) = get_imessage_db_path().and_then(|path| chatdb::open(&path).ok()) {
+        aliases::apply_handle_fallbacks(&conn, &schema, &mut names);
+    }
+
+    names
+}
+
+/// Convert an AddressBook ZBIRTHDAY (seconds since 2001-01-01) to YYYY-MM-DD.
+/// Birthdays saved without a year use the placeholder year 1604 and are returned as --MM-DD.
+fn format_addressbook_birthday(seconds: f64) -> Option<String> {
+    let unix = seconds.round() as i64 + MAC_EPOCH_OFFSET;
+    let date = Utc.timestamp_opt(unix, 0).single()?.date_naive();
+    if date.format("%Y").to_string() == "1604" {
+        Some(date.format("--%m-%d").to_string())
+    } else {
+        Some(date.format("%Y-%m-%d").to_string())
+    }
+}
+
+/// Read extended record fields (nickname, organization, birthday) from a single AddressBook database
+fn read_contact_details_from_db(db_path: &Path, details: &mut HashMap<String, ContactDetails>) {
+    let (conn, layout) = match addressbook::open(db_path) {
+        Ok(opened) => opened,
+        Err(_) => return,
+    };
+
+    // Identify the source so record keys stay unique across AddressBook databases
+    let source = db_path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Optional record fields read as NULL when this schema version lacks them
+    let record_columns = table_columns(&conn, "ZABCDRECORD");
+    let field = |name: &str| {
+        if record_columns.contains(name) {
+            format!("r.{}", name)
+        } else {
+            "NULL".to_string()
+        }
+    };
+    let fields = ["ZNICKNAME", "ZORGANIZATION", "ZJOBTITLE", "ZBIRTHDAY"].map(field).join(", ");
+
+    for (table, column) in layout.lookups() {
+        let query = format!(
+            "SELECT r.Z_PK, {fields}, v.{column}
+             FROM ZABCDRECORD r
+             JOIN {table} v ON r.Z_PK = v.ZOWNER
+             WHERE v.{column} IS NOT NULL",
+            fields = fields,
+            table = table,
+            column = column
+        );
+
+        let rows: Vec<(ContactDetails, String)> = conn
+            .prepare(&query)
+            .ok()
+            .map(|mut stmt| {
+                stmt.query_map([], |row| {
+                    let pk: i64 = row.get(0)?;
+                    let birthday: Option<f64> = row.get(4).ok().flatten();
+                    Ok((
+                        ContactDetails {
+                            record_key: format!("{}#{}", source, pk),
+                            nickname: row.get(1).ok().flatten(),
+                            organization: row.get(2).ok().flatten(),
+                            job_title: row.get(3).ok().flatten(),
+                            birthday: birthday.and_then(format_addressbook_birthday),
+                        },
+                        row.get::<_, String>(5)?,
+                    ))
+                })
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        for (record, identifier) in rows {
+            if (table, column) == layout.email {
+                details.insert(identifier.to_lowercase(), record);
+                continue;
+            }
+            let normalized = normalize_phone(&identifier);
+            if !normalized.is_empty() {
+                details.insert(normalized.clone(), record.clone());
+                details.insert(format!("+1{}", normalized), record.clone());
+            }
+            details.insert(identifier, record);
+        }
+    }
+}
+
+/// Get extended contact details from ALL AddressBook databases
+fn get_contact_details() -> HashMap<String, ContactDetails> {
+    let mut details: HashMap<String, ContactDetails> = HashMap::new();
+    for db_path in &get_all_addressbook_db_paths() {
+        read_contact_details_from_db(db_path, &mut details);
+    }
+    details
+}
+
+/// Extract text from attributedBody blob (NSKeyedArchiver/typedstream format)
+fn extract_text_from_attributed_body(blob: &[u8]) -> Option<String> {
+    // The attributedBody uses Apple's typedstream format
+    // The actual text is usually stored after a length byte followed by UTF-8 content
+
+    if blob.len() < 50 {
+        return None;
+    }
+
+    let mut best_text = String::new();
+
+    // Scan for length-prefixed UTF-8 strings
+    let mut i = 0;
+    while i < blob.len().saturating_sub(4) {
+        // Look for potential string length byte followed by valid UTF-8
+        let potential_len = blob[i] as usize;
+        if potential_len > 3 && potential_len < 2000 && i + 1 + potential_len <= blob.len() {
+            if let Ok(s) = std::str::from_utf8(&blob[i + 1..i + 1 + potential_len]) {
+                // Check if it looks like real text (not metadata)
+                let has_letter = s.chars().any(|c| c.is_alphabetic());
+                let is_clean = !s.contains("__kIM") &&
+                               !s.contains("NSMutable") &&
+                               !s.contains("NSAttributed") &&
+                               !s.contains("NSObject") &&
+                               !s.contains("NSData") &&
+                               !s.contains("NSKeyedArchiver") &&
+                               !s.contains("$archiver") &&
+                               !s.contains("$class") &&
+                               !s.contains("$version") &&
+                               !s.contains("NSDictionary") &&
+                               !s.contains("NSArray") &&
+                               !s.contains("NSValue") &&
+                               !s.contains("NSNumber") &&
+                               !s.contains("NSString") &&
+                               !s.contains("NS.rangeval") &&
+                               !s.contains("NS.range") &&
+                               !s.contains("NS.special") &&
+                               !s.contains("streamtyped") &&
+                               !s.contains("typedstream") &&
+                               !s.starts_with('+') &&
+                               !s.starts_with("bp:") &&
+                               !s.starts_with("p:") &&
+                               !s.starts_with("com.apple") &&
+                               !is_uuid_like(s) &&
+                               s.chars().all(|c| c >= ' ' || c == '\n' || c == '\r');
+
+                if has_letter && is_clean && s.len() > best_text.len() {
+                    best_text = s.trim().to_string();
+                }
+            }
+        }
+        i += 1;
+    }
+
+    if best_text.is_empty() || best_text.len() < 2 {
+        None
+    } else {
+        Some(best_text)
+    }
+}
+
+/// Decode a message's text, filtering out metadata/binary content and falling back to the
+/// attributedBody blob when the plain text column is empty
+fn decode_message_text(raw_text: Option<String>, attributed_body: Option<&[u8]>) -> Option<String> {
+    // Clean up text - filter out metadata/binary content
+    let mut text = raw_text.and_then(|t| {
+        // Skip if it looks like binary/metadata
+        if t.contains("__kIM") ||
+           t.contains("NSMutable") ||
+           t.contains("NSAttributed") ||
+           t.contains("NSObject") ||
+           t.contains("NSData") ||
+           t.contains("NSKeyedArchiver") ||
+           t.contains("$archiver") ||
+           t.contains("$version") ||
+           t.contains("streamtyped") ||
+           t.contains("NS.rangeval") ||
+           t.contains("NS.range") ||
+           t.contains("NS.special") ||
+           t.contains("NSNumber") ||
+           t.contains("NSString") ||
+           t.contains("NSDictionary") ||
+           t.contains("NSArray") ||
+           t.starts_with("\u{FFFC}") ||  // Object replacement character
+           t.chars().take(10).any(|c| c < ' ' && c != '\n' && c != '\r' && c != '\t') ||
+           // Skip if it's just a UUID (attachment reference)
+           is_uuid_like(&t) {
+            None
+        } else {
+            // Also trim any leading/trailing object replacement characters
+            let cleaned = t.trim_matches('\u{FFFC}').trim();
+            if cleaned.is_empty() {
+                None
+            } else {
+                Some(cleaned.to_string())
+            }
+        }
+    });
+
+    // If text is empty, try to extract from attributedBody
+    if text.is_none() {
+        if let Some(blob) = attributed_body {
+            text = extract_text_from_attributed_body(blob);
+        }
+    }
+
+    text
+}
+
+/// Look up a contact entry by phone/email
+fn lookup_contact<'a, T>(identifier: &str, contacts: &'a HashMap<String, T>) -> Option<&'a T> {
+    // Try direct lookup
+    if let Some(entry) = contacts.get(identifier) {
+        return Some(entry);
+    }
+
+    // Try lowercase for email
+    if let Some(entry) = contacts.get(&identifier.to_lowercase()) {
+        return Some(entry);
+    }
+
+    // Try normalized phone lookup
+    let normalized = normalize_phone(identifier);
+    contacts.get(&normalized)
+}
+
+/// Look up a contact name by phone/email
+fn lookup_contact_name(identifier: &str, contacts: &HashMap<String, String>) -> Option<String> {
+    lookup_contact(identifier, contacts).cloned()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Contact {
+    pub id: i64,
+    pub identifier: String,      // Phone number or email
+    pub display_name: Option<String>,
+    pub message_count: i64,
+    pub name: Option<String>,              // Resolved from AddressBook
+    pub details: Option<ContactDetails>,   // Extended AddressBook fields
+    pub tags: Vec<String>,                 // AddressBook groups and user-defined tags
+    pub note: Option<String>,              // User annotation
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContactDetails {
+    pub record_key: String,            // "<source>#<Z_PK>", shared by all handles of one card
+    pub nickname: Option<String>,
+    pub organization: Option<String>,
+    pub job_title: Option<String>,
+    pub birthday: Option<String>,      // YYYY-MM-DD, or --MM-DD when saved without a year
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Chat {
+    pub id: i64,
+    pub chat_identifier: String,
+    pub display_name: Option<String>,
+    pub is_group: bool,
+    pub participant_count: i64,
+    pub message_count: i64,
+    pub participants: Vec<String>,          // Resolved names
+    pub participant_ids: Vec<String>,       // Raw phone/email identifiers
+    pub tags: Vec<String>,                  // Union of participants' tags
+    pub note: Option<String>,               // User annotation
+    #[serde(default)]
+    pub name_history: Vec<(i64, String)>,   // (Unix timestamp, name) per rename, oldest first; "" = name removed
+    #[serde(default)]
+    pub last_message_date: Option<i64>,     // Unix timestamp of the newest message
+    #[serde(default)]
+    pub is_archived: bool,                  // Archived or deleted in Messages
+    #[serde(default)]
+    pub is_dormant: bool,                   // No activity within the dormancy window
+    #[serde(default)]
+    pub merged_chat_ids: Vec<i64>,          // Other 1:1 chats with the same person folded into this one
+}
+
+/// Which chats the chat list shows; archived and dormant chats are hidden unless asked for
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ChatListFilter {
+    pub include_archived: bool,
+    pub include_dormant: bool,
+}
+
+// A chat goes dormant after a year without messages, or a month for one-off threads
+const DORMANT_AFTER_DAYS: i64 = 365;
+const ONE_OFF_DORMANT_AFTER_DAYS: i64 = 30;
+const ONE_OFF_MAX_MESSAGES: i64 = 3;
+
+/// Whether a chat with these counts has gone quiet as of `now`
+fn is_dormant(message_count: i64, last_message_date: Option<i64>, now: i64) -> bool {
+    let days = if message_count <= ONE_OFF_MAX_MESSAGES { ONE_OFF_DORMANT_AFTER_DAYS } else { DORMANT_AFTER_DAYS };
+    last_message_date.map_or(true, |last| now - last > days * 86400)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Message {
+    pub id: i64,
+    pub guid: String,
+    pub text: Option<String>,
+    pub date: i64,               // Unix timestamp
+    pub date_formatted: String,
+    pub is_from_me: bool,
+    pub handle_id: i64,
+    pub contact_identifier: String,
+    pub sender_name: String,     // Resolved sender name
+    pub chat_id: Option<i64>,
+    pub has_attachment: bool,
+    pub attachments: Vec<Attachment>,
+    pub reactions: Vec<Reaction>,
+    pub delivery: DeliveryMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DeliveryMetadata {
+    pub is_scheduled: bool,              // Sent with "Send Later"
+    pub date_retracted: Option<i64>,     // Unix timestamp of "Undo Send"
+    pub date_edited: Option<i64>,        // Unix timestamp of last edit
+    pub delivered_quietly: bool,         // Delivered without a notification (Focus)
+    pub did_notify_recipient: bool,      // Sender chose "Notify Anyway"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregateBy {
+    Sender,
+    Chat,
+    Day,
+    Month,
+    Hour,
+    Weekday,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AggregateBucket {
+    pub key: String,             // Handle/"me", chat id, YYYY-MM-DD, YYYY-MM, 00-23 or 0-6 (Sunday = 0)
+    pub label: Option<String>,   // Resolved name for sender/chat buckets
+    pub count: i64,
+}
+
+/// Lightweight message row for list views: no attachments, reactions or full text
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageSummary {
+    pub id: i64,
+    pub guid: String,
+    pub date: i64,               // Unix timestamp
+    pub is_from_me: bool,
+    pub sender_name: String,
+    pub chat_id: Option<i64>,
+    pub snippet: Option<String>, // First SNIPPET_CHARS characters of the plain text column
+    pub has_attachment: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    pub transfer_name: Option<String>,
+    pub total_bytes: Option<i64>,
+    pub video: Option<media::VideoMetadata>,        // Duration/resolution for video files
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reaction {
+    pub reaction_type: i64,   // 2000=love, 2001=like, 2002=dislike, 2003=laugh, 2004=emphasis, 2005=question
+    pub sender: String,
+    pub sender_id: String,    // Raw phone/email handle, empty for reactions from me
+    pub is_from_me: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatStats {
+    pub total_messages: i64,
+    pub messages_sent: i64,
+    pub messages_received: i64,
+    pub total_contacts: i64,
+    pub date_range_start: Option<i64>,
+    pub date_range_end: Option<i64>,
+    pub scheduled_messages: i64,
+    pub retracted_messages: i64,
+    pub quiet_deliveries: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaStats {
+    pub attachment_count: i64,
+    pub total_bytes: i64,
+    pub image_count: i64,
+    pub video_count: i64,
+    pub audio_count: i64,
+    pub other_count: i64,
+    pub video_duration_secs: f64,  // Sum over videos whose container could be parsed
+    pub unreadable_videos: i64,    // Missing files or unsupported containers
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoPoint {
+    pub message_id: i64,
+    pub filename: String,
+    pub sent_date: i64,              // Unix timestamp
+    pub captured_date: Option<i64>,  // Unix timestamp from EXIF
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhotoTimeMismatch {
+    pub message_id: i64,
+    pub filename: String,
+    pub sent_date: i64,
+    pub captured_date: i64,
+    pub difference_secs: i64,        // sent - captured; large values are old photos shared later
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhotoMap {
+    pub points: Vec<PhotoPoint>,
+    pub mismatches: Vec<PhotoTimeMismatch>,
+    pub photos_scanned: i64,
+    pub photos_without_exif: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OcrSummary {
+    pub available: bool,  // False when tesseract isn't installed
+    pub scanned: i64,
+    pub with_text: i64,
+    pub skipped: i64,     // Already in the cache, including images that failed before
+    pub failed: i64,      // Recorded, and not retried until the cache is cleared
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub message_id: i64,
+    pub chat_id: Option<i64>,
+    pub date: i64,        // Unix timestamp
+    pub source: String,   // "text" or "ocr"
+    pub text: String,
+    pub highlights: Vec<(usize, usize)>,          // Byte ranges of matches within `text`
+    pub snippet: String,                          // Context around the first match
+    pub snippet_highlights: Vec<(usize, usize)>,  // Byte ranges of matches within `snippet`
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageExtract {
+    pub id: i64,
+    pub date: i64,
+    pub sender_name: String,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BusyDay {
+    pub date: String,                  // YYYY-MM-DD in local time
+    pub message_count: i64,
+    pub dominant_chat_id: Option<i64>,
+    pub dominant_chat_name: Option<String>,
+    pub dominant_chat_count: i64,
+    pub extract: Vec<MessageExtract>,  // A few text messages from the middle of the dominant chat's day
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BusiestDaysReport {
+    pub days: Vec<BusyDay>,
+    pub record_days: Vec<(String, i64)>,  // Each day that beat every earlier day, chronologically
+    pub is_new_record: bool,              // Latest record was set within the recent window
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContactChat {
+    pub chat: Chat,
+    pub title: String,
+    pub contact_message_count: i64,  // Messages this handle sent in the chat
+}
+
+/// A group chat two handles are both in, with how much each has said there
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommonChat {
+    pub chat: Chat,
+    pub title: String,
+    pub contact_a_messages: i64,
+    pub contact_b_messages: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FirstMessages {
+    pub chat_id: i64,
+    pub chat_title: String,
+    pub first_date: Option<i64>,          // Unix timestamp of the very first message
+    pub first_from_me: Option<bool>,      // Who texted first
+    pub messages: Vec<Message>,           // Oldest first
+    pub transcript: String,               // Plain-text rendering for display or export
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageContext {
+    pub message: Message,
+    pub before: Vec<Message>,             // Oldest first, ending just before `message`
+    pub after: Vec<Message>,              // Oldest first, starting just after `message`
+    pub has_more_before: bool,
+    pub has_more_after: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<Message>,           // Oldest first
+    pub anchor_guid: Option<String>,      // First message at or after the requested date (or the last one before it)
+    pub before_cursor: Option<String>,    // GUID to pass to get_context to scroll back; None at the start of the chat
+    pub after_cursor: Option<String>,     // GUID to pass to get_context to scroll forward; None at the end
+}
+
+/// One message in a person's cross-chat feed, labelled with the chat it was sent in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonTimelineItem {
+    pub chat_id: Option<i64>,
+    pub chat_title: String,
+    pub is_group: bool,
+    pub message: Message,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonTimelinePage {
+    pub contact_id: i64,
+    pub total: usize,                     // Messages from this person across every chat
+    pub offset: usize,
+    pub items: Vec<PersonTimelineItem>,   // Newest first
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupComparison {
+    pub tag: String,
+    pub metrics: analytics::MessageMetrics,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct DateRange {
+    pub start: i64,  // Unix timestamp
+    pub end: i64,    // Unix timestamp
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ExportOptions {
+    pub start_date: Option<i64>,  // Unix timestamp
+    pub end_date: Option<i64>,    // Unix timestamp
+    pub contact_ids: Option<Vec<i64>>,
+    pub tags: Option<Vec<String>>,     // Only contacts carrying any of these tags
+    pub chat_ids: Option<Vec<i64>>,
+    pub drop_live_photo_videos: Option<bool>,
+    pub reaction_style: Option<export::ReactionStyle>,
+    pub inline_images_below_bytes: Option<u64>,  // HTML: embed smaller images as data URIs
+    pub copy_attachments: Option<bool>,          // HTML: copy other media into a <name>_files folder
+    pub include_imported: Option<bool>,          // Merge messages brought in by importers
+    pub source: Option<archive::DataSource>,     // Live chat.db (default) or the app-owned archive
+    pub template: Option<String>,                // Built-in template name or Tera source for txt/markdown/html
+    pub template_grouping: Option<templates::TemplateGrouping>,
+    pub include_pinned: Option<bool>,            // Append pinned messages (with notes) as an appendix
+    pub include_notes: Option<bool>,             // Put chat and participant notes in the report header
+    pub collapse_corrections: Option<bool>,      // Keep only the fixed version of rapid-fire typo corrections
+    pub csv_profile: Option<export::CsvProfile>,  // CSV column layout (ours or iMazing's)
+    pub mbox_grouping: Option<templates::TemplateGrouping>,  // mbox: one email per message, day or month
+    pub forensic: Option<bool>,  // Paginated record with per-message hashes and a manifest, no post-processing
+    pub include_system_messages: Option<bool>,   // Group renames and member changes; included unless false
+    pub include_reaction_rows: Option<bool>,     // Tapbacks as messages of their own, not only on their target
+    pub include_audio_transcripts: Option<bool>, // Text of audio messages; kept unless false
+    pub include_app_messages: Option<bool>,      // iMessage app content (games, Apple Pay); included unless false
+    pub include_recoverable: Option<bool>,       // "Recently Deleted": true also matches chat filters, false drops them
+    pub split_by: Option<split::SplitBy>,        // One file per month or year, with an index file
+    pub split_max_bytes: Option<u64>,            // Split further so no file exceeds this size
+    pub anonymize: Option<bool>,                 // Replace contacts with stable keyed pseudonyms
+    pub media_filter: Option<media_filter::MediaFilter>,  // Leave out photos of people, or keep only screenshots/documents
+    #[serde(skip)]
+    pub exact_chat_ids: bool,  // Use chat_ids as given instead of widening them to whole conversations
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatabaseStatus {
+    pub accessible: bool,
+    pub path: String,
+    pub error: Option<String>,
+    pub features: Option<chatdb::ChatDbFeatures>,  // What this macOS version's chat.db supports
+    pub integrity: Option<chatdb::IntegrityReport>,  // PRAGMA quick_check, run once per check
+}
+
+/// Check if we can access the iMessage database (Full Disk Access required)
+pub fn check_database_access() -> DatabaseStatus {
+    let path = match get_imessage_db_path() {
+        Some(p) => p,
+        None => {
+            return DatabaseStatus {
+                accessible: false,
+                path: String::new(),
+                error: Some("Could not determine home directory".to_string()),
+                features: None,
+                integrity: None,
+            }
+        }
+    };
+
+    let path_str = path.to_string_lossy().to_string();
+    let failed = |error: String| DatabaseStatus {
+        accessible: false,
+        path: path_str.clone(),
+        error: Some(error),
+        features: None,
+        integrity: None,
+    };
+
+    // Try to open the database
+    let conn = match chatdb::connect(&path) {
+        Ok(conn) => conn,
+        Err(e) => return failed(format!("Cannot open database. Please grant Full Disk Access in System Settings > Privacy & Security > Full Disk Access. Error: {}", e)),
+    };
+    // Try a simple query to verify we can actually read
+    if let Err(e) = conn.query_row("SELECT COUNT(*) FROM message", [], |row| row.get::<_, i64>(0)) {
+        return failed(format!("Cannot read database: {}", e));
+    }
+    let schema = match chatdb::ChatDbSchema::probe(&conn) {
+        Ok(schema) => schema,
+        Err(e) => return failed(e),
+    };
+
+    // Damage is reported but doesn't block reading; queries skip what SQLite can't read
+    let integrity = match chatdb::quick_check(&conn) {
+        Ok(report) => {
+            if !report.ok {
+                tracing::warn!("chat.db integrity check failed: {:?}", report.errors);
+            }
+            Some(report)
+        }
+        Err(e) => {
+            tracing::warn!("{}", e);
+            None
+        }
+    };
+
+    DatabaseStatus {
+        accessible: true,
+        path: path_str,
+        error: None,
+        features: Some(schema.features()),
+        integrity,
+    }
+}
+
+/// Get all contacts with message counts
+pub fn get_contacts() -> Result<Vec<Contact>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    let mut stmt = conn
+        .prepare(
+            &format!(
+                "SELECT h.ROWID, h.id, {}, COUNT(m.ROWID) as msg_count
+                 FROM handle h
+                 LEFT JOIN message m ON m.handle_id = h.ROWID
+                 GROUP BY h.ROWID
+                 ORDER BY msg_count DESC",
+                schema.handle_column("uncanonicalized_id")
+            ),
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+
+    let mut contacts: Vec<Contact> = stmt
+        .query_map([], |row| {
+            Ok(Contact {
+                id: row.get(0)?,
+                identifier: row.get::<_, String>(1)?,
+                display_name: row.get::<_, Option<String>>(2).ok().flatten(),
+                message_count: row.get(3)?,
+                name: None,
+                details: None,
+                tags: Vec::new(),
+                note: None,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Resolve names and extended fields from the address book
+    let contact_names = get_contact_names();
+    let contact_details = get_contact_details();
+    let contact_tags = tags::get_contact_tags();
+    let contact_notes = notes::load_notes(notes::NoteTarget::Contact);
+    for contact in &mut contacts {
+        contact.name = lookup_contact_name(&contact.identifier, &contact_names);
+        contact.details = lookup_contact(&contact.identifier, &contact_details).cloned();
+        contact.tags = tags::tags_for(&contact.identifier, &contact_tags);
+        contact.note = contact_notes.get(&contact.identifier).cloned();
+    }
+
+    Ok(contacts)
+}
+
+/// Get chat statistics, counted in SQL rather than over loaded messages. The same numbers
+/// are registered as metrics (message_count, scheduled_messages, first_message_date, ...).
+pub fn get_chat_stats(options: Option<ExportOptions>) -> Result<ChatStats, String> {
+    if options.as_ref().and_then(|o| o.source) == Some(archive::DataSource::Archive) {
+        return archive::chat_stats(options.as_ref());
+    }
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    // The same filters as every other message query, tags included
+    let (where_clauses, params) = build_message_filters(&conn, &schema, options.as_ref())?;
+    let count_if = |condition: &str| -> Result<i64, String> {
+        conn.query_row(
+            &format!(
+                "SELECT COUNT(DISTINCT m.ROWID)
+                 FROM message m
+                 LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+                 WHERE {} AND {}",
+                where_clauses.join(" AND "),
+                condition
+            ),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Query error: {}", e))
+    };
+
+    let total_messages = count_if("1")?;
+    let messages_sent = count_if("m.is_from_me = 1")?;
+
+    // Total contacts
+    let total_contacts: i64 = conn
+        .query_row("SELECT COUNT(*) FROM handle", [], |row| row.get(0))
+        .map_err(|e| format!("Query error: {}", e))?;
+
+    // Date range
+    let (date_start, date_end): (Option<i64>, Option<i64>) = conn
+        .query_row(
+            "SELECT MIN(date), MAX(date) FROM message WHERE date > 0",
+            [],
+            |row| {
+                let min: Option<i64> = row.get(0).ok();
+                let max: Option<i64> = row.get(1).ok();
+                Ok((
+                    min.map(mac_timestamp_to_unix),
+                    max.map(mac_timestamp_to_unix),
+                ))
+            },
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+
+    // Delivery metadata, only present on newer macOS versions
+    let scheduled_messages = if schema.has("schedule_type") {
+        count_if("m.schedule_type = 2").unwrap_or(0)
+    } else {
+        0
+    };
+    let retracted_messages = if schema.has("date_retracted") {
+        count_if("m.date_retracted > 0").unwrap_or(0)
+    } else {
+        0
+    };
+    let quiet_deliveries = if schema.has("was_delivered_quietly") {
+        count_if("m.was_delivered_quietly = 1").unwrap_or(0)
+    } else {
+        0
+    };
+
+    Ok(ChatStats {
+        total_messages,
+        messages_sent,
+        messages_received: total_messages - messages_sent,
+        total_contacts,
+        date_range_start: date_start,
+        date_range_end: date_end,
+        scheduled_messages,
+        retracted_messages,
+        quiet_deliveries,
+    })
+}
+
+// Link previews use the app balloon machinery but are ordinary messages
+const URL_BALLOON_PROVIDER: &str = "com.apple.messages.URLBalloonProvider";
+
+/// Build the WHERE clauses and parameters shared by every message query (m = message,
+/// cmj = chat_message_join). Edits are always excluded; reactions unless requested as rows.
+fn build_message_filters(
+    conn: &Connection,
+    schema: &chatdb::ChatDbSchema,
+    options: Option<&ExportOptions>,
+) -> Result<(Vec<String>, Vec<i64>), String> {
+    let content_filter = if options.and_then(|o| o.include_reaction_rows).unwrap_or(false) {
+        schema.reaction_rows_filter()
+    } else {
+        schema.content_filter()
+    };
+    let mut where_clauses = vec!["m.date > 0".to_string(), content_filter.to_string()];
+    let mut params: Vec<i64> = Vec::new();
+
+    if let Some(opts) = options {
+        if opts.include_system_messages == Some(false) && schema.has("item_type") {
+            where_clauses.push("m.item_type = 0".to_string());
+        }
+        if opts.include_app_messages == Some(false) && schema.has("balloon_bundle_id") {
+            where_clauses.push(format!(
+                "(m.balloon_bundle_id IS NULL OR m.balloon_bundle_id = '' OR m.balloon_bundle_id = '{}')",
+                URL_BALLOON_PROVIDER
+            ));
+        }
+        if opts.include_recoverable == Some(false) && schema.has_recoverable() {
+            where_clauses.push("m.ROWID NOT IN (SELECT message_id FROM chat_recoverable_message_join)".to_string());
+        }
+        if let Some(start) = opts.start_date {
+            let mac_start = schema.to_mac_time(start);
+            where_clauses.push("m.date >= ?".to_string());
+            params.push(mac_start);
+        }
+        if let Some(end) = opts.end_date {
+            let mac_end = schema.to_mac_time(end);
+            where_clauses.push("m.date <= ?".to_string());
+            params.push(mac_end);
+        }
+        if let Some(ref contact_ids) = opts.contact_ids {
+            if !contact_ids.is_empty() {
+                let placeholders: Vec<String> = contact_ids.iter().map(|_| "?".to_string()).collect();
+                where_clauses.push(format!("m.handle_id IN ({})", placeholders.join(",")));
+                params.extend(contact_ids.iter().cloned());
+            }
+        }
+        if let Some(ref chat_ids) = opts.chat_ids {
+            if !chat_ids.is_empty() {
+                // A chat stands for its whole conversation, duplicate 1:1 chats included
+                let chat_ids = if opts.exact_chat_ids || opts.forensic.unwrap_or(false) {
+                    chat_ids.clone()
+                } else {
+                    conversations::expand_chat_ids(conn, chat_ids)?
+                };
+                let placeholders = chat_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+                if opts.include_recoverable == Some(true) && schema.has_recoverable() {
+                    // Recently deleted messages leave chat_message_join for the recoverable table
+                    where_clauses.push(format!(
+                        "(cmj.chat_id IN ({p}) OR m.ROWID IN
+                          (SELECT message_id FROM chat_recoverable_message_join WHERE chat_id IN ({p})))",
+                        p = placeholders
+                    ));
+                    params.extend(chat_ids.iter().cloned());
+                } else {
+                    where_clauses.push(format!("cmj.chat_id IN ({})", placeholders));
+                }
+                params.extend(chat_ids.iter().cloned());
+            }
+        }
+        if let Some(ref tag_filter) = opts.tags {
+            if !tag_filter.is_empty() {
+                let handle_ids = tags::resolve_tag_handle_ids(conn, tag_filter)?;
+                if handle_ids.is_empty() {
+                    where_clauses.push("0".to_string());
+                } else {
+                    let placeholders: Vec<String> = handle_ids.iter().map(|_| "?".to_string()).collect();
+                    where_clauses.push(format!("m.handle_id IN ({})", placeholders.join(",")));
+                    params.extend(handle_ids);
+                }
+            }
+        }
+    }
+
+    Ok((where_clauses, params))
+}
+
+/// Count today's messages (local time) with a single indexed range query, for the tray
+pub fn count_messages_today() -> Result<i64, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    let today = settings::to_local_time(Utc::now().timestamp(), &settings::load_settings())
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .ok_or("Cannot determine today's date")?;
+    let (start, _) = local_day_bounds(&today).ok_or("Cannot determine today's date")?;
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM message m WHERE m.date >= ? AND {}", schema.content_filter()),
+        [schema.to_mac_time(start)],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Query error: {}", e))
+}
+
+/// Count messages matching the filters without materializing them
+pub fn count_messages(options: Option<ExportOptions>) -> Result<i64, String> {
+    if options.as_ref().and_then(|o| o.source) == Some(archive::DataSource::Archive) {
+        return archive::count_messages(options.as_ref());
+    }
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    let (where_clauses, params) = build_message_filters(&conn, &schema, options.as_ref())?;
+    let query = format!(
+        "SELECT COUNT(DISTINCT m.ROWID)
+         FROM message m
+         LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         WHERE {}",
+        where_clauses.join(" AND ")
+    );
+    conn.query_row(&query, rusqlite::params_from_iter(params.iter()), |row| row.get(0))
+        .map_err(|e| format!("Query error: {}", e))
+}
+
+/// Count messages grouped by sender, chat, or time bucket, entirely in SQL
+pub fn aggregate_messages(options: Option<ExportOptions>, group_by: AggregateBy) -> Result<Vec<AggregateBucket>, String> {
+    let from_archive = options.as_ref().and_then(|o| o.source) == Some(archive::DataSource::Archive);
+    let mut buckets = if from_archive {
+        archive::aggregate_messages(options.as_ref(), group_by)?
+    } else {
+        aggregate_live_messages(options.as_ref(), group_by)?
+    };
+
+    if group_by == AggregateBy::Sender {
+        let contact_names = get_contact_names();
+        for bucket in &mut buckets {
+            bucket.label = if bucket.key == "me" {
+                Some("Me".to_string())
+            } else {
+                lookup_contact_name(&bucket.key, &contact_names)
+            };
+        }
+    } else if group_by == AggregateBy::Chat && from_archive {
+        // Chats chat.db still has get their titles; the rest stay unlabeled
+        let titles: HashMap<String, String> =
+            load_chats().unwrap_or_default().iter().map(|c| (c.id.to_string(), chat_title(c))).collect();
+        for bucket in &mut buckets {
+            bucket.label = titles.get(&bucket.key).cloned();
+        }
+    }
+
+    Ok(buckets)
+}
+
+fn aggregate_live_messages(options: Option<&ExportOptions>, group_by: AggregateBy) -> Result<Vec<AggregateBucket>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    let (where_clauses, params) = build_message_filters(&conn, &schema, options)?;
+    let local_time = format!(
+        "datetime({}, 'unixepoch')",
+        settings::local_seconds_sql(&schema.unix_seconds_sql("m.date"), &settings::load_settings())
+    );
+    let (key_sql, label_sql, order_sql) = match group_by {
+        AggregateBy::Sender => (
+            "CASE WHEN m.is_from_me = 1 THEN 'me' ELSE COALESCE(h.id, '') END".to_string(),
+            "NULL".to_string(),
+            "COUNT(*) DESC",
+        ),
+        AggregateBy::Chat => (
+            "CAST(cmj.chat_id AS TEXT)".to_string(),
+            "COALESCE(c.display_name, c.chat_identifier)".to_string(),
+            "COUNT(*) DESC",
+        ),
+        AggregateBy::Day => (format!("strftime('%Y-%m-%d', {})", local_time), "NULL".to_string(), "key"),
+        AggregateBy::Month => (format!("strftime('%Y-%m', {})", local_time), "NULL".to_string(), "key"),
+        AggregateBy::Hour => (format!("strftime('%H', {})", local_time), "NULL".to_string(), "key"),
+        AggregateBy::Weekday => (format!("strftime('%w', {})", local_time), "NULL".to_string(), "key"),
+    };
+
+    let query = format!(
+        "SELECT {key} AS key, MAX({label}), COUNT(*)
+         FROM message m
+         LEFT JOIN handle h ON m.handle_id = h.ROWID
+         LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         LEFT JOIN chat c ON cmj.chat_id = c.ROWID
+         WHERE {filters}
+         GROUP BY key
+         ORDER BY {order}",
+        key = key_sql,
+        label = label_sql,
+        filters = where_clauses.join(" AND "),
+        order = order_sql
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let buckets = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(AggregateBucket {
+                key: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                label: row.get(1)?,
+                count: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(buckets)
+}
+
+// Length of MessageSummary snippets
+const SNIPPET_CHARS: usize = 120;
+
+/// The start of a message's plain text for a summary, without attachment placeholders
+pub fn summary_snippet(text: &str) -> Option<String> {
+    let snippet: String = text.trim_matches('\u{FFFC}').trim().chars().take(SNIPPET_CHARS).collect();
+    (!snippet.is_empty()).then_some(snippet)
+}
+
+/// Get lightweight message summaries for scrolling list views. Skips attributedBody decoding,
+/// attachments and reactions so the IPC payload stays small.
+pub fn get_message_summaries(
+    options: Option<ExportOptions>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<MessageSummary>, String> {
+    if options.as_ref().and_then(|o| o.source) == Some(archive::DataSource::Archive) {
+        return archive::message_summaries(options.as_ref(), limit, offset);
+    }
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    let contact_names = get_contact_names();
+    let (where_clauses, mut params) = build_message_filters(&conn, &schema, options.as_ref())?;
+    params.push(limit.unwrap_or(-1)); // SQLite treats a negative LIMIT as unbounded
+    params.push(offset.unwrap_or(0));
+
+    let query = format!(
+        "SELECT m.ROWID, m.guid, m.date, m.is_from_me, COALESCE(h.id, ''), cmj.chat_id, m.text,
+                COALESCE({}, 0)
+         FROM message m
+         LEFT JOIN handle h ON m.handle_id = h.ROWID
+         LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         WHERE {}
+         ORDER BY m.date DESC
+         LIMIT ? OFFSET ?",
+        schema.column("cache_has_attachments"),
+        where_clauses.join(" AND ")
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+
+    let summaries = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            let is_from_me = row.get::<_, i64>(3)? == 1;
+            let contact_identifier: String = row.get(4)?;
+            let sender_name = if is_from_me {
+                "Me".to_string()
+            } else if contact_identifier.is_empty() {
+                "Unknown".to_string()
+            } else {
+                lookup_contact_name(&contact_identifier, &contact_names).unwrap_or(contact_identifier)
+            };
+            let snippet = row.get::<_, Option<String>>(6)?.as_deref().and_then(summary_snippet);
+
+            Ok(MessageSummary {
+                id: row.get(0)?,
+                guid: row.get(1)?,
+                date: mac_timestamp_to_unix(row.get(2)?),
+                is_from_me,
+                sender_name,
+                chat_id: row.get(5)?,
+                snippet,
+                has_attachment: row.get::<_, i64>(7)? == 1,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(summaries)
+}
+
+/// Get messages with optional filtering
+pub fn get_messages(options: Option<ExportOptions>, limit: Option<i64>) -> Result<Vec<Message>, String> {
+    get_messages_named(options, limit, None)
+}
+
+/// `get_messages` with contact names the caller already looked up, for callers that read many
+/// chats one at a time
+pub fn get_messages_named(
+    options: Option<ExportOptions>,
+    limit: Option<i64>,
+    contact_names: Option<&HashMap<String, String>>,
+) -> Result<Vec<Message>, String> {
+    let collapse = options.as_ref().and_then(|o| o.collapse_corrections).unwrap_or(false);
+    let mut messages = if options.as_ref().and_then(|o| o.source) == Some(archive::DataSource::Archive) {
+        archive::load_messages(options.as_ref(), limit)?
+    } else {
+        let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+        query_messages_near(&path, options, contact_names, None, limit)?
+    };
+    if collapse {
+        corrections::collapse_corrections(&mut messages);
+    }
+    Ok(messages)
+}
+
+/// Query messages from a chat.db-format database: the live one, or a copy from another Mac or backup
+fn query_messages(
+    db_path: &std::path::Path,
+    options: Option<ExportOptions>,
+    limit: Option<i64>,
+) -> Result<Vec<Message>, String> {
+    query_messages_near(db_path, options, None, None, limit)
+}
+
+/// Position relative to messages (by ROWID), so context can be read without loading the whole chat
+#[derive(Debug, Clone)]
+enum Anchor {
+    At(Vec<i64>),
+    Before(i64),  // Newest first, walking back from the anchor
+    After(i64),   // Oldest first, walking forward from the anchor
+}
+
+fn query_messages_near(
+    db_path: &std::path::Path,
+    options: Option<ExportOptions>,
+    contact_names: Option<&HashMap<String, String>>,
+    anchor: Option<Anchor>,
+    limit: Option<i64>,
+) -> Result<Vec<Message>, String> {
+    let loaded;
+    let contact_names = match contact_names {
+        Some(names) => names,
+        None => {
+            loaded = get_contact_names();
+            &loaded
+        }
+    };
+    let mut messages: Vec<Message> = Vec::new();
+    for_each_message(db_path, options.as_ref(), contact_names, anchor, limit, |msg| messages.push(msg))?;
+
+    // Merge messages from external logs, keeping newest-first order and the row limit
+    if options.as_ref().and_then(|o| o.include_imported).unwrap_or(false) {
+        messages.extend(imports::load_imported_messages(options.as_ref())?);
+        messages.sort_by_key(|m| std::cmp::Reverse(m.date));
+        if let Some(l) = limit {
+            messages.truncate(l.max(0) as usize);
+        }
+    }
+
+    Ok(messages)
+}
+
+// Messages decoded and enriched per batch; bounds the attachment query and what is held in flight
+const MESSAGE_WINDOW: usize = 500;
+
+/// Messages matching the filters, newest first, read from chat.db a window at a time as they are
+/// consumed. For reports that look at each message once; imported messages follow the live ones.
+/// Call `finish` afterwards, since a failed read just ends the iteration.
+pub struct MessageStream {
+    live: Option<(PathBuf, Option<ExportOptions>, HashMap<String, String>)>,  // None once chat.db is exhausted
+    last_id: Option<i64>,        // Where the next page continues from
+    page: std::vec::IntoIter<Message>,
+    imported: Vec<Message>,      // Yielded after the live messages
+    error: Option<String>,
+}
+
+impl MessageStream {
+    fn loaded(messages: Vec<Message>) -> Self {
+        MessageStream { live: None, last_id: None, page: messages.into_iter(), imported: Vec::new(), error: None }
+    }
+
+    /// The error that ended the iteration early, if any
+    pub fn finish(self) -> Result<(), String> {
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl Iterator for MessageStream {
+    type Item = Message;
+
+    fn next(&mut self) -> Option<Message> {
+        loop {
+            if let Some(msg) = self.page.next() {
+                return Some(msg);
+            }
+            let (path, options, contact_names) = self.live.as_ref()?;
+            let mut page = Vec::with_capacity(MESSAGE_WINDOW);
+            let anchor = self.last_id.map(Anchor::Before);
+            let limit = Some(MESSAGE_WINDOW as i64);
+            let (rows, last_id) =
+                match for_each_message(path, options.as_ref(), contact_names, anchor, limit, |msg| page.push(msg)) {
+                    Ok(read) => read,
+                    Err(e) => {
+                        self.error = Some(e);
+                        self.live = None;
+                        return None;
+                    }
+                };
+            // Rows that don't decode are skipped, so only a short read from SQL means chat.db is done
+            self.last_id = last_id;
+            if rows < MESSAGE_WINDOW {
+                self.live = None;
+                page.append(&mut self.imported);
+            }
+            self.page = page.into_iter();
+        }
+    }
+}
+
+/// Stream the messages `get_messages` would return, without holding them all. Archive reads and
+/// collapsed corrections need the whole range anyway, so those are loaded up front.
+pub fn stream_messages(options: Option<ExportOptions>) -> Result<MessageStream, String> {
+    let whole = options.as_ref().is_some_and(|o| {
+        o.source == Some(archive::DataSource::Archive) || o.collapse_corrections.unwrap_or(false)
+    });
+    if whole {
+        return Ok(MessageStream::loaded(get_messages(options, None)?));
+    }
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let imported = if options.as_ref().and_then(|o| o.include_imported).unwrap_or(false) {
+        imports::load_imported_messages(options.as_ref())?
+    } else {
+        Vec::new()
+    };
+    Ok(MessageStream {
+        live: Some((path, options, get_contact_names())),
+        last_id: None,
+        page: Vec::new().into_iter(),
+        imported,
+        error: None,
+    })
+}
+
+/// Stream messages matching the filters to `f` in query order. Names are resolved as rows are
+/// read and attachments and reactions are added a window at a time, so callers that aggregate
+/// never need the whole range in memory. Returns how many rows the query read and the ROWID of
+/// the last one, which paging callers continue from.
+fn for_each_message<F: FnMut(Message)>(
+    db_path: &std::path::Path,
+    options: Option<&ExportOptions>,
+    contact_names: &HashMap<String, String>,
+    anchor: Option<Anchor>,
+    limit: Option<i64>,
+    mut f: F,
+) -> Result<(usize, Option<i64>), String> {
+    let _span = tracing::info_span!("query_messages", ?anchor, ?limit).entered();
+    let (conn, schema) = chatdb::open(db_path)?;
+
+    let (mut where_clauses, mut params) = build_message_filters(&conn, &schema, options)?;
+
+    let mut order = "DESC";
+    let side = match anchor {
+        Some(Anchor::At(row_ids)) => {
+            let placeholders: Vec<String> = row_ids.iter().map(|_| "?".to_string()).collect();
+            where_clauses.push(format!("m.ROWID IN ({})", placeholders.join(",")));
+            params.extend(row_ids);
+            None
+        }
+        Some(Anchor::Before(row_id)) => Some(("<", row_id)),
+        Some(Anchor::After(row_id)) => {
+            order = "ASC";
+            Some((">", row_id))
+        }
+        None => None,
+    };
+    if let Some((cmp, row_id)) = side {
+        // Ties on date are broken by ROWID so paging never skips or repeats a message
+        where_clauses.push(format!(
+            "(m.date {cmp} (SELECT date FROM message WHERE ROWID = ?)
+              OR (m.date = (SELECT date FROM message WHERE ROWID = ?) AND m.ROWID {cmp} ?))",
+            cmp = cmp
+        ));
+        params.extend([row_id, row_id, row_id]);
+    }
+
+    let where_sql = where_clauses.join(" AND ");
+    let limit_sql = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
+
+    // Delivery metadata columns only exist on newer macOS versions
+    let delivery_sql = [
+        "schedule_type",
+        "date_retracted",
+        "date_edited",
+        "was_delivered_quietly",
+        "did_notify_recipient",
+    ]
+    .iter()
+    .map(|c| schema.column(c))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+    // Recoverable messages have no chat_message_join row, so look their chat up separately
+    let chat_id_sql = if schema.has_recoverable() {
+        "COALESCE(cmj.chat_id, (SELECT chat_id FROM chat_recoverable_message_join WHERE message_id = m.ROWID))"
+    } else {
+        "cmj.chat_id"
+    };
+    let keep_transcripts = options.and_then(|o| o.include_audio_transcripts).unwrap_or(true);
+
+    let query = format!(
+        "SELECT m.ROWID, m.guid, m.text, m.date, m.is_from_me, COALESCE(m.handle_id, 0),
+                COALESCE(h.id, '') as contact_id,
+                COALESCE({}, 0),
+                {},
+                {},
+                {},
+                COALESCE({}, 0)
+         FROM message m
+         LEFT JOIN handle h ON m.handle_id = h.ROWID
+         LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         WHERE {}
+         ORDER BY m.date {order}, m.ROWID {order}
+         {}",
+        schema.column("cache_has_attachments"),
+        chat_id_sql,
+        schema.column("attributedBody"),
+        delivery_sql,
+        schema.column("is_audio_message"),
+        where_sql,
+        limit_sql,
+        order = order
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let mut rows = stmt
+        .query(rusqlite::params_from_iter(params.iter()))
+        .map_err(|e| format!("Query error: {}", e))?;
+
+    // Forensic exports keep attachments in stored order, so Live Photos aren't paired there
+    let live_photos = match options {
+        Some(o) if o.forensic.unwrap_or(false) => None,
+        _ => Some(options.and_then(|o| o.drop_live_photo_videos).unwrap_or(false)),
+    };
+    let mut window: Vec<Message> = Vec::with_capacity(MESSAGE_WINDOW);
+    let mut flush = |window: &mut Vec<Message>| {
+        attach_attachments(&conn, window, live_photos);
+        let mut reactions = load_reactions(&conn, contact_names, window);
+        for mut msg in window.drain(..) {
+            if let Some(found) = reactions.remove(&msg.guid) {
+                msg.reactions = found;
+            }
+            f(msg);
+        }
+    };
+
+    let mut read = 0;
+    let mut last_id = None;
+    while let Some(row) = rows.next().map_err(|e| format!("Query error: {}", e))? {
+        read += 1;
+        last_id = row.get::<_, i64>(0).ok().or(last_id);
+        if let Ok(msg) = decode_message_row(row, keep_transcripts, contact_names) {
+            window.push(msg);
+        }
+        if window.len() == MESSAGE_WINDOW {
+            flush(&mut window);
+        }
+    }
+    flush(&mut window);
+    Ok((read, last_id))
+}
+
+/// Build a message from a row of the `for_each_message` query, sender name resolved
+fn decode_message_row(
+    row: &rusqlite::Row,
+    keep_transcripts: bool,
+    contact_names: &HashMap<String, String>,
+) -> rusqlite::Result<Message> {
+    let mac_date: i64 = row.get(3)?;
+    let unix_date = mac_timestamp_to_unix(mac_date);
+    let datetime = Utc.timestamp_opt(unix_date, 0).single();
+    let date_formatted = datetime
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let is_from_me = row.get::<_, i64>(4)? == 1;
+    let contact_identifier: String = row.get(6)?;
+    let raw_text: Option<String> = row.get(2)?;
+    let attributed_body: Option<Vec<u8>> = row.get(9).ok().flatten();
+
+    let is_audio = row.get::<_, i64>(15)? == 1;
+    let text = if is_audio && !keep_transcripts {
+        None
+    } else {
+        decode_message_text(raw_text, attributed_body.as_deref())
+    };
+
+    // Resolve sender name
+    let sender_name = if is_from_me {
+        "Me".to_string()
+    } else if contact_identifier.is_empty() {
+        "Unknown".to_string()
+    } else {
+        lookup_contact_name(&contact_identifier, contact_names).unwrap_or_else(|| contact_identifier.clone())
+    };
+
+    Ok(Message {
+        id: row.get(0)?,
+        guid: row.get(1)?,
+        text,
+        date: unix_date,
+        date_formatted,
+        is_from_me,
+        handle_id: row.get(5)?,
+        contact_identifier,
+        sender_name,
+        chat_id: row.get(8)?,
+        has_attachment: row.get::<_, i64>(7)? == 1,
+        attachments: Vec::new(),
+        reactions: Vec::new(),
+        delivery: DeliveryMetadata {
+            is_scheduled: row.get::<_, Option<i64>>(10).ok().flatten() == Some(2),
+            date_retracted: row
+                .get::<_, Option<i64>>(11)
+                .ok()
+                .flatten()
+                .filter(|d| *d > 0)
+                .map(mac_timestamp_to_unix),
+            date_edited: row
+                .get::<_, Option<i64>>(12)
+                .ok()
+                .flatten()
+                .filter(|d| *d > 0)
+                .map(mac_timestamp_to_unix),
+            delivered_quietly: row.get::<_, Option<i64>>(13).ok().flatten() == Some(1),
+            did_notify_recipient: row.get::<_, Option<i64>>(14).ok().flatten() == Some(1),
+        },
+    })
+}
+
+/// Fetch attachments for a window of messages. With `live_photos` set, Live Photo stills and their
+/// motion videos are kept together, and `Some(true)` drops the videos.
+fn attach_attachments(conn: &Connection, window: &mut [Message], live_photos: Option<bool>) {
+    let index: HashMap<i64, usize> = window
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.has_attachment)
+        .map(|(i, m)| (m.id, i))
+        .collect();
+    if index.is_empty() {
+        return;
+    }
+
+    let placeholders: String = index.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+    let attach_query = format!(
+        "SELECT maj.message_id, a.filename, a.mime_type, a.transfer_name, a.total_bytes
+         FROM message_attachment_join maj
+         JOIN attachment a ON maj.attachment_id = a.ROWID
+         WHERE maj.message_id IN ({})",
+        placeholders
+    );
+
+    if let Ok(mut attach_stmt) = conn.prepare(&attach_query) {
+        if let Ok(rows) = attach_stmt.query_map(rusqlite::params_from_iter(index.keys()), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<i64>>(4).ok().flatten(),
+            ))
+        }) {
+            for row in rows.flatten() {
+                let (msg_id, filename, mime_type, transfer_name, total_bytes) = row;
+                if let Some(&i) = index.get(&msg_id) {
+                    // Expand ~ in filename path to actual home directory
+                    let expanded_filename = filename.map(expand_home_path);
+
+                    // Read duration/resolution from the video container header
+                    let video = if media::is_parseable_video(mime_type.as_deref()) {
+                        expanded_filename
+                            .as_ref()
+                            .and_then(|f| media::read_video_metadata(std::path::Path::new(f)))
+                    } else {
+                        None
+                    };
+
+                    window[i].attachments.push(Attachment {
+                        filename: expanded_filename,
+                        mime_type,
+                        transfer_name,
+                        total_bytes,
+                        video,
+                    });
+                }
+            }
+        }
+    }
+
+    let Some(drop_videos) = live_photos else { return };
+    for msg in window.iter_mut().filter(|m| m.attachments.len() > 1) {
+        let attachments = std::mem::take(&mut msg.attachments);
+        msg.attachments = media::pair_live_photos(attachments, drop_videos);
+    }
+}
+
+/// Reactions to a window of messages, keyed by the GUID of the message they react to, sender
+/// names resolved. Reactions have associated_message_type between 2000-2005 and reference their
+/// parent via associated_message_guid, as "bp:GUID" or "p:PART/GUID" for one part of it.
+/// Removing one sends 3000-3005 from the same sender, which cancels it here.
+fn load_reactions(
+    conn: &Connection,
+    contact_names: &HashMap<String, String>,
+    window: &[Message],
+) -> HashMap<String, Vec<Reaction>> {
+    let mut reactions: HashMap<String, Vec<Reaction>> = HashMap::new();
+    // Text runs and attachments are separate parts, so a message has at most 2n+1 of them
+    let targets: Vec<String> = window
+        .iter()
+        .flat_map(|m| {
+            let parts = (0..=m.attachments.len() * 2).map(move |part| format!("p:{}/{}", part, m.guid));
+            [m.guid.clone(), format!("bp:{}", m.guid)].into_iter().chain(parts)
+        })
+        .collect();
+    if targets.is_empty() {
+        return reactions;
+    }
+    let reaction_query = format!(
+        "SELECT m.associated_message_guid, m.associated_message_type, m.is_from_me, COALESCE(h.id, '') as sender
+         FROM message m
+         LEFT JOIN handle h ON m.handle_id = h.ROWID
+         WHERE m.associated_message_guid IN ({})
+           AND m.associated_message_type >= 2000 AND m.associated_message_type < 4000
+         ORDER BY m.date, m.ROWID",
+        targets.iter().map(|_| "?").collect::<Vec<_>>().join(",")
+    );
+
+    if let Ok(mut reaction_stmt) = conn.prepare(&reaction_query) {
+        if let Ok(rows) = reaction_stmt.query_map(rusqlite::params_from_iter(targets.iter()), |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)? == 1,
+                row.get::<_, String>(3)?,
+            ))
+        }) {
+            for row in rows.flatten() {
+                let (assoc_guid_opt, reaction_type, is_from_me, sender_id) = row;
+                if let Some(assoc_guid) = assoc_guid_opt {
+                    // The associated_message_guid has format like "p:0/guid" or "bp:guid"
+                    // Extract the actual GUID part
+                    let clean_guid = assoc_guid
+                        .split('/')
+                        .next_back()
+                        .unwrap_or(&assoc_guid);
+                    let clean_guid = clean_guid
+                        .strip_prefix("bp:")
+                        .unwrap_or(clean_guid)
+                        .to_string();
+
+                    let sender_id = if is_from_me { String::new() } else { sender_id };
+                    let target = reactions.entry(clean_guid).or_default();
+
+                    // A removal withdraws the sender's latest matching tapback on the message
+                    if reaction_type >= 3000 {
+                        let removed = target.iter().rposition(|r| {
+                            r.reaction_type == reaction_type - 1000
+                                && r.is_from_me == is_from_me
+                                && r.sender_id == sender_id
+                        });
+                        if let Some(i) = removed {
+                            target.remove(i);
+                        }
+                        continue;
+                    }
+
+                    let sender = if is_from_me {
+                        "Me".to_string()
+                    } else {
+                        lookup_contact_name(&sender_id, contact_names)
+                            .unwrap_or_else(|| sender_id.clone())
+                    };
+                    target.push(Reaction {
+                        reaction_type,
+                        sender,
+                        sender_id,
+                        is_from_me,
+                    });
+                }
+            }
+        }
+    }
+    reactions
+}
+
+/// Get messages for a specific contact formatted for export
+pub fn get_messages_for_contact(contact_id: i64, options: Option<ExportOptions>) -> Result<Vec<Message>, String> {
+    let mut opts = options.unwrap_or_default();
+    opts.contact_ids = Some(vec![contact_id]);
+    get_messages(Some(opts), None)
+}
+
+/// Open System Preferences to Full Disk Access
+pub fn open_system_preferences() -> Result<(), String> {
+    std::process::Command::new("open")
+        .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_AllFiles")
+        .spawn()
+        .map_err(|e| format!("Failed to open System Preferences: {}", e))?;
+    Ok(())
+}
+
+/// Open System Preferences to Contacts
+pub fn open_contacts_preferences() -> Result<(), String> {
+    std::process::Command::new("open")
+        .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Contacts")
+        .spawn()
+        .map_err(|e| format!("Failed to open System Preferences: {}", e))?;
+    Ok(())
+}
+
+/// Check Full Disk Access, each Contacts source, attachments and the app data dir in one call
+pub fn get_permission_report() -> Result<permissions::PermissionReport, String> {
+    permissions::permission_report()
+}
+
+/// Check if we can access the Contacts database, with the status of each AddressBook source
+pub fn check_contacts_access() -> addressbook::ContactsAccess {
+    let sources = addressbook::check_sources();
+    let accessible = !get_contact_names().is_empty();
+    let error = if accessible {
+        None
+    } else if sources.is_empty() {
+        Some("No AddressBook databases found".to_string())
+    } else {
+        // Surface the first concrete reason, e.g. an unsupported schema version
+        sources.iter().find_map(|s| s.error.clone())
+    };
+    addressbook::ContactsAccess { accessible, sources, error }
+}
+
+/// Show the macOS Contacts permission prompt, used when the AddressBook files can't be read.
+/// Blocks until the user answers, so it only runs when they click through onboarding.
+pub fn request_contacts_access() -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    return contacts_framework::request_access();
+    #[cfg(not(target_os = "macos"))]
+    Err("Contacts access is only available on macOS".to_string())
+}
+
+/// Chats for the chat list, one per conversation (see `conversations::group_chats`), hiding
+/// archived and dormant ones unless the filter asks for them
+pub fn get_chats(filter: Option<ChatListFilter>) -> Result<Vec<Chat>, String> {
+    let filter = filter.unwrap_or_default();
+    let mut chats = conversations::group_chats(load_chats()?);
+    chats.retain(|c| (filter.include_archived || !c.is_archived) && (filter.include_dormant || !c.is_dormant));
+    Ok(chats)
+}
+
+/// Get all chats with participants and message counts
+pub fn load_chats() -> Result<Vec<Chat>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    // Load contact names for resolution
+    let contact_names = get_contact_names();
+
+    // Get all chats with message counts, newest message and archived flag
+    let query = format!(
+        "SELECT c.ROWID, c.chat_identifier, c.display_name, c.style,
+                COUNT(DISTINCT cmj.message_id) as msg_count,
+                MAX(CASE WHEN m.date > 0 THEN {last} END),
+                COALESCE({archived}, 0)
+         FROM chat c
+         LEFT JOIN chat_message_join cmj ON c.ROWID = cmj.chat_id
+         LEFT JOIN message m ON m.ROWID = cmj.message_id
+         GROUP BY c.ROWID
+         ORDER BY msg_count DESC",
+        last = schema.unix_seconds_sql("m.date"),
+        archived = schema.chat_column("is_archived"),
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut chats: Vec<Chat> = stmt
+        .query_map([], |row| {
+            let style: i64 = row.get(3)?;
+            let message_count: i64 = row.get(4)?;
+            let last_message_date: Option<i64> = row.get(5)?;
+            Ok(Chat {
+                id: row.get(0)?,
+                chat_identifier: row.get(1)?,
+                display_name: row.get::<_, Option<String>>(2).ok().flatten(),
+                is_group: style == 43, // 43 = group chat, 45 = individual
+                participant_count: 0,
+                message_count,
+                participants: Vec::new(),
+                participant_ids: Vec::new(),
+                tags: Vec::new(),
+                note: None,
+                name_history: Vec::new(),
+                last_message_date,
+                is_archived: row.get::<_, i64>(6)? != 0,
+                is_dormant: is_dormant(message_count, last_message_date, now),
+                merged_chat_ids: Vec::new(),
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut name_history = group_name_history(&conn, &schema)?;
+    for chat in &mut chats {
+        chat.name_history = name_history.remove(&chat.id).unwrap_or_default();
+    }
+
+    let contact_tags = tags::get_contact_tags();
+    let chat_notes = notes::load_notes(notes::NoteTarget::Chat);
+
+    // Get participants for each chat and resolve names
+    for chat in &mut chats {
+        chat.note = chat_notes.get(&chat.id.to_string()).cloned();
+        let mut participant_stmt = conn
+            .prepare(
+                "SELECT h.id FROM handle h
+                 JOIN chat_handle_join chj ON h.ROWID = chj.handle_id
+                 WHERE chj.chat_id = ?",
+            )
+            .map_err(|e| format!("Query error: {}", e))?;
+
+        let raw_participants: Vec<String> = participant_stmt
+            .query_map([chat.id], |row| row.get(0))
+            .map_err(|e| format!("Query error: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        // Resolve participant names
+        let participants: Vec<String> = raw_participants
+            .iter()
+            .map(|p| {
+                lookup_contact_name(p, &contact_names)
+                    .unwrap_or_else(|| p.clone())
+            })
+            .collect();
+
+        chat.participant_count = participants.len() as i64;
+        chat.participants = participants;
+        chat.participant_ids = raw_participants.clone();
+
+        let mut chat_tags: Vec<String> = raw_participants
+            .iter()
+            .flat_map(|p| tags::tags_for(p, &contact_tags))
+            .collect();
+        chat_tags.sort();
+        chat_tags.dedup();
+        chat.tags = chat_tags;
+
+        // For individual chats without display_name, try to set it from contact
+        if chat.display_name.is_none() && raw_participants.len() == 1 {
+            if let Some(name) = lookup_contact_name(&raw_participants[0], &contact_names) {
+                chat.display_name = Some(name);
+            }
+        }
+    }
+
+    Ok(chats)
+}
+
+/// Per-quarter attachment types (photos, videos, audio, links, stickers) per chat and overall
+pub fn get_media_trends(chat_id: Option<i64>) -> Result<media_trends::MediaTrends, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+    media_trends::media_trends(&conn, &schema, chat_id, &settings::load_settings())
+}
+
+/// GIF and meme-link counts per sender, and the most reused GIF, for each chat (or one chat).
+/// Live only: it reads chat.db's attachment rows, which the archive doesn't keep.
+pub fn get_gif_report(chat_id: Option<i64>, source: Option<archive::DataSource>) -> Result<Vec<gifs::ChatGifReport>, String> {
+    archive::require_live(source, "The GIF report")?;
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+    gifs::gif_report(&conn, &schema, chat_id, &get_contact_names())
+}
+
+/// Photos and videos shared in several conversations ("this photo was shared in 5 conversations").
+/// Live only: attachments are matched through chat.db's attachment joins.
+pub fn get_shared_media(
+    min_chats: Option<usize>,
+    limit: Option<usize>,
+    source: Option<archive::DataSource>,
+) -> Result<Vec<duplicates::SharedMedia>, String> {
+    archive::require_live(source, "The shared media report")?;
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+    let titles: HashMap<i64, String> = load_chats()?.iter().map(|c| (c.id, chat_title(c))).collect();
+
+    let mut shared = duplicates::shared_media(&conn, &schema, min_chats.unwrap_or(2).max(2), &titles)?;
+    shared.truncate(limit.unwrap_or(100));
+    Ok(shared)
+}
+
+/// Get attachment storage and video duration totals, optionally for a single chat
+pub fn get_media_stats(chat_id: Option<i64>) -> Result<MediaStats, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, _schema) = chatdb::open(&path)?;
+
+    let (chat_sql, params): (&str, Vec<i64>) = match chat_id {
+        Some(id) => (
+            "WHERE a.ROWID IN (
+                SELECT maj.attachment_id FROM message_attachment_join maj
+                JOIN chat_message_join cmj ON maj.message_id = cmj.message_id
+                WHERE cmj.chat_id = ?)",
+            vec![id],
+        ),
+        None => ("", Vec::new()),
+    };
+
+    let query = format!(
+        "SELECT a.filename, a.mime_type, COALESCE(a.total_bytes, 0) FROM attachment a {}",
+        chat_sql
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+
+    let rows: Vec<(Option<String>, Option<String>, i64)> = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut stats = MediaStats {
+        attachment_count: 0,
+        total_bytes: 0,
+        image_count: 0,
+        video_count: 0,
+        audio_count: 0,
+        other_count: 0,
+        video_duration_secs: 0.0,
+        unreadable_videos: 0,
+    };
+
+    for (filename, mime_type, bytes) in rows {
+        stats.attachment_count += 1;
+        stats.total_bytes += bytes;
+
+        let mime = mime_type.as_deref().unwrap_or("");
+        if mime.starts_with("image/") {
+            stats.image_count += 1;
+        } else if mime.starts_with("video/") {
+            stats.video_count += 1;
+            let metadata = filename
+                .map(expand_home_path)
+                .filter(|_| media::is_parseable_video(Some(mime)))
+                .and_then(|f| media::read_video_metadata(std::path::Path::new(&f)));
+            match metadata {
+                Some(m) => stats.video_duration_secs += m.duration_secs,
+                None => stats.unreadable_videos += 1,
+            }
+        } else if mime.starts_with("audio/") {
+            stats.audio_count += 1;
+        } else {
+            stats.other_count += 1;
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Get geotagged photo points for a chat plus photos whose capture time differs wildly from send time
+pub fn get_photo_map(chat_id: i64, mismatch_threshold_hours: Option<i64>) -> Result<PhotoMap, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, _schema) = chatdb::open(&path)?;
+
+    let threshold_secs = mismatch_threshold_hours.unwrap_or(24) * 3600;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.ROWID, m.date, a.filename
+             FROM message m
+             JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+             JOIN message_attachment_join maj ON m.ROWID = maj.message_id
+             JOIN attachment a ON maj.attachment_id = a.ROWID
+             WHERE cmj.chat_id = ? AND a.mime_type LIKE 'image/%' AND a.filename IS NOT NULL
+             ORDER BY m.date",
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+
+    let photos: Vec<(i64, i64, String)> = stmt
+        .query_map([chat_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut map = PhotoMap {
+        points: Vec::new(),
+        mismatches: Vec::new(),
+        photos_scanned: 0,
+        photos_without_exif: 0,
+    };
+
+    for (message_id, mac_date, filename) in photos {
+        map.photos_scanned += 1;
+        let filename = expand_home_path(filename);
+        let sent_date = mac_timestamp_to_unix(mac_date);
+
+        let exif = match media::read_photo_exif(std::path::Path::new(&filename)) {
+            Some(e) => e,
+            None => {
+                map.photos_without_exif += 1;
+                continue;
+            }
+        };
+
+        if let (Some(latitude), Some(longitude)) = (exif.latitude, exif.longitude) {
+            map.points.push(PhotoPoint {
+                message_id,
+                filename: filename.clone(),
+                sent_date,
+                captured_date: exif.captured_at,
+                latitude,
+                longitude,
+            });
+        }
+
+        if let Some(captured_date) = exif.captured_at {
+            let difference_secs = sent_date - captured_date;
+            if difference_secs.abs() > threshold_secs {
+                map.mismatches.push(PhotoTimeMismatch {
+                    message_id,
+                    filename,
+                    sent_date,
+                    captured_date,
+                    difference_secs,
+                });
+            }
+        }
+    }
+
+    Ok(map)
+}
+
+/// Run OCR over image attachments and store the recognized text in the search index
+pub fn run_ocr(
+    task_registry: &tasks::TaskRegistry,
+    chat_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<OcrSummary, String> {
+    scan_images(task_registry, chat_id, limit)
+}
+
+/// `run_ocr` for callers outside the webview, such as scheduled cache refreshes
+fn scan_images(task_registry: &tasks::TaskRegistry, chat_id: Option<i64>, limit: Option<i64>) -> Result<OcrSummary, String> {
+    let mut summary = OcrSummary {
+        available: false,
+        scanned: 0,
+        with_text: 0,
+        skipped: 0,
+        failed: 0,
+    };
+
+    let tesseract = match ocr::find_tesseract() {
+        Some(t) => t,
+        None => return Ok(summary),
+    };
+    summary.available = true;
+    let _span = tracing::info_span!("run_ocr", ?chat_id).entered();
+    let task = task_registry.begin(tasks::TaskKind::CacheRefresh, "Scanning images for text")?;
+
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, _schema) = chatdb::open(&path)?;
+    let mut cache = cache::open_cache_db()?;
+
+    let chat_sql = if chat_id.is_some() { "AND cmj.chat_id = ?" } else { "" };
+    let query = format!(
+        "SELECT a.ROWID, maj.message_id, cmj.chat_id, a.filename, a.mime_type
+         FROM attachment a
+         JOIN message_attachment_join maj ON a.ROWID = maj.attachment_id
+         LEFT JOIN chat_message_join cmj ON maj.message_id = cmj.message_id
+         WHERE a.mime_type LIKE 'image/%' AND a.filename IS NOT NULL {}
+         ORDER BY a.ROWID DESC",
+        chat_sql
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let images: Vec<(i64, i64, Option<i64>, String, String)> = stmt
+        .query_map(rusqlite::params_from_iter(chat_id.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let scratch_dir = std::env::temp_dir().join("message-insights-ocr");
+    std::fs::create_dir_all(&scratch_dir)
+        .map_err(|e| format!("Cannot create OCR scratch directory: {}", e))?;
+
+    for (attachment_id, message_id, msg_chat_id, filename, mime_type) in images {
+        // Everything scanned so far stays in the cache when cancelled
+        // Failures use up the limit too, or a folder of unreadable images would scan forever
+        if task.is_cancelled() || limit.map(|l| summary.scanned + summary.failed >= l).unwrap_or(false) {
+            break;
+        }
+
+        let already_scanned: bool = cache
+            .query_row(
+                "SELECT 1 FROM ocr_results WHERE attachment_id = ?",
+                [attachment_id],
+                |_| Ok(true),
+            )
+            .unwrap_or(false);
+        if already_scanned {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let filename = expand_home_path(filename);
+        let text = match ocr::extract_image_text(
+            &tesseract,
+            std::path::Path::new(&filename),
+            &mime_type,
+            &scratch_dir,
+        ) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!("OCR failed for attachment {}: {}", attachment_id, e);
+                summary.failed += 1;
+                cache
+                    .execute(
+                        "INSERT OR REPLACE INTO ocr_results (attachment_id, message_id, chat_id, text, scanned_at, status)
+                         VALUES (?, ?, ?, '', ?, 'failed')",
+                        rusqlite::params![attachment_id, message_id, msg_chat_id, Utc::now().timestamp()],
+                    )
+                    .map_err(|e| format!("Cache error: {}", e))?;
+                continue;
+            }
+        };
+        summary.scanned += 1;
+
+        let tx = cache.transaction().map_err(|e| format!("Cache error: {}", e))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO ocr_results (attachment_id, message_id, chat_id, text, scanned_at)
+             VALUES (?, ?, ?, ?, ?)",
+            rusqlite::params![attachment_id, message_id, msg_chat_id, text, Utc::now().timestamp()],
+        )
+        .map_err(|e| format!("Cache error: {}", e))?;
+        if !text.is_empty() {
+            summary.with_text += 1;
+            tx.execute(
+                "INSERT INTO ocr_text_fts (rowid, text, message_id, chat_id) VALUES (?, ?, ?, ?)",
+                rusqlite::params![attachment_id, text, message_id, msg_chat_id],
+            )
+            .map_err(|e| format!("Cache error: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Cache error: {}", e))?;
+    }
+
+    Ok(summary)
+}
+
+/// Search message text and OCR'd attachment text
+pub fn search_messages(query: String, limit: Option<i64>) -> Result<Vec<SearchHit>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    let limit = limit.unwrap_or(200);
+    let trimmed = query.trim();
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Plain message text
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.ROWID, cmj.chat_id, m.date, m.text
+             FROM message m
+             LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+             WHERE m.text LIKE ? ESCAPE '\\'
+             ORDER BY m.date DESC
+             LIMIT ?",
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+    let pattern = format!(
+        "%{}%",
+        trimmed.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+    );
+    let text_hit = |message_id: i64, chat_id: Option<i64>, mac_date: i64, text: String| {
+        let highlights = search::find_matches(&text, trimmed);
+        let (snippet, snippet_highlights) = search::snippet(&text, &highlights);
+        SearchHit {
+            message_id,
+            chat_id,
+            date: mac_timestamp_to_unix(mac_date),
+            source: "text".to_string(),
+            text,
+            highlights,
+            snippet,
+            snippet_highlights,
+        }
+    };
+    let mut hits: Vec<SearchHit> = stmt
+        .query_map(rusqlite::params![pattern, limit], |row| {
+            Ok(text_hit(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Newer macOS often leaves `text` empty and keeps the words only in attributedBody, which
+    // SQL can't search, so those are decoded and matched here, newest first
+    if schema.has("attributedBody") {
+        let mut body_stmt = conn
+            .prepare(
+                "SELECT m.ROWID, cmj.chat_id, m.date, m.attributedBody
+                 FROM message m
+                 LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+                 WHERE (m.text IS NULL OR m.text = '') AND m.attributedBody IS NOT NULL
+                 ORDER BY m.date DESC",
+            )
+            .map_err(|e| format!("Query error: {}", e))?;
+        let mut rows = body_stmt.query([]).map_err(|e| format!("Query error: {}", e))?;
+        let mut found = 0;
+        while found < limit {
+            let Some(row) = rows.next().map_err(|e| format!("Query error: {}", e))? else {
+                break;
+            };
+            let (Ok(message_id), Ok(chat_id), Ok(mac_date), Ok(body)) =
+                (row.get(0), row.get(1), row.get(2), row.get::<_, Vec<u8>>(3))
+            else {
+                continue;
+            };
+            let Some(text) = decode_message_text(None, Some(&body)) else { continue };
+            if search::find_matches(&text, trimmed).is_empty() {
+                continue;
+            }
+            hits.push(text_hit(message_id, chat_id, mac_date, text));
+            found += 1;
+        }
+    }
+
+    // Text recognized in image attachments; quote the query so FTS syntax characters are literal
+    if let Ok(cache) = cache::open_cache_db() {
+        let fts_query = format!("\"{}\"", trimmed.replace('"', "\"\""));
+        // Matches are marked by FTS5 itself, so highlights follow its tokenizer rather than a substring scan
+        if let Ok(mut ocr_stmt) = cache.prepare(
+            "SELECT message_id, chat_id,
+                    highlight(ocr_text_fts, 0, char(2), char(3)),
+                    snippet(ocr_text_fts, 0, char(2), char(3), '…', 16)
+             FROM ocr_text_fts WHERE ocr_text_fts MATCH ? LIMIT ?",
+        ) {
+            let ocr_rows: Vec<(i64, Option<i64>, String, String)> = ocr_stmt
+                .query_map(rusqlite::params![fts_query, limit], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default();
+
+            for (message_id, chat_id, marked_text, marked_snippet) in ocr_rows {
+                let (text, highlights) = search::parse_marked(&marked_text);
+                let (snippet, snippet_highlights) = search::parse_marked(&marked_snippet);
+                let mac_date: i64 = conn
+                    .query_row("SELECT date FROM message WHERE ROWID = ?", [message_id], |row| row.get(0))
+                    .unwrap_or(0);
+                hits.push(SearchHit {
+                    message_id,
+                    chat_id,
+                    date: mac_timestamp_to_unix(mac_date),
+                    source: "ocr".to_string(),
+                    text,
+                    highlights,
+                    snippet,
+                    snippet_highlights,
+                });
+            }
+        }
+    }
+
+    hits.sort_by_key(|h| std::cmp::Reverse(h.date));
+    hits.truncate(limit as usize);
+    Ok(hits)
+}
+
+/// Add a user-defined tag to a contact
+pub fn tag_contact(identifier: String, tag: String) -> Result<(), String> {
+    let tag = tags::normalize_tag(&tag);
+    if tag.is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+    let conn = store::open_store_db()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO contact_tags (identifier, tag, created_at) VALUES (?, ?, ?)",
+        rusqlite::params![identifier, tag, Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// Remove a user-defined tag from a contact
+pub fn untag_contact(identifier: String, tag: String) -> Result<(), String> {
+    let conn = store::open_store_db()?;
+    conn.execute(
+        "DELETE FROM contact_tags WHERE identifier = ? AND tag = ?",
+        rusqlite::params![identifier, tags::normalize_tag(&tag)],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// List every known tag (AddressBook groups and user tags)
+pub fn get_tags() -> Result<Vec<String>, String> {
+    let mut all: Vec<String> = tags::get_contact_tags()
+        .into_values()
+        .flatten()
+        .collect();
+    all.sort();
+    all.dedup();
+    Ok(all)
+}
+
+/// Compare volume, response time, sentiment and emoji usage across contact tags
+pub fn compare_groups(tags: Vec<String>, options: Option<ExportOptions>) -> Result<Vec<GroupComparison>, String> {
+    let base = options.unwrap_or_default();
+    let mut results = Vec::new();
+
+    for tag in tags {
+        let mut opts = base.clone();
+        opts.tags = Some(vec![tag.clone()]);
+        let messages = get_messages(Some(opts), None)?;
+        results.push(GroupComparison {
+            tag,
+            metrics: analytics::compute_metrics(&messages),
+        });
+    }
+
+    Ok(results)
+}
+
+/// Get Unix timestamps for the start and end of a calendar day in the configured timezone
+pub fn local_day_bounds(day: &str) -> Option<(i64, i64)> {
+    let date = chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()?;
+    let settings = settings::load_settings();
+    let start = settings::from_local_time(date.and_hms_opt(0, 0, 0)?, &settings)?;
+    let end = settings::from_local_time(date.succ_opt()?.and_hms_opt(0, 0, 0)?, &settings)? - 1;
+    Some((start, end))
+}
+
+/// Get the top N busiest days (overall or for one chat) and detect record-breaking days
+pub fn get_busiest_days(
+    limit: Option<usize>,
+    chat_id: Option<i64>,
+    recent_days: Option<i64>,
+    source: Option<archive::DataSource>,
+) -> Result<BusiestDaysReport, String> {
+    let limit = limit.unwrap_or(10);
+    let rows = if source == Some(archive::DataSource::Archive) {
+        archive::day_chat_counts(chat_id)?
+    } else {
+        live_day_chat_counts(chat_id)?
+    };
+
+    // Fold into per-day totals, remembering the chat with the most messages that day
+    let mut totals: HashMap<String, (i64, Option<i64>, i64)> = HashMap::new();
+    for (day, row_chat_id, count) in rows {
+        let entry = totals.entry(day).or_insert((0, None, 0));
+        entry.0 += count;
+        if count > entry.2 {
+            entry.1 = row_chat_id;
+            entry.2 = count;
+        }
+    }
+
+    // Record progression in chronological order
+    let mut chronological: Vec<(&String, i64)> = totals.iter().map(|(d, t)| (d, t.0)).collect();
+    chronological.sort();
+    let mut record_days: Vec<(String, i64)> = Vec::new();
+    for (day, count) in chronological {
+        if record_days.last().map(|(_, best)| count > *best).unwrap_or(true) {
+            record_days.push((day.clone(), count));
+        }
+    }
+
+    let app_settings = settings::load_settings();
+    let recent_cutoff = settings::to_local_time(Utc::now().timestamp() - recent_days.unwrap_or(7) * 86_400, &app_settings)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let is_new_record = record_days.len() > 1
+        && record_days.last().map(|(d, _)| *d >= recent_cutoff).unwrap_or(false);
+
+    let mut ranked: Vec<_> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| b.0.cmp(&a.0)));
+    ranked.truncate(limit);
+
+    let chat_names: HashMap<i64, String> = load_chats()
+        .map(|chats| {
+            chats
+                .into_iter()
+                .map(|c| (c.id, c.display_name.unwrap_or(c.chat_identifier)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Text messages of each ranked day's dominant chat, read in one pass over the span of the
+    // ranked days and bucketed by local day; other days and chats in the span are skipped
+    let mut day_messages: HashMap<(String, i64), Vec<MessageExtract>> = ranked
+        .iter()
+        .filter_map(|(day, (_, dominant, _))| dominant.map(|chat| ((day.clone(), chat), Vec::new())))
+        .collect();
+    let bounds: Vec<(i64, i64)> = ranked.iter().filter_map(|(day, _)| local_day_bounds(day)).collect();
+    if !day_messages.is_empty() {
+        let mut chat_ids: Vec<i64> = day_messages.keys().map(|(_, chat)| *chat).collect();
+        chat_ids.sort();
+        chat_ids.dedup();
+        let opts = ExportOptions {
+            start_date: bounds.iter().map(|(start, _)| *start).min(),
+            end_date: bounds.iter().map(|(_, end)| *end).max(),
+            chat_ids: Some(chat_ids),
+            ..Default::default()
+        };
+        let collect = |m: Message| {
+            let (Some(chat), Some(local)) = (m.chat_id, settings::to_local_time(m.date, &app_settings)) else {
+                return;
+            };
+            if m.text.is_none() {
+                return;
+            }
+            if let Some(bucket) = day_messages.get_mut(&(local.format("%Y-%m-%d").to_string(), chat)) {
+                bucket.push(MessageExtract {
+                    id: m.id,
+                    date: m.date,
+                    sender_name: m.sender_name,
+                    text: m.text,
+                });
+            }
+        };
+        if source == Some(archive::DataSource::Archive) {
+            archive::load_messages(Some(&opts), None)?.into_iter().for_each(collect);
+        } else {
+            let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+            for_each_message(&path, Some(&opts), &get_contact_names(), None, None, collect)?;
+        }
+    }
+
+    let mut days = Vec::new();
+    for (day, (count, dominant_chat_id, dominant_chat_count)) in ranked {
+        // Five text messages from the middle of the dominant chat's day, oldest first
+        let extract = match dominant_chat_id.and_then(|chat| day_messages.remove(&(day.clone(), chat))) {
+            Some(mut messages) => {
+                messages.reverse();
+                let from = (messages.len() / 2).saturating_sub(2);
+                messages.into_iter().skip(from).take(5).collect()
+            }
+            None => Vec::new(),
+        };
+
+        days.push(BusyDay {
+            date: day,
+            message_count: count,
+            dominant_chat_name: dominant_chat_id.and_then(|id| chat_names.get(&id).cloned()),
+            dominant_chat_id,
+            dominant_chat_count,
+            extract,
+        });
+    }
+
+    Ok(BusiestDaysReport {
+        days,
+        record_days,
+        is_new_record,
+    })
+}
+
+/// Live message counts per (local day, chat) for the busiest days report
+fn live_day_chat_counts(chat_id: Option<i64>) -> Result<Vec<(String, Option<i64>, i64)>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+    let chat_sql = if chat_id.is_some() { "AND cmj.chat_id = ?" } else { "" };
+
+    let query = format!(
+        "SELECT {day} AS day, cmj.chat_id, COUNT(*)
+         FROM message m
+         LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         WHERE m.date > 0
+           AND {content}
+           {chat}
+         GROUP BY day, cmj.chat_id",
+        day = schema.local_day_sql(&settings::load_settings()),
+        content = schema.content_filter(),
+        chat = chat_sql
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(chat_id.iter()), |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+/// Get the persisted app settings. The API token is left out: it belongs in the Keychain, and
+/// `get_api_server_status` hands it out while the API runs.
+pub fn get_settings() -> settings::AppSettings {
+    let mut app_settings = settings::load_settings();
+    app_settings.api_token = None;
+    app_settings
+}
+
+/// Validate and persist app settings
+pub fn update_settings(mut new_settings: settings::AppSettings) -> Result<(), String> {
+    settings::validate_settings(&new_settings)?;
+    // Not shown by get_settings, so keep any token an older version left until it is moved
+    new_settings.api_token = settings::load_settings().api_token;
+    settings::save_settings(&new_settings)
+}
+
+/// Report late-night messaging: share during quiet hours, monthly trend and the contacts driving it
+pub fn get_night_owl_report(
+    quiet_start_hour: Option<u32>,
+    quiet_end_hour: Option<u32>,
+    options: Option<ExportOptions>,
+) -> Result<analytics::NightOwlReport, String> {
+    let start = quiet_start_hour.unwrap_or(23);
+    let end = quiet_end_hour.unwrap_or(6);
+    if start > 23 || end > 23 {
+        return Err("Quiet hours must be between 0 and 23".to_string());
+    }
+
+    let messages = get_messages(options, None)?;
+    Ok(analytics::night_owl_report(&messages, start, end, &settings::load_settings()))
+}
+
+/// Get per-conversation tempo (messages per minute and alternation within sessions), conversations in parallel
+pub fn get_conversation_tempo(
+    options: Option<ExportOptions>,
+    session_gap_minutes: Option<i64>,
+) -> Result<Vec<analytics::ChatTempo>, String> {
+    let gap = session_gap_minutes.unwrap_or(analytics::DEFAULT_SESSION_GAP_MINUTES);
+    let settings = settings::load_settings();
+    let per_chat = parallel::map_chats(options.as_ref(), |chat_id, messages| {
+        (!messages.is_empty()).then(|| analytics::conversation_tempo(chat_id, messages, gap, &settings))
+    })?;
+    let mut tempos: Vec<analytics::ChatTempo> = per_chat.into_iter().filter_map(|(_, t)| t).collect();
+    tempos.sort_by(|a, b| b.overall.messages.cmp(&a.overall.messages).then(a.chat_id.cmp(&b.chat_id)));
+    Ok(tempos)
+}
+
+/// Get the distribution of a metric (message length, reply latency, session length or
+/// attachment size) as a histogram, for charts that need no dedicated aggregation
+pub fn get_histogram(
+    metric: histogram::HistogramMetric,
+    options: Option<ExportOptions>,
+    buckets: Option<usize>,
+) -> Result<histogram::Histogram, String> {
+    let messages = get_messages(options, None)?;
+    Ok(histogram::histogram(&messages, metric, buckets.unwrap_or(histogram::DEFAULT_BUCKETS)))
+}
+
+/// List the metrics `compute_metrics` can compute
+pub fn list_metrics() -> Vec<metrics::MetricInfo> {
+    metrics::list_metrics()
+}
+
+/// Compute registered metrics by name over one filtered set of messages
+pub fn compute_metrics(
+    names: Vec<String>,
+    options: Option<ExportOptions>,
+    params: Option<metrics::MetricParams>,
+) -> Result<Vec<metrics::MetricResult>, String> {
+    let messages = get_messages(options, None)?;
+    let settings = settings::load_settings();
+    let params = params.unwrap_or_default();
+    metrics::compute(&names, &messages, &metrics::MetricContext::new(&settings, &params))
+}
+
+/// Get a weighted co-membership graph of contacts across group chats
+pub fn get_social_graph(options: Option<ExportOptions>) -> Result<social::SocialGraph, String> {
+    snapshots::cached("social_graph", &options, options.as_ref(), || {
+        let chats = load_chats()?;
+        let messages = get_messages(options.clone(), None)?;
+        Ok(social::build_social_graph(&chats, &messages))
+    })
+}
+
+/// Get who-reacts-to-whom counts for a group chat, normalized by each member's message count
+pub fn get_reaction_matrix(chat_id: i64, options: Option<ExportOptions>) -> Result<social::ReactionMatrix, String> {
+    let chat = load_chats()?
+        .into_iter()
+        .find(|c| c.id == chat_id)
+        .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+
+    let mut opts = options.unwrap_or_default();
+    opts.chat_ids = Some(vec![chat_id]);
+    let messages = get_messages(Some(opts), None)?;
+    Ok(social::build_reaction_matrix(&chat, &messages))
+}
+
+/// Get a chat's title for reports and exports
+pub fn chat_title(chat: &Chat) -> String {
+    chat.display_name
+        .clone()
+        .unwrap_or_else(|| chat.participants.join(", "))
+}
+
+/// Group renames per chat, from the "named the conversation" events (item_type 2) whose
+/// group_title holds the new name
+fn group_name_history(conn: &Connection, schema: &chatdb::ChatDbSchema) -> Result<HashMap<i64, Vec<(i64, String)>>, String> {
+    let mut history: HashMap<i64, Vec<(i64, String)>> = HashMap::new();
+    if !schema.has("item_type") || !schema.has("group_title") {
+        return Ok(history);
+    }
+    let query = format!(
+        "SELECT cmj.chat_id, {}, COALESCE(m.group_title, '')
+         FROM message m
+         JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         WHERE m.item_type = 2 AND m.date > 0
+         ORDER BY m.date, m.ROWID",
+        schema.unix_seconds_sql("m.date")
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    let rows: Vec<(i64, i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (chat_id, date, name) in rows {
+        history.entry(chat_id).or_default().push((date, name));
+    }
+    Ok(history)
+}
+
+/// The chat's name as it was at `unix`: the latest rename up to then, or the participants
+/// before it was first named. Chats without rename events use their current title.
+fn chat_name_at(chat: &Chat, unix: i64) -> String {
+    if chat.name_history.is_empty() {
+        return chat_title(chat);
+    }
+    match chat.name_history.iter().rev().find(|(date, _)| *date <= unix) {
+        Some((_, name)) if !name.is_empty() => name.clone(),
+        _ => chat.participants.join(", "),
+    }
+}
+
+/// Header lines listing a chat's renames, oldest first
+fn name_history_notes(chat: &Chat, settings: &settings::AppSettings) -> Vec<String> {
+    chat.name_history
+        .iter()
+        .map(|(date, name)| match name.as_str() {
+            "" => format!("Name removed on {}", settings::format_date(*date, settings)),
+            _ => format!("Named \"{}\" on {}", name, settings::format_date(*date, settings)),
+        })
+        .collect()
+}
+
+/// Put a "Named the conversation" line in the export stream at each rename within the exported
+/// range, returning how many were added. Messages must be oldest first.
+fn insert_rename_markers(messages: &mut Vec<Message>, chat: &Chat, opts: &ExportOptions) -> usize {
+    let in_range = |date: i64| {
+        opts.start_date.map_or(true, |start| date >= start) && opts.end_date.map_or(true, |end| date <= end)
+    };
+    let mut added = 0;
+    for (date, name) in chat.name_history.iter().filter(|(date, _)| in_range(*date)) {
+        let text = match name.as_str() {
+            "" => "Removed the conversation name".to_string(),
+            _ => format!("Named the conversation \"{}\"", name),
+        };
+        let marker = Message {
+            id: 0,
+            guid: format!("rename:{}:{}", chat.id, date),
+            text: Some(text),
+            date: *date,
+            date_formatted: Utc
+                .timestamp_opt(*date, 0)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            is_from_me: false,
+            handle_id: 0,
+            contact_identifier: String::new(),
+            sender_name: "Conversation".to_string(),
+            chat_id: Some(chat.id),
+            has_attachment: false,
+            attachments: Vec::new(),
+            reactions: Vec::new(),
+            delivery: DeliveryMetadata::default(),
+        };
+        // After the messages sent at the same second, which include the rename event itself
+        let at = messages.partition_point(|m| m.date <= *date);
+        messages.insert(at, marker);
+        added += 1;
+    }
+    added
+}
+
+/// The chat's note followed by its participants' notes, labelled by name, for report headers
+fn chat_header_notes(chat: &Chat) -> Vec<String> {
+    let contact_notes = notes::load_notes(notes::NoteTarget::Contact);
+    let participant_notes = chat
+        .participant_ids
+        .iter()
+        .zip(&chat.participants)
+        .filter_map(|(id, name)| contact_notes.get(id).map(|note| format!("{}: {}", name, note)));
+    chat.note.clone().into_iter().chain(participant_notes).collect()
+}
+
+/// Export a chat to a file in the given format
+pub fn export_chat(
+    task_registry: &tasks::TaskRegistry,
+    chat_id: i64,
+    format: export::ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<export::ExportResult, String> {
+    write_chat_export(task_registry, chat_id, format, path, options)
+}
+
+/// `export_chat` for callers outside the webview: the API, automation URLs, jobs and the CLI
+pub fn write_chat_export(
+    task_registry: &tasks::TaskRegistry,
+    chat_id: i64,
+    format: export::ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<export::ExportResult, String> {
+    let mut opts = options.unwrap_or_default();
+    let forensic = opts.forensic.unwrap_or(false);
+    // Forensic exports hold exactly one chat.db chat; others cover the whole conversation
+    let chat = if forensic {
+        load_chats()?.into_iter().find(|c| c.id == chat_id)
+    } else {
+        conversations::conversation_of(chat_id)?
+    }
+    .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+
+    let _span = tracing::info_span!("export_chat", chat_id, ?format).entered();
+    opts.chat_ids = Some(if forensic { vec![chat_id] } else { conversations::chat_ids(&chat) });
+    opts.exact_chat_ids = true;
+
+    if forensic {
+        if format != export::ExportFormat::Txt {
+            return Err("Forensic exports are paginated plain text; use the txt format".to_string());
+        }
+        if opts.source == Some(archive::DataSource::Archive) {
+            return Err("Forensic exports read chat.db directly, not the archive".to_string());
+        }
+        // Exactly what chat.db holds: every row of the chat, reactions and recently deleted ones
+        // included, with no imports, collapsed corrections, or dropped or regrouped media
+        opts.include_imported = Some(false);
+        opts.collapse_corrections = Some(false);
+        opts.drop_live_photo_videos = Some(false);
+        opts.include_system_messages = Some(true);
+        opts.include_app_messages = Some(true);
+        opts.include_audio_transcripts = Some(true);
+        opts.include_recoverable = Some(true);
+        opts.include_reaction_rows = Some(true);
+        if opts.split_by.is_some() || opts.split_max_bytes.is_some() {
+            return Err("Forensic exports can't be split; their page numbering spans one file".to_string());
+        }
+        if opts.anonymize.unwrap_or(false) {
+            return Err("Forensic exports record handles as stored and can't be anonymized".to_string());
+        }
+        if opts.media_filter.is_some_and(|f| f != media_filter::MediaFilter::All) {
+            return Err("Forensic exports list every attachment and can't filter media".to_string());
+        }
+    }
+    let task = task_registry.begin(tasks::TaskKind::Export, format!("Exporting {}", chat_title(&chat)))?;
+
+    let mut messages = get_messages(Some(opts.clone()), None)?;
+    messages.reverse(); // Oldest first
+    task.check_cancelled()?;
+
+    // Notes name people, so anonymized exports leave them out. Forensic exports have no header
+    // notes and hold nothing chat.db doesn't.
+    let anonymize = opts.anonymize.unwrap_or(false);
+    let mut header_notes = if opts.include_notes.unwrap_or(false) && !anonymize && !forensic {
+        chat_header_notes(&chat)
+    } else {
+        Vec::new()
+    };
+    // Names often contain people's names, so anonymized exports keep only the pseudonymous title
+    let mut markers = 0;
+    if !anonymize && !forensic {
+        header_notes.extend(name_history_notes(&chat, &settings::load_settings()));
+        markers = insert_rename_markers(&mut messages, &chat, &opts);
+    }
+    let title = if anonymize {
+        let key = anonymize::pseudonym_key()?;
+        anonymize::anonymize_messages(&mut messages, &key);
+        anonymize::anonymized_title(&chat, &key)
+    } else {
+        chat_title(&chat)
+    };
+
+    let filter = opts.media_filter.unwrap_or_default();
+    if filter != media_filter::MediaFilter::All {
+        let skipped = media_filter::apply(&mut messages, filter);
+        if skipped > 0 {
+            header_notes.push(format!("{} attachments left out by the media filter", skipped));
+        }
+        task.check_cancelled()?;
+    }
+
+    let output = std::path::Path::new(&path);
+    let mut result = if forensic {
+        let db_path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+        forensic::write_forensic_export(&title, chat_id, &messages, &db_path, output)?
+    } else if opts.split_by.is_some() || opts.split_max_bytes.is_some() {
+        split::write_split_export(&title, &header_notes, &messages, format, &opts, output)?
+    } else {
+        export::write_export(&title, &header_notes, &messages, format, &opts, output)?
+    };
+    result.message_count -= markers as i64;
+
+    tracing::info!(messages = result.message_count, files = result.files.len(), "export finished");
+    if let Err(e) = export_history::record(chat_id, format, &path, &opts, &result) {
+        tracing::warn!("Cannot record export job: {}", e);
+    }
+
+    remember_export_dir(output);
+    Ok(result)
+}
+
+/// Copy one chat's attachments into a folder, filtered by date, kind, size and sender and
+/// optionally without stickers and GIFs, with a manifest of the copied files
+pub fn export_attachments(
+    task_registry: &tasks::TaskRegistry,
+    chat_id: i64,
+    dir: String,
+    filter: Option<attachment_export::AttachmentFilter>,
+) -> Result<attachment_export::AttachmentExportResult, String> {
+    let chat = load_chats()?
+        .into_iter()
+        .find(|c| c.id == chat_id)
+        .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+    let task = task_registry.begin(tasks::TaskKind::Export, format!("Exporting attachments of {}", chat_title(&chat)))?;
+
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+    let dir = std::path::PathBuf::from(expand_home_path(dir));
+    let result = attachment_export::export_attachments(&conn, &schema, chat_id, &filter.unwrap_or_default(), &dir, &task)?;
+
+    tracing::info!(exported = result.exported, missing = result.missing, "attachment export finished");
+    remember_export_dir(&dir.join("manifest.json"));
+    Ok(result)
+}
+
+/// Remembered for the tray's "Open Last Export Folder"
+fn remember_export_dir(output: &std::path::Path) {
+    if let Some(dir) = output.parent() {
+        let mut app_settings = settings::load_settings();
+        app_settings.last_export_dir = Some(dir.to_string_lossy().to_string());
+        if let Err(e) = settings::save_settings(&app_settings) {
+            tracing::warn!("Cannot remember export folder: {}", e);
+        }
+    }
+}
+
+/// Export everything exchanged with one contact: their 1:1 chats in full plus the messages they
+/// sent in group chats, as one chronological document with group messages labelled by chat
+pub fn export_contact(
+    task_registry: &tasks::TaskRegistry,
+    contact_id: i64,
+    format: export::ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<export::ExportResult, String> {
+    let chats = get_chats_for_contact(contact_id)?;
+    if chats.is_empty() {
+        return Err(format!("No chats with contact {}", contact_id));
+    }
+    let mut opts = options.unwrap_or_default();
+    if opts.forensic.unwrap_or(false) {
+        return Err("Forensic exports cover a single chat; use export_chat".to_string());
+    }
+
+    let _span = tracing::info_span!("export_contact", contact_id, ?format).entered();
+    let task = task_registry.begin(tasks::TaskKind::Export, format!("Exporting contact {}", contact_id))?;
+    opts.chat_ids = Some(chats.iter().map(|c| c.chat.id).collect());
+    let mut messages = get_messages(Some(opts.clone()), None)?;
+    task.check_cancelled()?;
+
+    // Group chats contribute only what this person said there
+    let groups: HashMap<i64, String> = chats
+        .iter()
+        .filter(|c| c.chat.is_group)
+        .map(|c| (c.chat.id, c.title.clone()))
+        .collect();
+    messages.retain(|m| match m.chat_id.filter(|id| groups.contains_key(id)) {
+        Some(_) => !m.is_from_me && m.handle_id == contact_id,
+        None => true,
+    });
+    messages.sort_by_key(|m| (m.date, m.id));
+
+    let identifier = messages
+        .iter()
+        .find(|m| m.handle_id == contact_id && !m.contact_identifier.is_empty())
+        .map(|m| m.contact_identifier.clone())
+        .unwrap_or_else(|| contact_id.to_string());
+    let anonymize = opts.anonymize.unwrap_or(false);
+    let (title, group_labels) = if anonymize {
+        let key = anonymize::pseudonym_key()?;
+        anonymize::anonymize_messages(&mut messages, &key);
+        // Group names often contain people's names
+        let mut ids: Vec<i64> = groups.keys().copied().collect();
+        ids.sort();
+        let labels = ids.iter().enumerate().map(|(i, id)| (*id, format!("group chat {}", i + 1))).collect();
+        (anonymize::pseudonym(&key, &identifier), labels)
+    } else {
+        let name = lookup_contact_name(&identifier, &get_contact_names()).unwrap_or_else(|| identifier.clone());
+        (name, groups.clone())
+    };
+    let group_chats: HashMap<i64, &Chat> = chats.iter().map(|c| (c.chat.id, &c.chat)).collect();
+    for msg in messages.iter_mut() {
+        let Some((chat_id, label)) = msg.chat_id.and_then(|id| group_labels.get(&id).map(|l| (id, l))) else {
+            continue;
+        };
+        // Labelled with the group's name when the message was sent
+        let label = match group_chats.get(&chat_id) {
+            Some(chat) if !anonymize => chat_name_at(chat, msg.date),
+            _ => label.clone(),
+        };
+        msg.sender_name = format!("{} (in {})", msg.sender_name, label);
+    }
+
+    let mut header_notes = vec![format!(
+        "{} conversations: {} direct, {} group",
+        chats.len(),
+        chats.len() - groups.len(),
+        groups.len()
+    )];
+    let filter = opts.media_filter.unwrap_or_default();
+    if filter != media_filter::MediaFilter::All {
+        let skipped = media_filter::apply(&mut messages, filter);
+        if skipped > 0 {
+            header_notes.push(format!("{} attachments left out by the media filter", skipped));
+        }
+    }
+    task.check_cancelled()?;
+
+    let output = std::path::Path::new(&path);
+    let result = if opts.split_by.is_some() || opts.split_max_bytes.is_some() {
+        split::write_split_export(&title, &header_notes, &messages, format, &opts, output)?
+    } else {
+        export::write_export(&title, &header_notes, &messages, format, &opts, output)?
+    };
+    tracing::info!(messages = result.message_count, files = result.files.len(), "contact export finished");
+    remember_export_dir(output);
+    Ok(result)
+}
+
+/// Where a contact ranks among all contacts on volume, reply speed, streaks and reactions
+pub fn get_contact_ranking(contact_id: i64, options: Option<ExportOptions>) -> Result<ranking::ContactRanking, String> {
+    snapshots::cached("contact_ranking", &(contact_id, &options), options.as_ref(), || {
+        let messages = get_messages(options.clone(), None)?;
+        ranking::contact_ranking(&messages, contact_id, &settings::load_settings())
+    })
+}
+
+/// A page of everything one handle sent, across their direct and group chats, newest first
+pub fn get_person_timeline(
+    contact_id: i64,
+    options: Option<ExportOptions>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<PersonTimelinePage, String> {
+    let chats: HashMap<i64, Chat> = load_chats()?.into_iter().map(|c| (c.id, c)).collect();
+    let mut opts = options.unwrap_or_default();
+    opts.contact_ids = Some(vec![contact_id]);
+    let mut messages = get_messages(Some(opts), None)?;
+    // Direct chats store my messages under the other person's handle too
+    messages.retain(|m| !m.is_from_me);
+    messages.sort_by_key(|m| std::cmp::Reverse((m.date, m.id)));
+
+    let total = messages.len();
+    let offset = offset.unwrap_or(0).min(total);
+    let limit = limit.unwrap_or(100);
+    let items: Vec<PersonTimelineItem> = messages
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|message| {
+            let chat = message.chat_id.and_then(|id| chats.get(&id));
+            PersonTimelineItem {
+                chat_id: message.chat_id,
+                chat_title: chat.map(|c| chat_name_at(c, message.date)).unwrap_or_default(),
+                is_group: chat.is_some_and(|c| c.is_group),
+                message,
+            }
+        })
+        .collect();
+
+    Ok(PersonTimelinePage {
+        contact_id,
+        total,
+        offset,
+        has_more: offset + items.len() < total,
+        items,
+    })
+}
+
+/// List every chat (1:1 and group) a handle participates in, with per-chat message counts
+pub fn get_chats_for_contact(contact_id: i64) -> Result<Vec<ContactChat>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, _schema) = chatdb::open(&path)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT chj.chat_id,
+                    (SELECT COUNT(*) FROM message m
+                     JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+                     WHERE cmj.chat_id = chj.chat_id AND m.handle_id = chj.handle_id AND m.is_from_me = 0)
+             FROM chat_handle_join chj
+             WHERE chj.handle_id = ?",
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+    let counts: HashMap<i64, i64> = stmt
+        .query_map([contact_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut chats: Vec<ContactChat> = load_chats()?
+        .into_iter()
+        .filter_map(|chat| {
+            let contact_message_count = *counts.get(&chat.id)?;
+            Some(ContactChat {
+                title: chat_title(&chat),
+                chat,
+                contact_message_count,
+            })
+        })
+        .collect();
+
+    chats.sort_by_key(|c| std::cmp::Reverse(c.chat.message_count));
+    Ok(chats)
+}
+
+/// Group chats containing both handles, busiest first, with each person's message count in them
+pub fn get_common_chats(contact_a: i64, contact_b: i64) -> Result<Vec<CommonChat>, String> {
+    let b_counts: HashMap<i64, i64> = get_chats_for_contact(contact_b)?
+        .into_iter()
+        .map(|c| (c.chat.id, c.contact_message_count))
+        .collect();
+
+    let mut common: Vec<CommonChat> = get_chats_for_contact(contact_a)?
+        .into_iter()
+        .filter(|c| c.chat.is_group)
+        .filter_map(|c| {
+            let contact_b_messages = *b_counts.get(&c.chat.id)?;
+            Some(CommonChat {
+                contact_a_messages: c.contact_message_count,
+                contact_b_messages,
+                title: c.title,
+                chat: c.chat,
+            })
+        })
+        .collect();
+    common.sort_by(|x, y| {
+        (y.contact_a_messages + y.contact_b_messages)
+            .cmp(&(x.contact_a_messages + x.contact_b_messages))
+            .then(x.chat.id.cmp(&y.chat.id))
+    });
+    Ok(common)
+}
+
+/// "Who introduced whom": contacts first met in a group chat, who brought them in, and when we
+/// first talked one-on-one. Live only: chats and their handles come from chat.db's own tables.
+pub fn get_introductions(source: Option<archive::DataSource>) -> Result<Vec<introductions::Introduction>, String> {
+    archive::require_live(source, "The introductions report")?;
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+    introductions::introductions(&conn, &schema, &load_chats()?, &get_contact_names())
+}
+
+/// Compare the main stats of two date ranges (e.g. this year vs last year); deltas are b relative to a
+pub fn compare_periods(
+    range_a: DateRange,
+    range_b: DateRange,
+    options: Option<ExportOptions>,
+    top_n: Option<usize>,
+) -> Result<analytics::PeriodComparison, String> {
+    let base = options.unwrap_or_default();
+    let fetch = |range: DateRange| {
+        let mut opts = base.clone();
+        opts.start_date = Some(range.start);
+        opts.end_date = Some(range.end);
+        get_messages(Some(opts), None)
+    };
+
+    let a_messages = fetch(range_a)?;
+    let b_messages = fetch(range_b)?;
+    Ok(analytics::compare_periods(&a_messages, &b_messages, top_n.unwrap_or(10)))
+}
+
+/// Compare two chats side by side (volume timeline, response times, emoji, sentiment) in one call
+pub fn compare_chats(chat_a: i64, chat_b: i64, options: Option<ExportOptions>) -> Result<analytics::ChatComparison, String> {
+    let chats = load_chats()?;
+    let base = options.unwrap_or_default();
+    let fetch = |chat_id: i64| -> Result<(i64, String, Vec<Message>), String> {
+        let chat = chats
+            .iter()
+            .find(|c| c.id == chat_id)
+            .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+        let mut opts = base.clone();
+        opts.chat_ids = Some(vec![chat_id]);
+        Ok((chat_id, chat_title(chat), get_messages(Some(opts), None)?))
+    };
+
+    let (a_id, a_title, a_messages) = fetch(chat_a)?;
+    let (b_id, b_title, b_messages) = fetch(chat_b)?;
+    Ok(analytics::compare_chats(
+        (a_id, a_title, &a_messages),
+        (b_id, b_title, &b_messages),
+        &settings::load_settings(),
+    ))
+}
+
+/// Decompose a chat's monthly volume into trend and seasonality, with detected change-points
+pub fn get_trend_decomposition(chat_id: i64, options: Option<ExportOptions>) -> Result<trends::TrendDecomposition, String> {
+    let mut opts = options.unwrap_or_default();
+    opts.chat_ids = Some(vec![chat_id]);
+    snapshots::cached("trend_decomposition", &opts, Some(&opts), || {
+        let messages = get_messages(Some(opts.clone()), None)?;
+        Ok(trends::decompose(chat_id, &messages, &settings::load_settings()))
+    })
+}
+
+/// Project each chat's message total for the current year from its pace and last year's seasonality
+pub fn get_year_end_projection(options: Option<ExportOptions>, limit: Option<usize>) -> Result<Vec<trends::YearProjection>, String> {
+    let mut opts = options.unwrap_or_default();
+    // Two calendar years back covers all of last year in any timezone
+    let floor = (Utc::now() - chrono::Duration::days(2 * 366)).timestamp();
+    opts.start_date = Some(opts.start_date.map_or(floor, |s| s.max(floor)));
+    // Projections depend on how much of the year has passed, so a snapshot only lasts the day
+    let key = (&opts, limit, Utc::now().date_naive());
+    snapshots::cached("year_end_projection", &key, Some(&opts), || {
+        let messages = get_messages(Some(opts.clone()), None)?;
+        let titles: HashMap<i64, String> = load_chats()?.iter().map(|c| (c.id, chat_title(c))).collect();
+
+        let mut projections = trends::year_end_projections(&messages, &titles, &settings::load_settings());
+        projections.truncate(limit.unwrap_or(20));
+        Ok(projections)
+    })
+}
+
+/// Cluster chats into behavioral archetypes ("daily companion", "planner group", ...) with the features behind each
+pub fn get_chat_archetypes(options: Option<ExportOptions>) -> Result<Vec<archetypes::ChatArchetype>, String> {
+    // Recency is measured from today, so a snapshot only lasts the day
+    let key = (&options, Utc::now().date_naive());
+    snapshots::cached("chat_archetypes", &key, options.as_ref(), || {
+        let messages = get_messages(options.clone(), None)?;
+        Ok(archetypes::classify_chats(&messages, &load_chats()?, Utc::now().timestamp()))
+    })
+}
+
+/// Import messages from another tool's CSV export into the app store
+pub fn import_csv(
+    task_registry: &tasks::TaskRegistry,
+    path: String,
+    column_mapping: imports::ColumnMapping,
+) -> Result<imports::ImportResult, String> {
+    let _task = task_registry.begin(tasks::TaskKind::Import, "Importing CSV")?;
+    imports::import_csv(std::path::Path::new(&expand_home_path(path)), &column_mapping)
+}
+
+/// Import an Android "SMS Backup & Restore" XML file into the app store
+pub fn import_sms_backup(
+    task_registry: &tasks::TaskRegistry,
+    path: String,
+) -> Result<imports::ImportResult, String> {
+    let _task = task_registry.begin(tasks::TaskKind::Import, "Importing SMS backup")?;
+    sms_backup::import_sms_backup(std::path::Path::new(&expand_home_path(path)))
+}
+
+/// Copy new chat.db and imported messages into the long-term archive
+pub fn sync_archive(task_registry: &tasks::TaskRegistry) -> Result<archive::ArchiveSyncResult, String> {
+    run_archive_sync(task_registry)
+}
+
+/// `sync_archive` for callers outside the webview: automation URLs and scheduled jobs
+fn run_archive_sync(task_registry: &tasks::TaskRegistry) -> Result<archive::ArchiveSyncResult, String> {
+    let _span = tracing::info_span!("sync_archive").entered();
+    let task = task_registry.begin(tasks::TaskKind::ArchiveSync, "Syncing archive")?;
+    let mut conn = archive::open_archive_db()?;
+
+    let live = get_messages(
+        Some(ExportOptions {
+            start_date: archive::resume_from(&conn),
+            ..Default::default()
+        }),
+        None,
+    )?;
+    let imported = imports::load_imported_messages(None)?;
+    task.check_cancelled()?;
+
+    let added = archive::archive_messages(&mut conn, archive::LIVE_SOURCE, &live)?
+        + archive::archive_messages(&mut conn, archive::IMPORT_SOURCE, &imported)?;
+    tracing::info!(scanned = live.len() + imported.len(), added, "archive synced");
+
+    Ok(archive::ArchiveSyncResult {
+        scanned: (live.len() + imported.len()) as i64,
+        added,
+        total: archive::archived_count(&conn),
+    })
+}
+
+/// Report archive size by chat and year
+pub fn get_archive_stats() -> Result<archive::ArchiveStats, String> {
+    archive::archive_stats()
+}
+
+/// Apply the attachment retention policy to the archive, optionally vacuuming afterwards
+pub fn compact_archive(
+    task_registry: &tasks::TaskRegistry,
+    vacuum: Option<bool>,
+) -> Result<archive::CompactionResult, String> {
+    let _task = task_registry.begin(tasks::TaskKind::ArchiveCompaction, "Compacting archive")?;
+    let settings = settings::load_settings();
+    archive::compact_archive(settings.archive_attachment_retention_years, vacuum.unwrap_or(false))
+}
+
+/// Start the opt-in localhost API and remember to start it with the app
+pub fn start_api_server(task_registry: &tasks::TaskRegistry) -> Result<api::ApiStatus, String> {
+    let mut settings = settings::load_settings();
+    let status = api::start(&settings, task_registry.clone())?;
+    if !settings.api_enabled {
+        settings.api_enabled = true;
+        settings::save_settings(&settings)?;
+    }
+    Ok(status)
+}
+
+/// Stop the localhost API and keep it off on the next launch
+pub fn stop_api_server() -> Result<(), String> {
+    api::stop();
+    let mut settings = settings::load_settings();
+    settings.api_enabled = false;
+    settings::save_settings(&settings)
+}
+
+/// Get the localhost API's address and token
+pub fn get_api_server_status() -> api::ApiStatus {
+    api::status()
+}
+
+/// Write per-contact and per-chat notes into an Obsidian vault, refreshing them on each run
+pub fn export_obsidian_vault(vault_path: String, options: Option<ExportOptions>) -> Result<obsidian::VaultExportResult, String> {
+    let vault = std::path::PathBuf::from(expand_home_path(vault_path));
+    if !vault.is_dir() {
+        return Err(format!("Vault folder not found: {}", vault.display()));
+    }
+
+    let chats = load_chats()?;
+    let mut messages = get_messages(options, None)?;
+    messages.reverse(); // Oldest first
+
+    obsidian::export_vault(&vault, &chats, &messages, &settings::load_settings())
+}
+
+/// Build the summary fields available to webhook templates
+fn summary_context(options: &ExportOptions) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let stats = get_chat_stats(Some(options.clone()))?;
+    let metrics = analytics::compute_metrics(&get_messages(Some(options.clone()), None)?);
+    let settings = settings::load_settings();
+    let day = |ts: Option<i64>| {
+        ts.and_then(|t| Utc.timestamp_opt(t, 0).single())
+            .map(|d| d.format("%Y-%m-%d").to_string())
+    };
+    let period_start = day(options.start_date.or(stats.date_range_start));
+    let period_end = day(options.end_date.or(stats.date_range_end));
+    let local_day = |ts: Option<i64>| ts.map(|t| settings::format_date(t, &settings));
+
+    let text = format!(
+        "{} messages ({} sent, {} received) with {} contacts between {} and {}",
+        settings::format_count(stats.total_messages, &settings),
+        settings::format_count(stats.messages_sent, &settings),
+        settings::format_count(stats.messages_received, &settings),
+        settings::format_count(stats.total_contacts, &settings),
+        local_day(options.start_date.or(stats.date_range_start)).as_deref().unwrap_or("the beginning"),
+        local_day(options.end_date.or(stats.date_range_end)).as_deref().unwrap_or("now"),
+    );
+
+    let mut context = serde_json::Map::new();
+    context.insert("text".into(), text.into());
+    context.insert("period_start".into(), period_start.into());
+    context.insert("period_end".into(), period_end.into());
+    context.insert("message_count".into(), stats.total_messages.into());
+    context.insert("messages_sent".into(), stats.messages_sent.into());
+    context.insert("messages_received".into(), stats.messages_received.into());
+    context.insert("contact_count".into(), stats.total_contacts.into());
+    context.insert("positive_ratio".into(), metrics.positive_ratio.into());
+    context.insert(
+        "top_emojis".into(),
+        metrics.top_emojis.iter().map(|(e, _)| e.clone()).collect::<Vec<_>>().into(),
+    );
+    Ok(context)
+}
+
+/// POST a summary of the filtered messages to a configured webhook
+pub fn push_summary_to_webhook(webhook: String, options: Option<ExportOptions>) -> Result<webhook::WebhookDelivery, String> {
+    let settings = settings::load_settings();
+    let config = webhook::find(&settings.webhooks, &webhook)?;
+    webhook::post(config, &summary_context(&options.unwrap_or_default())?)
+}
+
+/// Get stored weekly digests, newest first
+pub fn get_digests(limit: Option<i64>) -> Result<Vec<digest::WeeklyDigest>, String> {
+    digest::load_digests(limit.unwrap_or(52))
+}
+
+/// List notification triggers
+pub fn list_triggers() -> Result<Vec<triggers::Trigger>, String> {
+    triggers::list()
+}
+
+/// Create or update a notification trigger (a new id is assigned when empty)
+pub fn set_trigger(trigger: triggers::Trigger) -> Result<triggers::Trigger, String> {
+    triggers::save(trigger)
+}
+
+/// Delete a notification trigger
+pub fn delete_trigger(id: String) -> Result<(), String> {
+    triggers::delete(&id)
+}
+
+/// List the export templates that ship with the app
+pub fn list_export_templates() -> Vec<templates::ExportTemplate> {
+    templates::builtin_templates()
+}
+
+/// Check that a custom export template parses and renders
+pub fn validate_template(source: String) -> Result<(), String> {
+    templates::validate(&source)
+}
+
+// Neighbours returned on each side by get_context when not specified
+const DEFAULT_CONTEXT_MESSAGES: usize = 10;
+
+// Page size for get_messages_around_date when not specified
+const DEFAULT_PAGE_MESSAGES: usize = 50;
+
+/// Resolve a message GUID (from a search hit, digest or milestone) to the full message
+pub fn get_message_by_guid(guid: String) -> Result<Message, String> {
+    messages_by_guid(std::slice::from_ref(&guid))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Message {} not found", guid))
+}
+
+/// Load the messages with the given GUIDs in one query; GUIDs missing from chat.db are skipped
+fn messages_by_guid(guids: &[String]) -> Result<Vec<Message>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, _schema) = chatdb::open(&path)?;
+    let mut stmt = conn
+        .prepare("SELECT ROWID FROM message WHERE guid = ?")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let row_ids: Vec<i64> = guids
+        .iter()
+        .filter_map(|guid| stmt.query_row([guid], |row| row.get(0)).ok())
+        .collect();
+    if row_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    query_messages_near(&path, None, None, Some(Anchor::At(row_ids)), None)
+}
+
+/// Get a message with up to `before`/`after` neighbours from the same chat, for deep links
+pub fn get_context(guid: String, before: Option<usize>, after: Option<usize>) -> Result<MessageContext, String> {
+    let before = before.unwrap_or(DEFAULT_CONTEXT_MESSAGES);
+    let after = after.unwrap_or(DEFAULT_CONTEXT_MESSAGES);
+    let message = get_message_by_guid(guid)?;
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let options = ExportOptions {
+        chat_ids: message.chat_id.map(|id| vec![id]),
+        ..Default::default()
+    };
+
+    // Fetch one extra on each side to know whether there is more to scroll to. Both sides share
+    // one contact lookup, and reactions are only read for the messages returned.
+    let contact_names = get_contact_names();
+    let mut earlier = Vec::new();
+    let anchor = Some(Anchor::Before(message.id));
+    for_each_message(&path, Some(&options), &contact_names, anchor, Some(before as i64 + 1), |m| earlier.push(m))?;
+    let has_more_before = earlier.len() > before;
+    earlier.truncate(before);
+    earlier.reverse();
+
+    let mut later = Vec::new();
+    let anchor = Some(Anchor::After(message.id));
+    for_each_message(&path, Some(&options), &contact_names, anchor, Some(after as i64 + 1), |m| later.push(m))?;
+    let has_more_after = later.len() > after;
+    later.truncate(after);
+
+    Ok(MessageContext {
+        message,
+        before: earlier,
+        after: later,
+        has_more_before,
+        has_more_after,
+    })
+}
+
+/// Jump to a date in a chat: a page of `window` messages centred on `timestamp` (Unix seconds)
+pub fn get_messages_around_date(chat_id: i64, timestamp: i64, window: Option<usize>) -> Result<MessagePage, String> {
+    let window = window.unwrap_or(DEFAULT_PAGE_MESSAGES).max(1);
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    // First message on or after the date; past the end of the chat, fall back to the last one
+    let mac_date = schema.to_mac_time(timestamp);
+    let find_anchor = |cmp: &str, order: &str| {
+        conn.query_row(
+            &format!(
+                "SELECT m.guid FROM message m
+                 JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+                 WHERE cmj.chat_id = ?1 AND m.date {} ?2 AND m.date > 0 AND {}
+                 ORDER BY m.date {order}, m.ROWID {order} LIMIT 1",
+                cmp,
+                schema.content_filter(),
+                order = order
+            ),
+            rusqlite::params![chat_id, mac_date],
+            |row| row.get::<_, String>(0),
+        )
+    };
+    let anchor_guid = match find_anchor(">=", "ASC") {
+        Ok(guid) => Some(guid),
+        Err(rusqlite::Error::QueryReturnedNoRows) => find_anchor("<", "DESC").ok(),
+        Err(e) => return Err(format!("Query error: {}", e)),
+    };
+    let Some(anchor_guid) = anchor_guid else {
+        return Ok(MessagePage {
+            messages: Vec::new(),
+            anchor_guid: None,
+            before_cursor: None,
+            after_cursor: None,
+        });
+    };
+
+    let before = window / 2;
+    let context = get_context(anchor_guid.clone(), Some(before), Some(window - before - 1))?;
+    let mut messages = context.before;
+    messages.push(context.message);
+    messages.extend(context.after);
+
+    Ok(MessagePage {
+        before_cursor: messages.first().filter(|_| context.has_more_before).map(|m| m.guid.clone()),
+        after_cursor: messages.last().filter(|_| context.has_more_after).map(|m| m.guid.clone()),
+        anchor_guid: Some(anchor_guid),
+        messages,
+    })
+}
+
+/// Pin a message as a favorite, with an optional note; pinning again updates the note
+pub fn pin_message(guid: String, note: Option<String>) -> Result<(), String> {
+    get_message_by_guid(guid.clone())?;
+    pins::pin(&guid, note.as_deref())
+}
+
+/// Remove a message from the pinned list
+pub fn unpin_message(guid: String) -> Result<(), String> {
+    pins::unpin(&guid)
+}
+
+/// List pinned messages, most recently pinned first
+pub fn get_pinned() -> Result<Vec<pins::PinnedMessage>, String> {
+    let mut pinned = pins::load_pins()?;
+    let guids: Vec<String> = pinned.iter().map(|p| p.guid.clone()).collect();
+    let mut messages: HashMap<String, Message> = messages_by_guid(&guids)?
+        .into_iter()
+        .map(|m| (m.guid.clone(), m))
+        .collect();
+    for pin in &mut pinned {
+        pin.message = messages.remove(&pin.guid);
+    }
+    Ok(pinned)
+}
+
+/// Annotate a chat; an empty note removes it
+pub fn set_chat_note(chat_id: i64, note: String) -> Result<(), String> {
+    notes::set_note(notes::NoteTarget::Chat, &chat_id.to_string(), &note)
+}
+
+/// Annotate a contact by handle identifier; an empty note removes it
+pub fn set_contact_note(identifier: String, note: String) -> Result<(), String> {
+    notes::set_note(notes::NoteTarget::Contact, &identifier, &note)
+}
+
+/// List past exports, most recent first
+pub fn get_export_history(limit: Option<usize>) -> Result<Vec<export_history::ExportJob>, String> {
+    export_history::list(limit.unwrap_or(50))
+}
+
+/// Replay a previous export's configuration, optionally over a new date range
+/// (written next to the original with the range in the file name)
+pub fn rerun_export(
+    task_registry: &tasks::TaskRegistry,
+    job_id: i64,
+    new_range: Option<DateRange>,
+) -> Result<export::ExportResult, String> {
+    rerun_export_job(task_registry, job_id, new_range)
+}
+
+/// `rerun_export` for scheduled export jobs
+fn rerun_export_job(
+    task_registry: &tasks::TaskRegistry,
+    job_id: i64,
+    new_range: Option<DateRange>,
+) -> Result<export::ExportResult, String> {
+    let job = export_history::load(job_id)?;
+    let mut options = job.options;
+    let path = match new_range {
+        Some(range) => {
+            if range.end < range.start {
+                return Err("Range end must not be before its start".to_string());
+            }
+            options.start_date = Some(range.start);
+            options.end_date = Some(range.end);
+            export_history::path_for_range(&job.path, &range)
+        }
+        None => job.path,
+    };
+    write_chat_export(task_registry, job.chat_id, job.format, path, Some(options))
+}
+
+/// List scheduled jobs with their last run and next scheduled time
+pub fn list_jobs() -> Result<Vec<scheduler::JobStatus>, String> {
+    scheduler::list()
+}
+
+/// Create or update a scheduled job
+pub fn save_job(job: scheduler::ScheduledJob) -> Result<scheduler::ScheduledJob, String> {
+    scheduler::save(job)
+}
+
+/// Delete a scheduled job and its run history
+pub fn delete_job(id: String) -> Result<(), String> {
+    scheduler::delete(&id)
+}
+
+/// Write the recent log files into one file the user can attach to a bug report
+pub fn export_logs(path: String) -> Result<String, String> {
+    let path = expand_home_path(path);
+    let count = logging::export_logs(std::path::Path::new(&path))?;
+    tracing::info!(files = count, "logs exported");
+    Ok(path)
+}
+
+/// Generate the synthetic demo databases, so the app can be explored without access to real messages
+pub fn generate_demo_data(options: Option<fixtures::FixtureOptions>) -> Result<fixtures::FixtureSummary, String> {
+    let dir = fixtures::demo_dir().ok_or("Could not determine app data directory")?;
+    fixtures::generate(&dir, &options.unwrap_or_default())
+}
+
+pub fn is_demo_mode() -> bool {
+    fixtures::is_demo_mode()
+}
+
+/// Get each chat's language mix (e.g. 62% Spanish), detected per message
+pub fn get_language_stats(options: Option<ExportOptions>) -> Result<Vec<language::ChatLanguages>, String> {
+    let mut messages = stream_messages(options)?;
+    let report = language::language_report(&mut messages);
+    messages.finish()?;
+    Ok(report)
+}
+
+/// Get the most frequent words, using the stopword list of each message's language and the
+/// tokenizer settings. Chats are counted in parallel.
+pub fn get_word_frequencies(options: Option<ExportOptions>, limit: Option<usize>) -> Result<Vec<language::WordCount>, String> {
+    let tokenizer = analytics::Tokenizer::new(&settings::load_settings().tokenizer);
+    let counts = parallel::map_chats(options.as_ref(), |_, messages| language::word_counts(messages, &tokenizer))?;
+    Ok(language::merge_word_counts(counts.into_iter().map(|(_, c)| c), limit.unwrap_or(50)))
+}
+
+/// Get monthly per-chat counts of messages mentioning each keyword category. Uses the
+/// categories saved in settings unless others are passed.
+pub fn get_keyword_trends(
+    options: Option<ExportOptions>,
+    categories: Option<Vec<keywords::KeywordCategory>>,
+) -> Result<Vec<keywords::CategorySeries>, String> {
+    let settings = settings::load_settings();
+    let categories = categories.unwrap_or_else(|| settings.keyword_categories.clone());
+    for category in &categories {
+        keywords::validate(category)?;
+    }
+    let messages = get_messages(options, None)?;
+    Ok(keywords::category_trends(&messages, &categories, &settings))
+}
+
+/// Get each chat's inside vocabulary (terms used heavily there and rarely elsewhere) with
+/// when and by whom each was first used
+pub fn get_inside_vocabulary(
+    options: Option<ExportOptions>,
+    min_uses: Option<i64>,
+    terms_per_chat: Option<usize>,
+) -> Result<Vec<vocabulary::ChatVocabulary>, String> {
+    let messages = get_messages(options, None)?;
+    let tokenizer = analytics::Tokenizer::new(&settings::load_settings().tokenizer);
+    Ok(vocabulary::inside_vocabulary(
+        &messages,
+        &tokenizer,
+        min_uses.unwrap_or(vocabulary::DEFAULT_MIN_USES),
+        terms_per_chat.unwrap_or(vocabulary::DEFAULT_TERMS_PER_CHAT),
+    ))
+}
+
+/// Count apology, thanks and affection phrases (or the saved custom categories) per sender per
+/// chat, with monthly series
+pub fn get_phrase_counts(
+    options: Option<ExportOptions>,
+    categories: Option<Vec<keywords::KeywordCategory>>,
+) -> Result<Vec<keywords::PhraseCategoryCounts>, String> {
+    let settings = settings::load_settings();
+    let categories = categories
+        .or_else(|| settings.phrase_categories.clone())
+        .unwrap_or_else(keywords::default_phrase_categories);
+    for category in &categories {
+        keywords::validate(category)?;
+    }
+    let messages = get_messages(options, None)?;
+    Ok(keywords::phrase_counts(&messages, &categories, &settings))
+}
+
+/// Get each sender's writing style (all-caps, exclamation, ellipsis and emoji rates) as a
+/// fingerprint comparable across contacts
+pub fn get_style_fingerprints(
+    options: Option<ExportOptions>,
+    min_messages: Option<i64>,
+) -> Result<Vec<style::StyleFingerprint>, String> {
+    let mut messages = stream_messages(options)?;
+    let fingerprints = style::style_fingerprints(&mut messages, min_messages.unwrap_or(style::DEFAULT_MIN_MESSAGES));
+    messages.finish()?;
+    Ok(fingerprints)
+}
+
+/// Get each sender's yearly use of common abbreviations (or the saved list)
+pub fn get_abbreviation_timeline(
+    options: Option<ExportOptions>,
+    terms: Option<Vec<String>>,
+) -> Result<Vec<style::AbbreviationTimeline>, String> {
+    let settings = settings::load_settings();
+    let terms = terms
+        .or_else(|| settings.abbreviations.clone())
+        .unwrap_or_else(|| style::DEFAULT_ABBREVIATIONS.iter().map(|t| t.to_string()).collect());
+    if terms.iter().all(|t| t.trim().is_empty()) {
+        return Err("No abbreviations to track".to_string());
+    }
+    let mut messages = stream_messages(options)?;
+    let timelines = style::abbreviation_timelines(&mut messages, &terms, &settings);
+    messages.finish()?;
+    Ok(timelines)
+}
+
+/// Count "correction messages" per sender: messages re-sent within seconds with a small fix
+pub fn get_correction_counts(options: Option<ExportOptions>) -> Result<Vec<corrections::SenderCorrections>, String> {
+    let mut opts = options.unwrap_or_default();
+    opts.collapse_corrections = Some(false);
+    let messages = get_messages(Some(opts), None)?;
+    Ok(corrections::correction_report(&messages))
+}
+
+/// Compile everything the app stores about a contact: chat.db counts, tags, links, notes, pins,
+/// archived and imported messages, OCR results and digests
+pub fn get_personal_data_report(identifier: String) -> Result<privacy::PersonalDataReport, String> {
+    privacy::personal_data_report(&identifier)
+}
+
+/// Delete everything the app stores or derived about a contact. chat.db is never modified.
+pub fn forget_contact(identifier: String) -> Result<privacy::ForgetResult, String> {
+    privacy::forget_contact(&identifier)
+}
+
+/// Whether app-owned databases are encrypted, and which exist
+pub fn get_encryption_status() -> Result<encryption::EncryptionStatus, String> {
+    encryption::status()
+}
+
+/// Encrypt or decrypt the store, cache and archive databases in place, keeping the key in the Keychain
+pub fn set_app_data_encryption(
+    task_registry: &tasks::TaskRegistry,
+    enabled: bool,
+) -> Result<encryption::EncryptionStatus, String> {
+    encryption::set_encryption(task_registry, enabled)
+}
+
+/// Store an API key or token in the Keychain under `name`; an empty value removes it
+pub fn set_secret(name: String, value: String) -> Result<(), String> {
+    secrets::set_secret(&name, &value)
+}
+
+/// Whether a secret is stored. Secret values are never sent to the frontend.
+pub fn has_secret(name: String) -> Result<bool, String> {
+    secrets::has_secret(&name)
+}
+
+/// Local-only mode and every network-capable feature, so users can verify nothing leaves the machine
+pub fn get_privacy_status() -> privacy::PrivacyStatus {
+    privacy::privacy_status()
+}
+
+/// Disk used by the app's own caches and data
+pub fn get_storage_usage() -> Result<storage::StorageUsage, String> {
+    storage::storage_usage()
+}
+
+/// Delete cached files of the given kinds, or every cache kind when none are given
+pub fn clear_caches(kinds: Option<Vec<storage::CacheKind>>) -> Result<storage::ClearResult, String> {
+    storage::clear_caches(&kinds.unwrap_or_default())
+}
+
+/// Get the first messages exchanged with a contact ("how we met"), preferring their 1:1 chat
+pub fn get_first_messages(contact_id: i64, count: Option<usize>) -> Result<FirstMessages, String> {
+    let count = count.unwrap_or(20).max(1);
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    // Find the chat holding the earliest message with this handle, favouring 1:1 chats
+    let chat_id: i64 = conn
+        .query_row(
+            "SELECT chj.chat_id
+             FROM chat_handle_join chj
+             JOIN chat_message_join cmj ON cmj.chat_id = chj.chat_id
+             JOIN message m ON m.ROWID = cmj.message_id
+             WHERE chj.handle_id = ? AND m.date > 0
+             GROUP BY chj.chat_id
+             ORDER BY (SELECT COUNT(*) FROM chat_handle_join x WHERE x.chat_id = chj.chat_id) > 1,
+                      MIN(m.date)
+             LIMIT 1",
+            [contact_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("No conversation found for contact {}: {}", contact_id, e))?;
+
+    // Date of the Nth message bounds the fetch so huge chats aren't loaded in full
+    let cutoff: Option<i64> = conn
+        .query_row(
+            &format!(
+                "SELECT m.date FROM message m
+                 JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+                 WHERE cmj.chat_id = ? AND m.date > 0 AND {}
+                 ORDER BY m.date ASC
+                 LIMIT 1 OFFSET ?",
+                schema.content_filter()
+            ),
+            rusqlite::params![chat_id, count as i64 - 1],
+            |row| row.get(0),
+        )
+        .ok();
+
+    let opts = ExportOptions {
+        chat_ids: Some(vec![chat_id]),
+        // Whole-second bound; a few same-second extras are trimmed below
+        end_date: cutoff.map(|d| mac_timestamp_to_unix(d) + 1),
+        ..Default::default()
+    };
+    let mut messages = get_messages(Some(opts), None)?;
+    messages.reverse(); // Oldest first
+    messages.truncate(count);
+
+    let chat = load_chats()?
+        .into_iter()
+        .find(|c| c.id == chat_id)
+        .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+    let title = chat_title(&chat);
+
+    Ok(FirstMessages {
+        chat_id,
+        first_date: messages.first().map(|m| m.date),
+        first_from_me: messages.first().map(|m| m.is_from_me),
+        transcript: export::render_txt(&title, &[], &messages, export::ReactionStyle::Inline, &settings::load_settings()),
+        chat_title: title,
+        messages,
+    })
+}
+
+/// Manually link an old handle (e.g. a previous phone number) to a contact's current identifier
+pub fn link_handles(identifier: String, canonical: String) -> Result<(), String> {
+    if identifier == canonical {
+        return Err("Cannot link a handle to itself".to_string());
+    }
+    let conn = store::open_store_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO handle_links (identifier, canonical, created_at) VALUES (?, ?, ?)",
+        rusqlite::params![identifier, canonical, Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// Remove a manual handle link
+pub fn unlink_handle(identifier: String) -> Result<(), String> {
+    let conn = store::open_store_db()?;
+    conn.execute("DELETE FROM handle_links WHERE identifier = ?", [identifier])
+        .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// Suggest handles that are likely one person's old and new identifier, to confirm with
+/// `link_handles`. Live only: it compares chat.db's handle rows and their chats.
+pub fn get_suggested_merges(source: Option<archive::DataSource>) -> Result<Vec<aliases::SuggestedMerge>, String> {
+    archive::require_live(source, "Merge suggestions")?;
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = chatdb::open(&path)?;
+
+    aliases::suggested_merges(
+        &conn,
+        &schema,
+        &get_contact_names(),
+        &get_contact_details(),
+        &aliases::load_handle_links(),
+    )
+}
+
+/// List handles that belong to the same person (same contact card or manually linked)
+pub fn get_handle_aliases() -> Result<Vec<aliases::AliasGroup>, String> {
+    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, _schema) = chatdb::open(&path)?;
+
+    aliases::find_alias_groups(
+        &conn,
+        &get_contact_names(),
+        &get_contact_details(),
+        &aliases::load_handle_links(),
+    )
+}
@@ -0,0 +1,64 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+// Days of logs kept on disk
+const MAX_LOG_FILES: usize = 7;
+
+const LOG_PREFIX: &str = "message-insights";
+
+// Keeps the background writer flushing for the life of the app
+static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+fn log_dir() -> Option<PathBuf> {
+    crate::get_app_data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Send logs to daily-rotated files under the app data dir (and stdout in debug builds)
+pub fn init() {
+    let file_layer = log_dir().and_then(|dir| {
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+            .ok()?;
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        let _ = GUARD.set(guard);
+        Some(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false))
+    });
+    let stdout_layer = cfg!(debug_assertions).then(tracing_subscriber::fmt::layer);
+
+    // Also captures `log` records from dependencies
+    let _ = tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(file_layer)
+        .with(stdout_layer)
+        .try_init();
+}
+
+/// Concatenate the retained log files (oldest first) into one file for bug reports
+pub fn export_logs(destination: &std::path::Path) -> Result<usize, String> {
+    let dir = log_dir().ok_or("Could not determine app data directory")?;
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("Cannot read log directory: {}", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with(LOG_PREFIX)))
+        .collect();
+    // Rotated names end in the date, so name order is chronological
+    files.sort();
+
+    let mut out = std::fs::File::create(destination).map_err(|e| format!("Cannot create log export: {}", e))?;
+    for file in &files {
+        let contents = std::fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+        writeln!(out, "==> {} <==", file.display()).map_err(|e| format!("Cannot write log export: {}", e))?;
+        out.write_all(&contents).map_err(|e| format!("Cannot write log export: {}", e))?;
+    }
+    Ok(files.len())
+}
@@ -0,0 +1,174 @@
+use crate::export::{attachment_names, format_reactions, ReactionStyle};
+use crate::settings::{to_local_time, AppSettings};
+use crate::templates::TemplateGrouping;
+use crate::Message;
+use base64::Engine as _;
+use chrono::{TimeZone, Utc};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+// Typical mail server limit; larger files are listed by name instead of attached
+pub const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+// RFC 2045 line length for base64 bodies
+const BASE64_LINE: usize = 76;
+
+// Phone numbers aren't addresses, so they get a placeholder domain that can never resolve
+const ADDRESS_DOMAIN: &str = "imessage.invalid";
+
+fn base64_lines(bytes: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / BASE64_LINE * 2);
+    for chunk in encoded.as_bytes().chunks(BASE64_LINE) {
+        out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// RFC 2047 encoded word for header values that aren't plain ASCII
+fn encode_header(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(value))
+    }
+}
+
+fn sender_address(msg: &Message) -> String {
+    if msg.is_from_me {
+        return format!("me@{}", ADDRESS_DOMAIN);
+    }
+    let id = msg.contact_identifier.trim();
+    if id.contains('@') {
+        id.to_string()
+    } else {
+        let local: String = id.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '+').collect();
+        format!("{}@{}", if local.is_empty() { "unknown" } else { &local }, ADDRESS_DOMAIN)
+    }
+}
+
+fn sender_label(msg: &Message) -> &str {
+    if msg.is_from_me {
+        "Me"
+    } else {
+        &msg.sender_name
+    }
+}
+
+/// One email: a plain-text part followed by each attachment as its own MIME part. Only this
+/// email (and its attachments) is held in memory before it is written out.
+fn write_email(
+    writer: &mut dyn Write,
+    title: &str,
+    subject: &str,
+    messages: &[&Message],
+    style: ReactionStyle,
+    settings: &AppSettings,
+) -> std::io::Result<()> {
+    let Some(first) = messages.first() else { return Ok(()) };
+    let mut out = String::new();
+    let utc = Utc.timestamp_opt(first.date, 0).single().unwrap_or_default();
+    let boundary = format!("----=_message_insights_{}", first.guid.replace(|c: char| !c.is_ascii_alphanumeric(), ""));
+
+    // mbox separator line, then the headers
+    let _ = write!(out, "From {} {}\r\n", sender_address(first), utc.format("%a %b %e %H:%M:%S %Y"));
+    let _ = write!(out, "From: \"{}\" <{}>\r\n", encode_header(sender_label(first)).replace('"', "'"), sender_address(first));
+    let _ = write!(out, "To: {}:;\r\n", encode_header(title));  // Group syntax: the chat, no addresses
+    let _ = write!(out, "Subject: {}\r\n", encode_header(subject));
+    let _ = write!(out, "Date: {}\r\n", utc.to_rfc2822());
+    let _ = write!(out, "Message-ID: <{}@{}>\r\n", first.guid, ADDRESS_DOMAIN);
+    out.push_str("MIME-Version: 1.0\r\n");
+    let _ = write!(out, "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n", boundary);
+
+    // Read attachments first so any that can't be attached are noted in the text part
+    let mut parts: Vec<(String, &str, Vec<u8>)> = Vec::new();
+    let mut missing: Vec<String> = Vec::new();
+    for msg in messages {
+        for (attachment, name) in msg.attachments.iter().zip(attachment_names(msg)) {
+            let bytes = attachment
+                .filename
+                .as_deref()
+                .map(Path::new)
+                .filter(|p| std::fs::metadata(p).map(|m| m.len() <= MAX_ATTACHMENT_BYTES).unwrap_or(false))
+                .and_then(|p| std::fs::read(p).ok());
+            match bytes {
+                Some(bytes) => parts.push((name, attachment.mime_type.as_deref().unwrap_or("application/octet-stream"), bytes)),
+                None => missing.push(name),  // Offloaded to iCloud, deleted, or too large
+            }
+        }
+    }
+
+    let mut body = String::new();
+    for msg in messages {
+        if messages.len() > 1 {
+            let time = to_local_time(msg.date, settings).map(|dt| dt.format("%H:%M").to_string()).unwrap_or_default();
+            let _ = write!(body, "[{}] {}: ", time, sender_label(msg));
+        }
+        body.push_str(msg.text.as_deref().unwrap_or(""));
+        body.push('\n');
+        if !msg.reactions.is_empty() && !matches!(style, ReactionStyle::Omit | ReactionStyle::Csv) {
+            let _ = writeln!(body, "Reactions: {}", format_reactions(&msg.reactions));
+        }
+    }
+    for name in &missing {
+        let _ = writeln!(body, "Attachment not included: {}", name);
+    }
+
+    // Base64 bodies never start a line with "From ", so no mbox quoting is needed
+    let _ = write!(out, "--{}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n", boundary);
+    out.push_str(&base64_lines(body.as_bytes()));
+    for (name, mime, bytes) in parts {
+        let name = encode_header(&name).replace('"', "'");
+        let _ = write!(
+            out,
+            "--{}\r\nContent-Type: {}; name=\"{}\"\r\nContent-Disposition: attachment; filename=\"{}\"\r\nContent-Transfer-Encoding: base64\r\n\r\n",
+            boundary, mime, name, name
+        );
+        out.push_str(&base64_lines(&bytes));
+    }
+    let _ = write!(out, "--{}--\r\n\r\n", boundary);
+    writer.write_all(out.as_bytes())
+}
+
+/// Write messages (chronological order) as an mbox, one email at a time: one email per
+/// message, or per local day or month of conversation
+pub fn write_mbox(
+    out: &mut dyn Write,
+    title: &str,
+    messages: &[Message],
+    grouping: TemplateGrouping,
+    style: ReactionStyle,
+    settings: &AppSettings,
+) -> std::io::Result<()> {
+    let period = |msg: &Message, pattern: &str| {
+        to_local_time(msg.date, settings).map(|dt| dt.format(pattern).to_string()).unwrap_or_default()
+    };
+    match grouping {
+        TemplateGrouping::None => {
+            for msg in messages {
+                let preview: String = msg.text.as_deref().unwrap_or("").chars().take(60).collect();
+                let subject = if preview.trim().is_empty() { title.to_string() } else { format!("{}: {}", title, preview.trim()) };
+                write_email(out, title, &subject, &[msg], style, settings)?;
+            }
+        }
+        TemplateGrouping::Day | TemplateGrouping::Month => {
+            let pattern = if grouping == TemplateGrouping::Day { "%Y-%m-%d" } else { "%Y-%m" };
+            let mut group: Vec<&Message> = Vec::new();
+            let mut label = String::new();
+            for msg in messages {
+                let current = period(msg, pattern);
+                if current != label && !group.is_empty() {
+                    write_email(out, title, &format!("{} — {}", title, label), &group, style, settings)?;
+                    group.clear();
+                }
+                label = current;
+                group.push(msg);
+            }
+            write_email(out, title, &format!("{} — {}", title, label), &group, style, settings)?;
+        }
+    }
+    Ok(())
+}
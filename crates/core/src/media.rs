@@ -0,0 +1,418 @@
+use crate::Attachment;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Check if an attachment is the still image half of a Live Photo
+fn is_live_photo_still(attachment: &Attachment) -> bool {
+    matches!(attachment.mime_type.as_deref(), Some("image/heic") | Some("image/jpeg"))
+}
+
+/// Check if an attachment is the motion (video) half of a Live Photo
+fn is_live_photo_motion(attachment: &Attachment) -> bool {
+    attachment.mime_type.as_deref() == Some("video/quicktime")
+}
+
+/// Get the file stem used to match the two halves of a Live Photo (IMG_1234.HEIC + IMG_1234.MOV)
+fn attachment_stem(attachment: &Attachment) -> Option<String> {
+    let name = attachment
+        .transfer_name
+        .as_deref()
+        .or(attachment.filename.as_deref())?;
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    let stem = match file_name.rfind('.') {
+        Some(pos) => &file_name[..pos],
+        None => file_name,
+    };
+    if stem.is_empty() {
+        None
+    } else {
+        Some(stem.to_lowercase())
+    }
+}
+
+/// Pair Live Photo stills with their motion video, which is moved to sit right after its still.
+/// When `drop_video` is set the paired video is discarded instead.
+pub fn pair_live_photos(attachments: Vec<Attachment>, drop_video: bool) -> Vec<Attachment> {
+    if attachments.len() < 2 {
+        return attachments;
+    }
+
+    let mut stills: Vec<Attachment> = Vec::new();
+    let mut motions: Vec<Option<Attachment>> = Vec::new();
+    let mut others: Vec<Attachment> = Vec::new();
+    let mut order: Vec<(bool, usize)> = Vec::new(); // (is_still, index into stills/others)
+
+    for attachment in attachments {
+        if is_live_photo_still(&attachment) {
+            order.push((true, stills.len()));
+            stills.push(attachment);
+        } else if is_live_photo_motion(&attachment) {
+            motions.push(Some(attachment));
+        } else {
+            order.push((false, others.len()));
+            others.push(attachment);
+        }
+    }
+
+    // Match each motion video to the still sharing its file stem
+    let mut paired: Vec<Option<Attachment>> = Vec::with_capacity(stills.len());
+    for still in &stills {
+        let Some(stem) = attachment_stem(still) else {
+            paired.push(None);
+            continue;
+        };
+        let matched = motions.iter_mut().find(|m| {
+            m.as_ref()
+                .and_then(attachment_stem)
+                .map(|s| s == stem)
+                .unwrap_or(false)
+        });
+        let video = matched.and_then(Option::take);
+        paired.push(if drop_video { None } else { video });
+    }
+
+    // Rebuild in original order, keeping unpaired videos as regular attachments
+    let mut stills = stills.into_iter().map(Some).collect::<Vec<_>>();
+    let mut others = others.into_iter().map(Some).collect::<Vec<_>>();
+    let mut result: Vec<Attachment> = Vec::new();
+    for (is_still, idx) in order {
+        if is_still {
+            result.extend(stills[idx].take());
+            result.extend(paired[idx].take());
+        } else {
+            result.extend(others[idx].take());
+        }
+    }
+    result.extend(motions.into_iter().flatten());
+    result
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VideoMetadata {
+    pub duration_secs: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+// Upper bound on how much of a moov box we are willing to load into memory
+const MAX_MOOV_SIZE: u64 = 32 * 1024 * 1024;
+
+fn read_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    buf.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_u64(buf: &[u8], pos: usize) -> Option<u64> {
+    buf.get(pos..pos + 8).map(|b| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(b);
+        u64::from_be_bytes(bytes)
+    })
+}
+
+/// Iterate the boxes in an in-memory MP4/QuickTime buffer, returning (type, payload) pairs
+fn child_boxes(buf: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= buf.len() {
+        let size = read_u32(buf, pos).unwrap_or(0) as usize;
+        let mut box_type = [0u8; 4];
+        box_type.copy_from_slice(&buf[pos + 4..pos + 8]);
+
+        let (header_len, box_len) = match size {
+            0 => (8, buf.len() - pos),
+            1 => match read_u64(buf, pos + 8) {
+                Some(large) => (16, large as usize),
+                None => break,
+            },
+            n => (8, n),
+        };
+        // A 64-bit size from a corrupt file can wrap `pos + box_len`, so add with a check
+        let Some(end) = pos.checked_add(box_len) else {
+            break;
+        };
+        if box_len < header_len || end > buf.len() {
+            break;
+        }
+        boxes.push((box_type, &buf[pos + header_len..end]));
+        pos = end;
+    }
+    boxes
+}
+
+/// Find the top-level moov box in a file without reading the (potentially huge) media data
+fn read_moov_box(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let mut offset = 0u64;
+
+    while offset + 8 <= file_len {
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let size = read_u32(&header, 0)? as u64;
+        let is_moov = &header[4..8] == b"moov";
+
+        let (header_len, box_len) = match size {
+            0 => (8, file_len - offset),
+            1 => {
+                file.read_exact(&mut header[8..16]).ok()?;
+                (16, read_u64(&header, 8)?)
+            }
+            n => (8, n),
+        };
+        if box_len < header_len {
+            return None;
+        }
+
+        if is_moov {
+            let payload_len = box_len - header_len;
+            if payload_len > MAX_MOOV_SIZE {
+                return None;
+            }
+            let mut payload = vec![0u8; payload_len as usize];
+            file.read_exact(&mut payload).ok()?;
+            return Some(payload);
+        }
+        offset = offset.checked_add(box_len)?;
+    }
+    None
+}
+
+/// Parse (timescale, duration) out of an mvhd box payload
+fn parse_mvhd(payload: &[u8]) -> Option<(u32, u64)> {
+    match payload.first()? {
+        0 => Some((read_u32(payload, 12)?, read_u32(payload, 16)? as u64)),
+        1 => Some((read_u32(payload, 20)?, read_u64(payload, 24)?)),
+        _ => None,
+    }
+}
+
+/// Parse (width, height) out of a tkhd box payload; dimensions are 16.16 fixed point
+fn parse_tkhd(payload: &[u8]) -> Option<(u32, u32)> {
+    let dims_offset = match payload.first()? {
+        0 => 76,
+        1 => 88,
+        _ => return None,
+    };
+    let width = read_u32(payload, dims_offset)? >> 16;
+    let height = read_u32(payload, dims_offset + 4)? >> 16;
+    if width == 0 || height == 0 {
+        None
+    } else {
+        Some((width, height))
+    }
+}
+
+/// Read duration and resolution from an MP4/QuickTime file
+pub fn read_video_metadata(path: &Path) -> Option<VideoMetadata> {
+    let moov = read_moov_box(path)?;
+
+    let mut duration_secs = None;
+    let mut dimensions = None;
+
+    for (box_type, payload) in child_boxes(&moov) {
+        match &box_type {
+            b"mvhd" => {
+                if let Some((timescale, duration)) = parse_mvhd(payload) {
+                    if timescale > 0 {
+                        duration_secs = Some(duration as f64 / timescale as f64);
+                    }
+                }
+            }
+            b"trak" if dimensions.is_none() => {
+                // The first track with non-zero dimensions is the video track
+                dimensions = child_boxes(payload)
+                    .into_iter()
+                    .find(|(t, _)| t == b"tkhd")
+                    .and_then(|(_, tkhd)| parse_tkhd(tkhd));
+            }
+            _ => {}
+        }
+    }
+
+    Some(VideoMetadata {
+        duration_secs: duration_secs?,
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+    })
+}
+
+/// Check if a MIME type is a video container we know how to parse
+pub fn is_parseable_video(mime_type: Option<&str>) -> bool {
+    matches!(mime_type, Some("video/quicktime") | Some("video/mp4") | Some("video/x-m4v"))
+}
+
+#[derive(Debug, Clone)]
+pub struct PhotoExif {
+    pub captured_at: Option<i64>,  // Unix timestamp
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// Convert an EXIF GPS coordinate (degrees, minutes, seconds rationals) to decimal degrees
+fn exif_coordinate(exif: &exif::Exif, value_tag: exif::Tag, ref_tag: exif::Tag, negative_ref: &str) -> Option<f64> {
+    let field = exif.get_field(value_tag, exif::In::PRIMARY)?;
+    let degrees = match field.value {
+        exif::Value::Rational(ref parts) if parts.len() >= 3 => {
+            parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+
+    let is_negative = exif
+        .get_field(ref_tag, exif::In::PRIMARY)
+        .map(|f| f.display_value().to_string().trim().eq_ignore_ascii_case(negative_ref))
+        .unwrap_or(false);
+
+    Some(if is_negative { -degrees } else { degrees })
+}
+
+/// Read the capture time as a Unix timestamp, honoring OffsetTimeOriginal when present
+fn exif_capture_time(exif: &exif::Exif) -> Option<i64> {
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+    let raw = match field.value {
+        exif::Value::Ascii(ref parts) => parts.first()?,
+        _ => return None,
+    };
+    let mut dt = exif::DateTime::from_ascii(raw).ok()?;
+
+    if let Some(offset_field) = exif.get_field(exif::Tag::OffsetTimeOriginal, exif::In::PRIMARY) {
+        if let exif::Value::Ascii(ref parts) = offset_field.value {
+            if let Some(offset) = parts.first() {
+                let _ = dt.parse_offset(offset);
+            }
+        }
+    }
+
+    let naive = chrono::NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)?
+        .and_hms_opt(dt.hour as u32, dt.minute as u32, dt.second as u32)?;
+
+    match dt.offset {
+        Some(offset_minutes) => Some(naive.and_utc().timestamp() - offset_minutes as i64 * 60),
+        // Without an offset, EXIF time is the camera's local time; assume it matches ours
+        None => chrono::Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|d| d.timestamp()),
+    }
+}
+
+/// Read capture time and GPS position from a JPEG/HEIC/TIFF image
+pub fn read_photo_exif(path: &Path) -> Option<PhotoExif> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+
+    Some(PhotoExif {
+        captured_at: exif_capture_time(&exif),
+        latitude: exif_coordinate(&exif, exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, "S"),
+        longitude: exif_coordinate(&exif, exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, "W"),
+    })
+}
+
+/// SHA-256 of an attachment's bytes, so the same file sent twice is recognized
+pub fn content_hash(path: &Path) -> Option<String> {
+    use sha2::{Digest, Sha256};
+    let mut file = File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).ok()?;
+    Some(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// Version 0 mvhd/tkhd payloads with only the fields we read filled in
+    fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; 100];
+        payload[12..16].copy_from_slice(&timescale.to_be_bytes());
+        payload[16..20].copy_from_slice(&duration.to_be_bytes());
+        mp4_box(b"mvhd", &payload)
+    }
+
+    fn tkhd(width: u32, height: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; 84];
+        payload[76..80].copy_from_slice(&(width << 16).to_be_bytes());
+        payload[80..84].copy_from_slice(&(height << 16).to_be_bytes());
+        mp4_box(b"tkhd", &payload)
+    }
+
+    #[test]
+    fn walks_sibling_boxes() {
+        let mut buf = mp4_box(b"ftyp", b"qt  ");
+        buf.extend(mp4_box(b"free", &[]));
+        let boxes = child_boxes(&buf);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(&boxes[0].0, b"ftyp");
+        assert_eq!(boxes[0].1, b"qt  ");
+        assert_eq!(&boxes[1].0, b"free");
+        assert!(boxes[1].1.is_empty());
+    }
+
+    #[test]
+    fn reads_64_bit_and_to_end_sizes() {
+        let mut buf = 1u32.to_be_bytes().to_vec();
+        buf.extend_from_slice(b"wide");
+        buf.extend_from_slice(&20u64.to_be_bytes());
+        buf.extend_from_slice(b"abcd");
+        buf.extend_from_slice(&0u32.to_be_bytes());
+        buf.extend_from_slice(b"mdat");
+        buf.extend_from_slice(b"rest of file");
+        let boxes = child_boxes(&buf);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!((&boxes[0].0, boxes[0].1), (b"wide", &b"abcd"[..]));
+        assert_eq!((&boxes[1].0, boxes[1].1), (b"mdat", &b"rest of file"[..]));
+    }
+
+    #[test]
+    fn stops_at_corrupt_sizes() {
+        // Claims more bytes than the buffer holds
+        let mut buf = mp4_box(b"ftyp", b"qt  ");
+        buf.extend_from_slice(&1000u32.to_be_bytes());
+        buf.extend_from_slice(b"moov");
+        assert_eq!(child_boxes(&buf).len(), 1);
+
+        // Smaller than its own header
+        let mut buf = 4u32.to_be_bytes().to_vec();
+        buf.extend_from_slice(b"moov");
+        assert!(child_boxes(&buf).is_empty());
+
+        // A 64-bit size that would wrap the offset
+        let mut buf = 1u32.to_be_bytes().to_vec();
+        buf.extend_from_slice(b"moov");
+        buf.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(child_boxes(&buf).is_empty());
+    }
+
+    #[test]
+    fn reads_duration_and_dimensions_from_a_file() {
+        let mut moov = mvhd(600, 1800);
+        moov.extend(mp4_box(b"trak", &tkhd(1920, 1080)));
+        let mut file = mp4_box(b"ftyp", b"qt  ");
+        file.extend(mp4_box(b"mdat", &[0u8; 64]));
+        file.extend(mp4_box(b"moov", &moov));
+
+        let path = std::env::temp_dir().join(format!("message-insights-media-{}.mov", std::process::id()));
+        std::fs::write(&path, &file).unwrap();
+        let metadata = read_video_metadata(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let metadata = metadata.unwrap();
+        assert_eq!(metadata.duration_secs, 3.0);
+        assert_eq!((metadata.width, metadata.height), (Some(1920), Some(1080)));
+    }
+}
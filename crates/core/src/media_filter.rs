@@ -0,0 +1,97 @@
+use crate::{Attachment, Message};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Which attachments an export keeps, for archiving information rather than memories
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaFilter {
+    #[default]
+    All,
+    NoPeople,       // Drop photos with a face or person in them, and all videos
+    DocumentsOnly,  // Keep only screenshots and document files
+}
+
+// Document types kept by `DocumentsOnly`, besides text/*
+const DOCUMENT_MIME_PREFIXES: &[&str] = &[
+    "application/pdf",
+    "application/msword",
+    "application/vnd.",  // Office, iWork and OpenDocument formats
+    "application/rtf",
+    "application/zip",
+    "text/",
+];
+
+/// macOS names screenshots "Screenshot ..." (older versions "Screen Shot ...")
+fn is_screenshot(attachment: &Attachment) -> bool {
+    let name = attachment.transfer_name.as_deref().unwrap_or("").to_lowercase();
+    name.starts_with("screenshot") || name.starts_with("screen shot")
+}
+
+fn has_no_people(attachment: &Attachment) -> bool {
+    attachment.filename.as_deref().and_then(|f| contains_people(Path::new(f))) == Some(false)
+}
+
+/// iOS screenshots arrive as IMG_nnnn.PNG, but so do edited photos and saved images, so an
+/// unnamed PNG only counts as a screenshot when Vision finds nobody in it
+fn looks_like_screenshot(attachment: &Attachment) -> bool {
+    is_screenshot(attachment) || (attachment.mime_type.as_deref() == Some("image/png") && has_no_people(attachment))
+}
+
+fn is_document(attachment: &Attachment) -> bool {
+    let mime = attachment.mime_type.as_deref().unwrap_or("");
+    DOCUMENT_MIME_PREFIXES.iter().any(|p| mime.starts_with(p))
+}
+
+/// Whether Vision finds a face or a human body in the image. None when it can't tell.
+#[cfg(target_os = "macos")]
+fn contains_people(path: &Path) -> Option<bool> {
+    use objc2::rc::Retained;
+    use objc2::ClassType;
+    use objc2_foundation::{NSArray, NSDictionary, NSString, NSURL};
+    use objc2_vision::{VNDetectFaceRectanglesRequest, VNDetectHumanRectanglesRequest, VNImageRequestHandler, VNRequest};
+
+    let url = unsafe { NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy())) };
+    let handler = unsafe {
+        VNImageRequestHandler::initWithURL_options(VNImageRequestHandler::alloc(), &url, &NSDictionary::new())
+    };
+    let faces = unsafe { VNDetectFaceRectanglesRequest::new() };
+    let humans = unsafe { VNDetectHumanRectanglesRequest::new() };
+    let requests: Retained<NSArray<VNRequest>> = NSArray::from_slice(&[faces.as_super().as_super(), humans.as_super().as_super()]);
+    unsafe { handler.performRequests_error(&requests) }.ok()?;
+
+    let found_faces = unsafe { faces.results() }.is_some_and(|r| r.count() > 0);
+    let found_humans = unsafe { humans.results() }.is_some_and(|r| r.count() > 0);
+    Some(found_faces || found_humans)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn contains_people(_path: &Path) -> Option<bool> {
+    None
+}
+
+/// Whether an attachment survives the filter. Anything that can't be classified is left out,
+/// so a missing file or an unreadable image never leaks a photo of someone.
+pub fn keep_attachment(attachment: &Attachment, filter: MediaFilter) -> bool {
+    let mime = attachment.mime_type.as_deref().unwrap_or("");
+    match filter {
+        MediaFilter::All => true,
+        MediaFilter::DocumentsOnly => is_document(attachment) || looks_like_screenshot(attachment),
+        MediaFilter::NoPeople if mime.starts_with("video/") => false,
+        MediaFilter::NoPeople if mime.starts_with("image/") => is_screenshot(attachment) || has_no_people(attachment),
+        MediaFilter::NoPeople => true,
+    }
+}
+
+/// Drop filtered-out attachments (and Live Photo motion halves with them) from messages,
+/// returning how many were left out
+pub fn apply(messages: &mut [Message], filter: MediaFilter) -> usize {
+    let mut skipped = 0;
+    for msg in messages.iter_mut().filter(|m| !m.attachments.is_empty()) {
+        let before = msg.attachments.len();
+        msg.attachments.retain(|a| keep_attachment(a, filter));
+        skipped += before - msg.attachments.len();
+        msg.has_attachment = !msg.attachments.is_empty();
+    }
+    skipped
+}
@@ -0,0 +1,209 @@
+use crate::chatdb::ChatDbSchema;
+use crate::settings::{self, AppSettings};
+use crate::URL_BALLOON_PROVIDER;
+use chrono::Datelike;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Photo,
+    Video,
+    Audio,    // Voice notes and audio files
+    Link,     // URL previews and messages containing a link
+    Sticker,
+    Other,
+}
+
+/// Attachment counts for one quarter
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QuarterCounts {
+    pub quarter: String,  // YYYY-Qn
+    pub photos: i64,
+    pub videos: i64,
+    pub audio: i64,
+    pub links: i64,
+    pub stickers: i64,
+    pub other: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMediaTrend {
+    pub chat_id: i64,
+    pub quarters: Vec<QuarterCounts>,  // Aligned with `MediaTrends::quarters`
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaTrends {
+    pub quarters: Vec<String>,         // Every quarter from the first to the last item
+    pub overall: Vec<QuarterCounts>,
+    pub chats: Vec<ChatMediaTrend>,
+}
+
+impl QuarterCounts {
+    fn add(&mut self, kind: MediaKind) {
+        match kind {
+            MediaKind::Photo => self.photos += 1,
+            MediaKind::Video => self.videos += 1,
+            MediaKind::Audio => self.audio += 1,
+            MediaKind::Link => self.links += 1,
+            MediaKind::Sticker => self.stickers += 1,
+            MediaKind::Other => self.other += 1,
+        }
+    }
+}
+
+/// Kind of one attachment; stickers are images flagged by Messages
+pub fn attachment_kind(mime_type: Option<&str>, is_sticker: bool) -> MediaKind {
+    let mime = mime_type.unwrap_or("");
+    if is_sticker {
+        MediaKind::Sticker
+    } else if mime.starts_with("image/") {
+        MediaKind::Photo
+    } else if mime.starts_with("video/") {
+        MediaKind::Video
+    } else if mime.starts_with("audio/") {
+        MediaKind::Audio
+    } else {
+        MediaKind::Other
+    }
+}
+
+/// (year, quarter 1-4) parsed back from a YYYY-Qn label
+fn parse_quarter(label: &str) -> Option<(i32, u32)> {
+    let (year, quarter) = label.split_once("-Q")?;
+    Some((year.parse().ok()?, quarter.parse().ok()?))
+}
+
+/// Quarters from `first` to `last` inclusive
+fn quarter_range(first: &str, last: &str) -> Vec<String> {
+    let (Some((mut year, mut quarter)), Some(end)) = (parse_quarter(first), parse_quarter(last)) else {
+        return Vec::new();
+    };
+    let mut quarters = Vec::new();
+    while (year, quarter) <= end {
+        quarters.push(format!("{}-Q{}", year, quarter));
+        quarter += 1;
+        if quarter > 4 {
+            quarter = 1;
+            year += 1;
+        }
+    }
+    quarters
+}
+
+/// Per-quarter attachment types per chat and overall. Each attachment counts once as its own
+/// kind, and a message with a link adds one link: a photo sent with a URL is a photo and a
+/// link. Only URL previews' attachments aren't counted, being the preview image.
+pub fn media_trends(
+    conn: &Connection,
+    schema: &ChatDbSchema,
+    chat_id: Option<i64>,
+    settings: &AppSettings,
+) -> Result<MediaTrends, String> {
+    let balloon = schema.column("balloon_bundle_id");
+    let body = schema.column("attributedBody");
+    // Messages whose text lives only in attributedBody are fetched too; their links only show
+    // once the blob is decoded
+    let query = format!(
+        "SELECT m.ROWID, cmj.chat_id, {date}, a.ROWID, a.mime_type, COALESCE({sticker}, 0), {balloon}, m.text, {body}
+         FROM message m
+         JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+         LEFT JOIN message_attachment_join maj ON m.ROWID = maj.message_id
+         LEFT JOIN attachment a ON maj.attachment_id = a.ROWID
+         WHERE m.date > 0
+           AND {content}
+           AND (a.ROWID IS NOT NULL OR {balloon} = '{url}'
+                OR m.text LIKE '%http://%' OR m.text LIKE '%https://%'
+                OR ((m.text IS NULL OR m.text = '') AND {body} IS NOT NULL))
+           {chat}",
+        date = schema.unix_seconds_sql("m.date"),
+        sticker = schema.attachment_column("is_sticker"),
+        balloon = balloon,
+        body = body,
+        content = schema.content_filter(),
+        url = URL_BALLOON_PROVIDER,
+        chat = if chat_id.is_some() { "AND cmj.chat_id = ?" } else { "" },
+    );
+    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
+    type Row = (i64, i64, i64, Option<i64>, Option<String>, bool, Option<String>, Option<String>, Option<Vec<u8>>);
+    let rows: Vec<Row> = stmt
+        .query_map(rusqlite::params_from_iter(chat_id.iter()), |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+                row.get(8)?,
+            ))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut per_chat: BTreeMap<i64, BTreeMap<String, QuarterCounts>> = BTreeMap::new();
+    let mut all_quarters: BTreeSet<String> = BTreeSet::new();
+    let mut link_messages: HashSet<i64> = HashSet::new();
+    for (message_id, row_chat, date, attachment_id, mime_type, is_sticker, balloon, text, body) in rows {
+        let Some(dt) = settings::to_local_time(date, settings) else {
+            continue;
+        };
+        let quarter = format!("{}-Q{}", dt.year(), dt.month0() / 3 + 1);
+        let is_preview = balloon.as_deref() == Some(URL_BALLOON_PROVIDER);
+        let has_link = is_preview
+            || crate::decode_message_text(text, body.as_deref())
+                .is_some_and(|t| t.contains("http://") || t.contains("https://"));
+
+        let mut kinds = Vec::new();
+        // One row per attachment, so the link is only counted on the message's first row
+        if has_link && link_messages.insert(message_id) {
+            kinds.push(MediaKind::Link);
+        }
+        if attachment_id.is_some() && !is_preview {
+            kinds.push(attachment_kind(mime_type.as_deref(), is_sticker));
+        }
+        if kinds.is_empty() {
+            continue;
+        }
+        let counts = per_chat.entry(row_chat).or_default().entry(quarter.clone()).or_default();
+        for kind in kinds {
+            counts.add(kind);
+        }
+        all_quarters.insert(quarter);
+    }
+
+    let (Some(first), Some(last)) = (all_quarters.first(), all_quarters.last()) else {
+        return Ok(MediaTrends { quarters: Vec::new(), overall: Vec::new(), chats: Vec::new() });
+    };
+    let quarters = quarter_range(first, last);
+    let aligned = |counts: &BTreeMap<String, QuarterCounts>| -> Vec<QuarterCounts> {
+        quarters
+            .iter()
+            .map(|q| QuarterCounts { quarter: q.clone(), ..counts.get(q).cloned().unwrap_or_default() })
+            .collect()
+    };
+
+    let chats: Vec<ChatMediaTrend> = per_chat
+        .iter()
+        .map(|(&id, counts)| ChatMediaTrend { chat_id: id, quarters: aligned(counts) })
+        .collect();
+    let mut overall = aligned(&BTreeMap::new());
+    for chat in &chats {
+        for (total, q) in overall.iter_mut().zip(&chat.quarters) {
+            total.photos += q.photos;
+            total.videos += q.videos;
+            total.audio += q.audio;
+            total.links += q.links;
+            total.stickers += q.stickers;
+            total.other += q.other;
+        }
+    }
+
+    Ok(MediaTrends { quarters, overall, chats })
+}
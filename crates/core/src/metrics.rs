@@ -0,0 +1,421 @@
+use crate::analytics::{
+    average, compute_metrics, extract_emojis, median, monthly_counts, response_times, top_counts, MessageMetrics,
+};
+use crate::histogram::{histogram, Histogram, HistogramMetric, DEFAULT_BUCKETS};
+use crate::settings::AppSettings;
+use crate::Message;
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    Scalar,
+    Series,
+    Histogram,
+}
+
+/// What a metric computes to; the variant always matches the metric's `kind`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum MetricValue {
+    Scalar(Option<f64>),           // None when there's nothing to measure
+    Series(Vec<(String, f64)>),    // Labelled points, in display order
+    Histogram(Histogram),
+}
+
+/// Optional knobs shared by all metrics; each metric reads the ones it understands
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MetricParams {
+    pub buckets: Option<usize>,  // Histogram metrics
+    pub top_n: Option<usize>,    // Ranked series
+}
+
+pub struct MetricContext<'a> {
+    pub settings: &'a AppSettings,
+    pub params: &'a MetricParams,
+    headline: OnceCell<MessageMetrics>,  // Shared by the headline metrics of one request
+}
+
+impl<'a> MetricContext<'a> {
+    pub fn new(settings: &'a AppSettings, params: &'a MetricParams) -> Self {
+        MetricContext { settings, params, headline: OnceCell::new() }
+    }
+}
+
+/// A named computation over the filtered messages. New insights implement this and add
+/// themselves to `REGISTRY`; `compute_metrics` serves them without new commands.
+pub trait Metric: Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn kind(&self) -> MetricKind;
+    fn unit(&self) -> &'static str;
+    fn compute(&self, messages: &[Message], ctx: &MetricContext) -> MetricValue;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricInfo {
+    pub name: String,
+    pub description: String,
+    pub kind: MetricKind,
+    pub unit: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricResult {
+    pub name: String,
+    pub unit: String,
+    #[serde(flatten)]
+    pub value: MetricValue,
+}
+
+/// Headline numbers from `compute_metrics`, one field each
+struct Headline {
+    name: &'static str,
+    description: &'static str,
+    unit: &'static str,
+    pick: fn(&MessageMetrics) -> Option<f64>,
+}
+
+impl Metric for Headline {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Scalar
+    }
+    fn unit(&self) -> &'static str {
+        self.unit
+    }
+    fn compute(&self, messages: &[Message], ctx: &MetricContext) -> MetricValue {
+        MetricValue::Scalar((self.pick)(ctx.headline.get_or_init(|| compute_metrics(messages))))
+    }
+}
+
+struct ReplyMinutes {
+    name: &'static str,
+    description: &'static str,
+    mine: bool,
+    summarize: fn(&[f64]) -> Option<f64>,
+}
+
+impl Metric for ReplyMinutes {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Scalar
+    }
+    fn unit(&self) -> &'static str {
+        "minutes"
+    }
+    fn compute(&self, messages: &[Message], _ctx: &MetricContext) -> MetricValue {
+        let (mine, theirs) = response_times(messages);
+        MetricValue::Scalar((self.summarize)(if self.mine { &mine } else { &theirs }))
+    }
+}
+
+/// Messages matching a predicate, for the delivery metadata `get_chat_stats` reports
+struct Count {
+    name: &'static str,
+    description: &'static str,
+    matches: fn(&Message) -> bool,
+}
+
+impl Metric for Count {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Scalar
+    }
+    fn unit(&self) -> &'static str {
+        "messages"
+    }
+    fn compute(&self, messages: &[Message], _ctx: &MetricContext) -> MetricValue {
+        MetricValue::Scalar(Some(messages.iter().filter(|m| (self.matches)(m)).count() as f64))
+    }
+}
+
+/// First or last message date, as `get_chat_stats` reports the date range
+struct DateBound {
+    name: &'static str,
+    description: &'static str,
+    latest: bool,
+}
+
+impl Metric for DateBound {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Scalar
+    }
+    fn unit(&self) -> &'static str {
+        "unix_seconds"
+    }
+    fn compute(&self, messages: &[Message], _ctx: &MetricContext) -> MetricValue {
+        let dates = messages.iter().map(|m| m.date);
+        let bound = if self.latest { dates.max() } else { dates.min() };
+        MetricValue::Scalar(bound.map(|d| d as f64))
+    }
+}
+
+struct MonthlyMessages;
+
+impl Metric for MonthlyMessages {
+    fn name(&self) -> &'static str {
+        "monthly_messages"
+    }
+    fn description(&self) -> &'static str {
+        "Messages per local month (YYYY-MM)"
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Series
+    }
+    fn unit(&self) -> &'static str {
+        "messages"
+    }
+    fn compute(&self, messages: &[Message], ctx: &MetricContext) -> MetricValue {
+        MetricValue::Series(monthly_counts(messages, ctx.settings).into_iter().map(|(m, c)| (m, c as f64)).collect())
+    }
+}
+
+struct HourlyMessages;
+
+impl Metric for HourlyMessages {
+    fn name(&self) -> &'static str {
+        "hourly_messages"
+    }
+    fn description(&self) -> &'static str {
+        "Messages per local hour of day (00-23)"
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Series
+    }
+    fn unit(&self) -> &'static str {
+        "messages"
+    }
+    fn compute(&self, messages: &[Message], ctx: &MetricContext) -> MetricValue {
+        let mut hours = [0i64; 24];
+        for msg in messages {
+            if let Some(dt) = crate::settings::to_local_time(msg.date, ctx.settings) {
+                hours[dt.hour() as usize] += 1;
+            }
+        }
+        MetricValue::Series(hours.iter().enumerate().map(|(h, &c)| (format!("{:02}", h), c as f64)).collect())
+    }
+}
+
+struct TopEmojis;
+
+impl Metric for TopEmojis {
+    fn name(&self) -> &'static str {
+        "top_emojis"
+    }
+    fn description(&self) -> &'static str {
+        "Most used emojis in message text (top_n, default 10)"
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Series
+    }
+    fn unit(&self) -> &'static str {
+        "uses"
+    }
+    fn compute(&self, messages: &[Message], ctx: &MetricContext) -> MetricValue {
+        let emojis = messages
+            .iter()
+            .filter_map(|m| m.text.as_deref())
+            .flat_map(extract_emojis)
+            .map(|c| c.to_string());
+        MetricValue::Series(
+            top_counts(emojis, ctx.params.top_n.unwrap_or(10)).into_iter().map(|(e, c)| (e, c as f64)).collect(),
+        )
+    }
+}
+
+struct Distribution {
+    name: &'static str,
+    description: &'static str,
+    metric: HistogramMetric,
+}
+
+impl Metric for Distribution {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn description(&self) -> &'static str {
+        self.description
+    }
+    fn kind(&self) -> MetricKind {
+        MetricKind::Histogram
+    }
+    fn unit(&self) -> &'static str {
+        self.metric.unit()
+    }
+    fn compute(&self, messages: &[Message], ctx: &MetricContext) -> MetricValue {
+        MetricValue::Histogram(histogram(messages, self.metric, ctx.params.buckets.unwrap_or(DEFAULT_BUCKETS)))
+    }
+}
+
+static REGISTRY: &[&dyn Metric] = &[
+    &Headline {
+        name: "message_count",
+        description: "Total messages",
+        unit: "messages",
+        pick: |m| Some(m.message_count as f64),
+    },
+    &Headline {
+        name: "messages_sent",
+        description: "Messages I sent",
+        unit: "messages",
+        pick: |m| Some(m.messages_sent as f64),
+    },
+    &Headline {
+        name: "messages_received",
+        description: "Messages I received",
+        unit: "messages",
+        pick: |m| Some(m.messages_received as f64),
+    },
+    &Headline {
+        name: "contact_count",
+        description: "Distinct people who messaged",
+        unit: "contacts",
+        pick: |m| Some(m.contact_count as f64),
+    },
+    &Headline {
+        name: "chat_count",
+        description: "Distinct chats with messages",
+        unit: "chats",
+        pick: |m| Some(m.chat_count as f64),
+    },
+    &Headline {
+        name: "positive_ratio",
+        description: "Positive share of positive and negative messages",
+        unit: "ratio",
+        pick: |m| Some(m.positive_ratio),
+    },
+    &Headline {
+        name: "emoji_per_message",
+        description: "Emojis per message",
+        unit: "emojis",
+        pick: |m| Some(m.emoji_per_message),
+    },
+    &Count {
+        name: "scheduled_messages",
+        description: "Messages sent with Send Later",
+        matches: |m| m.delivery.is_scheduled,
+    },
+    &Count {
+        name: "retracted_messages",
+        description: "Messages unsent with Undo Send",
+        matches: |m| m.delivery.date_retracted.is_some(),
+    },
+    &Count {
+        name: "quiet_deliveries",
+        description: "Messages delivered quietly because of a Focus",
+        matches: |m| m.delivery.delivered_quietly,
+    },
+    &DateBound {
+        name: "first_message_date",
+        description: "Date of the earliest message",
+        latest: false,
+    },
+    &DateBound {
+        name: "last_message_date",
+        description: "Date of the latest message",
+        latest: true,
+    },
+    &ReplyMinutes {
+        name: "avg_my_reply_minutes",
+        description: "Average time I took to reply",
+        mine: true,
+        summarize: average,
+    },
+    &ReplyMinutes {
+        name: "median_my_reply_minutes",
+        description: "Median time I took to reply",
+        mine: true,
+        summarize: median,
+    },
+    &ReplyMinutes {
+        name: "avg_their_reply_minutes",
+        description: "Average time others took to reply",
+        mine: false,
+        summarize: average,
+    },
+    &ReplyMinutes {
+        name: "median_their_reply_minutes",
+        description: "Median time others took to reply",
+        mine: false,
+        summarize: median,
+    },
+    &MonthlyMessages,
+    &HourlyMessages,
+    &TopEmojis,
+    &Distribution {
+        name: "message_length",
+        description: "Distribution of characters per text message",
+        metric: HistogramMetric::MessageLength,
+    },
+    &Distribution {
+        name: "reply_latency",
+        description: "Distribution of reply times, both directions",
+        metric: HistogramMetric::ReplyLatency,
+    },
+    &Distribution {
+        name: "session_length",
+        description: "Distribution of conversation session durations",
+        metric: HistogramMetric::SessionLength,
+    },
+    &Distribution {
+        name: "attachment_size",
+        description: "Distribution of attachment sizes",
+        metric: HistogramMetric::AttachmentSize,
+    },
+];
+
+/// Every registered metric, in registry order
+pub fn list_metrics() -> Vec<MetricInfo> {
+    REGISTRY
+        .iter()
+        .map(|m| MetricInfo {
+            name: m.name().to_string(),
+            description: m.description().to_string(),
+            kind: m.kind(),
+            unit: m.unit().to_string(),
+        })
+        .collect()
+}
+
+/// Compute the named metrics over one set of messages, in the order asked for
+pub fn compute(names: &[String], messages: &[Message], ctx: &MetricContext) -> Result<Vec<MetricResult>, String> {
+    names
+        .iter()
+        .map(|name| {
+            let metric = REGISTRY
+                .iter()
+                .find(|m| m.name() == name)
+                .ok_or_else(|| format!("Unknown metric: {}", name))?;
+            Ok(MetricResult {
+                name: name.clone(),
+                unit: metric.unit().to_string(),
+                value: metric.compute(messages, ctx),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,55 @@
+use crate::store;
+use chrono::Utc;
+use std::collections::HashMap;
+
+/// What a note is attached to; chats are keyed by chat.db ROWID, contacts by handle identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteTarget {
+    Chat,
+    Contact,
+}
+
+impl NoteTarget {
+    fn as_str(self) -> &'static str {
+        match self {
+            NoteTarget::Chat => "chat",
+            NoteTarget::Contact => "contact",
+        }
+    }
+}
+
+/// Save a note, or remove it when the text is blank
+pub fn set_note(target: NoteTarget, key: &str, text: &str) -> Result<(), String> {
+    let conn = store::open_store_db()?;
+    let text = text.trim();
+    if text.is_empty() {
+        conn.execute(
+            "DELETE FROM notes WHERE target = ? AND key = ?",
+            rusqlite::params![target.as_str(), key],
+        )
+    } else {
+        conn.execute(
+            "INSERT INTO notes (target, key, text, updated_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT(target, key) DO UPDATE SET text = excluded.text, updated_at = excluded.updated_at",
+            rusqlite::params![target.as_str(), key, text, Utc::now().timestamp()],
+        )
+    }
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// Load every note for one kind of target, keyed by chat ID or identifier
+pub fn load_notes(target: NoteTarget) -> HashMap<String, String> {
+    let conn = match store::open_store_db() {
+        Ok(c) => c,
+        Err(_) => return HashMap::new(),
+    };
+    conn.prepare("SELECT key, text FROM notes WHERE target = ?")
+        .ok()
+        .map(|mut stmt| {
+            stmt.query_map([target.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,213 @@
+use crate::settings::{self, AppSettings};
+use crate::{chat_title, Chat, Message};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+// Everything above this line is regenerated on each run; notes written below it are kept
+const GENERATED_END: &str = "%% message-insights: notes below this line are preserved %%";
+
+const ROOT_FOLDER: &str = "Message Insights";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultExportResult {
+    pub folder: String,
+    pub notes_written: i64,
+    pub notes_unchanged: i64,
+}
+
+/// Strip characters Obsidian doesn't allow in note names (and that break wiki-links)
+fn note_name(raw: &str) -> String {
+    let cleaned: String = raw
+        .chars()
+        .map(|c| if "[]#^|\\/:*?\"<>".contains(c) { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.chars().take(100).collect()
+    }
+}
+
+/// JSON strings are valid YAML scalars, so reuse serde_json for quoting
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+fn yaml_list(values: &[String]) -> String {
+    format!("[{}]", values.iter().map(|v| yaml_string(v)).collect::<Vec<_>>().join(", "))
+}
+
+/// First and last local day, kept ISO in frontmatter so Obsidian reads them as date properties
+fn period(messages: &[&Message], settings: &AppSettings) -> (String, String) {
+    let day = |m: Option<&&Message>| {
+        m.and_then(|m| settings::to_local_time(m.date, settings))
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    (day(messages.first()), day(messages.last()))
+}
+
+/// Write a note, keeping any user-written section after the generated block
+fn write_note(path: &Path, generated: &str) -> Result<bool, String> {
+    let existing = std::fs::read_to_string(path).ok();
+    let preserved = existing
+        .as_deref()
+        .and_then(|text| text.split_once(GENERATED_END))
+        .map(|(_, rest)| rest.to_string())
+        .unwrap_or_else(|| "\n".to_string());
+    let content = format!("{}{}{}", generated, GENERATED_END, preserved);
+
+    if existing.as_deref() == Some(content.as_str()) {
+        return Ok(false);
+    }
+    crate::export::write_atomic(path, content).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+/// Give every chat and contact a distinct note name
+fn assign_names(chats: &[&Chat], contacts: &BTreeMap<String, String>) -> (HashMap<i64, String>, HashMap<String, String>) {
+    let mut used: HashSet<String> = HashSet::new();
+    let mut contact_notes = HashMap::new();
+    for (identifier, name) in contacts {
+        let mut note = note_name(name);
+        if !used.insert(note.to_lowercase()) {
+            note = note_name(&format!("{} ({})", name, identifier));
+            used.insert(note.to_lowercase());
+        }
+        contact_notes.insert(identifier.clone(), note);
+    }
+
+    let mut chat_notes = HashMap::new();
+    for chat in chats {
+        let title = chat_title(chat);
+        let mut note = note_name(&format!("Chat - {}", title));
+        if !used.insert(note.to_lowercase()) {
+            note = note_name(&format!("Chat - {} ({})", title, chat.id));
+            used.insert(note.to_lowercase());
+        }
+        chat_notes.insert(chat.id, note);
+    }
+    (chat_notes, contact_notes)
+}
+
+/// Write per-contact and per-chat notes with frontmatter and wiki-links into an Obsidian vault.
+/// Messages must be oldest first; chats without messages in the period are skipped.
+pub fn export_vault(
+    vault: &Path,
+    chats: &[Chat],
+    messages: &[Message],
+    settings: &AppSettings,
+) -> Result<VaultExportResult, String> {
+    let root = vault.join(ROOT_FOLDER);
+    let contacts_dir = root.join("Contacts");
+    let chats_dir = root.join("Chats");
+    for dir in [&contacts_dir, &chats_dir] {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+
+    let mut by_chat: HashMap<i64, Vec<&Message>> = HashMap::new();
+    for msg in messages {
+        if let Some(chat_id) = msg.chat_id {
+            by_chat.entry(chat_id).or_default().push(msg);
+        }
+    }
+    let chats: Vec<&Chat> = chats.iter().filter(|c| by_chat.contains_key(&c.id)).collect();
+
+    let mut contacts: BTreeMap<String, String> = BTreeMap::new();
+    for chat in &chats {
+        for (identifier, name) in chat.participant_ids.iter().zip(chat.participants.iter()) {
+            contacts.entry(identifier.clone()).or_insert_with(|| name.clone());
+        }
+    }
+    let (chat_notes, contact_notes) = assign_names(&chats, &contacts);
+
+    let mut result = VaultExportResult {
+        folder: root.to_string_lossy().to_string(),
+        notes_written: 0,
+        notes_unchanged: 0,
+    };
+    let mut record = |written: bool| {
+        if written {
+            result.notes_written += 1;
+        } else {
+            result.notes_unchanged += 1;
+        }
+    };
+
+    for chat in &chats {
+        let chat_messages = &by_chat[&chat.id];
+        let (start, end) = period(chat_messages, settings);
+        let participant_links: Vec<String> = chat
+            .participant_ids
+            .iter()
+            .filter_map(|id| contact_notes.get(id))
+            .map(|note| format!("[[{}]]", note))
+            .collect();
+
+        let mut note = String::new();
+        let _ = writeln!(note, "---");
+        let _ = writeln!(note, "type: chat");
+        let _ = writeln!(note, "chat: {}", yaml_string(&chat_title(chat)));
+        let _ = writeln!(note, "group: {}", chat.is_group);
+        let _ = writeln!(note, "participants: {}", yaml_list(&chat.participants));
+        let _ = writeln!(note, "period_start: {}", start);
+        let _ = writeln!(note, "period_end: {}", end);
+        let _ = writeln!(note, "messages: {}", chat_messages.len());
+        let _ = writeln!(note, "sent: {}", chat_messages.iter().filter(|m| m.is_from_me).count());
+        let _ = writeln!(note, "---\n");
+        let _ = writeln!(note, "Participants: {}\n", participant_links.join(", "));
+
+        let mut current_day = String::new();
+        for msg in chat_messages {
+            let day = settings::format_date(msg.date, settings);
+            if day != current_day {
+                let _ = writeln!(note, "\n## {}\n", day);
+                current_day = day;
+            }
+            let time = settings::format_time(msg.date, settings);
+            let text = msg.text.as_deref().unwrap_or("").replace('\n', " ");
+            let _ = writeln!(note, "- {} **{}**: {}", time, msg.sender_name, text);
+        }
+        note.push('\n');
+
+        record(write_note(&chats_dir.join(format!("{}.md", chat_notes[&chat.id])), &note)?);
+    }
+
+    for (identifier, name) in &contacts {
+        let contact_chats: Vec<&&Chat> = chats.iter().filter(|c| c.participant_ids.contains(identifier)).collect();
+        let received: Vec<&Message> = messages
+            .iter()
+            .filter(|m| !m.is_from_me && &m.contact_identifier == identifier)
+            .collect();
+        let sent = contact_chats
+            .iter()
+            .filter(|c| !c.is_group)
+            .map(|c| by_chat[&c.id].iter().filter(|m| m.is_from_me).count())
+            .sum::<usize>();
+        let (start, end) = period(&received, settings);
+
+        let mut note = String::new();
+        let _ = writeln!(note, "---");
+        let _ = writeln!(note, "type: contact");
+        let _ = writeln!(note, "contact: {}", yaml_string(name));
+        let _ = writeln!(note, "identifier: {}", yaml_string(identifier));
+        let _ = writeln!(note, "period_start: {}", start);
+        let _ = writeln!(note, "period_end: {}", end);
+        let _ = writeln!(note, "messages_received: {}", received.len());
+        let _ = writeln!(note, "messages_sent_direct: {}", sent);
+        let _ = writeln!(note, "chats: {}", contact_chats.len());
+        let _ = writeln!(note, "---\n");
+        let _ = writeln!(note, "## Chats\n");
+        for chat in &contact_chats {
+            let _ = writeln!(note, "- [[{}]] ({} messages)", chat_notes[&chat.id], by_chat[&chat.id].len());
+        }
+        note.push('\n');
+
+        record(write_note(&contacts_dir.join(format!("{}.md", contact_notes[identifier])), &note)?);
+    }
+
+    Ok(result)
+}
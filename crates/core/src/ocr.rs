@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Check if the tesseract OCR engine is installed (e.g. via Homebrew)
+pub fn find_tesseract() -> Option<PathBuf> {
+    ["/opt/homebrew/bin/tesseract", "/usr/local/bin/tesseract", "/usr/bin/tesseract"]
+        .iter()
+        .map(PathBuf::from)
+        .find(|p| p.exists())
+}
+
+/// Convert formats tesseract cannot read (HEIC) to PNG using the built-in `sips` tool
+fn convert_for_ocr(image: &Path, mime_type: &str, scratch_dir: &Path) -> Option<PathBuf> {
+    if mime_type != "image/heic" && mime_type != "image/heif" {
+        return Some(image.to_path_buf());
+    }
+
+    let stem = image.file_stem()?.to_string_lossy().to_string();
+    let out = scratch_dir.join(format!("{}.png", stem));
+    let status = Command::new("sips")
+        .args(["-s", "format", "png"])
+        .arg(image)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .ok()?
+        .status;
+
+    if status.success() && out.exists() {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+/// Run OCR over an image file, returning the recognized text (empty if none)
+pub fn extract_image_text(
+    tesseract: &Path,
+    image: &Path,
+    mime_type: &str,
+    scratch_dir: &Path,
+) -> Result<String, String> {
+    let input = convert_for_ocr(image, mime_type, scratch_dir)
+        .ok_or_else(|| format!("Cannot convert {} for OCR", image.display()))?;
+
+    let output = Command::new(tesseract)
+        .arg(&input)
+        .arg("stdout")
+        .output()
+        .map_err(|e| format!("Failed to run tesseract: {}", e));
+
+    if input != image {
+        let _ = std::fs::remove_file(&input);
+    }
+
+    let output = output?;
+    if !output.status.success() {
+        return Err(format!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Collapse whitespace so snippets read naturally
+    let text = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(text)
+}
@@ -0,0 +1,74 @@
+use crate::archive::{self, DataSource};
+use crate::{conversations, corrections, get_messages_named, imports, ExportOptions, Message};
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+// Key of the unit holding messages outside any chat (imported logs)
+const NO_CHAT: i64 = 0;
+
+/// Run `work` over each conversation's messages on a rayon pool sized by the `analytics_threads`
+/// setting. A person's duplicate 1:1 chats count as one conversation, keyed by its lead chat id
+/// (see `conversations::group_chats`). Every conversation is loaded by its own query, so each
+/// worker reads through its own connection; contact names are looked up once for all of them.
+/// Results come back ordered by chat id whatever order workers finish in. Unless specific chats
+/// are asked for, chats only the archive still has are visited too, and messages outside any
+/// chat come last under `NO_CHAT`.
+pub fn map_chats<T, F>(options: Option<&ExportOptions>, work: F) -> Result<Vec<(i64, T)>, String>
+where
+    T: Send,
+    F: Fn(i64, &[Message]) -> T + Sync,
+{
+    let from_archive = options.and_then(|o| o.source) == Some(DataSource::Archive);
+    let wanted = options.and_then(|o| o.chat_ids.as_deref()).filter(|ids| !ids.is_empty());
+    let mut units = conversations::conversation_units(wanted)?;
+    if from_archive && wanted.is_none() {
+        let known: HashSet<i64> = units.iter().flat_map(|(_, ids)| ids.iter().copied()).collect();
+        units.extend(archive::chat_ids()?.into_iter().filter(|id| !known.contains(id)).map(|id| (id, vec![id])));
+    }
+    let contact_names = crate::get_contact_names();
+
+    let threads = crate::settings::load_settings().analytics_threads.unwrap_or(0);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("analytics-{}", i))
+        .build()
+        .map_err(|e| format!("Cannot start analytics workers: {}", e))?;
+    let _span = tracing::info_span!("map_chats", chats = units.len(), threads = pool.current_num_threads()).entered();
+
+    let mut results: Vec<(i64, T)> = pool.install(|| {
+        units
+            .par_iter()
+            .map(|&(chat_id, ref chat_ids)| {
+                let mut opts = options.cloned().unwrap_or_default();
+                opts.chat_ids = Some(chat_ids.clone());
+                opts.exact_chat_ids = true;
+                let messages = get_messages_named(Some(opts), None, Some(&contact_names))?;
+                Ok((chat_id, work(chat_id, &messages)))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })?;
+    results.sort_by_key(|(chat_id, _)| *chat_id);
+
+    if wanted.is_none() {
+        let loose = messages_without_chat(options, from_archive)?;
+        if !loose.is_empty() {
+            results.push((NO_CHAT, work(NO_CHAT, &loose)));
+        }
+    }
+    Ok(results)
+}
+
+/// Messages no chat query returns: imported logs, and archived messages that had no chat
+fn messages_without_chat(options: Option<&ExportOptions>, from_archive: bool) -> Result<Vec<Message>, String> {
+    let mut messages = if from_archive {
+        archive::load_messages_without_chat(options)?
+    } else if options.and_then(|o| o.include_imported).unwrap_or(false) {
+        imports::load_imported_messages(options)?
+    } else {
+        Vec::new()
+    };
+    if options.and_then(|o| o.collapse_corrections).unwrap_or(false) {
+        corrections::collapse_corrections(&mut messages);
+    }
+    Ok(messages)
+}
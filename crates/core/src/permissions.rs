@@ -0,0 +1,126 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// System Settings switch that fixes a failed check
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionFix {
+    FullDiskAccess,  // open_system_preferences
+    Contacts,        // open_contacts_preferences
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PermissionCheck {
+    pub name: String,
+    pub path: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub fix: Option<PermissionFix>,  // Set when a failed check can be fixed in System Settings
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionReport {
+    pub messages_db: PermissionCheck,
+    pub contacts: Vec<PermissionCheck>,  // One per AddressBook source
+    pub attachments: PermissionCheck,
+    pub app_data: PermissionCheck,
+    pub all_ok: bool,
+}
+
+fn check(name: &str, path: &Path, result: Result<(), String>, fix: Option<PermissionFix>) -> PermissionCheck {
+    let ok = result.is_ok();
+    PermissionCheck {
+        name: name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        ok,
+        error: result.err(),
+        fix: if ok { None } else { fix },
+    }
+}
+
+fn read_sqlite(path: &Path, probe: &str) -> Result<(), String> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Cannot open: {}", e))?;
+    conn.query_row(probe, [], |row| row.get::<_, i64>(0))
+        .map(|_| ())
+        .map_err(|e| format!("Cannot read: {}", e))
+}
+
+fn check_contacts(home: &Path) -> Vec<PermissionCheck> {
+    let sources_dir = home.join("Library/Application Support/AddressBook/Sources");
+    if let Err(e) = std::fs::read_dir(&sources_dir) {
+        return vec![check(
+            "Contacts",
+            &sources_dir,
+            Err(format!("Cannot list AddressBook sources: {}", e)),
+            Some(PermissionFix::FullDiskAccess),
+        )];
+    }
+
+    let paths = crate::get_all_addressbook_db_paths();
+    if paths.is_empty() {
+        return vec![check(
+            "Contacts",
+            &sources_dir,
+            Err("No AddressBook databases found".to_string()),
+            Some(PermissionFix::Contacts),
+        )];
+    }
+    paths
+        .iter()
+        .map(|path| {
+            let source = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let result = read_sqlite(path, "SELECT COUNT(*) FROM ZABCDRECORD");
+            check(&format!("Contacts ({})", source), path, result, Some(PermissionFix::Contacts))
+        })
+        .collect()
+}
+
+fn check_app_data(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create: {}", e))?;
+    let probe = dir.join(".write-check");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("Cannot write: {}", e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Check every location the app reads or writes, so onboarding can point at the exact fix
+pub fn permission_report() -> Result<PermissionReport, String> {
+    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
+    let app_dir = crate::get_app_data_dir().ok_or("Could not determine app data directory")?;
+
+    let messages_path = home.join("Library/Messages/chat.db");
+    let messages_db = check(
+        "Messages database",
+        &messages_path,
+        read_sqlite(&messages_path, "SELECT COUNT(*) FROM message"),
+        Some(PermissionFix::FullDiskAccess),
+    );
+
+    let attachments_path = home.join("Library/Messages/Attachments");
+    let attachments = check(
+        "Attachments",
+        &attachments_path,
+        std::fs::read_dir(&attachments_path)
+            .map(|_| ())
+            .map_err(|e| format!("Cannot list attachments: {}", e)),
+        Some(PermissionFix::FullDiskAccess),
+    );
+
+    let app_data = check("App data", &app_dir, check_app_data(&app_dir), None);
+    let contacts = check_contacts(&home);
+
+    let all_ok = messages_db.ok && attachments.ok && app_data.ok && contacts.iter().all(|c| c.ok);
+    Ok(PermissionReport {
+        messages_db,
+        contacts,
+        attachments,
+        app_data,
+        all_ok,
+    })
+}
@@ -0,0 +1,58 @@
+use crate::{store, Message};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PinnedMessage {
+    pub guid: String,
+    pub note: Option<String>,
+    pub pinned_at: i64,
+    pub message: Option<Message>,  // None once the message is gone from chat.db
+}
+
+/// Pin a message, replacing the note if it was already pinned
+pub fn pin(guid: &str, note: Option<&str>) -> Result<(), String> {
+    let note = note.map(str::trim).filter(|n| !n.is_empty());
+    let conn = store::open_store_db()?;
+    conn.execute(
+        "INSERT INTO pinned_messages (guid, note, pinned_at) VALUES (?, ?, ?)
+         ON CONFLICT(guid) DO UPDATE SET note = excluded.note",
+        rusqlite::params![guid, note, Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+pub fn unpin(guid: &str) -> Result<(), String> {
+    let conn = store::open_store_db()?;
+    conn.execute("DELETE FROM pinned_messages WHERE guid = ?", [guid])
+        .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+/// Load pins, most recently pinned first, without resolving their messages
+pub fn load_pins() -> Result<Vec<PinnedMessage>, String> {
+    let conn = store::open_store_db()?;
+    let mut stmt = conn
+        .prepare("SELECT guid, note, pinned_at FROM pinned_messages ORDER BY pinned_at DESC")
+        .map_err(|e| format!("Store error: {}", e))?;
+    let pins = stmt
+        .query_map([], |row| {
+            Ok(PinnedMessage {
+                guid: row.get(0)?,
+                note: row.get(1)?,
+                pinned_at: row.get(2)?,
+                message: None,
+            })
+        })
+        .map_err(|e| format!("Store error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(pins)
+}
+
+/// Notes keyed by GUID, for marking pinned messages in exports
+pub fn load_notes() -> Result<HashMap<String, Option<String>>, String> {
+    Ok(load_pins()?.into_iter().map(|p| (p.guid, p.note)).collect())
+}
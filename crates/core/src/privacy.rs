@@ -0,0 +1,482 @@
+use crate::{archive, cache, chatdb, get_contact_names, get_imessage_db_path, lookup_contact_name, normalize_phone, settings, store};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Network attempts refused by local-only mode since launch
+static BLOCKED_ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+
+/// Everything the app holds about one contact. chat.db is Apple's and only counted; every
+/// other field is app-owned data that `forget_contact` deletes.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PersonalDataReport {
+    pub identifier: String,
+    pub name: Option<String>,
+    pub matched_handles: Vec<String>,      // chat.db handles treated as this contact
+    pub chat_db_messages: i64,             // Read-only: Messages owns these
+    pub chat_db_chats: i64,
+    pub first_message: Option<i64>,        // Unix timestamp
+    pub last_message: Option<i64>,
+    pub tags: Vec<String>,                 // User-defined tags
+    pub handle_links: Vec<String>,         // Manual links to or from this contact
+    pub note: Option<String>,
+    pub pinned_messages: i64,
+    pub archived_messages: i64,
+    pub imported_messages: i64,
+    pub imported_contact_name: Option<String>,
+    pub ocr_results: i64,                  // Text recognized in their attachments
+    pub digests: i64,                      // Weekly digests naming them
+}
+
+/// How many app-owned records `forget_contact` removed from each store
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ForgetResult {
+    pub tags: usize,
+    pub handle_links: usize,
+    pub notes: usize,
+    pub pinned_messages: usize,
+    pub archived_messages: usize,
+    pub imported_messages: usize,
+    pub imported_attachments: usize,
+    pub imported_contacts: usize,
+    pub archived_reactions: usize,     // Their tapbacks on other people's archived messages
+    pub ocr_results: usize,
+    pub media_hashes: usize,
+    pub report_snapshots: usize,       // All snapshots: any report may include them
+    pub digests: usize,
+}
+
+/// A feature able to send data off the machine and whether it may run right now
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkFeature {
+    pub name: String,
+    pub configured: bool,  // Has a destination set up
+    pub allowed: bool,     // False whenever local-only mode is on
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrivacyStatus {
+    pub local_only: bool,
+    pub network_features: Vec<NetworkFeature>,
+    pub blocked_attempts: u64,  // Outgoing requests refused since launch
+    pub api_loopback_only: bool,  // The local API only listens on 127.0.0.1
+    pub app_data_encrypted: bool,
+}
+
+/// Gate for every outgoing network request; refuses it while local-only mode is on
+pub fn ensure_network_allowed(feature: &str) -> Result<(), String> {
+    if settings::load_settings().local_only {
+        BLOCKED_ATTEMPTS.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(feature, "network access blocked by local-only mode");
+        return Err(format!("{} is disabled: local-only mode is on", feature));
+    }
+    Ok(())
+}
+
+pub fn privacy_status() -> PrivacyStatus {
+    let app_settings = settings::load_settings();
+    let allowed = !app_settings.local_only;
+    let network_features = vec![
+        NetworkFeature {
+            name: "webhooks".to_string(),
+            configured: app_settings.webhooks.iter().any(|w| w.enabled),
+            allowed,
+        },
+        NetworkFeature {
+            name: "digest_webhook".to_string(),
+            configured: app_settings.digest_webhook.is_some(),
+            allowed,
+        },
+    ];
+    PrivacyStatus {
+        local_only: app_settings.local_only,
+        network_features,
+        blocked_attempts: BLOCKED_ATTEMPTS.load(Ordering::Relaxed),
+        api_loopback_only: true,
+        app_data_encrypted: app_settings.encrypt_app_data,
+    }
+}
+
+/// Whether two handles are the same person: emails case-insensitively, phones by last 10 digits
+fn same_handle(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim(), b.trim());
+    if a.eq_ignore_ascii_case(b) {
+        return true;
+    }
+    if a.contains('@') || b.contains('@') {
+        return false;
+    }
+    let (pa, pb) = (normalize_phone(a), normalize_phone(b));
+    !pa.is_empty() && pa == pb
+}
+
+/// chat.db handles and messages belonging to the contact
+struct ChatDbMatches {
+    handle_ids: Vec<i64>,
+    handles: Vec<String>,
+    message_ids: HashSet<i64>,
+    message_guids: HashSet<String>,
+    attachment_paths: Vec<String>,  // Files of their messages, as media_hashes keys them
+}
+
+fn chat_db_matches(conn: &Connection, identifier: &str) -> Result<ChatDbMatches, String> {
+    let mut stmt = conn
+        .prepare("SELECT ROWID, id FROM handle")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let (handle_ids, handles): (Vec<i64>, Vec<String>) = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .flatten()
+        .filter(|(_, id)| same_handle(id, identifier))
+        .unzip();
+
+    let mut message_ids = HashSet::new();
+    let mut message_guids = HashSet::new();
+    if !handle_ids.is_empty() {
+        let placeholders = handle_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let mut stmt = conn
+            .prepare(&format!("SELECT ROWID, guid FROM message WHERE handle_id IN ({})", placeholders))
+            .map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(handle_ids.iter()), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Query error: {}", e))?;
+        for (id, guid) in rows.flatten() {
+            message_ids.insert(id);
+            message_guids.insert(guid);
+        }
+    }
+
+    let mut attachment_paths = Vec::new();
+    if !handle_ids.is_empty() {
+        let placeholders = handle_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT a.filename FROM attachment a
+                 JOIN message_attachment_join maj ON maj.attachment_id = a.ROWID
+                 JOIN message m ON m.ROWID = maj.message_id
+                 WHERE m.handle_id IN ({}) AND a.filename IS NOT NULL",
+                placeholders
+            ))
+            .map_err(|e| format!("Query error: {}", e))?;
+        attachment_paths = stmt
+            .query_map(rusqlite::params_from_iter(handle_ids.iter()), |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Query error: {}", e))?
+            .flatten()
+            .map(crate::expand_home_path)
+            .collect();
+    }
+    Ok(ChatDbMatches {
+        handle_ids,
+        handles,
+        message_ids,
+        message_guids,
+        attachment_paths,
+    })
+}
+
+/// Rows of `sql` (first column TEXT key) whose key matches the contact
+fn matching_keys(conn: &Connection, sql: &str, identifier: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("Store error: {}", e))?;
+    let keys = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))
+        .map_err(|e| format!("Store error: {}", e))?
+        .flatten()
+        .flatten()
+        .filter(|key| same_handle(key, identifier))
+        .collect();
+    Ok(keys)
+}
+
+/// Weekly digests whose stored JSON mentions the contact's handle or name
+fn matching_digests(conn: &Connection, needles: &[String]) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT week_start, digest_json FROM digests")
+        .map_err(|e| format!("Store error: {}", e))?;
+    let weeks = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Store error: {}", e))?
+        .flatten()
+        .filter(|(_, json)| needles.iter().any(|n| !n.is_empty() && json.contains(n.as_str())))
+        .map(|(week, _)| week)
+        .collect();
+    Ok(weeks)
+}
+
+fn count_in(items: &HashSet<i64>, candidates: impl Iterator<Item = i64>) -> i64 {
+    candidates.filter(|c| items.contains(c)).count() as i64
+}
+
+/// Compile what the app stores about a contact
+pub fn personal_data_report(identifier: &str) -> Result<PersonalDataReport, String> {
+    let identifier = identifier.trim();
+    if identifier.is_empty() {
+        return Err("Identifier cannot be empty".to_string());
+    }
+    let mut report = PersonalDataReport {
+        identifier: identifier.to_string(),
+        name: lookup_contact_name(identifier, &get_contact_names()),
+        ..Default::default()
+    };
+
+    let mut matches = None;
+    if let Some(path) = get_imessage_db_path() {
+        if let Ok((conn, schema)) = chatdb::open(&path) {
+            let found = chat_db_matches(&conn, identifier)?;
+            if !found.handle_ids.is_empty() {
+                let placeholders = found.handle_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+                let query = format!(
+                    "SELECT COUNT(*), COUNT(DISTINCT cmj.chat_id), MIN(m.date), MAX(m.date)
+                     FROM message m
+                     LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
+                     WHERE m.handle_id IN ({}) AND m.date > 0 AND {}",
+                    placeholders,
+                    schema.content_filter()
+                );
+                let (messages, chats, first, last): (i64, i64, Option<i64>, Option<i64>) = conn
+                    .query_row(&query, rusqlite::params_from_iter(found.handle_ids.iter()), |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                    })
+                    .map_err(|e| format!("Query error: {}", e))?;
+                report.chat_db_messages = messages;
+                report.chat_db_chats = chats;
+                report.first_message = first.map(crate::mac_timestamp_to_unix);
+                report.last_message = last.map(crate::mac_timestamp_to_unix);
+            }
+            report.matched_handles = found.handles.clone();
+            matches = Some(found);
+        }
+    }
+
+    let conn = store::open_store_db()?;
+    let mut stmt = conn
+        .prepare("SELECT identifier, tag FROM contact_tags")
+        .map_err(|e| format!("Store error: {}", e))?;
+    report.tags = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Store error: {}", e))?
+        .flatten()
+        .filter(|(id, _)| same_handle(id, identifier))
+        .map(|(_, tag)| tag)
+        .collect();
+    report.handle_links = linked_identifiers(&conn, identifier)?;
+    report.note = crate::notes::load_notes(crate::notes::NoteTarget::Contact)
+        .into_iter()
+        .find(|(key, _)| same_handle(key, identifier))
+        .map(|(_, note)| note);
+    report.imported_messages = matching_keys(
+        &conn,
+        "SELECT COALESCE(sender, conversation) FROM imported_messages WHERE is_from_me = 0",
+        identifier,
+    )?
+    .len() as i64;
+    report.imported_contact_name = conn
+        .prepare("SELECT identifier, name FROM imported_contacts")
+        .map_err(|e| format!("Store error: {}", e))?
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Store error: {}", e))?
+        .flatten()
+        .find(|(id, _)| same_handle(id, identifier))
+        .map(|(_, name)| name);
+    report.digests = matching_digests(&conn, &digest_needles(identifier, report.name.as_deref()))?.len() as i64;
+
+    if let Some(found) = &matches {
+        let pinned: Vec<String> = conn
+            .prepare("SELECT guid FROM pinned_messages")
+            .map_err(|e| format!("Store error: {}", e))?
+            .query_map([], |row| row.get(0))
+            .map_err(|e| format!("Store error: {}", e))?
+            .flatten()
+            .collect();
+        report.pinned_messages = pinned.iter().filter(|g| found.message_guids.contains(*g)).count() as i64;
+
+        let cache = cache::open_cache_db()?;
+        let ocr_ids: Vec<i64> = cache
+            .prepare("SELECT message_id FROM ocr_results")
+            .map_err(|e| format!("Cache error: {}", e))?
+            .query_map([], |row| row.get(0))
+            .map_err(|e| format!("Cache error: {}", e))?
+            .flatten()
+            .collect();
+        report.ocr_results = count_in(&found.message_ids, ocr_ids.into_iter());
+    }
+
+    let archive = archive::open_archive_db()?;
+    report.archived_messages = matching_keys(
+        &archive,
+        "SELECT contact_identifier FROM archived_messages WHERE is_from_me = 0",
+        identifier,
+    )?
+    .len() as i64;
+
+    Ok(report)
+}
+
+/// Handles manually linked to the contact, in either direction
+fn linked_identifiers(conn: &Connection, identifier: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT identifier, canonical FROM handle_links")
+        .map_err(|e| format!("Store error: {}", e))?;
+    let links = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Store error: {}", e))?
+        .flatten()
+        .filter(|(from, to)| same_handle(from, identifier) || same_handle(to, identifier))
+        .map(|(from, _)| from)
+        .collect();
+    Ok(links)
+}
+
+fn digest_needles(identifier: &str, name: Option<&str>) -> Vec<String> {
+    let mut needles = vec![identifier.to_string()];
+    needles.extend(name.map(str::to_string));
+    needles
+}
+
+fn delete_keys(conn: &Connection, sql: &str, keys: &[String]) -> Result<usize, String> {
+    let mut deleted = 0;
+    for key in keys {
+        deleted += conn.execute(sql, [key]).map_err(|e| format!("Store error: {}", e))?;
+    }
+    Ok(deleted)
+}
+
+/// Drop the contact's tapbacks from archived messages they didn't send, returning how many
+fn forget_archived_reactions(archive: &Connection, identifier: &str) -> Result<usize, String> {
+    let rows: Vec<(String, String)> = archive
+        .prepare("SELECT guid, reactions_json FROM archived_messages WHERE reactions_json != '[]'")
+        .map_err(|e| format!("Archive error: {}", e))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Archive error: {}", e))?
+        .flatten()
+        .collect();
+
+    let change = crate::archive::next_change(archive)?;
+    let mut removed = 0;
+    for (guid, json) in rows {
+        let Ok(mut reactions) = serde_json::from_str::<Vec<serde_json::Value>>(&json) else { continue };
+        let before = reactions.len();
+        reactions.retain(|r| !r["sender_id"].as_str().is_some_and(|id| same_handle(id, identifier)));
+        if reactions.len() == before {
+            continue;
+        }
+        removed += before - reactions.len();
+        let json = serde_json::to_string(&reactions).map_err(|e| format!("Archive error: {}", e))?;
+        archive
+            .execute(
+                "UPDATE archived_messages SET reactions_json = ?, change_seq = ? WHERE guid = ?",
+                rusqlite::params![json, change, guid],
+            )
+            .map_err(|e| format!("Archive error: {}", e))?;
+    }
+    Ok(removed)
+}
+
+/// Delete everything the app derived or stored about a contact. chat.db and the AddressBook
+/// belong to macOS and are never touched.
+pub fn forget_contact(identifier: &str) -> Result<ForgetResult, String> {
+    let identifier = identifier.trim();
+    if identifier.is_empty() {
+        return Err("Identifier cannot be empty".to_string());
+    }
+    let name = lookup_contact_name(identifier, &get_contact_names());
+    let matches = get_imessage_db_path()
+        .and_then(|p| chatdb::connect(&p).ok())
+        .map(|conn| chat_db_matches(&conn, identifier))
+        .transpose()?;
+    let mut result = ForgetResult::default();
+
+    let conn = store::open_store_db()?;
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Store error: {}", e))?;
+    let tagged = matching_keys(&tx, "SELECT DISTINCT identifier FROM contact_tags", identifier)?;
+    result.tags = delete_keys(&tx, "DELETE FROM contact_tags WHERE identifier = ?", &tagged)?;
+    let linked = linked_identifiers(&tx, identifier)?;
+    result.handle_links = delete_keys(&tx, "DELETE FROM handle_links WHERE identifier = ?", &linked)?;
+    let noted = matching_keys(&tx, "SELECT key FROM notes WHERE target = 'contact'", identifier)?;
+    result.notes = delete_keys(&tx, "DELETE FROM notes WHERE target = 'contact' AND key = ?", &noted)?;
+    let senders = matching_keys(
+        &tx,
+        "SELECT DISTINCT COALESCE(sender, conversation) FROM imported_messages WHERE is_from_me = 0",
+        identifier,
+    )?;
+    // Deleted explicitly too, for stores created before foreign keys were switched on
+    let mut imported_files: Vec<String> = Vec::new();
+    for sender in &senders {
+        let mut stmt = tx
+            .prepare(
+                "SELECT filename FROM imported_attachments WHERE message_id IN
+                   (SELECT id FROM imported_messages WHERE is_from_me = 0 AND COALESCE(sender, conversation) = ?)",
+            )
+            .map_err(|e| format!("Store error: {}", e))?;
+        imported_files.extend(
+            stmt.query_map([sender], |row| row.get::<_, String>(0))
+                .map_err(|e| format!("Store error: {}", e))?
+                .flatten(),
+        );
+    }
+    result.imported_attachments = delete_keys(
+        &tx,
+        "DELETE FROM imported_attachments WHERE message_id IN
+           (SELECT id FROM imported_messages WHERE is_from_me = 0 AND COALESCE(sender, conversation) = ?)",
+        &senders,
+    )?;
+    result.imported_messages = delete_keys(
+        &tx,
+        "DELETE FROM imported_messages WHERE is_from_me = 0 AND COALESCE(sender, conversation) = ?",
+        &senders,
+    )?;
+    let contacts = matching_keys(&tx, "SELECT identifier FROM imported_contacts", identifier)?;
+    result.imported_contacts = delete_keys(&tx, "DELETE FROM imported_contacts WHERE identifier = ?", &contacts)?;
+    let weeks = matching_digests(&tx, &digest_needles(identifier, name.as_deref()))?;
+    result.digests = delete_keys(&tx, "DELETE FROM digests WHERE week_start = ?", &weeks)?;
+    if let Some(found) = &matches {
+        let guids: Vec<String> = found.message_guids.iter().cloned().collect();
+        result.pinned_messages = delete_keys(&tx, "DELETE FROM pinned_messages WHERE guid = ?", &guids)?;
+    }
+    tx.commit().map_err(|e| format!("Store error: {}", e))?;
+
+    // Media the importers copied into the app data folder; files elsewhere belong to the user
+    if let Some(app_dir) = crate::get_data_dir() {
+        for file in &imported_files {
+            let path = std::path::Path::new(file);
+            if path.starts_with(&app_dir) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    let archive = archive::open_archive_db()?;
+    let archived = matching_keys(&archive, "SELECT DISTINCT contact_identifier FROM archived_messages", identifier)?;
+    result.archived_messages = delete_keys(
+        &archive,
+        "DELETE FROM archived_messages WHERE contact_identifier = ? AND is_from_me = 0",
+        &archived,
+    )?;
+    result.archived_reactions = forget_archived_reactions(&archive, identifier)?;
+
+    let cache = cache::open_cache_db()?;
+    let tx = cache.unchecked_transaction().map_err(|e| format!("Cache error: {}", e))?;
+    if let Some(found) = &matches {
+        for id in &found.message_ids {
+            result.ocr_results += tx
+                .execute("DELETE FROM ocr_results WHERE message_id = ?", [id])
+                .map_err(|e| format!("Cache error: {}", e))?;
+            tx.execute("DELETE FROM ocr_text_fts WHERE message_id = ?", [id])
+                .map_err(|e| format!("Cache error: {}", e))?;
+        }
+    }
+    let hashed = matches.iter().flat_map(|found| found.attachment_paths.iter()).chain(&imported_files);
+    for path in hashed {
+        result.media_hashes += tx
+            .execute("DELETE FROM media_hashes WHERE path = ?", [path])
+            .map_err(|e| format!("Cache error: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Cache error: {}", e))?;
+    // Snapshots store finished reports, which can name them or count their messages
+    result.report_snapshots = crate::snapshots::clear()?;
+
+    tracing::info!(?result, "forgot contact");
+    Ok(result)
+}
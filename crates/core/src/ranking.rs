@@ -0,0 +1,143 @@
+use crate::analytics::{median, messages_by_chat};
+use crate::settings::{self, AppSettings};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+// Contacts with fewer messages are left out of the field so one-off numbers don't skew ranks
+const MIN_MESSAGES: usize = 5;
+
+// Replies slower than this start a new conversation rather than answer one
+const MAX_REPLY_MINUTES: f64 = 1440.0;
+
+/// Where a contact stands on one metric among every ranked contact
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricRank {
+    pub metric: String,
+    pub value: Option<f64>,
+    pub rank: Option<usize>,        // 1 = best
+    pub of: usize,                  // Contacts with a value for this metric
+    pub top_percent: Option<f64>,   // rank / of * 100, so 3.0 reads "top 3%"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContactRanking {
+    pub contact_id: i64,
+    pub identifier: String,
+    pub name: String,
+    pub ranks: Vec<MetricRank>,     // messages, reply_speed, longest_streak, reactions
+}
+
+#[derive(Default)]
+struct ContactStats {
+    identifier: String,
+    name: String,
+    messages: usize,
+    reply_minutes: Vec<f64>,        // Their replies to me
+    days: BTreeSet<chrono::NaiveDate>,
+    reactions: usize,               // Tapbacks exchanged either way
+}
+
+/// Longest run of consecutive days in a sorted set
+fn longest_streak(days: &BTreeSet<chrono::NaiveDate>) -> usize {
+    let mut best = 0;
+    let mut run = 0;
+    let mut prev: Option<chrono::NaiveDate> = None;
+    for &day in days {
+        run = match prev {
+            Some(p) if p.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        best = best.max(run);
+        prev = Some(day);
+    }
+    best
+}
+
+fn collect_stats(messages: &[Message], settings: &AppSettings) -> HashMap<i64, ContactStats> {
+    let mut stats: HashMap<i64, ContactStats> = HashMap::new();
+    for msg in messages.iter().filter(|m| m.handle_id != 0) {
+        let entry = stats.entry(msg.handle_id).or_default();
+        if entry.identifier.is_empty() {
+            entry.identifier = msg.contact_identifier.clone();
+        }
+        if !msg.is_from_me && entry.name.is_empty() {
+            entry.name = msg.sender_name.clone();
+        }
+        entry.messages += 1;
+        if let Some(dt) = settings::to_local_time(msg.date, settings) {
+            entry.days.insert(dt.date());
+        }
+        entry.reactions += msg
+            .reactions
+            .iter()
+            .filter(|r| if msg.is_from_me { !r.is_from_me } else { r.is_from_me })
+            .count();
+    }
+
+    for chat_messages in messages_by_chat(messages).values() {
+        for pair in chat_messages.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            if next.is_from_me || !prev.is_from_me || next.handle_id == 0 {
+                continue;
+            }
+            let minutes = (next.date - prev.date) as f64 / 60.0;
+            if (0.0..MAX_REPLY_MINUTES).contains(&minutes) {
+                stats.entry(next.handle_id).or_default().reply_minutes.push(minutes);
+            }
+        }
+    }
+    stats
+}
+
+/// Rank `target` among `values`; `higher_is_better` picks the direction
+fn rank_of(metric: &str, target: Option<f64>, values: &[f64], higher_is_better: bool) -> MetricRank {
+    let rank = target.map(|t| {
+        1 + values
+            .iter()
+            .filter(|&&v| if higher_is_better { v > t } else { v < t })
+            .count()
+    });
+    MetricRank {
+        metric: metric.to_string(),
+        value: target,
+        rank,
+        of: values.len(),
+        top_percent: rank.filter(|_| !values.is_empty()).map(|r| r as f64 / values.len() as f64 * 100.0),
+    }
+}
+
+/// Percentile ranks of one contact (by handle ROWID) against every contact with enough messages
+pub fn contact_ranking(messages: &[Message], contact_id: i64, settings: &AppSettings) -> Result<ContactRanking, String> {
+    let stats = collect_stats(messages, settings);
+    let target = stats
+        .get(&contact_id)
+        .ok_or_else(|| format!("No messages with contact {}", contact_id))?;
+    let field: Vec<&ContactStats> = stats
+        .iter()
+        .filter(|(&id, s)| s.messages >= MIN_MESSAGES || id == contact_id)
+        .map(|(_, s)| s)
+        .collect();
+
+    type Metric = fn(&ContactStats) -> Option<f64>;
+    let metrics: [(&str, Metric, bool); 4] = [
+        ("messages", |s| Some(s.messages as f64), true),
+        ("reply_speed", |s| median(&s.reply_minutes), false),
+        ("longest_streak", |s| Some(longest_streak(&s.days) as f64), true),
+        ("reactions", |s| Some(s.reactions as f64), true),
+    ];
+    let ranks = metrics
+        .iter()
+        .map(|&(name, value, higher_is_better)| {
+            let values: Vec<f64> = field.iter().filter_map(|s| value(s)).collect();
+            rank_of(name, value(target), &values, higher_is_better)
+        })
+        .collect();
+
+    Ok(ContactRanking {
+        contact_id,
+        identifier: target.identifier.clone(),
+        name: if target.name.is_empty() { target.identifier.clone() } else { target.name.clone() },
+        ranks,
+    })
+}
@@ -0,0 +1,393 @@
+use crate::tasks::TaskRegistry;
+use crate::{settings, store, DateRange};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+// Jobs currently running, so a slow job isn't started again by the next tick or run_job_now
+static RUNNING: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Window a recurring export covers, relative to when it runs
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RollingRange {
+    PreviousDay,
+    PreviousWeek,   // Monday to Sunday
+    PreviousMonth,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobTask {
+    ArchiveSync,                                              // Back up new messages to the archive
+    CacheRefresh,                                             // OCR any new image attachments
+    WeeklyDigest,
+    Export { job_id: i64, range: Option<RollingRange> },      // Re-run a past export from the history
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    #[serde(default)]
+    pub id: String,       // Assigned by save_job when empty
+    pub name: String,
+    pub schedule: String, // Cron expression: minute hour day-of-month month day-of-week
+    pub task: JobTask,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobRun {
+    pub job_id: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub ok: bool,
+    pub message: String,  // Result summary or error
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub job: ScheduledJob,
+    pub last_run: Option<JobRun>,
+    pub next_run: Option<i64>,  // Unix timestamp; None when disabled
+    pub running: bool,
+}
+
+/// A parsed cron expression, one bit per allowed value
+struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("Invalid step in '{}'", part))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(format!("Invalid step in '{}'", part));
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            let a = a.parse::<u32>().map_err(|_| format!("Invalid value in '{}'", part))?;
+            let b = b.parse::<u32>().map_err(|_| format!("Invalid value in '{}'", part))?;
+            (a, b)
+        } else {
+            let a = range.parse::<u32>().map_err(|_| format!("Invalid value in '{}'", part))?;
+            // "5/15" means every 15 starting at 5
+            (a, if part.contains('/') { max } else { a })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("'{}' is outside {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("Schedule '{}' must have five fields", expression));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1; // 7 is also Sunday
+        }
+        Ok(CronSchedule {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        // As in cron, restricting both day fields means either may match
+        let day_ok = match (self.any_day, self.any_weekday) {
+            (true, _) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        day_ok && self.months & (1 << date.month()) != 0
+    }
+
+    fn matches(&self, time: NaiveDateTime) -> bool {
+        self.matches_day(time.date())
+            && self.hours & (1 << time.hour()) != 0
+            && self.minutes & (1 << time.minute()) != 0
+    }
+
+    /// First matching minute after `after`, looking at most a year ahead
+    fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = time + Duration::days(366);
+        while time < limit {
+            if !self.matches_day(time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// Check a job before it is saved
+pub fn validate(job: &ScheduledJob) -> Result<(), String> {
+    if job.name.trim().is_empty() {
+        return Err("Job name cannot be empty".to_string());
+    }
+    CronSchedule::parse(&job.schedule)?;
+    Ok(())
+}
+
+/// Create or update a job in settings
+pub fn save(mut job: ScheduledJob) -> Result<ScheduledJob, String> {
+    if job.id.is_empty() {
+        job.id = format!("job-{}", Utc::now().timestamp_millis());
+    }
+    let mut app_settings = settings::load_settings();
+    match app_settings.scheduled_jobs.iter_mut().find(|j| j.id == job.id) {
+        Some(existing) => *existing = job.clone(),
+        None => app_settings.scheduled_jobs.push(job.clone()),
+    }
+    settings::validate_settings(&app_settings)?;
+    settings::save_settings(&app_settings)?;
+    Ok(job)
+}
+
+pub fn delete(id: &str) -> Result<(), String> {
+    let mut app_settings = settings::load_settings();
+    app_settings.scheduled_jobs.retain(|j| j.id != id);
+    settings::save_settings(&app_settings)?;
+    let conn = store::open_store_db()?;
+    conn.execute("DELETE FROM job_runs WHERE job_id = ?", [id])
+        .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+fn last_run(conn: &rusqlite::Connection, job_id: &str) -> Option<JobRun> {
+    conn.query_row(
+        "SELECT job_id, started_at, finished_at, ok, message FROM job_runs WHERE job_id = ?",
+        [job_id],
+        |row| {
+            Ok(JobRun {
+                job_id: row.get(0)?,
+                started_at: row.get(1)?,
+                finished_at: row.get(2)?,
+                ok: row.get(3)?,
+                message: row.get(4)?,
+            })
+        },
+    )
+    .ok()
+}
+
+fn is_running(job_id: &str) -> bool {
+    RUNNING
+        .lock()
+        .map(|running| running.as_ref().is_some_and(|r| r.contains(job_id)))
+        .unwrap_or(false)
+}
+
+/// Every configured job with its last run and next scheduled time
+pub fn list() -> Result<Vec<JobStatus>, String> {
+    let conn = store::open_store_db()?;
+    let now = Local::now().naive_local();
+    Ok(settings::load_settings()
+        .scheduled_jobs
+        .into_iter()
+        .map(|job| {
+            let next_run = CronSchedule::parse(&job.schedule)
+                .ok()
+                .filter(|_| job.enabled)
+                .and_then(|s| s.next_after(now))
+                .and_then(|t| t.and_local_timezone(Local).earliest())
+                .map(|t| t.timestamp());
+            JobStatus {
+                last_run: last_run(&conn, &job.id),
+                running: is_running(&job.id),
+                next_run,
+                job,
+            }
+        })
+        .collect())
+}
+
+/// Local-day bounds of the window before today
+fn rolling_range(range: RollingRange) -> Option<DateRange> {
+    let today = Local::now().date_naive();
+    let (first, last) = match range {
+        RollingRange::PreviousDay => (today.pred_opt()?, today.pred_opt()?),
+        RollingRange::PreviousWeek => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64 + 7);
+            (monday, monday + Duration::days(6))
+        }
+        RollingRange::PreviousMonth => {
+            let first_of_month = today.with_day(1)?;
+            let last = first_of_month.pred_opt()?;
+            (last.with_day(1)?, last)
+        }
+    };
+    let (start, _) = crate::local_day_bounds(&first.format("%Y-%m-%d").to_string())?;
+    let (_, end) = crate::local_day_bounds(&last.format("%Y-%m-%d").to_string())?;
+    Some(DateRange { start, end })
+}
+
+fn run_task(task_registry: &TaskRegistry, task: &JobTask, notify: &dyn Fn(&str, &str)) -> Result<String, String> {
+    match task {
+        JobTask::ArchiveSync => {
+            let result = crate::run_archive_sync(task_registry)?;
+            Ok(format!("Archived {} new messages", result.added))
+        }
+        JobTask::CacheRefresh => {
+            let summary = crate::scan_images(task_registry, None, None)?;
+            if !summary.available {
+                return Err("Tesseract is not installed".to_string());
+            }
+            Ok(format!("Scanned {} images", summary.scanned))
+        }
+        JobTask::WeeklyDigest => {
+            crate::digest::run_if_due(notify)?;
+            Ok("Digest checked".to_string())
+        }
+        JobTask::Export { job_id, range } => {
+            let range = match range {
+                Some(r) => Some(rolling_range(*r).ok_or("Cannot compute export range")?),
+                None => None,
+            };
+            let result = crate::rerun_export_job(task_registry, *job_id, range)?;
+            Ok(format!("Exported {} messages", result.message_count))
+        }
+    }
+}
+
+/// Run a job now and record the outcome as its last run
+pub fn run(task_registry: &TaskRegistry, job: &ScheduledJob, notify: &dyn Fn(&str, &str)) -> Result<JobRun, String> {
+    let _span = tracing::info_span!("scheduled_job", id = %job.id, name = %job.name).entered();
+    {
+        let mut running = RUNNING.lock().map_err(|_| "Scheduler state is unavailable")?;
+        if !running.get_or_insert_with(HashSet::new).insert(job.id.clone()) {
+            return Err(format!("{} is already running", job.name));
+        }
+    }
+
+    let started_at = Utc::now().timestamp();
+    let outcome = run_task(task_registry, &job.task, notify);
+    if let Ok(mut running) = RUNNING.lock() {
+        if let Some(r) = running.as_mut() {
+            r.remove(&job.id);
+        }
+    }
+
+    let run = JobRun {
+        job_id: job.id.clone(),
+        started_at,
+        finished_at: Utc::now().timestamp(),
+        ok: outcome.is_ok(),
+        message: outcome.unwrap_or_else(|e| e),
+    };
+    let conn = store::open_store_db()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO job_runs (job_id, started_at, finished_at, ok, message) VALUES (?, ?, ?, ?, ?)",
+        rusqlite::params![run.job_id, run.started_at, run.finished_at, run.ok, run.message],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+    if !run.ok {
+        tracing::warn!("Scheduled job {} failed: {}", job.name, run.message);
+    }
+    Ok(run)
+}
+
+/// Enabled jobs whose schedule matches this minute
+pub fn due_jobs(minute: NaiveDateTime) -> Vec<ScheduledJob> {
+    settings::load_settings()
+        .scheduled_jobs
+        .into_iter()
+        .filter(|j| j.enabled)
+        .filter(|j| CronSchedule::parse(&j.schedule).map(|s| s.matches(minute)).unwrap_or(false))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<NaiveDateTime> {
+        CronSchedule::parse(expression).unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn parses_lists_ranges_and_steps() {
+        assert_eq!(parse_field("*", 0, 7).unwrap(), 0b1111_1111);
+        assert_eq!(parse_field("1,3", 0, 7).unwrap(), 0b1010);
+        assert_eq!(parse_field("2-4", 0, 7).unwrap(), 0b1_1100);
+        assert_eq!(parse_field("*/3", 0, 7).unwrap(), 0b0100_1001);
+        assert_eq!(parse_field("5/15", 0, 59).unwrap(), (1 << 5) | (1 << 20) | (1 << 35) | (1 << 50));
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        assert!(parse_field("60", 0, 59).is_err());
+        assert!(parse_field("0", 1, 31).is_err());
+        assert!(parse_field("5-2", 0, 59).is_err());
+        assert!(parse_field("*/0", 0, 59).is_err());
+        assert!(parse_field("x", 0, 59).is_err());
+        assert!(CronSchedule::parse("0 9 * *").is_err());
+        assert!(CronSchedule::parse("0 9 * * * *").is_err());
+    }
+
+    #[test]
+    fn finds_the_next_matching_minute() {
+        // Strictly after, never the same minute
+        assert_eq!(next("30 9 * * *", "2026-03-02 09:30"), Some(at("2026-03-03 09:30")));
+        assert_eq!(next("30 9 * * *", "2026-03-02 08:59"), Some(at("2026-03-02 09:30")));
+        assert_eq!(next("*/15 * * * *", "2026-03-02 23:50"), Some(at("2026-03-03 00:00")));
+        // 2026-03-02 is a Monday; 5 and 7 name Friday and Sunday
+        assert_eq!(next("0 8 * * 5", "2026-03-02 12:00"), Some(at("2026-03-06 08:00")));
+        assert_eq!(next("0 8 * * 7", "2026-03-02 12:00"), Some(at("2026-03-08 08:00")));
+        assert_eq!(next("0 0 1 1 *", "2026-03-02 12:00"), Some(at("2027-01-01 00:00")));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 10th or any Sunday, whichever comes first
+        assert_eq!(next("0 12 10 * 0", "2026-03-02 00:00"), Some(at("2026-03-08 12:00")));
+        assert_eq!(next("0 12 10 * 0", "2026-03-08 12:00"), Some(at("2026-03-10 12:00")));
+    }
+
+    #[test]
+    fn gives_up_on_dates_that_never_come() {
+        assert_eq!(next("0 0 31 2 *", "2026-01-01 00:00"), None);
+    }
+}
@@ -0,0 +1,78 @@
+// Highlight markers passed to FTS5 snippet()/highlight(); control characters never typed in messages
+pub const MARK_START: char = '\u{2}';
+pub const MARK_END: char = '\u{3}';
+
+// Characters of context kept either side of the first match in a snippet
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+const ELLIPSIS: &str = "…";
+
+/// Strip FTS5 highlight markers, returning the plain text and the byte ranges they enclosed
+pub fn parse_marked(marked: &str) -> (String, Vec<(usize, usize)>) {
+    let mut text = String::with_capacity(marked.len());
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for c in marked.chars() {
+        match c {
+            MARK_START => start = Some(text.len()),
+            MARK_END => {
+                if let Some(s) = start.take() {
+                    ranges.push((s, text.len()));
+                }
+            }
+            _ => text.push(c),
+        }
+    }
+    (text, ranges)
+}
+
+/// Byte ranges of every non-overlapping occurrence of `needle`, ignoring ASCII case the way
+/// SQLite's LIKE does
+pub fn find_matches(text: &str, needle: &str) -> Vec<(usize, usize)> {
+    let (hay, pat) = (text.as_bytes(), needle.as_bytes());
+    let mut ranges = Vec::new();
+    if pat.is_empty() {
+        return ranges;
+    }
+    let mut i = 0;
+    while i + pat.len() <= hay.len() {
+        // Both ends must sit on char boundaries so the range slices the text cleanly
+        if text.is_char_boundary(i) && text.is_char_boundary(i + pat.len()) && hay[i..i + pat.len()].eq_ignore_ascii_case(pat) {
+            ranges.push((i, i + pat.len()));
+            i += pat.len();
+        } else {
+            i += 1;
+        }
+    }
+    ranges
+}
+
+/// A window of `text` around its first match, with the matches inside it re-based onto the
+/// snippet. Cut ends are marked with an ellipsis.
+pub fn snippet(text: &str, matches: &[(usize, usize)]) -> (String, Vec<(usize, usize)>) {
+    let Some(&(first_start, first_end)) = matches.first() else {
+        return (text.chars().take(SNIPPET_CONTEXT_CHARS * 2).collect(), Vec::new());
+    };
+    let start = text[..first_start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let end = text[first_end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT_CHARS)
+        .map_or(text.len(), |(i, _)| first_end + i);
+
+    let prefix = if start > 0 { ELLIPSIS } else { "" };
+    let mut snippet = format!("{}{}", prefix, &text[start..end]);
+    if end < text.len() {
+        snippet.push_str(ELLIPSIS);
+    }
+    let shift = prefix.len();
+    let ranges = matches
+        .iter()
+        .filter(|&&(s, e)| s >= start && e <= end)
+        .map(|&(s, e)| (s - start + shift, e - start + shift))
+        .collect();
+    (snippet, ranges)
+}
@@ -0,0 +1,85 @@
+// Credentials live only in the macOS Keychain, never in settings JSON or app databases
+
+#[cfg(target_os = "macos")]
+const KEYCHAIN_SERVICE: &str = "com.messageinsights.app";
+
+// Keychain account prefix for user-provided secrets, keeping them apart from internal keys
+const SECRET_PREFIX: &str = "secret:";
+
+/// Secret names are short identifiers such as "openai" or "webhook-home"
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("Secret name must be 1-64 characters".to_string());
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return Err(format!("Secret name '{}' may only use letters, digits, '-', '_' and '.'", name));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn keychain_get(account: &str) -> Result<Option<String>, String> {
+    // errSecItemNotFound
+    const NOT_FOUND: i32 = -25300;
+    match security_framework::passwords::get_generic_password(KEYCHAIN_SERVICE, account) {
+        Ok(bytes) => String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| format!("Keychain entry '{}' is corrupt", account)),
+        Err(e) if e.code() == NOT_FOUND => Ok(None),
+        Err(e) => Err(format!("Cannot read '{}' from Keychain: {}", account, e)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn keychain_set(account: &str, value: &str) -> Result<(), String> {
+    security_framework::passwords::set_generic_password(KEYCHAIN_SERVICE, account, value.as_bytes())
+        .map_err(|e| format!("Cannot store '{}' in Keychain: {}", account, e))
+}
+
+#[cfg(target_os = "macos")]
+pub fn keychain_delete(account: &str) -> Result<(), String> {
+    // errSecItemNotFound
+    const NOT_FOUND: i32 = -25300;
+    match security_framework::passwords::delete_generic_password(KEYCHAIN_SERVICE, account) {
+        Ok(()) => Ok(()),
+        Err(e) if e.code() == NOT_FOUND => Ok(()),
+        Err(e) => Err(format!("Cannot remove '{}' from Keychain: {}", account, e)),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn keychain_get(_account: &str) -> Result<Option<String>, String> {
+    Err("Secret storage needs the macOS Keychain".to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn keychain_set(_account: &str, _value: &str) -> Result<(), String> {
+    Err("Secret storage needs the macOS Keychain".to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn keychain_delete(_account: &str) -> Result<(), String> {
+    Err("Secret storage needs the macOS Keychain".to_string())
+}
+
+/// Store a named secret (an API key or token); an empty value removes it
+pub fn set_secret(name: &str, value: &str) -> Result<(), String> {
+    validate_name(name)?;
+    let account = format!("{}{}", SECRET_PREFIX, name);
+    if value.is_empty() {
+        keychain_delete(&account)
+    } else {
+        keychain_set(&account, value)
+    }
+}
+
+pub fn has_secret(name: &str) -> Result<bool, String> {
+    validate_name(name)?;
+    Ok(keychain_get(&format!("{}{}", SECRET_PREFIX, name))?.is_some())
+}
+
+/// Read a secret for an outgoing request. Never returned to the frontend.
+pub fn get_secret(name: &str) -> Result<String, String> {
+    validate_name(name)?;
+    keychain_get(&format!("{}{}", SECRET_PREFIX, name))?.ok_or_else(|| format!("No secret named '{}' is stored", name))
+}
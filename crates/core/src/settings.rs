@@ -0,0 +1,319 @@
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AppSettings {
+    pub timezone: Option<String>,  // IANA name (e.g. "America/New_York"); system zone when unset
+    pub archive_attachment_retention_years: Option<u32>,  // Archive drops attachments older than this, keeping text
+    pub api_enabled: bool,          // Start the localhost API with the app
+    pub api_port: Option<u16>,      // Defaults to api::DEFAULT_PORT
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_token: Option<String>,  // Only kept until a token saved by an older version moves to the Keychain
+    pub webhooks: Vec<crate::webhook::WebhookConfig>,
+    pub weekly_digest_enabled: Option<bool>,  // On unless explicitly disabled
+    pub digest_webhook: Option<String>,       // Webhook name that also receives each weekly digest
+    pub last_export_dir: Option<String>,      // Folder of the most recent export, for the tray shortcut
+    pub export_folder: Option<String>,        // The only folder the localhost API and automation URLs may export into
+    pub self_names: Vec<String>,              // How you appear in imported chat logs (e.g. WhatsApp)
+    pub locale: LocaleSettings,
+    pub scheduled_jobs: Vec<crate::scheduler::ScheduledJob>,
+    pub tokenizer: TokenizerSettings,
+    pub keyword_categories: Vec<crate::keywords::KeywordCategory>,  // Topics tracked over time
+    pub phrase_categories: Option<Vec<crate::keywords::KeywordCategory>>,  // Apology/thanks/affection counters; built-ins when unset
+    pub abbreviations: Option<Vec<String>>,  // Tracked by the abbreviation timeline; built-ins when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pseudonym_key: Option<String>,       // Only kept until a key saved by an older version moves to the Keychain
+    pub local_only: bool,                    // Hard-disables every feature that can reach the network
+    pub encrypt_app_data: bool,              // store/cache/archive DBs are SQLCipher-encrypted, key in the Keychain
+    pub analytics_threads: Option<usize>,    // Workers for per-chat analytics; one per CPU core when unset, 1 disables
+    pub separate_duplicate_chats: bool,      // Keep a person's iMessage/SMS/email 1:1 chats apart instead of merging them
+}
+
+/// Order of day, month and year in generated reports
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    #[default]
+    Ymd,  // 2024-03-09
+    Dmy,  // 09/03/2024
+    Mdy,  // 03/09/2024
+}
+
+/// How dates, times and counts are written in exports and other Rust-generated text
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LocaleSettings {
+    pub date_order: DateOrder,
+    pub use_12_hour_clock: bool,
+    pub thousands_separator: Option<String>,  // "," when unset; "" disables grouping
+}
+
+/// How message text is split into words for word stats and other word-based analysis
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TokenizerSettings {
+    pub extra_stop_words: Vec<String>,    // Ignored in every language, on top of the built-in lists
+    pub allowed_stop_words: Vec<String>,  // Built-in stop words to count anyway
+    pub keep_urls: bool,                  // Count links as words instead of dropping them
+    pub split_hyphens: bool,              // "check-in" counts as "check" and "in"
+    pub min_word_length: usize,
+}
+
+impl Default for TokenizerSettings {
+    fn default() -> Self {
+        TokenizerSettings {
+            extra_stop_words: Vec::new(),
+            allowed_stop_words: Vec::new(),
+            keep_urls: false,
+            split_hyphens: false,
+            min_word_length: 3,
+        }
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    crate::get_data_dir().map(|dir| dir.join("settings.json"))
+}
+
+/// Load settings from disk, falling back to defaults when missing or unreadable
+pub fn load_settings() -> AppSettings {
+    settings_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Persist settings to disk
+pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
+    let path = settings_path().ok_or("Could not determine app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Cannot create app data directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Cannot serialize settings: {}", e))?;
+    crate::export::write_atomic(&path, json).map_err(|e| format!("Cannot write settings: {}", e))
+}
+
+/// Validate settings before they are saved
+pub fn validate_settings(settings: &AppSettings) -> Result<(), String> {
+    if let Some(ref tz) = settings.timezone {
+        tz.parse::<chrono_tz::Tz>()
+            .map_err(|_| format!("Unknown timezone: {}", tz))?;
+    }
+    if settings.api_port == Some(0) {
+        return Err("API port must be non-zero".to_string());
+    }
+    for webhook in &settings.webhooks {
+        crate::webhook::validate(webhook)?;
+    }
+    for job in &settings.scheduled_jobs {
+        crate::scheduler::validate(job)?;
+    }
+    if settings.locale.thousands_separator.as_deref().is_some_and(|s| s.chars().count() > 1) {
+        return Err("Thousands separator must be a single character".to_string());
+    }
+    for category in settings.keyword_categories.iter().chain(settings.phrase_categories.iter().flatten()) {
+        crate::keywords::validate(category)?;
+    }
+    if settings.tokenizer.min_word_length == 0 {
+        return Err("Minimum word length must be at least 1".to_string());
+    }
+    if settings.archive_attachment_retention_years == Some(0) {
+        return Err("Attachment retention must be at least one year".to_string());
+    }
+    Ok(())
+}
+
+/// Resolve an export path requested from outside the app, which may only write inside the
+/// export folder the user approved in settings. Relative paths are taken from that folder.
+pub fn approved_export_path(path: &str, settings: &AppSettings) -> Result<PathBuf, String> {
+    let folder = settings
+        .export_folder
+        .as_deref()
+        .ok_or("Choose an export folder in Settings to allow exports from outside the app")?;
+    let folder = std::fs::canonicalize(crate::expand_home_path(folder.to_string()))
+        .map_err(|e| format!("Export folder is unavailable: {}", e))?;
+
+    let requested = PathBuf::from(crate::expand_home_path(path.to_string()));
+    let requested = if requested.is_relative() { folder.join(requested) } else { requested };
+    // Canonicalizing the parent resolves `..` and symlinks before the containment check
+    let name = requested.file_name().ok_or("Export path must name a file")?.to_owned();
+    let parent = requested
+        .parent()
+        .and_then(|p| std::fs::canonicalize(p).ok())
+        .ok_or("Export path's folder doesn't exist")?;
+    if !parent.starts_with(&folder) {
+        return Err(format!("Exports from outside the app must go inside {}", folder.display()));
+    }
+    Ok(parent.join(name))
+}
+
+/// Convert a Unix timestamp to wall-clock time in the configured timezone
+pub fn to_local_time(unix_ts: i64, settings: &AppSettings) -> Option<NaiveDateTime> {
+    let utc = Utc.timestamp_opt(unix_ts, 0).single()?;
+    match settings.timezone.as_deref().and_then(|tz| tz.parse::<chrono_tz::Tz>().ok()) {
+        Some(tz) => Some(utc.with_timezone(&tz).naive_local()),
+        None => Some(utc.with_timezone(&chrono::Local).naive_local()),
+    }
+}
+
+/// Convert wall-clock time in the configured timezone to a Unix timestamp, taking the earlier
+/// instant when clocks go back
+pub fn from_local_time(local: NaiveDateTime, settings: &AppSettings) -> Option<i64> {
+    match settings.timezone.as_deref().and_then(|tz| tz.parse::<chrono_tz::Tz>().ok()) {
+        Some(tz) => tz.from_local_datetime(&local).earliest().map(|dt| dt.timestamp()),
+        None => chrono::Local.from_local_datetime(&local).earliest().map(|dt| dt.timestamp()),
+    }
+}
+
+/// Seconds the configured timezone is ahead of UTC at a Unix timestamp
+fn utc_offset(unix_ts: i64, settings: &AppSettings) -> i64 {
+    to_local_time(unix_ts, settings).map_or(0, |local| local.and_utc().timestamp() - unix_ts)
+}
+
+// Offset changes are looked up from here to a year ahead; earlier dates keep the first offset
+const OFFSET_HISTORY_START: i64 = 631_152_000; // 1990-01-01
+
+/// SQL expression turning Unix seconds into wall-clock seconds in the configured timezone, to
+/// feed SQLite's date functions with 'unixepoch' instead of the process-wide 'localtime'.
+/// Daylight saving and other offset changes become a CASE over the instants they happen.
+pub fn local_seconds_sql(unix_sql: &str, settings: &AppSettings) -> String {
+    let end = Utc::now().timestamp() + 366 * 86_400;
+    let mut changes = Vec::new();
+    let mut day = OFFSET_HISTORY_START;
+    let mut offset = utc_offset(day, settings);
+    let first = offset;
+    while day < end {
+        let next_day = day + 86_400;
+        let next = utc_offset(next_day, settings);
+        if next != offset {
+            // The first second on the new offset
+            let (mut before, mut after) = (day, next_day);
+            while after - before > 1 {
+                let mid = before + (after - before) / 2;
+                if utc_offset(mid, settings) == offset {
+                    before = mid;
+                } else {
+                    after = mid;
+                }
+            }
+            changes.push((after, next));
+            offset = next;
+        }
+        day = next_day;
+    }
+
+    // Newest first, since most messages are recent
+    let branches: String = changes
+        .iter()
+        .rev()
+        .map(|(at, offset)| format!(" WHEN {} >= {} THEN {}", unix_sql, at, offset))
+        .collect();
+    if branches.is_empty() {
+        format!("({} + {})", unix_sql, first)
+    } else {
+        format!("({} + CASE{} ELSE {} END)", unix_sql, branches, first)
+    }
+}
+
+fn date_pattern(locale: &LocaleSettings) -> &'static str {
+    match locale.date_order {
+        DateOrder::Ymd => "%Y-%m-%d",
+        DateOrder::Dmy => "%d/%m/%Y",
+        DateOrder::Mdy => "%m/%d/%Y",
+    }
+}
+
+/// Format a Unix timestamp as a local date using the configured date order
+pub fn format_date(unix_ts: i64, settings: &AppSettings) -> String {
+    to_local_time(unix_ts, settings)
+        .map(|dt| dt.format(date_pattern(&settings.locale)).to_string())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Format a Unix timestamp as a local month using the configured date order
+pub fn format_month(unix_ts: i64, settings: &AppSettings) -> String {
+    let pattern = match settings.locale.date_order {
+        DateOrder::Ymd => "%Y-%m",
+        DateOrder::Dmy | DateOrder::Mdy => "%m/%Y",
+    };
+    to_local_time(unix_ts, settings)
+        .map(|dt| dt.format(pattern).to_string())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Format a Unix timestamp as a local time of day (no seconds) using the configured clock
+pub fn format_time(unix_ts: i64, settings: &AppSettings) -> String {
+    let pattern = if settings.locale.use_12_hour_clock { "%-I:%M %p" } else { "%H:%M" };
+    to_local_time(unix_ts, settings).map(|dt| dt.format(pattern).to_string()).unwrap_or_default()
+}
+
+/// Format a Unix timestamp as a local date and time using the configured date order and clock
+pub fn format_datetime(unix_ts: i64, settings: &AppSettings) -> String {
+    let time_pattern = if settings.locale.use_12_hour_clock { "%-I:%M:%S %p" } else { "%H:%M:%S" };
+    to_local_time(unix_ts, settings)
+        .map(|dt| format!("{} {}", dt.format(date_pattern(&settings.locale)), dt.format(time_pattern)))
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Format a count with the configured thousands separator (e.g. 12,345)
+pub fn format_count(n: i64, settings: &AppSettings) -> String {
+    let separator = settings.locale.thousands_separator.as_deref().unwrap_or(",");
+    let digits = n.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push_str(separator);
+        }
+        out.push(c);
+    }
+    if n < 0 {
+        out.insert(0, '-');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_zone(tz: &str) -> AppSettings {
+        AppSettings { timezone: Some(tz.to_string()), ..Default::default() }
+    }
+
+    /// Run the offset expression in SQLite for one instant
+    fn local_day(unix_ts: i64, settings: &AppSettings) -> String {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let sql = format!("SELECT datetime({}, 'unixepoch')", local_seconds_sql("?1", settings));
+        conn.query_row(&sql, [unix_ts], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn local_seconds_follow_daylight_saving() {
+        let new_york = in_zone("America/New_York");
+        // 2024-01-15 03:30 UTC is the evening before in EST (-5)
+        assert_eq!(local_day(1_705_289_400, &new_york), "2024-01-14 22:30:00");
+        // 2024-07-15 03:30 UTC is EDT (-4)
+        assert_eq!(local_day(1_721_014_200, &new_york), "2024-07-14 23:30:00");
+        // Either side of the 2024-03-10 07:00 UTC switch
+        assert_eq!(local_day(1_710_053_999, &new_york), "2024-03-10 01:59:59");
+        assert_eq!(local_day(1_710_054_000, &new_york), "2024-03-10 03:00:00");
+    }
+
+    #[test]
+    fn fixed_zones_need_no_case() {
+        let tokyo = in_zone("Asia/Tokyo");
+        assert_eq!(local_seconds_sql("t", &tokyo), "(t + 32400)");
+        assert_eq!(local_day(1_705_289_400, &tokyo), "2024-01-15 12:30:00");
+    }
+
+    #[test]
+    fn local_times_round_trip() {
+        let new_york = in_zone("America/New_York");
+        let local = to_local_time(1_721_014_200, &new_york).unwrap();
+        assert_eq!(from_local_time(local, &new_york), Some(1_721_014_200));
+    }
+}
@@ -0,0 +1,266 @@
+use crate::imports::{self, ImportResult, ImportedAttachment, ImportedMessage};
+use crate::store;
+use base64::Engine;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+const SOURCE: &str = "sms-backup-restore";
+
+// SMS "type" and MMS "msg_box" both use 2 for sent messages
+const SENT_BOX: &str = "2";
+
+/// Collect an element's attributes into a map
+fn attributes(element: &BytesStart) -> HashMap<String, String> {
+    element
+        .attributes()
+        .flatten()
+        .filter_map(|attr| {
+            let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+            let value = attr.unescape_value().ok()?.to_string();
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// The backup stores "null" for missing values
+fn non_null(value: Option<&String>) -> Option<String> {
+    value
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && *v != "null")
+        .map(|v| v.to_string())
+}
+
+/// Dates are Unix milliseconds
+fn parse_date(attrs: &HashMap<String, String>) -> Option<i64> {
+    attrs.get("date")?.parse::<i64>().ok().map(|ms| ms / 1000)
+}
+
+fn parse_sms(attrs: &HashMap<String, String>) -> Option<ImportedMessage> {
+    let is_from_me = attrs.get("type").map(|t| t == SENT_BOX).unwrap_or(false);
+    let address = non_null(attrs.get("address"));
+    Some(ImportedMessage {
+        sender: if is_from_me {
+            None
+        } else {
+            non_null(attrs.get("contact_name")).or_else(|| address.clone())
+        },
+        conversation: address,
+        is_from_me,
+        date: parse_date(attrs)?,
+        text: non_null(attrs.get("body")),
+        attachments: Vec::new(),
+    })
+}
+
+/// An MMS being assembled from its <part> and <addr> children
+struct PendingMms {
+    attrs: HashMap<String, String>,
+    text: Vec<String>,
+    attachments: Vec<ImportedAttachment>,
+    from_address: Option<String>,
+}
+
+/// Decode a base64 media part into the import media folder
+fn save_part(
+    media_dir: &Path,
+    date: i64,
+    index: usize,
+    attrs: &HashMap<String, String>,
+) -> Option<ImportedAttachment> {
+    let data = attrs.get("data")?;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?;
+    let name = non_null(attrs.get("name"))
+        .or_else(|| non_null(attrs.get("cl")))
+        .unwrap_or_else(|| "part".to_string());
+    // Names come from the backup; keep only the final path component
+    let name = name.rsplit(['/', '\\']).next().unwrap_or("part").to_string();
+
+    std::fs::create_dir_all(media_dir).ok()?;
+    let path = media_dir.join(format!("{}_{}_{}", date, index, name));
+    std::fs::write(&path, &bytes).ok()?;
+
+    Some(ImportedAttachment {
+        filename: path.to_string_lossy().to_string(),
+        mime_type: non_null(attrs.get("ct")),
+        total_bytes: Some(bytes.len() as i64),
+    })
+}
+
+fn finish_mms(mms: PendingMms) -> Option<ImportedMessage> {
+    let is_from_me = mms.attrs.get("msg_box").map(|b| b == SENT_BOX).unwrap_or(false);
+    let text = mms.text.join("\n");
+    Some(ImportedMessage {
+        sender: if is_from_me {
+            None
+        } else {
+            non_null(mms.attrs.get("contact_name")).or(mms.from_address)
+        },
+        // Group MMS list every participant as "a~b~c"
+        conversation: non_null(mms.attrs.get("address")),
+        is_from_me,
+        date: parse_date(&mms.attrs)?,
+        text: if text.is_empty() { None } else { Some(text) },
+        attachments: mms.attachments,
+    })
+}
+
+fn media_dir() -> Result<PathBuf, String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    Ok(dir.join("imports").join(SOURCE))
+}
+
+/// Parse the backup's sms and mms entries, saving MMS media into `media_dir`; returns the
+/// messages and a note for each entry that could not be read
+fn read_backup<R: BufRead>(input: R, media_dir: &Path) -> Result<(Vec<ImportedMessage>, Vec<String>), String> {
+    let mut reader = Reader::from_reader(input);
+    let mut messages = Vec::new();
+    let mut errors = Vec::new();
+    let mut pending: Option<PendingMms> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("Invalid backup XML at byte {}: {}", reader.buffer_position(), e))?;
+        match event {
+            Event::Eof => break,
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                let attrs = attributes(e);
+                match e.name().as_ref() {
+                    b"sms" => match parse_sms(&attrs) {
+                        Some(msg) => messages.push(msg),
+                        None => errors.push("SMS without a valid date".to_string()),
+                    },
+                    b"mms" => {
+                        let mms = PendingMms {
+                            attrs,
+                            text: Vec::new(),
+                            attachments: Vec::new(),
+                            from_address: None,
+                        };
+                        if is_empty {
+                            messages.extend(finish_mms(mms));
+                        } else {
+                            pending = Some(mms);
+                        }
+                    }
+                    b"part" => {
+                        if let Some(ref mut mms) = pending {
+                            let content_type = attrs.get("ct").map(|s| s.as_str()).unwrap_or("");
+                            if content_type == "text/plain" {
+                                mms.text.extend(non_null(attrs.get("text")));
+                            } else if content_type != "application/smil" {
+                                let date = parse_date(&mms.attrs).unwrap_or(0);
+                                match save_part(media_dir, date, mms.attachments.len(), &attrs) {
+                                    Some(a) => mms.attachments.push(a),
+                                    None => errors.push(format!("Could not save MMS part from {}", date)),
+                                }
+                            }
+                        }
+                    }
+                    b"addr" => {
+                        // type 137 is the sender of the MMS
+                        if let Some(ref mut mms) = pending {
+                            if attrs.get("type").map(|t| t == "137").unwrap_or(false) {
+                                mms.from_address = non_null(attrs.get("address"));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(ref e) if e.name().as_ref() == b"mms" => {
+                if let Some(mms) = pending.take() {
+                    match finish_mms(mms) {
+                        Some(msg) => messages.push(msg),
+                        None => errors.push("MMS without a valid date".to_string()),
+                    }
+                }
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok((messages, errors))
+}
+
+/// Import an Android "SMS Backup & Restore" XML file (sms and mms entries, with base64 media)
+pub fn import_sms_backup(path: &Path) -> Result<ImportResult, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open backup: {}", e))?;
+    let (messages, mut errors) = read_backup(BufReader::new(file), &media_dir()?)?;
+
+    let mut conn = store::open_store_db()?;
+    let (imported, skipped) = imports::insert_imported(&mut conn, SOURCE, &messages)?;
+
+    errors.truncate(imports::MAX_REPORTED_ERRORS);
+    Ok(ImportResult {
+        source: SOURCE.to_string(),
+        imported,
+        skipped,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKUP: &str = r#"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?>
+<smses count="4">
+  <sms address="+15551234" date="1700000000123" type="1" body="Hi &amp; welcome" contact_name="Ana" />
+  <sms address="+15551234" date="1700000060000" type="2" body="Thanks" contact_name="null" />
+  <sms address="+15551234" date="soon" type="1" body="No date" />
+  <mms date="1700000120000" msg_box="1" address="+15551234~+15559876" contact_name="null">
+    <parts>
+      <part ct="application/smil" text="&lt;smil /&gt;" />
+      <part ct="text/plain" text="Look" />
+      <part ct="image/png" name="../../pic.png" data="aGVsbG8=" />
+    </parts>
+    <addrs>
+      <addr address="+15559876" type="137" />
+      <addr address="+15551234" type="151" />
+    </addrs>
+  </mms>
+</smses>"#;
+
+    #[test]
+    fn reads_sms_and_mms_entries() {
+        let media = std::env::temp_dir().join(format!("message-insights-sms-{}", std::process::id()));
+        let (messages, errors) = read_backup(BACKUP.as_bytes(), &media).unwrap();
+        let saved = messages.get(2).and_then(|m| m.attachments.first()).map(|a| std::fs::read(&a.filename));
+        let _ = std::fs::remove_dir_all(&media);
+
+        assert_eq!(errors, vec!["SMS without a valid date".to_string()]);
+        assert_eq!(messages.len(), 3);
+
+        let received = &messages[0];
+        assert_eq!(received.date, 1_700_000_000);
+        assert_eq!(received.text.as_deref(), Some("Hi & welcome"));
+        assert_eq!(received.sender.as_deref(), Some("Ana"));
+        assert!(!received.is_from_me);
+
+        let sent = &messages[1];
+        assert!(sent.is_from_me);
+        assert_eq!(sent.sender, None);
+        assert_eq!(sent.conversation.as_deref(), Some("+15551234"));
+
+        let mms = &messages[2];
+        assert_eq!(mms.text.as_deref(), Some("Look"));
+        assert_eq!(mms.sender.as_deref(), Some("+15559876"));
+        assert_eq!(mms.conversation.as_deref(), Some("+15551234~+15559876"));
+        assert_eq!(mms.attachments.len(), 1);
+        assert!(mms.attachments[0].filename.ends_with("1700000120_0_pic.png"));
+        assert_eq!(mms.attachments[0].mime_type.as_deref(), Some("image/png"));
+        assert_eq!(saved.unwrap().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        let media = std::env::temp_dir();
+        assert!(read_backup(&b"<smses><sms date='1'></smses>"[..], &media).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::archive::{self, DataSource};
+use crate::ExportOptions;
+use rusqlite::OptionalExtension;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// Marks what turns handles into people: linked handles, imported contacts and messages, and
+/// the AddressBook files (plus their WAL, where Contacts writes first)
+fn people_watermark() -> String {
+    let store = crate::store::open_store_db()
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT (SELECT COUNT(*) || ':' || COALESCE(MAX(created_at), 0) FROM handle_links),
+                        (SELECT COUNT(*) || ':' || COALESCE(MAX(imported_at), 0) FROM imported_contacts),
+                        (SELECT COUNT(*) || ':' || COALESCE(MAX(id), 0) FROM imported_messages)",
+                [],
+                |row| Ok(format!("{}:{}:{}", row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+            )
+            .map_err(|e| format!("Query error: {}", e))
+        })
+        .unwrap_or_default();
+
+    let books: Vec<String> = crate::get_all_addressbook_db_paths()
+        .into_iter()
+        .flat_map(|path| {
+            let wal = PathBuf::from(format!("{}-wal", path.display()));
+            [path, wal]
+        })
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| {
+            let modified = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+            format!("{}@{}", meta.len(), modified.map_or(0, |d| d.as_nanos()))
+        })
+        .collect();
+    format!("people:{}:{}", store, books.join(","))
+}
+
+/// Marks the state of the data a report reads. Any new, edited, unsent or deleted message
+/// changes it, and so does anything that changes who a message is from, so a snapshot with a
+/// different watermark is stale.
+fn watermark(options: Option<&ExportOptions>) -> Result<String, String> {
+    Ok(format!("{}|{}", message_watermark(options)?, people_watermark()))
+}
+
+fn message_watermark(options: Option<&ExportOptions>) -> Result<String, String> {
+    if options.and_then(|o| o.source) == Some(DataSource::Archive) {
+        let conn = archive::open_archive_db()?;
+        return conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(MAX(rowid), 0), COALESCE(MAX(change_seq), 0) FROM archived_messages",
+                [],
+                |row| {
+                    Ok(format!(
+                        "archive:{}:{}:{}",
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?
+                    ))
+                },
+            )
+            .map_err(|e| format!("Query error: {}", e));
+    }
+
+    let path = crate::get_imessage_db_path().ok_or("Could not find iMessage database")?;
+    let (conn, schema) = crate::chatdb::open(&path)?;
+    let edited = match (schema.has("date_edited"), schema.has("date_retracted")) {
+        (true, true) => "MAX(MAX(date_edited), MAX(date_retracted))",
+        (true, false) => "MAX(date_edited)",
+        (false, true) => "MAX(date_retracted)",
+        (false, false) => "0",
+    };
+    conn.query_row(
+        &format!("SELECT COUNT(*), COALESCE(MAX(ROWID), 0), COALESCE({}, 0) FROM message", edited),
+        [],
+        |row| {
+            Ok(format!(
+                "live:{}:{}:{}",
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?
+            ))
+        },
+    )
+    .map_err(|e| format!("Query error: {}", e))
+}
+
+/// Hash of everything besides the data that shapes a report: its arguments and the settings
+/// (timezone, tokenizer, ...) it was computed under
+fn filter_hash<K: Serialize>(key: &K) -> String {
+    let input = serde_json::to_string(&(key, crate::settings::load_settings())).unwrap_or_default();
+    Sha256::digest(input.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Return the stored snapshot of `report` for these arguments while the messages it read are
+/// unchanged; otherwise compute it and store the result. Cache failures never fail the report.
+pub fn cached<K, T, F>(report: &str, key: &K, options: Option<&ExportOptions>, compute: F) -> Result<T, String>
+where
+    K: Serialize,
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Result<T, String>,
+{
+    let hash = filter_hash(key);
+    let mark = match watermark(options) {
+        Ok(mark) => mark,
+        Err(e) => {
+            tracing::warn!("No watermark for {} snapshot: {}", report, e);
+            return compute();
+        }
+    };
+    let conn = crate::cache::open_cache_db();
+
+    if let Ok(conn) = &conn {
+        let stored: Option<String> = conn
+            .query_row(
+                "SELECT payload FROM report_snapshots WHERE report = ? AND filter_hash = ? AND watermark = ?",
+                rusqlite::params![report, hash, mark],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten();
+        if let Some(value) = stored.and_then(|p| serde_json::from_str(&p).ok()) {
+            tracing::debug!(report, "snapshot hit");
+            return Ok(value);
+        }
+    }
+
+    let value = compute()?;
+    if let (Ok(conn), Ok(payload)) = (&conn, serde_json::to_string(&value)) {
+        // One row per report and arguments; a stale snapshot is replaced rather than kept
+        let _ = conn.execute(
+            "INSERT OR REPLACE INTO report_snapshots (report, filter_hash, watermark, payload, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            rusqlite::params![report, hash, mark, payload, chrono::Utc::now().timestamp()],
+        );
+    }
+    Ok(value)
+}
+
+/// Bytes of report snapshots stored in cache.db
+pub fn stored_bytes() -> u64 {
+    let Ok(conn) = crate::cache::open_cache_db() else {
+        return 0;
+    };
+    conn.query_row("SELECT COALESCE(SUM(LENGTH(payload)), 0) FROM report_snapshots", [], |row| row.get::<_, i64>(0))
+        .map(|n| n.max(0) as u64)
+        .unwrap_or(0)
+}
+
+/// Delete every stored snapshot, returning how many there were
+pub fn clear() -> Result<usize, String> {
+    let conn = crate::cache::open_cache_db()?;
+    conn.execute("DELETE FROM report_snapshots", [])
+        .map_err(|e| format!("Cannot clear report snapshots: {}", e))
+}
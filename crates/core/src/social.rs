@@ -0,0 +1,194 @@
+use crate::{Chat, Message};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// How much one reaction between two people counts relative to one shared message
+const REACTION_WEIGHT: f64 = 3.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphNode {
+    pub id: String,           // Raw phone/email handle
+    pub name: String,
+    pub message_count: i64,   // Messages sent in the group chats considered
+    pub group_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub shared_chats: i64,
+    pub shared_message_volume: i64,  // Messages both people sent in the chats they share
+    pub mutual_reactions: i64,       // Reactions either gave to the other
+    pub weight: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SocialGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Order a pair so (a, b) and (b, a) share one edge
+fn edge_key(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// Build a graph where contacts are connected by shared group chats, weighted by
+/// how much they talk there and how often they react to each other
+pub fn build_social_graph(chats: &[Chat], messages: &[Message]) -> SocialGraph {
+    let groups: Vec<&Chat> = chats.iter().filter(|c| c.is_group).collect();
+
+    // Messages per (chat, sender handle)
+    let mut volume: HashMap<(i64, &str), i64> = HashMap::new();
+    let mut author_of: HashMap<&str, &str> = HashMap::new();
+    for msg in messages {
+        if msg.is_from_me || msg.contact_identifier.is_empty() {
+            continue;
+        }
+        author_of.insert(msg.guid.as_str(), msg.contact_identifier.as_str());
+        if let Some(chat_id) = msg.chat_id {
+            *volume.entry((chat_id, msg.contact_identifier.as_str())).or_insert(0) += 1;
+        }
+    }
+
+    let mut nodes: BTreeMap<String, GraphNode> = BTreeMap::new();
+    let mut edges: BTreeMap<(String, String), GraphEdge> = BTreeMap::new();
+
+    for chat in &groups {
+        for (idx, id) in chat.participant_ids.iter().enumerate() {
+            let node = nodes.entry(id.clone()).or_insert_with(|| GraphNode {
+                id: id.clone(),
+                name: chat.participants.get(idx).cloned().unwrap_or_else(|| id.clone()),
+                message_count: 0,
+                group_count: 0,
+            });
+            node.group_count += 1;
+            node.message_count += volume.get(&(chat.id, id.as_str())).copied().unwrap_or(0);
+        }
+
+        for (i, a) in chat.participant_ids.iter().enumerate() {
+            for b in chat.participant_ids.iter().skip(i + 1) {
+                if a == b {
+                    continue;
+                }
+                let (source, target) = edge_key(a, b);
+                let edge = edges
+                    .entry((source.clone(), target.clone()))
+                    .or_insert_with(|| GraphEdge {
+                        source,
+                        target,
+                        shared_chats: 0,
+                        shared_message_volume: 0,
+                        mutual_reactions: 0,
+                        weight: 0.0,
+                    });
+                edge.shared_chats += 1;
+                edge.shared_message_volume += volume.get(&(chat.id, a.as_str())).copied().unwrap_or(0)
+                    + volume.get(&(chat.id, b.as_str())).copied().unwrap_or(0);
+            }
+        }
+    }
+
+    // Reactions between two contacts strengthen the edge connecting them
+    for msg in messages {
+        let author = match author_of.get(msg.guid.as_str()) {
+            Some(a) => *a,
+            None => continue,
+        };
+        for reaction in &msg.reactions {
+            if reaction.is_from_me || reaction.sender_id.is_empty() || reaction.sender_id == author {
+                continue;
+            }
+            if let Some(edge) = edges.get_mut(&edge_key(author, &reaction.sender_id)) {
+                edge.mutual_reactions += 1;
+            }
+        }
+    }
+
+    let edges: Vec<GraphEdge> = edges
+        .into_values()
+        .map(|mut e| {
+            e.weight = e.shared_message_volume as f64 + e.mutual_reactions as f64 * REACTION_WEIGHT;
+            e
+        })
+        .collect();
+
+    SocialGraph {
+        nodes: nodes.into_values().collect(),
+        edges,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReactionMatrix {
+    pub chat_id: i64,
+    pub members: Vec<String>,          // Display names; "Me" first
+    pub member_ids: Vec<String>,       // Raw handles; empty string for me
+    pub messages_sent: Vec<i64>,       // Messages each member sent in the period
+    pub reactions: Vec<Vec<i64>>,      // reactions[giver][receiver]
+    pub normalized: Vec<Vec<f64>>,     // reactions[giver][receiver] / messages_sent[receiver]
+}
+
+/// Build an NxN table of reactions given from each member of a chat to each other member
+pub fn build_reaction_matrix(chat: &Chat, messages: &[Message]) -> ReactionMatrix {
+    let mut members = vec!["Me".to_string()];
+    let mut member_ids = vec![String::new()];
+    members.extend(chat.participants.iter().cloned());
+    member_ids.extend(chat.participant_ids.iter().cloned());
+
+    let index: HashMap<&str, usize> = member_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    let member_index = |is_from_me: bool, id: &str| -> Option<usize> {
+        if is_from_me {
+            Some(0)
+        } else {
+            index.get(id).copied()
+        }
+    };
+
+    let n = members.len();
+    let mut messages_sent = vec![0i64; n];
+    let mut reactions = vec![vec![0i64; n]; n];
+
+    for msg in messages.iter().filter(|m| m.chat_id == Some(chat.id)) {
+        let receiver = match member_index(msg.is_from_me, &msg.contact_identifier) {
+            Some(r) => r,
+            None => continue,
+        };
+        messages_sent[receiver] += 1;
+
+        for reaction in &msg.reactions {
+            // load_reactions has already cancelled tapbacks their sender removed (3000+)
+            if let Some(giver) = member_index(reaction.is_from_me, &reaction.sender_id) {
+                reactions[giver][receiver] += 1;
+            }
+        }
+    }
+
+    let normalized = reactions
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(&messages_sent)
+                .map(|(count, sent)| if *sent > 0 { *count as f64 / *sent as f64 } else { 0.0 })
+                .collect()
+        })
+        .collect();
+
+    ReactionMatrix {
+        chat_id: chat.id,
+        members,
+        member_ids,
+        messages_sent,
+        reactions,
+        normalized,
+    }
+}
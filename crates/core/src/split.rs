@@ -0,0 +1,310 @@
+use crate::export::{escape_html, write_atomic, write_export, ExportFormat, ExportResult};
+use crate::settings::{self, AppSettings};
+use crate::{ExportOptions, Message};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Period each file of a split export covers
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    Month,
+    Year,
+}
+
+/// One file of a split export, as listed in the index
+struct Part {
+    file: PathBuf,
+    label: String,
+    message_count: usize,
+    first: i64,
+    last: i64,
+}
+
+/// Everything a part needs besides its messages and path
+struct SplitContext<'a> {
+    title: &'a str,
+    notes: &'a [String],
+    format: ExportFormat,
+    options: &'a ExportOptions,
+    max_bytes: Option<u64>,
+    stem: String,
+    extension: String,
+    base: &'a Path,
+}
+
+impl SplitContext<'_> {
+    /// <stem>_<period>[_partN].<ext>, next to the requested path
+    fn part_path(&self, label: &str, number: Option<usize>) -> PathBuf {
+        let mut name = self.stem.clone();
+        if !label.is_empty() {
+            name.push('_');
+            name.push_str(label);
+        }
+        if let Some(n) = number {
+            let _ = write!(name, "_part{}", n);
+        }
+        self.base.with_file_name(format!("{}.{}", name, self.extension))
+    }
+
+    /// Estimated bytes `msg` adds to a part: its text plus the format's per-message markup,
+    /// and attachments the format embeds (base64 in HTML and mbox) or copies next to the file
+    fn cost(&self, msg: &Message) -> u64 {
+        let overhead = match self.format {
+            ExportFormat::Txt | ExportFormat::Csv => 48,
+            ExportFormat::Markdown => 64,
+            ExportFormat::Html => 256,
+            ExportFormat::Json => 384,
+            ExportFormat::Mbox => 640,
+        };
+        let text = msg.text.as_deref().map_or(0, str::len) + msg.sender_name.len();
+        let inline_limit = self.options.inline_images_below_bytes.unwrap_or(0);
+        let copies = self.options.copy_attachments.unwrap_or(false);
+        let media: u64 = msg
+            .attachments
+            .iter()
+            .filter_map(|a| a.total_bytes)
+            .map(|bytes| bytes.max(0) as u64)
+            .map(|bytes| match self.format {
+                ExportFormat::Html if bytes < inline_limit => bytes * 4 / 3,
+                ExportFormat::Html if copies => bytes,
+                ExportFormat::Mbox if bytes <= crate::mbox::MAX_ATTACHMENT_BYTES => bytes * 4 / 3,
+                _ => 0,
+            })
+            .sum();
+        text as u64 + overhead + media
+    }
+
+    /// Cut `messages` into consecutive runs whose estimated size stays within the limit, with
+    /// estimates multiplied by `scale`. A message over the limit on its own gets its own run.
+    fn cut<'m>(&self, messages: &'m [Message], scale: f64) -> Vec<&'m [Message]> {
+        let Some(max) = self.max_bytes else {
+            return vec![messages];
+        };
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut size = 0.0;
+        for (i, msg) in messages.iter().enumerate() {
+            let cost = self.cost(msg) as f64 * scale;
+            if i > start && size + cost > max as f64 {
+                runs.push(&messages[start..i]);
+                start = i;
+                size = 0.0;
+            }
+            size += cost;
+        }
+        runs.push(&messages[start..]);
+        runs
+    }
+
+    /// Write one part, returning its files' total size (copied media and reactions CSV included)
+    fn write(&self, messages: &[Message], path: &Path) -> Result<(ExportResult, u64), String> {
+        let result = write_export(self.title, self.notes, messages, self.format, self.options, path)?;
+        let bytes = result
+            .files
+            .iter()
+            .filter_map(|f| std::fs::metadata(f).ok())
+            .map(|m| m.len())
+            .sum();
+        Ok((result, bytes))
+    }
+
+    /// How far the estimate was off for a part that came out at `bytes`
+    fn scale(&self, messages: &[Message], bytes: u64) -> f64 {
+        let estimated: u64 = messages.iter().map(|m| self.cost(m)).sum();
+        bytes as f64 / estimated.max(1) as f64
+    }
+
+    /// Write `runs` as numbered parts. A run the estimate got wrong is cut again with the
+    /// observed ratio and rewritten once, so each message is rendered at most twice.
+    fn write_parts(
+        &self,
+        runs: &[&[Message]],
+        label: &str,
+        parts: &mut Vec<Part>,
+        files: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let mut number = 0;
+        for run in runs {
+            number += 1;
+            let path = self.part_path(label, Some(number));
+            let (result, bytes) = self.write(run, &path)?;
+            if self.max_bytes.map_or(true, |max| bytes <= max) || run.len() <= 1 {
+                files.extend(result.files);
+                parts.push(Part::new(path, label, run));
+                continue;
+            }
+            discard(&result);
+            for (i, smaller) in self.cut(run, self.scale(run, bytes)).into_iter().enumerate() {
+                if i > 0 {
+                    number += 1;
+                }
+                let path = self.part_path(label, Some(number));
+                let (result, _) = self.write(smaller, &path)?;
+                files.extend(result.files);
+                parts.push(Part::new(path, label, smaller));
+            }
+        }
+        Ok(())
+    }
+
+    /// One period: a single file when it fits, otherwise numbered parts
+    fn write_period(
+        &self,
+        messages: &[Message],
+        label: &str,
+        parts: &mut Vec<Part>,
+        files: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let runs = self.cut(messages, 1.0);
+        if runs.len() > 1 {
+            return self.write_parts(&runs, label, parts, files);
+        }
+
+        let path = self.part_path(label, None);
+        let (result, bytes) = self.write(messages, &path)?;
+        if self.max_bytes.map_or(true, |max| bytes <= max) || messages.len() <= 1 {
+            files.extend(result.files);
+            parts.push(Part::new(path, label, messages));
+            return Ok(());
+        }
+        discard(&result);
+        let runs = self.cut(messages, self.scale(messages, bytes));
+        self.write_parts(&runs, label, parts, files)
+    }
+}
+
+impl Part {
+    fn new(file: PathBuf, label: &str, messages: &[Message]) -> Self {
+        Part {
+            file,
+            label: label.to_string(),
+            message_count: messages.len(),
+            first: messages.first().map(|m| m.date).unwrap_or(0),
+            last: messages.last().map(|m| m.date).unwrap_or(0),
+        }
+    }
+}
+
+/// Remove the files of an oversized attempt, including its reactions CSV and copied media
+fn discard(result: &ExportResult) {
+    for file in &result.files {
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+/// Group chronological messages into consecutive runs sharing a local month or year
+fn by_period<'a>(messages: &'a [Message], split_by: Option<SplitBy>, settings: &AppSettings) -> Vec<(String, &'a [Message])> {
+    let Some(split_by) = split_by else {
+        return vec![(String::new(), messages)];
+    };
+    let pattern = match split_by {
+        SplitBy::Month => "%Y-%m",
+        SplitBy::Year => "%Y",
+    };
+    let label_of = |m: &Message| {
+        settings::to_local_time(m.date, settings)
+            .map(|dt| dt.format(pattern).to_string())
+            .unwrap_or_else(|| "undated".to_string())
+    };
+
+    let mut groups: Vec<(String, &[Message])> = Vec::new();
+    let mut start = 0;
+    for (i, msg) in messages.iter().enumerate() {
+        let label = label_of(msg);
+        if groups.last().map(|(l, _)| l) != Some(&label) {
+            if let Some(last) = groups.last_mut() {
+                last.1 = &messages[start..i];
+            }
+            groups.push((label, &messages[i..]));
+            start = i;
+        }
+    }
+    if groups.is_empty() {
+        groups.push((String::new(), messages));
+    }
+    groups
+}
+
+fn render_index(title: &str, format: ExportFormat, parts: &[Part], settings: &AppSettings) -> String {
+    let name = |p: &Part| p.file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let range = |p: &Part| {
+        format!(
+            "{} – {}",
+            settings::format_datetime(p.first, settings),
+            settings::format_datetime(p.last, settings)
+        )
+    };
+    let mut out = String::new();
+    if format == ExportFormat::Html {
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n<ul>",
+            escape_html(title)
+        );
+        for part in parts {
+            let file = name(part);
+            let _ = writeln!(
+                out,
+                "<li><a href=\"{}\">{}</a> · {} messages · {}</li>",
+                escape_html(&file),
+                escape_html(if part.label.is_empty() { &file } else { &part.label }),
+                part.message_count,
+                escape_html(&range(part))
+            );
+        }
+        out.push_str("</ul>\n</body>\n</html>\n");
+    } else {
+        let _ = writeln!(out, "{}\n", title);
+        for part in parts {
+            let _ = writeln!(out, "{}\t{} messages\t{}", name(part), part.message_count, range(part));
+        }
+    }
+    out
+}
+
+/// Write messages (chronological order) as one file per period and/or per `max_bytes`, plus a
+/// `<stem>_index` listing every file. Oversized periods are cut by estimated size, so each
+/// message is rendered once or, when the estimate is off, twice; a single message larger than
+/// the limit still gets its own file.
+pub fn write_split_export(
+    title: &str,
+    notes: &[String],
+    messages: &[Message],
+    format: ExportFormat,
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<ExportResult, String> {
+    let settings = settings::load_settings();
+    let ctx = SplitContext {
+        title,
+        notes,
+        format,
+        options,
+        max_bytes: options.split_max_bytes,
+        stem: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "export".to_string()),
+        extension: format.extension().to_string(),
+        base: path,
+    };
+
+    let mut parts = Vec::new();
+    let mut files = Vec::new();
+    for (label, period) in by_period(messages, options.split_by, &settings) {
+        ctx.write_period(period, &label, &mut parts, &mut files)?;
+    }
+
+    let index_extension = if format == ExportFormat::Html { "html" } else { "txt" };
+    let index = path.with_file_name(format!("{}_index.{}", ctx.stem, index_extension));
+    write_atomic(&index, render_index(title, format, &parts, &settings))
+        .map_err(|e| format!("Cannot write export index: {}", e))?;
+    files.insert(0, index.to_string_lossy().to_string());
+
+    Ok(ExportResult {
+        files,
+        message_count: messages.len() as i64,
+    })
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Disk the app can regenerate, and so may delete on request
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    Snapshots,    // Cached report results (cache.db)
+    Ocr,          // Text recognized in attachments (cache.db)
+    MediaHashes,  // Content hashes of attachments, for duplicate detection (cache.db)
+    Logs,         // Rotated log files; today's file is kept
+    Demo,         // Generated demo databases; kept while demo mode is on
+}
+
+const ALL_KINDS: [CacheKind; 5] = [
+    CacheKind::Snapshots,
+    CacheKind::Ocr,
+    CacheKind::MediaHashes,
+    CacheKind::Logs,
+    CacheKind::Demo,
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageItem {
+    pub name: String,
+    pub bytes: u64,
+    pub clearable: bool,  // A cache kind `clear_caches` accepts; otherwise user data
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageUsage {
+    pub directory: String,
+    pub total_bytes: u64,
+    pub items: Vec<StorageItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClearResult {
+    pub kinds: Vec<CacheKind>,
+    pub files_deleted: usize,
+    pub bytes_freed: u64,
+}
+
+impl CacheKind {
+    fn name(self) -> &'static str {
+        match self {
+            CacheKind::Snapshots => "snapshots",
+            CacheKind::Ocr => "ocr",
+            CacheKind::MediaHashes => "media_hashes",
+            CacheKind::Logs => "logs",
+            CacheKind::Demo => "demo",
+        }
+    }
+
+    /// Directory holding this kind's files, when it is file-based
+    fn dir(self, app_dir: &Path) -> Option<PathBuf> {
+        match self {
+            CacheKind::Logs => Some(app_dir.join("logs")),
+            CacheKind::Demo => Some(app_dir.join("demo")),
+            CacheKind::Ocr | CacheKind::Snapshots | CacheKind::MediaHashes => None,
+        }
+    }
+}
+
+fn app_dir() -> Result<PathBuf, String> {
+    crate::get_app_data_dir().ok_or_else(|| "Could not determine app data directory".to_string())
+}
+
+/// Total size of a file or directory tree; 0 when missing
+fn size_of(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| size_of(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// A database file plus its WAL and shared-memory files
+fn db_size(path: &Path) -> u64 {
+    let name = path.to_string_lossy();
+    size_of(path) + size_of(Path::new(&format!("{}-wal", name))) + size_of(Path::new(&format!("{}-shm", name)))
+}
+
+/// Bytes of OCR text stored in cache.db
+fn ocr_bytes() -> u64 {
+    let Ok(conn) = crate::cache::open_cache_db() else {
+        return 0;
+    };
+    conn.query_row("SELECT COALESCE(SUM(LENGTH(text)), 0) FROM ocr_results", [], |row| row.get::<_, i64>(0))
+        .map(|n| n.max(0) as u64 * 2)  // Counted twice: the table and its full-text index
+        .unwrap_or(0)
+}
+
+/// Bytes of media hash rows stored in cache.db
+fn media_hash_bytes() -> u64 {
+    let Ok(conn) = crate::cache::open_cache_db() else {
+        return 0;
+    };
+    conn.query_row(
+        "SELECT COALESCE(SUM(LENGTH(path) + LENGTH(hash) + 24), 0) FROM media_hashes",  // 24: the three integers
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n.max(0) as u64)
+    .unwrap_or(0)
+}
+
+fn kind_bytes(kind: CacheKind, app_dir: &Path) -> u64 {
+    match (kind, kind.dir(app_dir)) {
+        (_, Some(dir)) => size_of(&dir),
+        (CacheKind::Snapshots, None) => crate::snapshots::stored_bytes(),
+        (CacheKind::MediaHashes, None) => media_hash_bytes(),
+        (_, None) => ocr_bytes(),
+    }
+}
+
+/// Disk used by everything under the app data directory, caches first
+pub fn storage_usage() -> Result<StorageUsage, String> {
+    let dir = app_dir()?;
+    let mut items: Vec<StorageItem> = ALL_KINDS
+        .iter()
+        .map(|&kind| StorageItem {
+            name: kind.name().to_string(),
+            bytes: kind_bytes(kind, &dir),
+            clearable: true,
+        })
+        .collect();
+
+    let user_data = [
+        ("store", db_size(&dir.join("store.db"))),
+        ("archive", db_size(&dir.join("archive.db"))),
+        ("imports", size_of(&dir.join("imports"))),
+        ("settings", size_of(&dir.join("settings.json"))),
+    ];
+    items.extend(user_data.iter().map(|(name, bytes)| StorageItem {
+        name: name.to_string(),
+        bytes: *bytes,
+        clearable: false,
+    }));
+
+    Ok(StorageUsage {
+        directory: dir.to_string_lossy().to_string(),
+        total_bytes: size_of(&dir),
+        items,
+    })
+}
+
+/// Delete the files directly or indirectly under `dir`, except `keep`
+fn clear_dir(dir: &Path, keep: Option<&Path>, result: &mut ClearResult) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if Some(path.as_path()) == keep {
+            continue;
+        }
+        let bytes = size_of(&path);
+        let removed = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        match removed {
+            Ok(()) => {
+                result.files_deleted += 1;
+                result.bytes_freed += bytes;
+            }
+            Err(e) => tracing::warn!("Cannot delete {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// The log file currently being written, which must stay open
+fn newest_log(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .max()
+}
+
+fn clear_ocr(result: &mut ClearResult) -> Result<(), String> {
+    let bytes = ocr_bytes();
+    let conn = crate::cache::open_cache_db()?;
+    let rows = conn
+        .execute("DELETE FROM ocr_results", [])
+        .and_then(|n| conn.execute("DELETE FROM ocr_text_fts", []).map(|_| n))
+        .map_err(|e| format!("Cannot clear OCR cache: {}", e))?;
+    conn.execute_batch("VACUUM;").map_err(|e| format!("Cannot compact cache database: {}", e))?;
+    result.files_deleted += rows;
+    result.bytes_freed += bytes;
+    Ok(())
+}
+
+fn clear_media_hashes(result: &mut ClearResult) -> Result<(), String> {
+    let bytes = media_hash_bytes();
+    let conn = crate::cache::open_cache_db()?;
+    result.files_deleted += conn
+        .execute("DELETE FROM media_hashes", [])
+        .map_err(|e| format!("Cannot clear media hashes: {}", e))?;
+    result.bytes_freed += bytes;
+    Ok(())
+}
+
+/// Delete the given cache kinds (all of them when empty). User data is never touched, nor the
+/// demo databases while demo mode is using them.
+pub fn clear_caches(kinds: &[CacheKind]) -> Result<ClearResult, String> {
+    let dir = app_dir()?;
+    let demo_active = crate::fixtures::is_demo_mode();
+    if demo_active && kinds.contains(&CacheKind::Demo) {
+        return Err("Turn off demo mode before deleting the demo data".to_string());
+    }
+    let kinds: Vec<CacheKind> = if kinds.is_empty() {
+        ALL_KINDS.iter().copied().filter(|&k| !(demo_active && k == CacheKind::Demo)).collect()
+    } else {
+        kinds.to_vec()
+    };
+    let mut result = ClearResult {
+        kinds: kinds.clone(),
+        files_deleted: 0,
+        bytes_freed: 0,
+    };
+    for kind in kinds {
+        match (kind, kind.dir(&dir)) {
+            (_, Some(kind_dir)) => {
+                let keep = if kind == CacheKind::Logs { newest_log(&kind_dir) } else { None };
+                clear_dir(&kind_dir, keep.as_deref(), &mut result);
+            }
+            (CacheKind::Snapshots, None) => {
+                let bytes = crate::snapshots::stored_bytes();
+                result.files_deleted += crate::snapshots::clear()?;
+                result.bytes_freed += bytes;
+            }
+            (CacheKind::MediaHashes, None) => clear_media_hashes(&mut result)?,
+            (_, None) => clear_ocr(&mut result)?,
+        }
+    }
+    tracing::info!(files = result.files_deleted, bytes = result.bytes_freed, "cleared caches");
+    Ok(result)
+}
@@ -0,0 +1,106 @@
+use rusqlite::Connection;
+
+const STORE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS contact_tags (
+        identifier TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (identifier, tag)
+    );
+    CREATE TABLE IF NOT EXISTS handle_links (
+        identifier TEXT PRIMARY KEY,
+        canonical TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS imported_messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        source TEXT NOT NULL,
+        dedupe_key TEXT NOT NULL,
+        conversation TEXT,
+        sender TEXT,
+        is_from_me INTEGER NOT NULL,
+        date INTEGER NOT NULL,
+        text TEXT,
+        imported_at INTEGER NOT NULL,
+        UNIQUE (source, dedupe_key)
+    );
+    CREATE INDEX IF NOT EXISTS idx_imported_messages_date ON imported_messages(date);
+    CREATE TABLE IF NOT EXISTS imported_attachments (
+        message_id INTEGER NOT NULL REFERENCES imported_messages(id) ON DELETE CASCADE,
+        filename TEXT NOT NULL,
+        mime_type TEXT,
+        total_bytes INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS imported_contacts (
+        identifier TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        imported_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS digests (
+        week_start TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        digest_json TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS triggers (
+        id TEXT PRIMARY KEY,
+        kind_json TEXT NOT NULL,
+        enabled INTEGER NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS trigger_firings (
+        trigger_id TEXT NOT NULL,
+        key TEXT NOT NULL,
+        fired_at INTEGER NOT NULL,
+        PRIMARY KEY (trigger_id, key)
+    );
+    CREATE TABLE IF NOT EXISTS pinned_messages (
+        guid TEXT PRIMARY KEY,
+        note TEXT,
+        pinned_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS notes (
+        target TEXT NOT NULL,
+        key TEXT NOT NULL,
+        text TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (target, key)
+    );
+    CREATE TABLE IF NOT EXISTS export_jobs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        chat_id INTEGER NOT NULL,
+        format TEXT NOT NULL,
+        path TEXT NOT NULL,
+        options_json TEXT NOT NULL,
+        message_count INTEGER NOT NULL,
+        files_json TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS job_runs (
+        job_id TEXT PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER NOT NULL,
+        ok INTEGER NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS export_partials (
+        path TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL
+    );
+";
+
+/// Open (creating if needed) the app-owned store for user-curated data (tags, notes, pins, digests).
+/// Unlike the cache, nothing in here can be regenerated from chat.db.
+pub fn open_store_db() -> Result<Connection, String> {
+    let dir = crate::get_data_dir().ok_or("Could not determine app data directory")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Cannot create app data directory: {}", e))?;
+
+    let conn = crate::encryption::open_app_db(&dir.join("store.db"))
+        .map_err(|e| format!("Cannot open store database: {}", e))?;
+    // Off by default in SQLite; imported attachments rely on ON DELETE CASCADE
+    conn.execute_batch("PRAGMA foreign_keys = ON;")
+        .map_err(|e| format!("Cannot open store database: {}", e))?;
+    conn.execute_batch(STORE_SCHEMA)
+        .map_err(|e| format!("Cannot initialize store database: {}", e))?;
+    Ok(conn)
+}
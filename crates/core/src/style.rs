@@ -0,0 +1,179 @@
+use crate::analytics::extract_emojis;
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+
+// Below this, a couple of excited messages would dominate a sender's fingerprint
+pub const DEFAULT_MIN_MESSAGES: i64 = 20;
+
+// "OK" or "LA" is an abbreviation, not shouting
+const MIN_CAPS_LETTERS: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StyleFingerprint {
+    pub sender: String,                  // "me" or the sender's identifier
+    pub sender_name: String,
+    pub messages: i64,                   // Messages with text
+    pub all_caps_percent: f64,           // Messages written entirely in capitals
+    pub exclamations_per_message: f64,
+    pub ellipsis_percent: f64,           // Messages containing "..." or "…"
+    pub emoji_per_message: f64,
+}
+
+#[derive(Default)]
+struct StyleCounts {
+    name: String,
+    messages: i64,
+    all_caps: i64,
+    exclamations: i64,
+    ellipses: i64,
+    emoji: i64,
+}
+
+/// Whether a message is shouted: at least a few letters and none of them lowercase
+fn is_all_caps(text: &str) -> bool {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    letters.len() >= MIN_CAPS_LETTERS && letters.iter().all(|c| !c.is_lowercase())
+}
+
+/// Per-sender punctuation and capitalization habits, normalized per message so senders with
+/// very different volumes compare directly. Senders below `min_messages` are left out.
+pub fn style_fingerprints<M: Borrow<Message>>(
+    messages: impl IntoIterator<Item = M>,
+    min_messages: i64,
+) -> Vec<StyleFingerprint> {
+    let mut by_sender: HashMap<String, StyleCounts> = HashMap::new();
+    for msg in messages {
+        let msg: &Message = msg.borrow();
+        let Some(text) = msg.text.as_deref().filter(|t| !t.trim().is_empty()) else { continue };
+        let sender = if msg.is_from_me { "me".to_string() } else { msg.contact_identifier.clone() };
+        if sender.is_empty() {
+            continue;
+        }
+        let counts = by_sender.entry(sender).or_insert_with(|| StyleCounts {
+            name: if msg.is_from_me { "Me".to_string() } else { msg.sender_name.clone() },
+            ..Default::default()
+        });
+        counts.messages += 1;
+        if is_all_caps(text) {
+            counts.all_caps += 1;
+        }
+        counts.exclamations += text.matches('!').count() as i64;
+        if text.contains("...") || text.contains('…') {
+            counts.ellipses += 1;
+        }
+        counts.emoji += extract_emojis(text).len() as i64;
+    }
+
+    let mut results: Vec<StyleFingerprint> = by_sender
+        .into_iter()
+        .filter(|(_, c)| c.messages >= min_messages.max(1))
+        .map(|(sender, c)| {
+            let n = c.messages as f64;
+            StyleFingerprint {
+                sender,
+                sender_name: c.name,
+                messages: c.messages,
+                all_caps_percent: c.all_caps as f64 * 100.0 / n,
+                exclamations_per_message: c.exclamations as f64 / n,
+                ellipsis_percent: c.ellipses as f64 * 100.0 / n,
+                emoji_per_message: c.emoji as f64 / n,
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.messages.cmp(&a.messages).then_with(|| a.sender.cmp(&b.sender)));
+    results
+}
+
+/// Abbreviations tracked until the user saves their own list
+pub const DEFAULT_ABBREVIATIONS: &[&str] = &[
+    "lol", "lmao", "omg", "brb", "btw", "idk", "tbh", "imo", "fr", "ngl", "rn", "smh", "ikr", "😂", "💀", "😭",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AbbreviationSeries {
+    pub term: String,
+    pub total: i64,                  // Messages using the term
+    pub yearly: Vec<(String, i64)>,  // Every year the sender wrote in, including zeros
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AbbreviationTimeline {
+    pub sender: String,                         // "me" or the sender's identifier
+    pub sender_name: String,
+    pub messages_per_year: Vec<(String, i64)>,  // For turning counts into rates
+    pub terms: Vec<AbbreviationSeries>,         // Most used first; unused terms left out
+}
+
+struct SenderYears {
+    name: String,
+    messages: BTreeMap<String, i64>,               // year -> messages
+    uses: HashMap<String, BTreeMap<String, i64>>,  // term -> year -> messages using it
+}
+
+/// Yearly use of each abbreviation per sender, so a report can show when "lol" gave way to "💀"
+pub fn abbreviation_timelines<M: Borrow<Message>>(
+    messages: impl IntoIterator<Item = M>,
+    terms: &[String],
+    settings: &crate::settings::AppSettings,
+) -> Vec<AbbreviationTimeline> {
+    let terms = crate::keywords::normalize_keywords(terms);
+    let mut by_sender: HashMap<String, SenderYears> = HashMap::new();
+    for msg in messages {
+        let msg: &Message = msg.borrow();
+        let Some(text) = msg.text.as_deref() else { continue };
+        let sender = if msg.is_from_me { "me".to_string() } else { msg.contact_identifier.clone() };
+        if sender.is_empty() {
+            continue;
+        }
+        let Some(year) = crate::settings::to_local_time(msg.date, settings).map(|dt| dt.format("%Y").to_string()) else {
+            continue;
+        };
+        let entry = by_sender.entry(sender).or_insert_with(|| SenderYears {
+            name: if msg.is_from_me { "Me".to_string() } else { msg.sender_name.clone() },
+            messages: BTreeMap::new(),
+            uses: HashMap::new(),
+        });
+        *entry.messages.entry(year.clone()).or_insert(0) += 1;
+
+        let text = text.to_lowercase();
+        for term in terms.iter().filter(|t| crate::keywords::contains_phrase(&text, t)) {
+            *entry.uses.entry(term.clone()).or_default().entry(year.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut results: Vec<AbbreviationTimeline> = by_sender
+        .into_iter()
+        .filter(|(_, s)| !s.uses.is_empty())
+        .map(|(sender, s)| {
+            let mut series: Vec<AbbreviationSeries> = s
+                .uses
+                .into_iter()
+                .map(|(term, years)| AbbreviationSeries {
+                    total: years.values().sum(),
+                    yearly: s
+                        .messages
+                        .keys()
+                        .map(|y| (y.clone(), years.get(y).copied().unwrap_or(0)))
+                        .collect(),
+                    term,
+                })
+                .collect();
+            series.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.term.cmp(&b.term)));
+            AbbreviationTimeline {
+                sender,
+                sender_name: s.name,
+                messages_per_year: s.messages.into_iter().collect(),
+                terms: series,
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        let volume = |t: &AbbreviationTimeline| t.messages_per_year.iter().map(|(_, n)| n).sum::<i64>();
+        volume(b).cmp(&volume(a)).then_with(|| a.sender.cmp(&b.sender))
+    });
+    results
+}
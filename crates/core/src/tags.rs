@@ -0,0 +1,146 @@
+use crate::{addressbook, get_all_addressbook_db_paths, lookup_contact, normalize_phone, store};
+use rusqlite::Connection;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Find the Core Data many-to-many table linking contacts to groups.
+/// Its name and column names embed entity numbers that vary between AddressBook versions
+/// (e.g. Z_22PARENTGROUPS with columns Z_22CONTACTS / Z_19PARENTGROUPS1).
+fn find_group_join_table(conn: &Connection) -> Option<(String, String, String)> {
+    let table: String = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name LIKE 'Z\\_%PARENTGROUPS' ESCAPE '\\'",
+            [],
+            |row| row.get(0),
+        )
+        .ok()?;
+
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).ok()?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get(1))
+        .ok()?
+        .flatten()
+        .collect();
+
+    let contact_col = columns.iter().find(|c| c.ends_with("CONTACTS"))?.clone();
+    let group_col = columns.iter().find(|c| c.contains("PARENTGROUPS"))?.clone();
+    Some((table, contact_col, group_col))
+}
+
+/// Read AddressBook group memberships from a single database, keyed by identifier
+fn read_groups_from_db(db_path: &Path, groups: &mut HashMap<String, BTreeSet<String>>) {
+    let (conn, layout) = match addressbook::open(db_path) {
+        Ok(opened) => opened,
+        Err(_) => return,
+    };
+
+    let (table, contact_col, group_col) = match find_group_join_table(&conn) {
+        Some(t) => t,
+        None => return,
+    };
+
+    for (value_table, value_column) in layout.lookups() {
+        let query = format!(
+            "SELECT g.ZNAME, v.{value_column}
+             FROM {table} j
+             JOIN ZABCDRECORD g ON g.Z_PK = j.{group_col}
+             JOIN {value_table} v ON v.ZOWNER = j.{contact_col}
+             WHERE g.ZNAME IS NOT NULL AND v.{value_column} IS NOT NULL",
+            value_column = value_column,
+            table = table,
+            group_col = group_col,
+            value_table = value_table,
+            contact_col = contact_col
+        );
+
+        let rows: Vec<(String, String)> = conn
+            .prepare(&query)
+            .ok()
+            .map(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map(|rows| rows.flatten().collect())
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        for (group, identifier) in rows {
+            let keys = if (value_table, value_column) == layout.email {
+                vec![identifier.to_lowercase()]
+            } else {
+                let normalized = normalize_phone(&identifier);
+                let mut keys = vec![identifier];
+                if !normalized.is_empty() {
+                    keys.push(format!("+1{}", normalized));
+                    keys.push(normalized);
+                }
+                keys
+            };
+            for key in keys {
+                groups.entry(key).or_default().insert(group.clone());
+            }
+        }
+    }
+}
+
+/// Normalize a tag for storage and comparison
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Get every tag (AddressBook groups plus user-defined tags) keyed by identifier
+pub fn get_contact_tags() -> HashMap<String, BTreeSet<String>> {
+    let mut tags: HashMap<String, BTreeSet<String>> = HashMap::new();
+
+    for db_path in &get_all_addressbook_db_paths() {
+        read_groups_from_db(db_path, &mut tags);
+    }
+    for set in tags.values_mut() {
+        *set = set.iter().map(|t| normalize_tag(t)).collect();
+    }
+
+    if let Ok(conn) = store::open_store_db() {
+        if let Ok(mut stmt) = conn.prepare("SELECT identifier, tag FROM contact_tags") {
+            if let Ok(rows) = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))) {
+                for (identifier, tag) in rows.flatten() {
+                    tags.entry(identifier).or_default().insert(tag);
+                }
+            }
+        }
+    }
+
+    tags
+}
+
+/// Get the tags for a single handle identifier
+pub fn tags_for(identifier: &str, tags: &HashMap<String, BTreeSet<String>>) -> Vec<String> {
+    let mut result: BTreeSet<String> = BTreeSet::new();
+    // User tags are stored under the exact handle id, groups under AddressBook forms
+    if let Some(set) = tags.get(identifier) {
+        result.extend(set.iter().cloned());
+    }
+    if let Some(set) = lookup_contact(identifier, tags) {
+        result.extend(set.iter().cloned());
+    }
+    result.into_iter().collect()
+}
+
+/// Resolve tag names to the handle ROWIDs of every contact carrying any of them
+pub fn resolve_tag_handle_ids(conn: &Connection, wanted: &[String]) -> Result<Vec<i64>, String> {
+    let wanted: BTreeSet<String> = wanted.iter().map(|t| normalize_tag(t)).collect();
+    let tags = get_contact_tags();
+
+    let mut stmt = conn
+        .prepare("SELECT ROWID, id FROM handle")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let handles: Vec<(i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(handles
+        .into_iter()
+        .filter(|(_, identifier)| tags_for(identifier, &tags).iter().any(|t| wanted.contains(t)))
+        .map(|(id, _)| id)
+        .collect())
+}
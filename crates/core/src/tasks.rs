@@ -0,0 +1,172 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    CacheRefresh,       // OCR index in cache.db
+    ArchiveSync,        // Writes archive.db
+    ArchiveCompaction,  // Writes archive.db
+    Import,             // Writes imported messages/contacts in store.db
+    Export,             // Writes only its own output files
+    Encryption,         // Rewrites store.db, cache.db and archive.db
+}
+
+impl TaskKind {
+    /// Files the task writes, always in the same order so tasks holding several can't deadlock
+    fn resources(self, registry: &Registry) -> Vec<&Resource> {
+        match self {
+            TaskKind::CacheRefresh => vec![&registry.cache],
+            TaskKind::ArchiveSync | TaskKind::ArchiveCompaction => vec![&registry.archive],
+            TaskKind::Import => vec![&registry.import],
+            TaskKind::Export => Vec::new(),
+            TaskKind::Encryption => vec![&registry.cache, &registry.archive, &registry.import],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub kind: TaskKind,
+    pub label: String,
+    pub started_at: i64,
+    pub waiting: bool,           // Queued behind another task using the same files
+    pub cancel_requested: bool,
+}
+
+struct Entry {
+    info: TaskInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+/// A set of files one task writes at a time, so e.g. two cache rebuilds queue instead of racing
+#[derive(Default)]
+struct Resource {
+    busy: Mutex<bool>,
+    freed: Condvar,
+}
+
+impl Resource {
+    fn acquire(&self) {
+        let mut busy = self.busy.lock().unwrap_or_else(PoisonError::into_inner);
+        while *busy {
+            busy = self.freed.wait(busy).unwrap_or_else(PoisonError::into_inner);
+        }
+        *busy = true;
+    }
+
+    fn release(&self) {
+        *self.busy.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        self.freed.notify_one();
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    entries: Mutex<Vec<Entry>>,
+    next_id: AtomicU64,
+    cache: Resource,
+    archive: Resource,
+    import: Resource,
+}
+
+/// Everything running or queued, kept as Tauri managed state (the CLI makes its own); clones
+/// share one registry
+#[derive(Default, Clone)]
+pub struct TaskRegistry(Arc<Registry>);
+
+/// A registered long-running operation; dropping it removes it from the registry
+pub struct Task {
+    id: u64,
+    kind: TaskKind,
+    holds_resource: bool,
+    cancel: Arc<AtomicBool>,
+    registry: TaskRegistry,
+}
+
+impl Task {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Stop with an error if the user cancelled this task
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err("Cancelled".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if self.holds_resource {
+            for resource in self.kind.resources(&self.registry.0) {
+                resource.release();
+            }
+        }
+        self.registry.entries().retain(|e| e.info.id != self.id);
+    }
+}
+
+impl TaskRegistry {
+    fn entries(&self) -> MutexGuard<'_, Vec<Entry>> {
+        self.0.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Register a task, waiting for any running task that writes the same files
+    pub fn begin(&self, kind: TaskKind, label: impl Into<String>) -> Result<Task, String> {
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let resources = kind.resources(&self.0);
+        self.entries().push(Entry {
+            info: TaskInfo {
+                id,
+                kind,
+                label: label.into(),
+                started_at: Utc::now().timestamp(),
+                waiting: !resources.is_empty(),
+                cancel_requested: false,
+            },
+            cancel: cancel.clone(),
+        });
+
+        for resource in &resources {
+            resource.acquire();
+        }
+        if let Some(entry) = self.entries().iter_mut().find(|e| e.info.id == id) {
+            entry.info.waiting = false;
+        }
+
+        let task = Task {
+            id,
+            kind,
+            holds_resource: !resources.is_empty(),
+            cancel,
+            registry: self.clone(),
+        };
+        task.check_cancelled()?;
+        Ok(task)
+    }
+
+    /// Snapshot of everything running or queued
+    pub fn active(&self) -> Vec<TaskInfo> {
+        self.entries().iter().map(|e| e.info.clone()).collect()
+    }
+
+    /// Ask a task to stop at its next checkpoint
+    pub fn cancel(&self, id: u64) -> Result<(), String> {
+        let mut entries = self.entries();
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.info.id == id)
+            .ok_or_else(|| format!("Task {} is not running", id))?;
+        entry.cancel.store(true, Ordering::Relaxed);
+        entry.info.cancel_requested = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use crate::export::ExportFormat;
+use crate::settings::{self, AppSettings};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How messages are grouped before they reach the template
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateGrouping {
+    #[default]
+    None,
+    Day,
+    Month,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportTemplate {
+    pub name: String,
+    pub description: String,
+    pub html: bool,  // Written for HTML exports; message text is escaped whatever the format
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TemplateMessage {
+    id: i64,
+    date: i64,
+    date_formatted: String,  // Local date and time in the configured date order and clock
+    day: String,             // Local date in the configured date order
+    time: String,            // Local time of day, 12- or 24-hour per settings
+    sender_name: String,
+    is_from_me: bool,
+    text: String,
+    attachments: Vec<String>,
+    reactions: String,  // Inline "❤️ Alice, 👍 Me" rendering; empty when none
+}
+
+#[derive(Debug, Serialize)]
+struct TemplateGroup {
+    label: String,      // Local day or month in the configured date order, or empty when ungrouped
+    messages: Vec<TemplateMessage>,
+}
+
+const TRANSCRIPT: &str = "{{ title }}
+{% for group in groups %}{% if group.label %}
+--- {{ group.label }} ---
+{% endif %}{% for m in group.messages %}[{{ m.time }}] {{ m.sender_name }}: {{ m.text }}{% for a in m.attachments %} <{{ a }}>{% endfor %}{% if m.reactions %} [{{ m.reactions }}]{% endif %}
+{% endfor %}{% endfor %}";
+
+const MARKDOWN_JOURNAL: &str = "# {{ title }}
+
+_{{ message_count }} messages_
+{% for group in groups %}
+## {{ group.label }}
+{% for m in group.messages %}
+- **{{ m.time }} {{ m.sender_name }}**: {{ m.text }}{% if m.reactions %} ({{ m.reactions }}){% endif %}
+{%- endfor %}
+{% endfor %}";
+
+const HTML_SIMPLE: &str = "<!DOCTYPE html>
+<html><head><meta charset=\"utf-8\"><title>{{ title }}</title>
+<style>body{font-family:-apple-system,sans-serif;max-width:720px;margin:auto}.me{text-align:right}.meta{color:#888;font-size:12px}</style>
+</head><body>
+<h1>{{ title }}</h1>
+{% for group in groups %}{% if group.label %}<h2>{{ group.label }}</h2>{% endif %}
+{% for m in group.messages %}<p class=\"{% if m.is_from_me %}me{% endif %}\"><span class=\"meta\">{{ m.sender_name }} · {{ m.time }}</span><br>{{ m.text }}{% if m.reactions %} <span class=\"meta\">{{ m.reactions }}</span>{% endif %}</p>
+{% endfor %}{% endfor %}
+</body></html>";
+
+/// Templates that ship with the app
+pub fn builtin_templates() -> Vec<ExportTemplate> {
+    vec![
+        ExportTemplate {
+            name: "transcript".to_string(),
+            description: "Plain text, one line per message".to_string(),
+            html: false,
+            source: TRANSCRIPT.to_string(),
+        },
+        ExportTemplate {
+            name: "markdown-journal".to_string(),
+            description: "Markdown with a heading per day or month".to_string(),
+            html: false,
+            source: MARKDOWN_JOURNAL.to_string(),
+        },
+        ExportTemplate {
+            name: "html-simple".to_string(),
+            description: "Minimal HTML page".to_string(),
+            html: true,
+            source: HTML_SIMPLE.to_string(),
+        },
+    ]
+}
+
+fn template_message(msg: &Message, settings: &AppSettings) -> TemplateMessage {
+    TemplateMessage {
+        id: msg.id,
+        date: msg.date,
+        date_formatted: settings::format_datetime(msg.date, settings),
+        day: settings::format_date(msg.date, settings),
+        time: settings::format_time(msg.date, settings),
+        sender_name: msg.sender_name.clone(),
+        is_from_me: msg.is_from_me,
+        text: msg.text.clone().unwrap_or_default(),
+        attachments: crate::export::attachment_names(msg),
+        reactions: crate::export::format_reactions(&msg.reactions),
+    }
+}
+
+fn build_context(
+    title: &str,
+    notes: &[String],
+    messages: &[Message],
+    grouping: TemplateGrouping,
+    settings: &AppSettings,
+) -> tera::Context {
+    // Keyed by sortable local day/month; labels follow the configured date order
+    let mut groups: BTreeMap<String, (String, Vec<TemplateMessage>)> = BTreeMap::new();
+    for msg in messages {
+        let local = settings::to_local_time(msg.date, settings);
+        let (key, label) = match grouping {
+            TemplateGrouping::None => (String::new(), String::new()),
+            TemplateGrouping::Day => (
+                local.map(|dt| dt.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                settings::format_date(msg.date, settings),
+            ),
+            TemplateGrouping::Month => (
+                local.map(|dt| dt.format("%Y-%m").to_string()).unwrap_or_default(),
+                settings::format_month(msg.date, settings),
+            ),
+        };
+        groups.entry(key).or_insert_with(|| (label, Vec::new())).1.push(template_message(msg, settings));
+    }
+
+    let mut context = tera::Context::new();
+    context.insert("title", title);
+    context.insert("notes", notes);
+    context.insert("message_count", &messages.len());
+    context.insert(
+        "groups",
+        &groups
+            .into_iter()
+            .map(|(_, (label, messages))| TemplateGroup { label, messages })
+            .collect::<Vec<_>>(),
+    );
+    context
+}
+
+/// Resolve a built-in template name, or treat the value as template source. The flag is set
+/// for built-in HTML templates.
+fn resolve(template: &str) -> (String, bool) {
+    match builtin_templates().into_iter().find(|t| t.name == template) {
+        Some(t) => (t.source, t.html),
+        None => (template.to_string(), false),
+    }
+}
+
+/// Render messages (chronological order) through a built-in or user template. Message text
+/// is escaped whenever the output is HTML, whatever the template looks like.
+pub fn render(
+    template: &str,
+    format: ExportFormat,
+    title: &str,
+    notes: &[String],
+    messages: &[Message],
+    grouping: TemplateGrouping,
+    settings: &AppSettings,
+) -> Result<String, String> {
+    let (source, html_template) = resolve(template);
+    let autoescape = html_template || format == ExportFormat::Html;
+    let context = build_context(title, notes, messages, grouping, settings);
+    tera::Tera::one_off(&source, &context, autoescape)
+        .map_err(|e| format!("Template error: {}", template_error(&e)))
+}
+
+/// Tera nests the useful part of an error in its source chain
+fn template_error(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(inner) = source {
+        message = format!("{}: {}", message, inner);
+        source = inner.source();
+    }
+    message
+}
+
+/// Check a template parses and renders against a sample conversation
+pub fn validate(source: &str) -> Result<(), String> {
+    let sample = crate::Message {
+        id: 1,
+        guid: "sample".to_string(),
+        text: Some("Sample message".to_string()),
+        date: 0,
+        date_formatted: "2024-01-01 09:30:00".to_string(),
+        is_from_me: false,
+        handle_id: 1,
+        contact_identifier: "+15555550100".to_string(),
+        sender_name: "Sample Contact".to_string(),
+        chat_id: Some(1),
+        has_attachment: false,
+        attachments: Vec::new(),
+        reactions: Vec::new(),
+        delivery: Default::default(),
+    };
+    for grouping in [TemplateGrouping::None, TemplateGrouping::Day, TemplateGrouping::Month] {
+        let notes = ["Sample note".to_string()];
+        let messages = std::slice::from_ref(&sample);
+        render(source, ExportFormat::Html, "Sample", &notes, messages, grouping, &AppSettings::default())?;
+    }
+    Ok(())
+}
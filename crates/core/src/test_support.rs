@@ -0,0 +1,40 @@
+//! Entry points for the integration tests in `tests/`, which run the same queries and exports
+//! as the app against databases from `fixtures::generate`. Not part of the app's interface.
+
+use crate::{fixtures, tasks, Chat, ChatStats, ExportOptions, Message, SearchHit};
+
+pub use crate::export::{ExportFormat, ExportResult};
+pub use crate::fixtures::{FixtureOptions, FixtureSummary};
+
+/// Generate fixture databases into the demo folder and switch to them, as demo mode does
+pub fn use_fixture_data(options: &FixtureOptions) -> Result<FixtureSummary, String> {
+    let dir = fixtures::demo_dir().ok_or("Could not determine app data directory")?;
+    let summary = fixtures::generate(&dir, options)?;
+    fixtures::enable_demo_mode()?;
+    Ok(summary)
+}
+
+pub fn get_chats() -> Result<Vec<Chat>, String> {
+    crate::load_chats()
+}
+
+pub fn get_messages(options: Option<ExportOptions>, limit: Option<i64>) -> Result<Vec<Message>, String> {
+    crate::get_messages(options, limit)
+}
+
+pub fn get_chat_stats(options: Option<ExportOptions>) -> Result<ChatStats, String> {
+    crate::get_chat_stats(options)
+}
+
+pub fn search_messages(query: &str, limit: Option<i64>) -> Result<Vec<SearchHit>, String> {
+    crate::search_messages(query.to_string(), limit)
+}
+
+pub fn export_chat(
+    chat_id: i64,
+    format: ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<ExportResult, String> {
+    crate::write_chat_export(&tasks::TaskRegistry::default(), chat_id, format, path, options)
+}
@@ -0,0 +1,302 @@
+use crate::settings::{self, AppSettings};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// Months averaged on each side of a candidate change-point
+const CHANGE_WINDOW: usize = 6;
+
+// A change-point needs the level to move by this many pooled standard deviations...
+const MIN_CHANGE_SCORE: f64 = 2.0;
+
+// ...and by at least this share of the chat's average month, so tiny chats don't flag noise
+const MIN_CHANGE_FRACTION: f64 = 0.3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+/// A month where the chat's deseasonalized level shifted (moved cities, started dating...)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Breakpoint {
+    pub month: String,               // First month of the new level
+    pub before: f64,                 // Mean messages/month in the window before
+    pub after: f64,
+    pub change_percent: Option<f64>, // None when `before` is zero
+    pub direction: Direction,
+    pub score: f64,                  // Shift in pooled standard deviations
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendDecomposition {
+    pub chat_id: i64,
+    pub months: Vec<String>,         // YYYY-MM, first to last message, gaps filled with 0
+    pub values: Vec<i64>,
+    pub trend: Vec<f64>,             // Centered 12-month moving average
+    pub seasonal: Vec<f64>,          // Calendar-month effect, repeating yearly; sums to ~0 per year
+    pub residual: Vec<f64>,          // values - trend - seasonal
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+/// Message counts per local month, filled so every month from first to last is present
+pub fn monthly_series(messages: &[Message], settings: &AppSettings) -> (Vec<String>, Vec<i64>) {
+    let mut counts: BTreeMap<String, i64> = BTreeMap::new();
+    for msg in messages {
+        if let Some(dt) = settings::to_local_time(msg.date, settings) {
+            *counts.entry(dt.format("%Y-%m").to_string()).or_insert(0) += 1;
+        }
+    }
+    let (Some(first), Some(last)) = (counts.keys().next(), counts.keys().next_back()) else {
+        return (Vec::new(), Vec::new());
+    };
+    let months = crate::keywords::month_range(first, last);
+    let values = months.iter().map(|m| counts.get(m).copied().unwrap_or(0)).collect();
+    (months, values)
+}
+
+/// Calendar month (0-11) of a YYYY-MM label
+pub fn month_index(month: &str) -> usize {
+    month
+        .split_once('-')
+        .and_then(|(_, m)| m.parse::<usize>().ok())
+        .map(|m| m.clamp(1, 12) - 1)
+        .unwrap_or(0)
+}
+
+/// Centered 2x12 moving average; near the edges, the mean of whatever part of the window exists
+fn moving_trend(values: &[f64]) -> Vec<f64> {
+    let n = values.len();
+    (0..n)
+        .map(|i| {
+            let lo = i.saturating_sub(6);
+            let hi = (i + 6).min(n - 1);
+            if i >= 6 && i + 6 < n {
+                // Half weight on the two ends keeps an even 12-month window centered
+                let inner: f64 = values[lo + 1..hi].iter().sum();
+                (inner + (values[lo] + values[hi]) / 2.0) / 12.0
+            } else {
+                values[lo..=hi].iter().sum::<f64>() / (hi - lo + 1) as f64
+            }
+        })
+        .collect()
+}
+
+/// Average detrended value for each calendar month, centered so the twelve effects sum to zero
+pub fn seasonal_effects(months: &[String], values: &[f64], trend: &[f64]) -> [f64; 12] {
+    let mut sums = [0.0; 12];
+    let mut counts = [0usize; 12];
+    for (i, month) in months.iter().enumerate() {
+        let m = month_index(month);
+        sums[m] += values[i] - trend[i];
+        counts[m] += 1;
+    }
+    let mut effects = [0.0; 12];
+    for ((effect, sum), &count) in effects.iter_mut().zip(sums).zip(&counts) {
+        if count > 0 {
+            *effect = sum / count as f64;
+        }
+    }
+    let seen = counts.iter().filter(|&&c| c > 0).count();
+    if seen > 0 {
+        let mean = effects.iter().sum::<f64>() / seen as f64;
+        for (effect, &count) in effects.iter_mut().zip(&counts) {
+            if count > 0 {
+                *effect -= mean;
+            }
+        }
+    }
+    effects
+}
+
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len().max(1) as f64;
+    (mean, variance)
+}
+
+/// Level shifts in the deseasonalized series: compare the windows either side of every month,
+/// then keep the strongest candidates at least one window apart
+fn find_breakpoints(months: &[String], adjusted: &[f64]) -> Vec<Breakpoint> {
+    let n = adjusted.len();
+    if n < 2 * CHANGE_WINDOW {
+        return Vec::new();
+    }
+    let overall = adjusted.iter().sum::<f64>() / n as f64;
+
+    let mut candidates: Vec<(usize, f64, f64, f64)> = Vec::new();
+    for i in CHANGE_WINDOW..=n - CHANGE_WINDOW {
+        let (before, var_before) = mean_and_variance(&adjusted[i - CHANGE_WINDOW..i]);
+        let (after, var_after) = mean_and_variance(&adjusted[i..i + CHANGE_WINDOW]);
+        // Floor of one message keeps perfectly flat windows from dividing by zero
+        let pooled = ((var_before + var_after) / 2.0).sqrt().max(1.0);
+        let score = (after - before).abs() / pooled;
+        if score >= MIN_CHANGE_SCORE && (after - before).abs() >= MIN_CHANGE_FRACTION * overall {
+            candidates.push((i, before, after, score));
+        }
+    }
+    candidates.sort_by(|a, b| b.3.partial_cmp(&a.3).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut chosen: Vec<(usize, f64, f64, f64)> = Vec::new();
+    for candidate in candidates {
+        if chosen.iter().all(|c| c.0.abs_diff(candidate.0) >= CHANGE_WINDOW) {
+            chosen.push(candidate);
+        }
+    }
+    chosen.sort_by_key(|c| c.0);
+
+    chosen
+        .into_iter()
+        .map(|(i, before, after, score)| Breakpoint {
+            month: months[i].clone(),
+            before: before.max(0.0),
+            after: after.max(0.0),
+            change_percent: (before > 0.0).then(|| (after - before) / before * 100.0),
+            direction: if after > before { Direction::Up } else { Direction::Down },
+            score,
+        })
+        .collect()
+}
+
+/// Split one chat's monthly volume into trend + seasonality + residual and flag level shifts
+pub fn decompose(chat_id: i64, messages: &[Message], settings: &AppSettings) -> TrendDecomposition {
+    let (months, values) = monthly_series(messages, settings);
+    let as_f64: Vec<f64> = values.iter().map(|&v| v as f64).collect();
+    let trend = if as_f64.is_empty() { Vec::new() } else { moving_trend(&as_f64) };
+    let effects = seasonal_effects(&months, &as_f64, &trend);
+    let seasonal: Vec<f64> = months.iter().map(|m| effects[month_index(m)]).collect();
+    let residual: Vec<f64> = (0..months.len()).map(|i| as_f64[i] - trend[i] - seasonal[i]).collect();
+    let adjusted: Vec<f64> = (0..months.len()).map(|i| as_f64[i] - seasonal[i]).collect();
+
+    TrendDecomposition {
+        chat_id,
+        breakpoints: find_breakpoints(&months, &adjusted),
+        months,
+        values,
+        trend,
+        seasonal,
+        residual,
+    }
+}
+
+// Completed months averaged for the current pace
+const PACE_MONTHS: usize = 3;
+
+// Seasonal ratios are clamped so one odd month last year can't dominate the projection
+const MAX_SEASONAL_RATIO: f64 = 3.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionMethod {
+    Seasonal,  // Current pace shaped by last year's months
+    RunRate,   // No usable history last year: current pace held flat
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct YearProjection {
+    pub chat_id: i64,
+    pub title: String,
+    pub year: i32,
+    pub year_to_date: i64,
+    pub projected_total: i64,
+    pub last_year_total: i64,
+    pub monthly: Vec<f64>,          // Jan-Dec: actual counts so far, projections after
+    pub method: ProjectionMethod,
+}
+
+/// Project `year`'s total from its counts so far and last year's monthly shape.
+/// `now_month` is 0-11 and `month_elapsed` the fraction of that month already gone.
+pub fn project_year(
+    this_year: &[i64; 12],
+    last_year: &[i64; 12],
+    now_month: usize,
+    month_elapsed: f64,
+) -> (Vec<f64>, ProjectionMethod) {
+    let last_total: i64 = last_year.iter().sum();
+    let last_mean = last_total as f64 / 12.0;
+    let (ratios, method) = if last_total >= 12 {
+        let ratios: Vec<f64> = last_year
+            .iter()
+            .map(|&c| (c as f64 / last_mean).clamp(1.0 / MAX_SEASONAL_RATIO, MAX_SEASONAL_RATIO))
+            .collect();
+        (ratios, ProjectionMethod::Seasonal)
+    } else {
+        (vec![1.0; 12], ProjectionMethod::RunRate)
+    };
+
+    // Pace in "average months": recent completed months with their seasonal effect removed,
+    // or the current partial month scaled up in January
+    let completed: Vec<usize> = (now_month.saturating_sub(PACE_MONTHS)..now_month).collect();
+    let pace = if completed.is_empty() {
+        this_year[now_month] as f64 / month_elapsed.max(0.05) / ratios[now_month]
+    } else {
+        completed.iter().map(|&m| this_year[m] as f64 / ratios[m]).sum::<f64>() / completed.len() as f64
+    };
+
+    let monthly = (0..12)
+        .map(|m| {
+            let actual = this_year[m] as f64;
+            match m.cmp(&now_month) {
+                std::cmp::Ordering::Less => actual,
+                // Rest of the current month at the projected pace
+                std::cmp::Ordering::Equal => actual + pace * ratios[m] * (1.0 - month_elapsed),
+                std::cmp::Ordering::Greater => pace * ratios[m],
+            }
+        })
+        .collect();
+    (monthly, method)
+}
+
+/// Year-end projections for every chat with messages this year, busiest first
+pub fn year_end_projections(
+    messages: &[Message],
+    titles: &HashMap<i64, String>,
+    settings: &AppSettings,
+) -> Vec<YearProjection> {
+    use chrono::{Datelike, Timelike};
+    let Some(now) = settings::to_local_time(chrono::Utc::now().timestamp(), settings) else {
+        return Vec::new();
+    };
+    let year = now.year();
+    let now_month = now.month0() as usize;
+    let days_in_month = chrono::NaiveDate::from_ymd_opt(year, now.month(), 1)
+        .and_then(|first| first.checked_add_months(chrono::Months::new(1)).map(|next| (next - first).num_days()))
+        .unwrap_or(30);
+    let elapsed_days = now.day0() as f64 + now.num_seconds_from_midnight() as f64 / 86400.0;
+    let month_elapsed = elapsed_days / days_in_month as f64;
+
+    let mut counts: HashMap<i64, ([i64; 12], [i64; 12])> = HashMap::new();
+    for msg in messages {
+        let Some(dt) = settings::to_local_time(msg.date, settings) else {
+            continue;
+        };
+        let entry = counts.entry(msg.chat_id.unwrap_or(0)).or_insert(([0; 12], [0; 12]));
+        if dt.year() == year {
+            entry.0[dt.month0() as usize] += 1;
+        } else if dt.year() == year - 1 {
+            entry.1[dt.month0() as usize] += 1;
+        }
+    }
+
+    let mut projections: Vec<YearProjection> = counts
+        .into_iter()
+        .filter(|(_, (this_year, _))| this_year.iter().sum::<i64>() > 0)
+        .map(|(chat_id, (this_year, last_year))| {
+            let (monthly, method) = project_year(&this_year, &last_year, now_month, month_elapsed);
+            YearProjection {
+                chat_id,
+                title: titles.get(&chat_id).cloned().unwrap_or_default(),
+                year,
+                year_to_date: this_year.iter().sum(),
+                projected_total: monthly.iter().sum::<f64>().round() as i64,
+                last_year_total: last_year.iter().sum(),
+                monthly,
+                method,
+            }
+        })
+        .collect();
+    projections.sort_by(|a, b| b.projected_total.cmp(&a.projected_total).then(a.chat_id.cmp(&b.chat_id)));
+    projections
+}
@@ -0,0 +1,164 @@
+use crate::{settings, store, AggregateBy};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerKind {
+    ChatMessageCount { chat_id: Option<i64>, threshold: i64 },  // Any chat when chat_id is unset
+    TotalMessageCount { threshold: i64 },
+    RecordDay,                                                   // Today beat every earlier day
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trigger {
+    #[serde(default)]
+    pub id: String,      // Assigned by set_trigger when empty
+    pub kind: TriggerKind,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Something a trigger noticed; `key` makes sure it is only announced once
+struct Firing {
+    key: String,
+    title: String,
+    body: String,
+}
+
+pub fn list() -> Result<Vec<Trigger>, String> {
+    let conn = store::open_store_db()?;
+    let mut stmt = conn
+        .prepare("SELECT id, kind_json, enabled FROM triggers ORDER BY created_at")
+        .map_err(|e| format!("Store error: {}", e))?;
+    let triggers = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+        })
+        .map_err(|e| format!("Store error: {}", e))?
+        .filter_map(|r| r.ok())
+        .filter_map(|(id, kind_json, enabled)| {
+            let kind = serde_json::from_str(&kind_json).ok()?;
+            Some(Trigger { id, kind, enabled })
+        })
+        .collect();
+    Ok(triggers)
+}
+
+/// Create or update a trigger
+pub fn save(mut trigger: Trigger) -> Result<Trigger, String> {
+    match trigger.kind {
+        TriggerKind::ChatMessageCount { threshold, .. } | TriggerKind::TotalMessageCount { threshold } if threshold <= 0 => {
+            return Err("Threshold must be positive".to_string());
+        }
+        _ => {}
+    }
+    if trigger.id.is_empty() {
+        trigger.id = format!("trigger-{}", Utc::now().timestamp_millis());
+    }
+
+    let conn = store::open_store_db()?;
+    let kind_json = serde_json::to_string(&trigger.kind).map_err(|e| format!("Cannot serialize trigger: {}", e))?;
+    conn.execute(
+        "INSERT INTO triggers (id, kind_json, enabled, created_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET kind_json = excluded.kind_json, enabled = excluded.enabled",
+        rusqlite::params![trigger.id, kind_json, trigger.enabled, Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Store error: {}", e))?;
+
+    // Only crossings after this point should notify, not milestones that were already passed
+    for firing in check(&trigger.kind)? {
+        record_firing(&conn, &trigger.id, &firing.key)?;
+    }
+    Ok(trigger)
+}
+
+/// Remember a firing; returns false when it was already recorded
+fn record_firing(conn: &rusqlite::Connection, trigger_id: &str, key: &str) -> Result<bool, String> {
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO trigger_firings (trigger_id, key, fired_at) VALUES (?, ?, ?)",
+            rusqlite::params![trigger_id, key, Utc::now().timestamp()],
+        )
+        .map_err(|e| format!("Store error: {}", e))?;
+    Ok(inserted > 0)
+}
+
+pub fn delete(id: &str) -> Result<(), String> {
+    let conn = store::open_store_db()?;
+    conn.execute("DELETE FROM triggers WHERE id = ?", [id])
+        .map_err(|e| format!("Store error: {}", e))?;
+    conn.execute("DELETE FROM trigger_firings WHERE trigger_id = ?", [id])
+        .map_err(|e| format!("Store error: {}", e))?;
+    Ok(())
+}
+
+fn check(kind: &TriggerKind) -> Result<Vec<Firing>, String> {
+    let app_settings = settings::load_settings();
+    let n = |count: i64| settings::format_count(count, &app_settings);
+    match kind {
+        TriggerKind::ChatMessageCount { chat_id, threshold } => Ok(crate::aggregate_messages(None, AggregateBy::Chat)?
+            .into_iter()
+            .filter(|b| chat_id.map(|id| b.key == id.to_string()).unwrap_or(!b.key.is_empty()))
+            .filter(|b| b.count >= *threshold)
+            .map(|b| {
+                let name = b.label.clone().unwrap_or_else(|| format!("Chat {}", b.key));
+                Firing {
+                    key: format!("{}:{}", b.key, threshold),
+                    title: format!("{} passed {} messages", name, n(*threshold)),
+                    body: format!("{} now has {} messages.", name, n(b.count)),
+                }
+            })
+            .collect()),
+        TriggerKind::TotalMessageCount { threshold } => {
+            let total = crate::count_messages(None)?;
+            Ok(if total >= *threshold {
+                vec![Firing {
+                    key: threshold.to_string(),
+                    title: format!("You passed {} messages", n(*threshold)),
+                    body: format!("Your archive now holds {} messages.", n(total)),
+                }]
+            } else {
+                Vec::new()
+            })
+        }
+        TriggerKind::RecordDay => {
+            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            let days = crate::aggregate_messages(None, AggregateBy::Day)?;
+            let today_count = days.iter().find(|d| d.key == today).map(|d| d.count).unwrap_or(0);
+            let previous_best = days.iter().filter(|d| d.key < today).map(|d| d.count).max().unwrap_or(0);
+            Ok(if today_count > previous_best && previous_best > 0 {
+                vec![Firing {
+                    key: today,
+                    title: "New record day".to_string(),
+                    body: format!("{} messages today, beating your previous best of {}.", n(today_count), n(previous_best)),
+                }]
+            } else {
+                Vec::new()
+            })
+        }
+    }
+}
+
+/// Evaluate every enabled trigger and `notify(title, body)` of anything not announced before
+pub fn evaluate(notify: &dyn Fn(&str, &str)) -> Result<(), String> {
+    let triggers = list()?;
+    if triggers.iter().all(|t| !t.enabled) {
+        return Ok(());
+    }
+    let conn = store::open_store_db()?;
+
+    for trigger in triggers.iter().filter(|t| t.enabled) {
+        for firing in check(&trigger.kind)? {
+            if !record_firing(&conn, &trigger.id, &firing.key)? {
+                continue;
+            }
+            notify(&firing.title, &firing.body);
+        }
+    }
+    Ok(())
+}
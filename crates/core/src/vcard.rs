@@ -0,0 +1,131 @@
+use crate::{normalize_phone, store};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VcardImportResult {
+    pub contacts: i64,
+    pub identifiers: i64,  // Phone numbers and emails now resolvable to a name
+}
+
+/// Unfold continuation lines (RFC 6350: a line starting with whitespace continues the previous one)
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Split "TEL;TYPE=CELL:+1 555" into ("TEL", "+1 555"), ignoring parameters and group prefixes
+fn property(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.split_once(':')?;
+    let name = key.split(';').next()?;
+    let name = name.rsplit('.').next()?.to_uppercase();
+    Some((name, value.trim().replace("\\,", ",").replace("\\;", ";")))
+}
+
+/// Read names with their phone numbers and emails from a .vcf file
+fn parse_vcards(content: &str) -> Vec<(String, Vec<String>)> {
+    let mut cards = Vec::new();
+    let mut name: Option<String> = None;
+    let mut structured: Option<String> = None;
+    let mut identifiers: Vec<String> = Vec::new();
+
+    for line in unfold(content) {
+        let (key, value) = match property(&line) {
+            Some(p) => p,
+            None => continue,
+        };
+        match key.as_str() {
+            "BEGIN" => {
+                name = None;
+                structured = None;
+                identifiers.clear();
+            }
+            "FN" if !value.is_empty() => name = Some(value),
+            "N" => {
+                // Family;Given;Additional;Prefix;Suffix
+                let parts: Vec<&str> = value.split(';').collect();
+                let given = parts.get(1).copied().unwrap_or("");
+                let family = parts.first().copied().unwrap_or("");
+                let full = format!("{} {}", given, family).trim().to_string();
+                if !full.is_empty() {
+                    structured = Some(full);
+                }
+            }
+            "TEL" => identifiers.push(value.trim_start_matches("tel:").to_string()),
+            "EMAIL" => identifiers.push(value.to_lowercase()),
+            "END" => {
+                if let Some(n) = name.take().or_else(|| structured.take()) {
+                    if !identifiers.is_empty() {
+                        cards.push((n, std::mem::take(&mut identifiers)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    cards
+}
+
+/// Import a vCard file so its contacts name handles AddressBook can't resolve
+pub fn import_vcard(path: &Path) -> Result<VcardImportResult, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read vCard: {}", e))?;
+    let cards = parse_vcards(&content);
+
+    let mut conn = store::open_store_db()?;
+    let tx = conn.transaction().map_err(|e| format!("Store error: {}", e))?;
+    let now = Utc::now().timestamp();
+    let mut identifiers = 0;
+    for (name, ids) in &cards {
+        for id in ids {
+            tx.execute(
+                "INSERT OR REPLACE INTO imported_contacts (identifier, name, imported_at) VALUES (?, ?, ?)",
+                rusqlite::params![id, name, now],
+            )
+            .map_err(|e| format!("Store error: {}", e))?;
+            identifiers += 1;
+        }
+    }
+    tx.commit().map_err(|e| format!("Store error: {}", e))?;
+
+    Ok(VcardImportResult {
+        contacts: cards.len() as i64,
+        identifiers,
+    })
+}
+
+/// Add imported vCard names for identifiers AddressBook didn't resolve
+pub fn merge_imported_contacts(names: &mut HashMap<String, String>) {
+    let conn = match store::open_store_db() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+    let rows: Vec<(String, String)> = conn
+        .prepare("SELECT identifier, name FROM imported_contacts")
+        .ok()
+        .map(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map(|rows| rows.flatten().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+
+    for (identifier, name) in rows {
+        if identifier.contains('@') {
+            names.entry(identifier.to_lowercase()).or_insert(name);
+        } else {
+            let normalized = normalize_phone(&identifier);
+            if !normalized.is_empty() {
+                names.entry(normalized).or_insert_with(|| name.clone());
+            }
+            names.entry(identifier).or_insert(name);
+        }
+    }
+}
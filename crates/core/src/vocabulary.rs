@@ -0,0 +1,119 @@
+use crate::analytics::{messages_by_chat, Tokenizer};
+use crate::language::{detect_language, stop_words};
+use crate::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_MIN_USES: i64 = 5;
+pub const DEFAULT_TERMS_PER_CHAT: usize = 15;
+
+// A term counts as inside vocabulary only if most of its uses happen in one chat
+const MIN_CHAT_SHARE: f64 = 0.6;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InsideTerm {
+    pub term: String,
+    pub uses: i64,               // Messages in this chat using the term
+    pub chat_share: f64,         // Fraction of all uses (across chats) that happen in this chat
+    pub score: f64,              // TF-IDF with each chat as a document
+    pub first_used: i64,         // Unix timestamp of the first message in this chat using it
+    pub first_used_by: String,   // "me" or the sender's identifier
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatVocabulary {
+    pub chat_id: i64,
+    pub terms: Vec<InsideTerm>,
+}
+
+struct TermStats {
+    uses: i64,
+    first_used: i64,
+    first_used_by: String,
+}
+
+/// Find each chat's "inside vocabulary": nicknames and in-jokes used heavily in that chat and
+/// rarely anywhere else
+pub fn inside_vocabulary(
+    messages: &[Message],
+    tokenizer: &Tokenizer,
+    min_uses: i64,
+    terms_per_chat: usize,
+) -> Vec<ChatVocabulary> {
+    // Per chat: term -> stats, counting each message at most once per term
+    let mut chats: Vec<(i64, HashMap<String, TermStats>, i64)> = Vec::new();
+    for (chat_id, chat_messages) in messages_by_chat(messages) {
+        let mut terms: HashMap<String, TermStats> = HashMap::new();
+        let mut total_uses = 0;
+        for msg in &chat_messages {
+            let Some(text) = msg.text.as_deref() else { continue };
+            let unique: HashSet<String> = tokenizer
+                .tokenize(text, stop_words(detect_language(text)))
+                .into_iter()
+                .collect();
+            for term in unique {
+                total_uses += 1;
+                // Messages are sorted, so the first insert is the first use
+                terms
+                    .entry(term)
+                    .or_insert_with(|| TermStats {
+                        uses: 0,
+                        first_used: msg.date,
+                        first_used_by: if msg.is_from_me { "me".to_string() } else { msg.contact_identifier.clone() },
+                    })
+                    .uses += 1;
+            }
+        }
+        chats.push((chat_id, terms, total_uses));
+    }
+
+    let mut overall: HashMap<&str, (i64, i64)> = HashMap::new();  // term -> (uses, chats using it)
+    for (_, terms, _) in &chats {
+        for (term, stats) in terms {
+            let entry = overall.entry(term.as_str()).or_insert((0, 0));
+            entry.0 += stats.uses;
+            entry.1 += 1;
+        }
+    }
+    let chat_count = chats.len() as f64;
+
+    let mut results: Vec<ChatVocabulary> = chats
+        .iter()
+        .filter(|(_, _, total)| *total > 0)
+        .map(|(chat_id, terms, total_uses)| {
+            let mut inside: Vec<InsideTerm> = terms
+                .iter()
+                .filter(|(_, stats)| stats.uses >= min_uses)
+                .filter_map(|(term, stats)| {
+                    let (all_uses, chats_using) = overall[term.as_str()];
+                    let chat_share = stats.uses as f64 / all_uses as f64;
+                    if chat_share < MIN_CHAT_SHARE {
+                        return None;
+                    }
+                    // Smoothed IDF stays positive when a term appears in every chat
+                    let idf = ((1.0 + chat_count) / (1.0 + chats_using as f64)).ln() + 1.0;
+                    Some(InsideTerm {
+                        term: term.clone(),
+                        uses: stats.uses,
+                        chat_share,
+                        score: stats.uses as f64 / *total_uses as f64 * idf,
+                        first_used: stats.first_used,
+                        first_used_by: stats.first_used_by.clone(),
+                    })
+                })
+                .collect();
+            inside.sort_by(|a, b| {
+                b.score
+                    .partial_cmp(&a.score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.term.cmp(&b.term))
+            });
+            inside.truncate(terms_per_chat);
+            ChatVocabulary { chat_id: *chat_id, terms: inside }
+        })
+        .filter(|v| !v.terms.is_empty())
+        .collect();
+
+    results.sort_by_key(|v| v.chat_id);
+    results
+}
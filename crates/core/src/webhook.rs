@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookConfig {
+    pub name: String,
+    pub url: String,
+    pub body_template: Option<String>,  // JSON with {{key}} placeholders; the full context when unset
+    #[serde(default)]
+    pub auth_secret: Option<String>,    // Keychain secret sent as a Bearer token
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub webhook: String,
+    pub status: u16,
+}
+
+/// Check a webhook before it is saved
+pub fn validate(config: &WebhookConfig) -> Result<(), String> {
+    if config.name.trim().is_empty() {
+        return Err("Webhook name cannot be empty".to_string());
+    }
+    if !config.url.starts_with("https://") && !config.url.starts_with("http://") {
+        return Err(format!("Webhook '{}' needs an http(s) URL", config.name));
+    }
+    if let Some(ref template) = config.body_template {
+        render_template(template, &Map::new())
+            .map_err(|e| format!("Webhook '{}' template: {}", config.name, e))?;
+    }
+    Ok(())
+}
+
+/// Replace {{key}} placeholders with the JSON encoding of the matching context value, so
+/// strings arrive quoted and lists/objects stay structured. Unknown keys become null.
+pub fn render_template(template: &str, context: &Map<String, Value>) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or("Unclosed {{ placeholder")?;
+        let key = after[..end].trim();
+        let value = context.get(key).unwrap_or(&Value::Null);
+        out.push_str(&value.to_string());
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+
+    serde_json::from_str::<Value>(&out).map_err(|e| format!("Rendered body is not valid JSON: {}", e))?;
+    Ok(out)
+}
+
+/// POST the context (through the webhook's template, if any) as JSON
+pub fn post(config: &WebhookConfig, context: &Map<String, Value>) -> Result<WebhookDelivery, String> {
+    crate::privacy::ensure_network_allowed(&format!("Webhook '{}'", config.name))?;
+    let body = match config.body_template {
+        Some(ref template) => render_template(template, context)?,
+        None => Value::Object(context.clone()).to_string(),
+    };
+
+    let mut request = ureq::post(&config.url).set("Content-Type", "application/json");
+    if let Some(ref secret) = config.auth_secret {
+        let token = crate::secrets::get_secret(secret).map_err(|e| format!("Webhook '{}': {}", config.name, e))?;
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    let status = match request.send_string(&body) {
+        Ok(response) => response.status(),
+        Err(ureq::Error::Status(code, _)) => {
+            return Err(format!("Webhook '{}' returned HTTP {}", config.name, code));
+        }
+        Err(e) => return Err(format!("Webhook '{}' failed: {}", config.name, e)),
+    };
+
+    Ok(WebhookDelivery {
+        webhook: config.name.clone(),
+        status,
+    })
+}
+
+/// Find an enabled webhook by name
+pub fn find<'a>(webhooks: &'a [WebhookConfig], name: &str) -> Result<&'a WebhookConfig, String> {
+    webhooks
+        .iter()
+        .find(|w| w.name == name)
+        .filter(|w| w.enabled)
+        .ok_or_else(|| format!("No enabled webhook named '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> Map<String, Value> {
+        json!({ "chat": "Family \"Group\"", "count": 42, "top": ["Ana", "Ben"] })
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn substitutes_json_encoded_values() {
+        let body = render_template(r#"{"text": {{ chat }}, "n": {{count}}, "who": {{top}}}"#, &context()).unwrap();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed, json!({ "text": "Family \"Group\"", "n": 42, "who": ["Ana", "Ben"] }));
+    }
+
+    #[test]
+    fn unknown_keys_become_null() {
+        assert_eq!(render_template(r#"{"x": {{missing}}}"#, &context()).unwrap(), r#"{"x": null}"#);
+    }
+
+    #[test]
+    fn rejects_unclosed_placeholders_and_invalid_json() {
+        assert!(render_template(r#"{"x": {{count}"#, &context()).is_err());
+        assert!(render_template(r#"{"x": "{{chat}}"}"#, &context()).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::imports::{self, ImportResult, ImportedMessage};
+use crate::{settings, store};
+use chrono::NaiveDateTime;
+use std::path::Path;
+
+const SOURCE: &str = "whatsapp";
+
+/// One "date, time - sender: text" header split into its parts
+struct RawLine<'a> {
+    date: &'a str,
+    time: &'a str,
+    sender: &'a str,
+    text: &'a str,
+}
+
+/// Split a message header. Handles both export styles:
+/// Android "12/31/20, 9:41 PM - Name: text" and iOS "[31/12/2020, 21:41:05] Name: text"
+fn split_header(line: &str) -> Option<RawLine<'_>> {
+    // iOS exports prefix some lines with a left-to-right mark
+    let line = line.trim_start_matches('\u{200e}');
+    let (stamp, rest) = if let Some(stripped) = line.strip_prefix('[') {
+        let end = stripped.find("] ")?;
+        (&stripped[..end], &stripped[end + 2..])
+    } else {
+        let end = line.find(" - ")?;
+        (&line[..end], &line[end + 3..])
+    };
+    let (date, time) = stamp.split_once(", ")?;
+    if !date.chars().next()?.is_ascii_digit() {
+        return None;
+    }
+    // System lines ("Messages are end-to-end encrypted") have no sender
+    let (sender, text) = rest.split_once(": ")?;
+    Some(RawLine {
+        date: date.trim(),
+        time: time.trim(),
+        sender: sender.trim(),
+        text,
+    })
+}
+
+/// Exports use the phone's locale, so decide once per file whether dates are day-first
+fn is_day_first(dates: &[&str]) -> bool {
+    dates.iter().any(|d| {
+        d.split(['/', '.', '-'])
+            .next()
+            .and_then(|first| first.parse::<u32>().ok())
+            .map(|first| first > 12)
+            .unwrap_or(false)
+    })
+}
+
+fn parse_timestamp(date: &str, time: &str, day_first: bool) -> Option<i64> {
+    let date = date.replace(['.', '-'], "/");
+    let time = time.replace('\u{202f}', " ").to_uppercase();
+    let date_formats: &[&str] = if day_first { &["%d/%m/%y", "%d/%m/%Y"] } else { &["%m/%d/%y", "%m/%d/%Y"] };
+    let time_formats = ["%I:%M %p", "%I:%M:%S %p", "%H:%M", "%H:%M:%S"];
+
+    date_formats.iter().find_map(|df| {
+        time_formats.iter().find_map(|tf| {
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, time), &format!("{} {}", df, tf))
+                .ok()
+                .and_then(|dt| dt.and_local_timezone(chrono::Local).earliest())
+                .map(|dt| dt.timestamp())
+        })
+    })
+}
+
+/// "WhatsApp Chat with Alex.txt" -> "Alex"
+fn conversation_name(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+    Some(stem.strip_prefix("WhatsApp Chat with ").unwrap_or(&stem).trim().to_string())
+}
+
+/// Import a WhatsApp "Export chat" text file. Senders matching the configured self names
+/// are treated as messages from me.
+pub fn import_whatsapp_chat(path: &Path) -> Result<ImportResult, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read chat export: {}", e))?;
+    let self_names: Vec<String> = settings::load_settings()
+        .self_names
+        .iter()
+        .map(|n| n.trim().to_lowercase())
+        .collect();
+    let conversation = conversation_name(path);
+
+    // Group continuation lines with the header line they belong to
+    let mut entries: Vec<(RawLine, String)> = Vec::new();
+    for line in content.lines() {
+        match split_header(line) {
+            Some(header) => {
+                let text = header.text.to_string();
+                entries.push((header, text));
+            }
+            None => {
+                if let Some((_, text)) = entries.last_mut() {
+                    text.push('\n');
+                    text.push_str(line);
+                }
+            }
+        }
+    }
+
+    let dates: Vec<&str> = entries.iter().map(|(h, _)| h.date).collect();
+    let day_first = is_day_first(&dates);
+
+    let mut messages = Vec::new();
+    let mut errors = Vec::new();
+    for (header, text) in &entries {
+        let date = match parse_timestamp(header.date, header.time, day_first) {
+            Some(d) => d,
+            None => {
+                errors.push(format!("Unrecognized timestamp: {}, {}", header.date, header.time));
+                continue;
+            }
+        };
+        let is_from_me = self_names.contains(&header.sender.to_lowercase());
+        messages.push(ImportedMessage {
+            conversation: conversation.clone(),
+            sender: if is_from_me { None } else { Some(header.sender.to_string()) },
+            is_from_me,
+            date,
+            text: Some(text.clone()),
+            attachments: Vec::new(),
+        });
+    }
+
+    let mut conn = store::open_store_db()?;
+    let (imported, skipped) = imports::insert_imported(&mut conn, SOURCE, &messages)?;
+
+    errors.truncate(imports::MAX_REPORTED_ERRORS);
+    Ok(ImportResult {
+        source: SOURCE.to_string(),
+        imported,
+        skipped,
+        errors,
+    })
+}
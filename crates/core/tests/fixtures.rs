@@ -0,0 +1,171 @@
+//! Queries and exports run against generated chat.db/AddressBook fixtures instead of real data.
+
+use message_insights_core::test_support::{self, ExportFormat, FixtureOptions, FixtureSummary};
+use message_insights_core::ExportOptions;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Generate the fixtures once per test binary, with HOME pointed at a scratch folder so the
+/// app data directory (demo databases, store.db) never touches the real one
+fn fixture() -> &'static (FixtureSummary, PathBuf) {
+    static FIXTURE: OnceLock<(FixtureSummary, PathBuf)> = OnceLock::new();
+    FIXTURE.get_or_init(|| {
+        let root = std::env::temp_dir().join(format!("message-insights-tests-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::env::set_var("HOME", &root);
+        std::env::set_var("XDG_DATA_HOME", root.join("data"));
+
+        let options = FixtureOptions {
+            chats: Some(4),
+            messages_per_chat: Some(50),
+            days: Some(90),
+            seed: Some(42),
+            ..Default::default()
+        };
+        let summary = test_support::use_fixture_data(&options).unwrap();
+        (summary, root)
+    })
+}
+
+/// The group chat, which has no edge-case messages mixed in
+fn group_chat_id() -> i64 {
+    fixture();
+    test_support::get_chats().unwrap().iter().find(|c| c.is_group).unwrap().id
+}
+
+#[test]
+fn lists_every_generated_chat() {
+    let (summary, _) = fixture();
+    let chats = test_support::get_chats().unwrap();
+    assert_eq!(chats.len(), summary.chats);
+    assert!(chats.iter().all(|c| c.message_count > 0));
+    assert!(chats.iter().any(|c| c.is_group && c.display_name.as_deref() == Some("Weekend Plans")));
+}
+
+#[test]
+fn get_messages_reads_every_message_with_names_and_reactions() {
+    let (summary, _) = fixture();
+    let messages = test_support::get_messages(None, None).unwrap();
+    assert!(!messages.is_empty());
+    assert!(messages.len() <= summary.messages);
+
+    // Tapbacks are attached to their targets rather than listed as messages
+    assert!(messages.iter().all(|m| !m.guid.ends_with("-R")));
+    let reactions: usize = messages.iter().map(|m| m.reactions.len()).sum();
+    assert_eq!(reactions, summary.reactions);
+
+    // attributedBody-only messages still come back with text
+    assert!(messages.iter().filter(|m| !m.guid.starts_with("DEMO-EDGE")).all(|m| m.text.is_some()));
+
+    // Senders resolve through the generated AddressBook
+    assert!(messages.iter().any(|m| !m.is_from_me && !m.sender_name.starts_with('+') && !m.sender_name.contains('@')));
+}
+
+#[test]
+fn get_messages_honors_chat_and_date_filters() {
+    fixture();
+    let chat_id = group_chat_id();
+    let in_chat = test_support::get_messages(
+        Some(ExportOptions {
+            chat_ids: Some(vec![chat_id]),
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap();
+    assert!(!in_chat.is_empty());
+    assert!(in_chat.iter().all(|m| m.chat_id == Some(chat_id)));
+
+    let all = test_support::get_messages(None, None).unwrap();
+    let mut dates: Vec<i64> = all.iter().map(|m| m.date).collect();
+    dates.sort();
+    let midpoint = dates[dates.len() / 2];
+    let recent = test_support::get_messages(
+        Some(ExportOptions {
+            start_date: Some(midpoint),
+            ..Default::default()
+        }),
+        None,
+    )
+    .unwrap();
+    assert!(!recent.is_empty() && recent.len() < all.len());
+    assert!(recent.iter().all(|m| m.date >= midpoint));
+
+    let limited = test_support::get_messages(None, Some(5)).unwrap();
+    assert_eq!(limited.len(), 5);
+}
+
+#[test]
+fn get_chat_stats_matches_the_messages() {
+    fixture();
+    let stats = test_support::get_chat_stats(None).unwrap();
+    assert!(stats.total_messages > 0);
+    assert_eq!(stats.messages_sent + stats.messages_received, stats.total_messages);
+    assert!(stats.date_range_start <= stats.date_range_end);
+
+    // The edge-case messages carry delivery metadata
+    assert!(stats.scheduled_messages >= 1);
+    assert!(stats.retracted_messages >= 1);
+    assert!(stats.quiet_deliveries >= 1);
+
+    let chat_id = group_chat_id();
+    let options = ExportOptions {
+        chat_ids: Some(vec![chat_id]),
+        ..Default::default()
+    };
+    let chat_stats = test_support::get_chat_stats(Some(options.clone())).unwrap();
+    let chat_messages = test_support::get_messages(Some(options), None).unwrap();
+    assert!(chat_stats.total_messages < stats.total_messages);
+    assert_eq!(
+        chat_stats.messages_sent,
+        chat_messages.iter().filter(|m| m.is_from_me).count() as i64
+    );
+}
+
+#[test]
+fn export_chat_writes_every_format() {
+    let (_, root) = fixture();
+    let chat_id = group_chat_id();
+    let out = root.join("exports");
+    std::fs::create_dir_all(&out).unwrap();
+
+    let formats = [
+        ExportFormat::Txt,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::Json,
+        ExportFormat::Csv,
+        ExportFormat::Mbox,
+    ];
+    for format in formats {
+        let path = out.join(format!("chat.{:?}", format).to_lowercase());
+        let result = test_support::export_chat(chat_id, format, path.to_string_lossy().to_string(), None)
+            .unwrap_or_else(|e| panic!("{:?} export failed: {}", format, e));
+        assert!(result.message_count > 0, "{:?} exported no messages", format);
+
+        let content = std::fs::read_to_string(&result.files[0]).unwrap();
+        assert!(!content.is_empty(), "{:?} export is empty", format);
+        assert!(!PathBuf::from(format!("{}.partial", result.files[0])).exists());
+        match format {
+            ExportFormat::Json => {
+                serde_json::from_str::<serde_json::Value>(&content).expect("JSON export parses");
+            }
+            ExportFormat::Html => assert!(content.contains("<html")),
+            ExportFormat::Mbox => assert!(content.starts_with("From ")),
+            ExportFormat::Csv => assert!(content.lines().count() as i64 > result.message_count),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn search_finds_messages_stored_only_in_attributed_body() {
+    fixture();
+    let messages = test_support::get_messages(None, None).unwrap();
+    let phrase = messages.iter().find_map(|m| m.text.clone()).unwrap();
+    let hits = test_support::search_messages(&phrase, Some(100_000)).unwrap();
+    for msg in messages.iter().filter(|m| m.text.as_deref().is_some_and(|t| t.contains(&phrase))) {
+        assert!(hits.iter().any(|h| h.message_id == msg.id), "message {} not found", msg.guid);
+    }
+}
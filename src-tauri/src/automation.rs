@@ -0,0 +1,23 @@
+use insights::automation::{self, AutomationResult};
+use insights::tasks::TaskRegistry;
+use tauri::{AppHandle, Emitter, Manager, Url};
+
+/// Handle a URL opened by Shortcuts/AppleScript (`open location`), reporting the outcome to the UI
+pub(crate) fn handle_url(app: &AppHandle, url: &Url) {
+    let app = app.clone();
+    let url = url.clone();
+
+    // Exports and reports can take a while; keep the event loop responsive
+    std::thread::spawn(move || {
+        let outcome = automation::run_url(&app.state::<TaskRegistry>(), &url);
+        let url = url.to_string();
+        if let Err(ref e) = outcome {
+            tracing::warn!("Automation URL {} failed: {}", url, e);
+        }
+        let (success, message) = match outcome {
+            Ok(m) => (true, m),
+            Err(e) => (false, e),
+        };
+        let _ = app.emit("automation-result", AutomationResult { url, success, message });
+    });
+}
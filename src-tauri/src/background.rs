@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+/// Passed by the login item so the app starts tray-only
+pub(crate) const BACKGROUND_ARG: &str = "--background";
+
+// Set for the lifetime of a login-item launch; closing the window then hides it instead of quitting
+static AGENT_MODE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn launched_in_background() -> bool {
+    std::env::args().any(|arg| arg == BACKGROUND_ARG)
+}
+
+pub(crate) fn is_agent_mode() -> bool {
+    AGENT_MODE.load(Ordering::Relaxed)
+}
+
+/// Hide the main window and drop the Dock icon, leaving the tray, watcher and scheduler running
+pub(crate) fn enter_background(app: &AppHandle) {
+    AGENT_MODE.store(true, Ordering::Relaxed);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+    }
+    #[cfg(target_os = "macos")]
+    let _ = app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+}
+
+/// Bring the main window (and the Dock icon) back
+pub(crate) fn show_main_window(app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    let _ = app.set_activation_policy(tauri::ActivationPolicy::Regular);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Register or remove the login item that starts the app in background mode
+pub(crate) fn set_launch_at_login(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    let launcher = app.autolaunch();
+    let result = if enabled { launcher.enable() } else { launcher.disable() };
+    result.map_err(|e| format!("Cannot update login item: {}", e))
+}
+
+pub(crate) fn launch_at_login(app: &AppHandle) -> Result<bool, String> {
+    app.autolaunch()
+        .is_enabled()
+        .map_err(|e| format!("Cannot read login item: {}", e))
+}
@@ -0,0 +1,670 @@
+//! Tauri commands: each one hands its arguments to the function of the same name in the core
+//! crate, which holds the queries, exports and analytics shared with the CLI.
+
+use insights::*;
+
+#[tauri::command]
+pub(crate) fn check_database_access() -> DatabaseStatus {
+    insights::check_database_access()
+}
+
+#[tauri::command]
+pub(crate) fn get_contacts() -> Result<Vec<Contact>, String> {
+    insights::get_contacts()
+}
+
+#[tauri::command]
+pub(crate) fn get_chat_stats(options: Option<ExportOptions>) -> Result<ChatStats, String> {
+    insights::get_chat_stats(options)
+}
+
+#[tauri::command]
+pub(crate) fn count_messages(options: Option<ExportOptions>) -> Result<i64, String> {
+    insights::count_messages(options)
+}
+
+#[tauri::command]
+pub(crate) fn aggregate_messages(
+    options: Option<ExportOptions>,
+    group_by: AggregateBy,
+) -> Result<Vec<AggregateBucket>, String> {
+    insights::aggregate_messages(options, group_by)
+}
+
+#[tauri::command]
+pub(crate) fn get_message_summaries(
+    options: Option<ExportOptions>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<MessageSummary>, String> {
+    insights::get_message_summaries(options, limit, offset)
+}
+
+#[tauri::command]
+pub(crate) fn get_messages(
+    options: Option<ExportOptions>,
+    limit: Option<i64>,
+) -> Result<Vec<Message>, String> {
+    insights::get_messages(options, limit)
+}
+
+#[tauri::command]
+pub(crate) fn get_messages_for_contact(
+    contact_id: i64,
+    options: Option<ExportOptions>,
+) -> Result<Vec<Message>, String> {
+    insights::get_messages_for_contact(contact_id, options)
+}
+
+#[tauri::command]
+pub(crate) fn open_system_preferences() -> Result<(), String> {
+    insights::open_system_preferences()
+}
+
+#[tauri::command]
+pub(crate) fn open_contacts_preferences() -> Result<(), String> {
+    insights::open_contacts_preferences()
+}
+
+#[tauri::command]
+pub(crate) fn get_permission_report() -> Result<permissions::PermissionReport, String> {
+    insights::get_permission_report()
+}
+
+#[tauri::command]
+pub(crate) fn check_contacts_access() -> addressbook::ContactsAccess {
+    insights::check_contacts_access()
+}
+
+#[tauri::command]
+pub(crate) fn request_contacts_access() -> Result<(), String> {
+    insights::request_contacts_access()
+}
+
+#[tauri::command]
+pub(crate) fn get_chats(filter: Option<ChatListFilter>) -> Result<Vec<Chat>, String> {
+    insights::get_chats(filter)
+}
+
+#[tauri::command]
+pub(crate) fn get_media_trends(chat_id: Option<i64>) -> Result<media_trends::MediaTrends, String> {
+    insights::get_media_trends(chat_id)
+}
+
+#[tauri::command]
+pub(crate) fn get_gif_report(
+    chat_id: Option<i64>,
+    source: Option<archive::DataSource>,
+) -> Result<Vec<gifs::ChatGifReport>, String> {
+    insights::get_gif_report(chat_id, source)
+}
+
+#[tauri::command]
+pub(crate) fn get_shared_media(
+    min_chats: Option<usize>,
+    limit: Option<usize>,
+    source: Option<archive::DataSource>,
+) -> Result<Vec<duplicates::SharedMedia>, String> {
+    insights::get_shared_media(min_chats, limit, source)
+}
+
+#[tauri::command]
+pub(crate) fn get_media_stats(chat_id: Option<i64>) -> Result<MediaStats, String> {
+    insights::get_media_stats(chat_id)
+}
+
+#[tauri::command]
+pub(crate) fn get_photo_map(chat_id: i64, mismatch_threshold_hours: Option<i64>) -> Result<PhotoMap, String> {
+    insights::get_photo_map(chat_id, mismatch_threshold_hours)
+}
+
+#[tauri::command]
+pub(crate) fn run_ocr(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    chat_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<OcrSummary, String> {
+    insights::run_ocr(&task_registry, chat_id, limit)
+}
+
+#[tauri::command]
+pub(crate) fn search_messages(query: String, limit: Option<i64>) -> Result<Vec<SearchHit>, String> {
+    insights::search_messages(query, limit)
+}
+
+#[tauri::command]
+pub(crate) fn tag_contact(identifier: String, tag: String) -> Result<(), String> {
+    insights::tag_contact(identifier, tag)
+}
+
+#[tauri::command]
+pub(crate) fn untag_contact(identifier: String, tag: String) -> Result<(), String> {
+    insights::untag_contact(identifier, tag)
+}
+
+#[tauri::command]
+pub(crate) fn get_tags() -> Result<Vec<String>, String> {
+    insights::get_tags()
+}
+
+#[tauri::command]
+pub(crate) fn compare_groups(
+    tags: Vec<String>,
+    options: Option<ExportOptions>,
+) -> Result<Vec<GroupComparison>, String> {
+    insights::compare_groups(tags, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_busiest_days(
+    limit: Option<usize>,
+    chat_id: Option<i64>,
+    recent_days: Option<i64>,
+    source: Option<archive::DataSource>,
+) -> Result<BusiestDaysReport, String> {
+    insights::get_busiest_days(limit, chat_id, recent_days, source)
+}
+
+#[tauri::command]
+pub(crate) fn get_settings() -> settings::AppSettings {
+    insights::get_settings()
+}
+
+#[tauri::command]
+pub(crate) fn update_settings(new_settings: settings::AppSettings) -> Result<(), String> {
+    insights::update_settings(new_settings)
+}
+
+#[tauri::command]
+pub(crate) fn get_night_owl_report(
+    quiet_start_hour: Option<u32>,
+    quiet_end_hour: Option<u32>,
+    options: Option<ExportOptions>,
+) -> Result<analytics::NightOwlReport, String> {
+    insights::get_night_owl_report(quiet_start_hour, quiet_end_hour, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_conversation_tempo(
+    options: Option<ExportOptions>,
+    session_gap_minutes: Option<i64>,
+) -> Result<Vec<analytics::ChatTempo>, String> {
+    insights::get_conversation_tempo(options, session_gap_minutes)
+}
+
+#[tauri::command]
+pub(crate) fn get_histogram(
+    metric: histogram::HistogramMetric,
+    options: Option<ExportOptions>,
+    buckets: Option<usize>,
+) -> Result<histogram::Histogram, String> {
+    insights::get_histogram(metric, options, buckets)
+}
+
+#[tauri::command]
+pub(crate) fn list_metrics() -> Vec<metrics::MetricInfo> {
+    insights::list_metrics()
+}
+
+#[tauri::command]
+pub(crate) fn compute_metrics(
+    names: Vec<String>,
+    options: Option<ExportOptions>,
+    params: Option<metrics::MetricParams>,
+) -> Result<Vec<metrics::MetricResult>, String> {
+    insights::compute_metrics(names, options, params)
+}
+
+#[tauri::command]
+pub(crate) fn get_social_graph(options: Option<ExportOptions>) -> Result<social::SocialGraph, String> {
+    insights::get_social_graph(options)
+}
+
+#[tauri::command]
+pub(crate) fn get_reaction_matrix(
+    chat_id: i64,
+    options: Option<ExportOptions>,
+) -> Result<social::ReactionMatrix, String> {
+    insights::get_reaction_matrix(chat_id, options)
+}
+
+#[tauri::command]
+pub(crate) fn export_chat(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    chat_id: i64,
+    format: export::ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<export::ExportResult, String> {
+    insights::export_chat(&task_registry, chat_id, format, path, options)
+}
+
+#[tauri::command]
+pub(crate) fn export_attachments(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    chat_id: i64,
+    dir: String,
+    filter: Option<attachment_export::AttachmentFilter>,
+) -> Result<attachment_export::AttachmentExportResult, String> {
+    insights::export_attachments(&task_registry, chat_id, dir, filter)
+}
+
+#[tauri::command]
+pub(crate) fn export_contact(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    contact_id: i64,
+    format: export::ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<export::ExportResult, String> {
+    insights::export_contact(&task_registry, contact_id, format, path, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_contact_ranking(
+    contact_id: i64,
+    options: Option<ExportOptions>,
+) -> Result<ranking::ContactRanking, String> {
+    insights::get_contact_ranking(contact_id, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_person_timeline(
+    contact_id: i64,
+    options: Option<ExportOptions>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<PersonTimelinePage, String> {
+    insights::get_person_timeline(contact_id, options, limit, offset)
+}
+
+#[tauri::command]
+pub(crate) fn get_chats_for_contact(contact_id: i64) -> Result<Vec<ContactChat>, String> {
+    insights::get_chats_for_contact(contact_id)
+}
+
+#[tauri::command]
+pub(crate) fn get_common_chats(contact_a: i64, contact_b: i64) -> Result<Vec<CommonChat>, String> {
+    insights::get_common_chats(contact_a, contact_b)
+}
+
+#[tauri::command]
+pub(crate) fn get_introductions(
+    source: Option<archive::DataSource>,
+) -> Result<Vec<introductions::Introduction>, String> {
+    insights::get_introductions(source)
+}
+
+#[tauri::command]
+pub(crate) fn compare_periods(
+    range_a: DateRange,
+    range_b: DateRange,
+    options: Option<ExportOptions>,
+    top_n: Option<usize>,
+) -> Result<analytics::PeriodComparison, String> {
+    insights::compare_periods(range_a, range_b, options, top_n)
+}
+
+#[tauri::command]
+pub(crate) fn compare_chats(
+    chat_a: i64,
+    chat_b: i64,
+    options: Option<ExportOptions>,
+) -> Result<analytics::ChatComparison, String> {
+    insights::compare_chats(chat_a, chat_b, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_trend_decomposition(
+    chat_id: i64,
+    options: Option<ExportOptions>,
+) -> Result<trends::TrendDecomposition, String> {
+    insights::get_trend_decomposition(chat_id, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_year_end_projection(
+    options: Option<ExportOptions>,
+    limit: Option<usize>,
+) -> Result<Vec<trends::YearProjection>, String> {
+    insights::get_year_end_projection(options, limit)
+}
+
+#[tauri::command]
+pub(crate) fn get_chat_archetypes(
+    options: Option<ExportOptions>,
+) -> Result<Vec<archetypes::ChatArchetype>, String> {
+    insights::get_chat_archetypes(options)
+}
+
+#[tauri::command]
+pub(crate) fn import_csv(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    path: String,
+    column_mapping: imports::ColumnMapping,
+) -> Result<imports::ImportResult, String> {
+    insights::import_csv(&task_registry, path, column_mapping)
+}
+
+#[tauri::command]
+pub(crate) fn import_sms_backup(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    path: String,
+) -> Result<imports::ImportResult, String> {
+    insights::import_sms_backup(&task_registry, path)
+}
+
+#[tauri::command]
+pub(crate) fn sync_archive(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+) -> Result<archive::ArchiveSyncResult, String> {
+    insights::sync_archive(&task_registry)
+}
+
+#[tauri::command]
+pub(crate) fn get_archive_stats() -> Result<archive::ArchiveStats, String> {
+    insights::get_archive_stats()
+}
+
+#[tauri::command]
+pub(crate) fn compact_archive(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    vacuum: Option<bool>,
+) -> Result<archive::CompactionResult, String> {
+    insights::compact_archive(&task_registry, vacuum)
+}
+
+#[tauri::command]
+pub(crate) fn start_api_server(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+) -> Result<api::ApiStatus, String> {
+    insights::start_api_server(&task_registry)
+}
+
+#[tauri::command]
+pub(crate) fn stop_api_server() -> Result<(), String> {
+    insights::stop_api_server()
+}
+
+#[tauri::command]
+pub(crate) fn get_api_server_status() -> api::ApiStatus {
+    insights::get_api_server_status()
+}
+
+#[tauri::command]
+pub(crate) fn export_obsidian_vault(
+    vault_path: String,
+    options: Option<ExportOptions>,
+) -> Result<obsidian::VaultExportResult, String> {
+    insights::export_obsidian_vault(vault_path, options)
+}
+
+#[tauri::command]
+pub(crate) fn push_summary_to_webhook(
+    webhook: String,
+    options: Option<ExportOptions>,
+) -> Result<webhook::WebhookDelivery, String> {
+    insights::push_summary_to_webhook(webhook, options)
+}
+
+#[tauri::command]
+pub(crate) fn get_digests(limit: Option<i64>) -> Result<Vec<digest::WeeklyDigest>, String> {
+    insights::get_digests(limit)
+}
+
+#[tauri::command]
+pub(crate) fn list_triggers() -> Result<Vec<triggers::Trigger>, String> {
+    insights::list_triggers()
+}
+
+#[tauri::command]
+pub(crate) fn set_trigger(trigger: triggers::Trigger) -> Result<triggers::Trigger, String> {
+    insights::set_trigger(trigger)
+}
+
+#[tauri::command]
+pub(crate) fn delete_trigger(id: String) -> Result<(), String> {
+    insights::delete_trigger(id)
+}
+
+#[tauri::command]
+pub(crate) fn list_export_templates() -> Vec<templates::ExportTemplate> {
+    insights::list_export_templates()
+}
+
+#[tauri::command]
+pub(crate) fn validate_template(source: String) -> Result<(), String> {
+    insights::validate_template(source)
+}
+
+#[tauri::command]
+pub(crate) fn get_message_by_guid(guid: String) -> Result<Message, String> {
+    insights::get_message_by_guid(guid)
+}
+
+#[tauri::command]
+pub(crate) fn get_context(
+    guid: String,
+    before: Option<usize>,
+    after: Option<usize>,
+) -> Result<MessageContext, String> {
+    insights::get_context(guid, before, after)
+}
+
+#[tauri::command]
+pub(crate) fn get_messages_around_date(
+    chat_id: i64,
+    timestamp: i64,
+    window: Option<usize>,
+) -> Result<MessagePage, String> {
+    insights::get_messages_around_date(chat_id, timestamp, window)
+}
+
+#[tauri::command]
+pub(crate) fn pin_message(guid: String, note: Option<String>) -> Result<(), String> {
+    insights::pin_message(guid, note)
+}
+
+#[tauri::command]
+pub(crate) fn unpin_message(guid: String) -> Result<(), String> {
+    insights::unpin_message(guid)
+}
+
+#[tauri::command]
+pub(crate) fn get_pinned() -> Result<Vec<pins::PinnedMessage>, String> {
+    insights::get_pinned()
+}
+
+#[tauri::command]
+pub(crate) fn set_chat_note(chat_id: i64, note: String) -> Result<(), String> {
+    insights::set_chat_note(chat_id, note)
+}
+
+#[tauri::command]
+pub(crate) fn set_contact_note(identifier: String, note: String) -> Result<(), String> {
+    insights::set_contact_note(identifier, note)
+}
+
+#[tauri::command]
+pub(crate) fn get_export_history(limit: Option<usize>) -> Result<Vec<export_history::ExportJob>, String> {
+    insights::get_export_history(limit)
+}
+
+#[tauri::command]
+pub(crate) fn rerun_export(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    job_id: i64,
+    new_range: Option<DateRange>,
+) -> Result<export::ExportResult, String> {
+    insights::rerun_export(&task_registry, job_id, new_range)
+}
+
+#[tauri::command]
+pub(crate) fn list_jobs() -> Result<Vec<scheduler::JobStatus>, String> {
+    insights::list_jobs()
+}
+
+#[tauri::command]
+pub(crate) fn save_job(job: scheduler::ScheduledJob) -> Result<scheduler::ScheduledJob, String> {
+    insights::save_job(job)
+}
+
+#[tauri::command]
+pub(crate) fn delete_job(id: String) -> Result<(), String> {
+    insights::delete_job(id)
+}
+
+#[tauri::command]
+pub(crate) fn export_logs(path: String) -> Result<String, String> {
+    insights::export_logs(path)
+}
+
+#[tauri::command]
+pub(crate) fn generate_demo_data(
+    options: Option<fixtures::FixtureOptions>,
+) -> Result<fixtures::FixtureSummary, String> {
+    insights::generate_demo_data(options)
+}
+
+#[tauri::command]
+pub(crate) fn is_demo_mode() -> bool {
+    insights::is_demo_mode()
+}
+
+#[tauri::command]
+pub(crate) fn get_language_stats(
+    options: Option<ExportOptions>,
+) -> Result<Vec<language::ChatLanguages>, String> {
+    insights::get_language_stats(options)
+}
+
+#[tauri::command]
+pub(crate) fn get_word_frequencies(
+    options: Option<ExportOptions>,
+    limit: Option<usize>,
+) -> Result<Vec<language::WordCount>, String> {
+    insights::get_word_frequencies(options, limit)
+}
+
+#[tauri::command]
+pub(crate) fn get_keyword_trends(
+    options: Option<ExportOptions>,
+    categories: Option<Vec<keywords::KeywordCategory>>,
+) -> Result<Vec<keywords::CategorySeries>, String> {
+    insights::get_keyword_trends(options, categories)
+}
+
+#[tauri::command]
+pub(crate) fn get_inside_vocabulary(
+    options: Option<ExportOptions>,
+    min_uses: Option<i64>,
+    terms_per_chat: Option<usize>,
+) -> Result<Vec<vocabulary::ChatVocabulary>, String> {
+    insights::get_inside_vocabulary(options, min_uses, terms_per_chat)
+}
+
+#[tauri::command]
+pub(crate) fn get_phrase_counts(
+    options: Option<ExportOptions>,
+    categories: Option<Vec<keywords::KeywordCategory>>,
+) -> Result<Vec<keywords::PhraseCategoryCounts>, String> {
+    insights::get_phrase_counts(options, categories)
+}
+
+#[tauri::command]
+pub(crate) fn get_style_fingerprints(
+    options: Option<ExportOptions>,
+    min_messages: Option<i64>,
+) -> Result<Vec<style::StyleFingerprint>, String> {
+    insights::get_style_fingerprints(options, min_messages)
+}
+
+#[tauri::command]
+pub(crate) fn get_abbreviation_timeline(
+    options: Option<ExportOptions>,
+    terms: Option<Vec<String>>,
+) -> Result<Vec<style::AbbreviationTimeline>, String> {
+    insights::get_abbreviation_timeline(options, terms)
+}
+
+#[tauri::command]
+pub(crate) fn get_correction_counts(
+    options: Option<ExportOptions>,
+) -> Result<Vec<corrections::SenderCorrections>, String> {
+    insights::get_correction_counts(options)
+}
+
+#[tauri::command]
+pub(crate) fn get_personal_data_report(identifier: String) -> Result<privacy::PersonalDataReport, String> {
+    insights::get_personal_data_report(identifier)
+}
+
+#[tauri::command]
+pub(crate) fn forget_contact(identifier: String) -> Result<privacy::ForgetResult, String> {
+    insights::forget_contact(identifier)
+}
+
+#[tauri::command]
+pub(crate) fn get_encryption_status() -> Result<encryption::EncryptionStatus, String> {
+    insights::get_encryption_status()
+}
+
+#[tauri::command]
+pub(crate) fn set_app_data_encryption(
+    task_registry: tauri::State<'_, tasks::TaskRegistry>,
+    enabled: bool,
+) -> Result<encryption::EncryptionStatus, String> {
+    insights::set_app_data_encryption(&task_registry, enabled)
+}
+
+#[tauri::command]
+pub(crate) fn set_secret(name: String, value: String) -> Result<(), String> {
+    insights::set_secret(name, value)
+}
+
+#[tauri::command]
+pub(crate) fn has_secret(name: String) -> Result<bool, String> {
+    insights::has_secret(name)
+}
+
+#[tauri::command]
+pub(crate) fn get_privacy_status() -> privacy::PrivacyStatus {
+    insights::get_privacy_status()
+}
+
+#[tauri::command]
+pub(crate) fn get_storage_usage() -> Result<storage::StorageUsage, String> {
+    insights::get_storage_usage()
+}
+
+#[tauri::command]
+pub(crate) fn clear_caches(kinds: Option<Vec<storage::CacheKind>>) -> Result<storage::ClearResult, String> {
+    insights::clear_caches(kinds)
+}
+
+#[tauri::command]
+pub(crate) fn get_first_messages(contact_id: i64, count: Option<usize>) -> Result<FirstMessages, String> {
+    insights::get_first_messages(contact_id, count)
+}
+
+#[tauri::command]
+pub(crate) fn link_handles(identifier: String, canonical: String) -> Result<(), String> {
+    insights::link_handles(identifier, canonical)
+}
+
+#[tauri::command]
+pub(crate) fn unlink_handle(identifier: String) -> Result<(), String> {
+    insights::unlink_handle(identifier)
+}
+
+#[tauri::command]
+pub(crate) fn get_suggested_merges(
+    source: Option<archive::DataSource>,
+) -> Result<Vec<aliases::SuggestedMerge>, String> {
+    insights::get_suggested_merges(source)
+}
+
+#[tauri::command]
+pub(crate) fn get_handle_aliases() -> Result<Vec<aliases::AliasGroup>, String> {
+    insights::get_handle_aliases()
+}
@@ -0,0 +1,18 @@
+use insights::{digest, encryption};
+use tauri::AppHandle;
+
+// How often the background job checks whether last week's digest is due
+const CHECK_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Check for a due digest now and then hourly for as long as the app runs
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        // Checked again next interval if the app databases are being re-encrypted
+        if !encryption::is_migrating() {
+            if let Err(e) = digest::run_if_due(&|title, body| crate::notify(&app, title, body)) {
+                tracing::warn!("Weekly digest failed: {}", e);
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+    });
+}
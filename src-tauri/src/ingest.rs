@@ -0,0 +1,17 @@
+use insights::ingest;
+use insights::tasks::TaskRegistry;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Route dropped files to the matching importer on a background thread, emitting
+/// `import-started` and `import-finished` events for each file
+pub(crate) fn handle_drop(app: &AppHandle, paths: Vec<PathBuf>) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for path in paths {
+            let _ = app.emit("import-started", path.to_string_lossy().to_string());
+            let report = ingest::ingest_file(&app.state::<TaskRegistry>(), &path);
+            let _ = app.emit("import-finished", report);
+        }
+    });
+}
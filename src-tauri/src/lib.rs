@@ -1,917 +1,288 @@
-use chrono::{TimeZone, Utc};
-use rusqlite::Connection;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
-
-// Mac Absolute Time epoch: 2001-01-01 00:00:00 UTC
-const MAC_EPOCH_OFFSET: i64 = 978307200;
-
-/// Convert macOS timestamp (nanoseconds since 2001-01-01) to Unix timestamp
-fn mac_timestamp_to_unix(mac_ts: i64) -> i64 {
-    // macOS High Sierra+ uses nanoseconds
-    let seconds = mac_ts / 1_000_000_000;
-    seconds + MAC_EPOCH_OFFSET
-}
-
-/// Get the path to the iMessage database
-fn get_imessage_db_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|home| home.join("Library/Messages/chat.db"))
-}
-
-/// Get ALL paths to AddressBook databases (iCloud, local, Exchange, etc.)
-fn get_all_addressbook_db_paths() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-
-    let home = match dirs::home_dir() {
-        Some(h) => h,
-        None => return paths,
-    };
-
-    let sources_dir = home.join("Library/Application Support/AddressBook/Sources");
-
-    // Find ALL source directories with AddressBook databases
-    if let Ok(entries) = std::fs::read_dir(&sources_dir) {
-        for entry in entries.flatten() {
-            let db_path = entry.path().join("AddressBook-v22.abcddb");
-            if db_path.exists() {
-                paths.push(db_path);
-            }
-        }
-    }
-
-    // Also check direct path (older macOS versions)
-    let direct_path = home.join("Library/Application Support/AddressBook/AddressBook-v22.abcddb");
-    if direct_path.exists() {
-        paths.push(direct_path);
-    }
-
-    paths
-}
-
-/// Normalize phone number for comparison (remove formatting)
-fn normalize_phone(phone: &str) -> String {
-    phone.chars()
-        .filter(|c| c.is_ascii_digit())
-        .collect::<String>()
-        .chars()
-        .rev()
-        .take(10) // Last 10 digits
-        .collect::<String>()
-        .chars()
-        .rev()
-        .collect()
-}
-
-/// Check if text looks like a UUID (attachment reference)
-fn is_uuid_like(text: &str) -> bool {
-    let trimmed = text.trim();
-    // UUID format: 8-4-4-4-12 hex characters with dashes
-    // Also match without dashes or with newlines
-    let clean: String = trimmed.chars()
-        .filter(|c| c.is_ascii_hexdigit())
-        .collect();
-
-    // A UUID has exactly 32 hex characters
-    // Allow some variance for partial UUIDs or UUIDs with extra chars
-    if clean.len() >= 32 && clean.len() <= 40 {
-        // Check if most of the original string was hex + dashes/whitespace
-        let valid_chars = trimmed.chars()
-            .filter(|c| c.is_ascii_hexdigit() || *c == '-' || c.is_whitespace())
-            .count();
-        return valid_chars as f32 / trimmed.len() as f32 > 0.9;
-    }
-    false
-}
-
-/// Read contacts from a single AddressBook database
-fn read_contacts_from_db(db_path: &PathBuf, names: &mut HashMap<String, String>) {
-    let conn = match Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY) {
-        Ok(c) => c,
-        Err(_) => return,
-    };
-
-    // Query for phone numbers
-    let phone_results: Vec<(Option<String>, Option<String>, String)> = {
-        let phone_query = "
-            SELECT ZABCDRECORD.ZFIRSTNAME, ZABCDRECORD.ZLASTNAME, ZABCDPHONENUMBER.ZFULLNUMBER
-            FROM ZABCDRECORD
-            LEFT JOIN ZABCDPHONENUMBER ON ZABCDRECORD.Z_PK = ZABCDPHONENUMBER.ZOWNER
-            WHERE ZABCDPHONENUMBER.ZFULLNUMBER IS NOT NULL
-        ";
-        conn.prepare(phone_query)
-            .ok()
-            .map(|mut stmt| {
-                stmt.query_map([], |row| {
-                    let first: Option<String> = row.get(0).ok();
-                    let last: Option<String> = row.get(1).ok();
-                    let phone: String = row.get(2)?;
-                    Ok((first, last, phone))
-                })
-                .map(|rows| rows.flatten().collect())
-                .unwrap_or_default()
-            })
-            .unwrap_or_default()
-    };
-
-    for (first, last, phone) in phone_results {
-        let name = match (first, last) {
-            (Some(f), Some(l)) => format!("{} {}", f, l),
-            (Some(f), None) => f,
-            (None, Some(l)) => l,
-            (None, None) => continue,
-        };
-
-        // Store both normalized and original
-        let normalized = normalize_phone(&phone);
-        if !normalized.is_empty() {
-            names.insert(normalized.clone(), name.clone());
-            // Also store with +1 prefix variations
-            names.insert(format!("+1{}", normalized), name.clone());
-        }
-        names.insert(phone, name);
-    }
-
-    // Query for email addresses
-    let email_results: Vec<(Option<String>, Option<String>, String)> = {
-        let email_query = "
-            SELECT ZABCDRECORD.ZFIRSTNAME, ZABCDRECORD.ZLASTNAME, ZABCDEMAILADDRESS.ZADDRESS
-            FROM ZABCDRECORD
-            LEFT JOIN ZABCDEMAILADDRESS ON ZABCDRECORD.Z_PK = ZABCDEMAILADDRESS.ZOWNER
-            WHERE ZABCDEMAILADDRESS.ZADDRESS IS NOT NULL
-        ";
-        conn.prepare(email_query)
-            .ok()
-            .map(|mut stmt| {
-                stmt.query_map([], |row| {
-                    let first: Option<String> = row.get(0).ok();
-                    let last: Option<String> = row.get(1).ok();
-                    let email: String = row.get(2)?;
-                    Ok((first, last, email))
-                })
-                .map(|rows| rows.flatten().collect())
-                .unwrap_or_default()
-            })
-            .unwrap_or_default()
-    };
-
-    for (first, last, email) in email_results {
-        let name = match (first, last) {
-            (Some(f), Some(l)) => format!("{} {}", f, l),
-            (Some(f), None) => f,
-            (None, Some(l)) => l,
-            (None, None) => continue,
-        };
-        names.insert(email.to_lowercase(), name);
-    }
-}
-
-/// Get contact name mappings from ALL AddressBook databases
-fn get_contact_names() -> HashMap<String, String> {
-    let mut names: HashMap<String, String> = HashMap::new();
-
-    let db_paths = get_all_addressbook_db_paths();
-
-    // Read from ALL AddressBook databases (iCloud, local, Exchange, etc.)
-    for db_path in &db_paths {
-        read_contacts_from_db(db_path, &mut names);
-    }
-
-    names
-}
-
-/// Extract text from attributedBody blob (NSKeyedArchiver/typedstream format)
-fn extract_text_from_attributed_body(blob: &[u8]) -> Option<String> {
-    // The attributedBody uses Apple's typedstream format
-    // The actual text is usually stored after a length byte followed by UTF-8 content
-
-    if blob.len() < 50 {
-        return None;
-    }
-
-    let mut best_text = String::new();
-
-    // Scan for length-prefixed UTF-8 strings
-    let mut i = 0;
-    while i < blob.len().saturating_sub(4) {
-        // Look for potential string length byte followed by valid UTF-8
-        let potential_len = blob[i] as usize;
-        if potential_len > 3 && potential_len < 2000 && i + 1 + potential_len <= blob.len() {
-            if let Ok(s) = std::str::from_utf8(&blob[i + 1..i + 1 + potential_len]) {
-                // Check if it looks like real text (not metadata)
-                let has_letter = s.chars().any(|c| c.is_alphabetic());
-                let is_clean = !s.contains("__kIM") &&
-                               !s.contains("NSMutable") &&
-                               !s.contains("NSAttributed") &&
-                               !s.contains("NSObject") &&
-                               !s.contains("NSData") &&
-                               !s.contains("NSKeyedArchiver") &&
-                               !s.contains("$archiver") &&
-                               !s.contains("$class") &&
-                               !s.contains("$version") &&
-                               !s.contains("NSDictionary") &&
-                               !s.contains("NSArray") &&
-                               !s.contains("NSValue") &&
-                               !s.contains("NSNumber") &&
-                               !s.contains("NSString") &&
-                               !s.contains("NS.rangeval") &&
-                               !s.contains("NS.range") &&
-                               !s.contains("NS.special") &&
-                               !s.contains("streamtyped") &&
-                               !s.contains("typedstream") &&
-                               !s.starts_with('+') &&
-                               !s.starts_with("bp:") &&
-                               !s.starts_with("p:") &&
-                               !s.starts_with("com.apple") &&
-                               !is_uuid_like(s) &&
-                               s.chars().all(|c| c >= ' ' || c == '\n' || c == '\r');
-
-                if has_letter && is_clean && s.len() > best_text.len() {
-                    best_text = s.trim().to_string();
-                }
-            }
-        }
-        i += 1;
-    }
-
-    if best_text.is_empty() || best_text.len() < 2 {
-        None
-    } else {
-        Some(best_text)
+use insights::{anonymize, api, encryption, export, fixtures, logging, settings, tasks};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_notification::NotificationExt;
+
+mod automation;
+mod background;
+mod commands;
+mod digest;
+mod ingest;
+mod scheduler;
+mod sync;
+mod tray;
+
+/// Show a system notification, logging rather than failing when notifications are unavailable
+pub(crate) fn notify(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        tracing::warn!("Notification failed: {}", e);
     }
 }
 
-/// Look up a contact name by phone/email
-fn lookup_contact_name(identifier: &str, contacts: &HashMap<String, String>) -> Option<String> {
-    // Try direct lookup
-    if let Some(name) = contacts.get(identifier) {
-        return Some(name.clone());
-    }
-
-    // Try lowercase for email
-    if let Some(name) = contacts.get(&identifier.to_lowercase()) {
-        return Some(name.clone());
-    }
-
-    // Try normalized phone lookup
-    let normalized = normalize_phone(identifier);
-    if let Some(name) = contacts.get(&normalized) {
-        return Some(name.clone());
+/// Open (or focus) a separate window for one chat; the page reads the chat id from `?chat=`
+#[tauri::command]
+fn open_chat_window(app: tauri::AppHandle, chat_id: i64) -> Result<(), String> {
+    let label = format!("chat-{}", chat_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        return window.set_focus().map_err(|e| format!("Cannot focus window: {}", e));
     }
 
-    None
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Contact {
-    pub id: i64,
-    pub identifier: String,      // Phone number or email
-    pub display_name: Option<String>,
-    pub message_count: i64,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Chat {
-    pub id: i64,
-    pub chat_identifier: String,
-    pub display_name: Option<String>,
-    pub is_group: bool,
-    pub participant_count: i64,
-    pub message_count: i64,
-    pub participants: Vec<String>,          // Resolved names
-    pub participant_ids: Vec<String>,       // Raw phone/email identifiers
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Message {
-    pub id: i64,
-    pub guid: String,
-    pub text: Option<String>,
-    pub date: i64,               // Unix timestamp
-    pub date_formatted: String,
-    pub is_from_me: bool,
-    pub handle_id: i64,
-    pub contact_identifier: String,
-    pub sender_name: String,     // Resolved sender name
-    pub chat_id: Option<i64>,
-    pub has_attachment: bool,
-    pub attachments: Vec<Attachment>,
-    pub reactions: Vec<Reaction>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Attachment {
-    pub filename: Option<String>,
-    pub mime_type: Option<String>,
-    pub transfer_name: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Reaction {
-    pub reaction_type: i64,   // 2000=love, 2001=like, 2002=dislike, 2003=laugh, 2004=emphasis, 2005=question
-    pub sender: String,
-    pub is_from_me: bool,
+    let chat = insights::load_chats()?
+        .into_iter()
+        .find(|c| c.id == chat_id)
+        .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+
+    tauri::WebviewWindowBuilder::new(
+        &app,
+        label,
+        tauri::WebviewUrl::App(format!("index.html?chat={}", chat_id).into()),
+    )
+    .title(insights::chat_title(&chat))
+    .inner_size(600.0, 800.0)
+    .min_inner_size(400.0, 500.0)
+    .build()
+    .map_err(|e| format!("Cannot open chat window: {}", e))?;
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ChatStats {
-    pub total_messages: i64,
-    pub messages_sent: i64,
-    pub messages_received: i64,
-    pub total_contacts: i64,
-    pub date_range_start: Option<i64>,
-    pub date_range_end: Option<i64>,
+/// Run a scheduled job immediately, regardless of its schedule
+#[tauri::command]
+fn run_job_now(app: tauri::AppHandle, id: String) -> Result<insights::scheduler::JobRun, String> {
+    let job = settings::load_settings()
+        .scheduled_jobs
+        .into_iter()
+        .find(|j| j.id == id)
+        .ok_or_else(|| format!("Job {} not found", id))?;
+    scheduler::run(&app, &job)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ExportOptions {
-    pub start_date: Option<i64>,  // Unix timestamp
-    pub end_date: Option<i64>,    // Unix timestamp
-    pub contact_ids: Option<Vec<i64>>,
+/// Start the app tray-only at login so the watcher, scheduler and backups keep running
+#[tauri::command]
+fn set_launch_at_login(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    background::set_launch_at_login(&app, enabled)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DatabaseStatus {
-    pub accessible: bool,
-    pub path: String,
-    pub error: Option<String>,
+/// Whether the login item is installed
+#[tauri::command]
+fn get_launch_at_login(app: tauri::AppHandle) -> Result<bool, String> {
+    background::launch_at_login(&app)
 }
 
-/// Check if we can access the iMessage database (Full Disk Access required)
+/// Long-running operations currently running or queued
 #[tauri::command]
-fn check_database_access() -> DatabaseStatus {
-    let path = match get_imessage_db_path() {
-        Some(p) => p,
-        None => {
-            return DatabaseStatus {
-                accessible: false,
-                path: String::new(),
-                error: Some("Could not determine home directory".to_string()),
-            }
-        }
-    };
-
-    let path_str = path.to_string_lossy().to_string();
-
-    // Try to open the database
-    match Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY) {
-        Ok(conn) => {
-            // Try a simple query to verify we can actually read
-            match conn.query_row("SELECT COUNT(*) FROM message", [], |row| row.get::<_, i64>(0)) {
-                Ok(_) => DatabaseStatus {
-                    accessible: true,
-                    path: path_str,
-                    error: None,
-                },
-                Err(e) => DatabaseStatus {
-                    accessible: false,
-                    path: path_str,
-                    error: Some(format!("Cannot read database: {}", e)),
-                },
-            }
-        }
-        Err(e) => DatabaseStatus {
-            accessible: false,
-            path: path_str,
-            error: Some(format!("Cannot open database. Please grant Full Disk Access in System Settings > Privacy & Security > Full Disk Access. Error: {}", e)),
-        },
-    }
+fn get_active_tasks(task_registry: tauri::State<'_, tasks::TaskRegistry>) -> Vec<tasks::TaskInfo> {
+    task_registry.active()
 }
 
-/// Get all contacts with message counts
+/// Ask a running or queued operation to stop
 #[tauri::command]
-fn get_contacts() -> Result<Vec<Contact>, String> {
-    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
-    let conn = Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
-        .map_err(|e| format!("Cannot open database: {}", e))?;
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT h.ROWID, h.id, h.uncanonicalized_id, COUNT(m.ROWID) as msg_count
-             FROM handle h
-             LEFT JOIN message m ON m.handle_id = h.ROWID
-             GROUP BY h.ROWID
-             ORDER BY msg_count DESC",
-        )
-        .map_err(|e| format!("Query error: {}", e))?;
-
-    let contacts = stmt
-        .query_map([], |row| {
-            Ok(Contact {
-                id: row.get(0)?,
-                identifier: row.get::<_, String>(1)?,
-                display_name: row.get::<_, Option<String>>(2).ok().flatten(),
-                message_count: row.get(3)?,
-            })
-        })
-        .map_err(|e| format!("Query error: {}", e))?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    Ok(contacts)
+fn cancel_task(task_registry: tauri::State<'_, tasks::TaskRegistry>, id: u64) -> Result<(), String> {
+    task_registry.cancel(id)
 }
 
-/// Get chat statistics
+/// Swap every command over to the generated demo databases, e.g. for screenshots or App Review
 #[tauri::command]
-fn get_chat_stats(options: Option<ExportOptions>) -> Result<ChatStats, String> {
-    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
-    let conn = Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
-        .map_err(|e| format!("Cannot open database: {}", e))?;
-
-    let mut where_clauses = Vec::new();
-    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-
-    if let Some(ref opts) = options {
-        if let Some(start) = opts.start_date {
-            let mac_start = (start - MAC_EPOCH_OFFSET) * 1_000_000_000;
-            where_clauses.push("date >= ?");
-            params.push(Box::new(mac_start));
-        }
-        if let Some(end) = opts.end_date {
-            let mac_end = (end - MAC_EPOCH_OFFSET) * 1_000_000_000;
-            where_clauses.push("date <= ?");
-            params.push(Box::new(mac_end));
-        }
-    }
-
-    let where_sql = if where_clauses.is_empty() {
-        String::new()
-    } else {
-        format!("WHERE {}", where_clauses.join(" AND "))
-    };
-
-    // Total messages
-    let total_messages: i64 = conn
-        .query_row(
-            &format!("SELECT COUNT(*) FROM message {}", where_sql),
-            rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Query error: {}", e))?;
-
-    // Messages sent
-    let mut params2: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-    if let Some(ref opts) = options {
-        if let Some(start) = opts.start_date {
-            let mac_start = (start - MAC_EPOCH_OFFSET) * 1_000_000_000;
-            params2.push(Box::new(mac_start));
-        }
-        if let Some(end) = opts.end_date {
-            let mac_end = (end - MAC_EPOCH_OFFSET) * 1_000_000_000;
-            params2.push(Box::new(mac_end));
-        }
-    }
-
-    let sent_where = if where_clauses.is_empty() {
-        "WHERE is_from_me = 1".to_string()
-    } else {
-        format!("{} AND is_from_me = 1", where_sql)
-    };
-
-    let messages_sent: i64 = conn
-        .query_row(
-            &format!("SELECT COUNT(*) FROM message {}", sent_where),
-            rusqlite::params_from_iter(params2.iter().map(|p| p.as_ref())),
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Query error: {}", e))?;
-
-    // Total contacts
-    let total_contacts: i64 = conn
-        .query_row("SELECT COUNT(*) FROM handle", [], |row| row.get(0))
-        .map_err(|e| format!("Query error: {}", e))?;
-
-    // Date range
-    let (date_start, date_end): (Option<i64>, Option<i64>) = conn
-        .query_row(
-            "SELECT MIN(date), MAX(date) FROM message WHERE date > 0",
-            [],
-            |row| {
-                let min: Option<i64> = row.get(0).ok();
-                let max: Option<i64> = row.get(1).ok();
-                Ok((
-                    min.map(mac_timestamp_to_unix),
-                    max.map(mac_timestamp_to_unix),
-                ))
-            },
-        )
-        .map_err(|e| format!("Query error: {}", e))?;
-
-    Ok(ChatStats {
-        total_messages,
-        messages_sent,
-        messages_received: total_messages - messages_sent,
-        total_contacts,
-        date_range_start: date_start,
-        date_range_end: date_end,
-    })
+fn enable_demo_mode(app: tauri::AppHandle) -> Result<(), String> {
+    fixtures::enable_demo_mode()?;
+    tray::update_today(&app);
+    let _ = app.emit("messages-changed", ());
+    Ok(())
 }
 
-/// Get messages with optional filtering
+/// Return to the real Messages and Contacts data
 #[tauri::command]
-fn get_messages(options: Option<ExportOptions>, limit: Option<i64>) -> Result<Vec<Message>, String> {
-    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
-    let conn = Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
-        .map_err(|e| format!("Cannot open database: {}", e))?;
-
-    // Load contact names for reaction sender resolution
-    let contact_names = get_contact_names();
+fn disable_demo_mode(app: tauri::AppHandle) {
+    fixtures::disable_demo_mode();
+    tray::update_today(&app);
+    let _ = app.emit("messages-changed", ());
+}
 
-    let mut where_clauses = vec![
-        "m.date > 0".to_string(),
-        // Exclude reaction messages (associated_message_type >= 2000) and edit messages (1000-1999)
-        "(m.associated_message_type IS NULL OR m.associated_message_type = 0)".to_string(),
-    ];
-    let mut params: Vec<i64> = Vec::new();
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .manage(tasks::TaskRegistry::default())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![background::BACKGROUND_ARG]),
+        ))
+        .setup(|app| {
+            logging::init();
 
-    if let Some(ref opts) = options {
-        if let Some(start) = opts.start_date {
-            let mac_start = (start - MAC_EPOCH_OFFSET) * 1_000_000_000;
-            where_clauses.push("m.date >= ?".to_string());
-            params.push(mac_start);
-        }
-        if let Some(end) = opts.end_date {
-            let mac_end = (end - MAC_EPOCH_OFFSET) * 1_000_000_000;
-            where_clauses.push("m.date <= ?".to_string());
-            params.push(mac_end);
-        }
-        if let Some(ref contact_ids) = opts.contact_ids {
-            if !contact_ids.is_empty() {
-                let placeholders: Vec<String> = contact_ids.iter().map(|_| "?".to_string()).collect();
-                where_clauses.push(format!("m.handle_id IN ({})", placeholders.join(",")));
-                params.extend(contact_ids.iter().cloned());
+            // Before anything opens store/cache/archive, in case the last encryption change was cut off
+            if let Err(e) = encryption::resume_pending() {
+                tracing::error!("Cannot finish the interrupted encryption change: {}", e);
             }
-        }
-    }
 
-    let where_sql = where_clauses.join(" AND ");
-    let limit_sql = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
-
-    let query = format!(
-        "SELECT m.ROWID, m.guid, m.text, m.date, m.is_from_me, COALESCE(m.handle_id, 0),
-                COALESCE(h.id, '') as contact_id,
-                m.cache_has_attachments,
-                cmj.chat_id,
-                m.attributedBody
-         FROM message m
-         LEFT JOIN handle h ON m.handle_id = h.ROWID
-         LEFT JOIN chat_message_join cmj ON m.ROWID = cmj.message_id
-         WHERE {}
-         ORDER BY m.date DESC
-         {}",
-        where_sql, limit_sql
-    );
-
-    let mut stmt = conn.prepare(&query).map_err(|e| format!("Query error: {}", e))?;
-
-    let mut messages: Vec<Message> = stmt
-        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
-            let mac_date: i64 = row.get(3)?;
-            let unix_date = mac_timestamp_to_unix(mac_date);
-            let datetime = Utc.timestamp_opt(unix_date, 0).single();
-            let date_formatted = datetime
-                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                .unwrap_or_else(|| "Unknown".to_string());
-
-            let is_from_me = row.get::<_, i64>(4)? == 1;
-            let contact_identifier: String = row.get(6)?;
-            let raw_text: Option<String> = row.get(2)?;
-            let attributed_body: Option<Vec<u8>> = row.get(9).ok().flatten();
-
-            // Clean up text - filter out metadata/binary content
-            let mut text = raw_text.and_then(|t| {
-                // Skip if it looks like binary/metadata
-                if t.contains("__kIM") ||
-                   t.contains("NSMutable") ||
-                   t.contains("NSAttributed") ||
-                   t.contains("NSObject") ||
-                   t.contains("NSData") ||
-                   t.contains("NSKeyedArchiver") ||
-                   t.contains("$archiver") ||
-                   t.contains("$version") ||
-                   t.contains("streamtyped") ||
-                   t.contains("NS.rangeval") ||
-                   t.contains("NS.range") ||
-                   t.contains("NS.special") ||
-                   t.contains("NSNumber") ||
-                   t.contains("NSString") ||
-                   t.contains("NSDictionary") ||
-                   t.contains("NSArray") ||
-                   t.starts_with("\u{FFFC}") ||  // Object replacement character
-                   t.chars().take(10).any(|c| c < ' ' && c != '\n' && c != '\r' && c != '\t') ||
-                   // Skip if it's just a UUID (attachment reference)
-                   is_uuid_like(&t) {
-                    None
-                } else {
-                    // Also trim any leading/trailing object replacement characters
-                    let cleaned = t.trim_matches('\u{FFFC}').trim();
-                    if cleaned.is_empty() {
-                        None
-                    } else {
-                        Some(cleaned.to_string())
-                    }
+            // message-insights:// URLs let Shortcuts and AppleScript drive exports and reports
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    automation::handle_url(&handle, &url);
                 }
             });
 
-            // If text is empty, try to extract from attributedBody
-            if text.is_none() {
-                if let Some(ref blob) = attributed_body {
-                    text = extract_text_from_attributed_body(blob);
+            digest::spawn_scheduler(app.handle().clone());
+            sync::spawn_watcher(app.handle().clone());
+            scheduler::spawn_scheduler(app.handle().clone());
+            let task_registry = app.state::<tasks::TaskRegistry>().inner().clone();
+            std::thread::spawn(move || {
+                let removed = export::clean_stale_partials(&task_registry);
+                if removed > 0 {
+                    tracing::info!(removed, "removed interrupted export files");
                 }
+            });
+            tray::setup(app)?;
+            if background::launched_in_background() {
+                background::enter_background(app.handle());
             }
 
-            // Resolve sender name
-            let sender_name = if is_from_me {
-                "Me".to_string()
-            } else if contact_identifier.is_empty() {
-                "Unknown".to_string()
-            } else {
-                // Will be resolved after query
-                contact_identifier.clone()
-            };
-
-            Ok(Message {
-                id: row.get(0)?,
-                guid: row.get(1)?,
-                text,
-                date: unix_date,
-                date_formatted,
-                is_from_me,
-                handle_id: row.get(5)?,
-                contact_identifier,
-                sender_name,
-                chat_id: row.get(8)?,
-                has_attachment: row.get::<_, i64>(7)? == 1,
-                attachments: Vec::new(),
-                reactions: Vec::new(),
-            })
-        })
-        .map_err(|e| format!("Query error: {}", e))?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    // Resolve sender names from contacts
-    for msg in &mut messages {
-        if !msg.is_from_me && !msg.contact_identifier.is_empty() {
-            if let Some(name) = lookup_contact_name(&msg.contact_identifier, &contact_names) {
-                msg.sender_name = name;
+            // Move a pseudonym key left in settings.json by an older version into the Keychain
+            if settings::load_settings().pseudonym_key.is_some() {
+                if let Err(e) = anonymize::pseudonym_key() {
+                    tracing::warn!("Pseudonym key not moved to the Keychain: {}", e);
+                }
             }
-        }
-    }
-
-    // Build a GUID lookup for attaching reactions
-    let guid_to_idx: HashMap<String, usize> = messages.iter().enumerate()
-        .map(|(i, m)| (m.guid.clone(), i))
-        .collect();
-
-    // Fetch attachments for messages that have them
-    let message_ids: Vec<i64> = messages.iter()
-        .filter(|m| m.has_attachment)
-        .map(|m| m.id)
-        .collect();
-
-    if !message_ids.is_empty() {
-        let placeholders: String = message_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let attach_query = format!(
-            "SELECT maj.message_id, a.filename, a.mime_type, a.transfer_name
-             FROM message_attachment_join maj
-             JOIN attachment a ON maj.attachment_id = a.ROWID
-             WHERE maj.message_id IN ({})",
-            placeholders
-        );
-
-        if let Ok(mut attach_stmt) = conn.prepare(&attach_query) {
-            if let Ok(rows) = attach_stmt.query_map(rusqlite::params_from_iter(message_ids.iter()), |row| {
-                Ok((
-                    row.get::<_, i64>(0)?,
-                    row.get::<_, Option<String>>(1)?,
-                    row.get::<_, Option<String>>(2)?,
-                    row.get::<_, Option<String>>(3)?,
-                ))
-            }) {
-                // Get home directory for expanding ~ in paths
-                let home_dir = dirs::home_dir().map(|h| h.to_string_lossy().to_string());
 
-                for row in rows.flatten() {
-                    let (msg_id, filename, mime_type, transfer_name) = row;
-                    if let Some(msg) = messages.iter_mut().find(|m| m.id == msg_id) {
-                        // Expand ~ in filename path to actual home directory
-                        let expanded_filename = filename.map(|f| {
-                            if f.starts_with("~/") {
-                                if let Some(ref home) = home_dir {
-                                    f.replacen("~", home, 1)
-                                } else {
-                                    f
-                                }
-                            } else {
-                                f
-                            }
-                        });
-
-                        msg.attachments.push(Attachment {
-                            filename: expanded_filename,
-                            mime_type,
-                            transfer_name,
-                        });
-                    }
+            // Likewise for the localhost API token
+            if settings::load_settings().api_token.is_some() {
+                if let Err(e) = api::token() {
+                    tracing::warn!("API token not moved to the Keychain: {}", e);
                 }
             }
-        }
-    }
-
-    // Fetch reactions for all messages
-    // Reactions have associated_message_type between 2000-2005 and reference parent via associated_message_guid
-    let reaction_query = "
-        SELECT m.associated_message_guid, m.associated_message_type, m.is_from_me, COALESCE(h.id, '') as sender
-        FROM message m
-        LEFT JOIN handle h ON m.handle_id = h.ROWID
-        WHERE m.associated_message_type >= 2000 AND m.associated_message_type < 3000
-    ";
-
-    if let Ok(mut reaction_stmt) = conn.prepare(reaction_query) {
-        if let Ok(rows) = reaction_stmt.query_map([], |row| {
-            Ok((
-                row.get::<_, Option<String>>(0)?,
-                row.get::<_, i64>(1)?,
-                row.get::<_, i64>(2)? == 1,
-                row.get::<_, String>(3)?,
-            ))
-        }) {
-            for row in rows.flatten() {
-                let (assoc_guid_opt, reaction_type, is_from_me, sender_id) = row;
-                if let Some(assoc_guid) = assoc_guid_opt {
-                    // The associated_message_guid has format like "p:0/guid" or "bp:guid"
-                    // Extract the actual GUID part
-                    let clean_guid = assoc_guid
-                        .split('/')
-                        .last()
-                        .unwrap_or(&assoc_guid)
-                        .to_string();
 
-                    if let Some(&idx) = guid_to_idx.get(&clean_guid) {
-                        let sender = if is_from_me {
-                            "Me".to_string()
-                        } else {
-                            lookup_contact_name(&sender_id, &contact_names)
-                                .unwrap_or_else(|| sender_id.clone())
-                        };
-                        messages[idx].reactions.push(Reaction {
-                            reaction_type,
-                            sender,
-                            is_from_me,
-                        });
-                    }
+            let app_settings = settings::load_settings();
+            if app_settings.api_enabled {
+                if let Err(e) = api::start(&app_settings, app.state::<tasks::TaskRegistry>().inner().clone()) {
+                    tracing::warn!("Localhost API not started: {}", e);
                 }
             }
-        }
-    }
-
-    Ok(messages)
-}
-
-/// Get messages for a specific contact formatted for export
-#[tauri::command]
-fn get_messages_for_contact(contact_id: i64, options: Option<ExportOptions>) -> Result<Vec<Message>, String> {
-    let mut opts = options.unwrap_or(ExportOptions {
-        start_date: None,
-        end_date: None,
-        contact_ids: None,
-    });
-    opts.contact_ids = Some(vec![contact_id]);
-    get_messages(Some(opts), None)
-}
-
-/// Open System Preferences to Full Disk Access
-#[tauri::command]
-fn open_system_preferences() -> Result<(), String> {
-    std::process::Command::new("open")
-        .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_AllFiles")
-        .spawn()
-        .map_err(|e| format!("Failed to open System Preferences: {}", e))?;
-    Ok(())
-}
-
-/// Open System Preferences to Contacts
-#[tauri::command]
-fn open_contacts_preferences() -> Result<(), String> {
-    std::process::Command::new("open")
-        .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Contacts")
-        .spawn()
-        .map_err(|e| format!("Failed to open System Preferences: {}", e))?;
-    Ok(())
-}
-
-/// Check if we can access the Contacts database
-#[tauri::command]
-fn check_contacts_access() -> bool {
-    let contact_names = get_contact_names();
-    !contact_names.is_empty()
-}
-
-/// Get all chats with participants and message counts
-#[tauri::command]
-fn get_chats() -> Result<Vec<Chat>, String> {
-    let path = get_imessage_db_path().ok_or("Could not find iMessage database")?;
-    let conn = Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
-        .map_err(|e| format!("Cannot open database: {}", e))?;
-
-    // Load contact names for resolution
-    let contact_names = get_contact_names();
-
-    // Get all chats with message counts
-    let mut stmt = conn
-        .prepare(
-            "SELECT c.ROWID, c.chat_identifier, c.display_name, c.style,
-                    COUNT(DISTINCT cmj.message_id) as msg_count
-             FROM chat c
-             LEFT JOIN chat_message_join cmj ON c.ROWID = cmj.chat_id
-             GROUP BY c.ROWID
-             ORDER BY msg_count DESC",
-        )
-        .map_err(|e| format!("Query error: {}", e))?;
-
-    let mut chats: Vec<Chat> = stmt
-        .query_map([], |row| {
-            let style: i64 = row.get(3)?;
-            Ok(Chat {
-                id: row.get(0)?,
-                chat_identifier: row.get(1)?,
-                display_name: row.get::<_, Option<String>>(2).ok().flatten(),
-                is_group: style == 43, // 43 = group chat, 45 = individual
-                participant_count: 0,
-                message_count: row.get(4)?,
-                participants: Vec::new(),
-                participant_ids: Vec::new(),
-            })
+            Ok(())
         })
-        .map_err(|e| format!("Query error: {}", e))?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    // Get participants for each chat and resolve names
-    for chat in &mut chats {
-        let mut participant_stmt = conn
-            .prepare(
-                "SELECT h.id FROM handle h
-                 JOIN chat_handle_join chj ON h.ROWID = chj.handle_id
-                 WHERE chj.chat_id = ?",
-            )
-            .map_err(|e| format!("Query error: {}", e))?;
-
-        let raw_participants: Vec<String> = participant_stmt
-            .query_map([chat.id], |row| row.get(0))
-            .map_err(|e| format!("Query error: {}", e))?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        // Resolve participant names
-        let participants: Vec<String> = raw_participants
-            .iter()
-            .map(|p| {
-                lookup_contact_name(p, &contact_names)
-                    .unwrap_or_else(|| p.clone())
-            })
-            .collect();
-
-        chat.participant_count = participants.len() as i64;
-        chat.participants = participants;
-        chat.participant_ids = raw_participants.clone();
-
-        // For individual chats without display_name, try to set it from contact
-        if chat.display_name.is_none() && raw_participants.len() == 1 {
-            if let Some(name) = lookup_contact_name(&raw_participants[0], &contact_names) {
-                chat.display_name = Some(name);
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+                ingest::handle_drop(window.app_handle(), paths.clone());
             }
-        }
-    }
-
-    Ok(chats)
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_fs::init())
-        .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+            // Started at login: closing the window goes back to tray-only instead of quitting
+            tauri::WindowEvent::CloseRequested { api, .. } if window.label() == "main" && background::is_agent_mode() => {
+                api.prevent_close();
+                background::enter_background(window.app_handle());
             }
-            Ok(())
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
-            check_database_access,
-            check_contacts_access,
-            get_contacts,
-            get_chats,
-            get_chat_stats,
-            get_messages,
-            get_messages_for_contact,
-            open_system_preferences,
-            open_contacts_preferences,
+            commands::check_database_access,
+            commands::check_contacts_access,
+            commands::request_contacts_access,
+            commands::get_contacts,
+            commands::get_chats,
+            commands::get_chat_stats,
+            commands::get_messages,
+            commands::get_messages_for_contact,
+            commands::get_message_summaries,
+            commands::count_messages,
+            commands::aggregate_messages,
+            commands::get_media_stats,
+            commands::get_media_trends,
+            commands::get_gif_report,
+            commands::get_shared_media,
+            commands::get_photo_map,
+            commands::run_ocr,
+            commands::search_messages,
+            commands::tag_contact,
+            commands::untag_contact,
+            commands::get_tags,
+            commands::compare_groups,
+            commands::get_busiest_days,
+            commands::get_settings,
+            commands::update_settings,
+            commands::get_night_owl_report,
+            commands::get_conversation_tempo,
+            commands::get_histogram,
+            commands::list_metrics,
+            commands::compute_metrics,
+            commands::get_social_graph,
+            commands::get_reaction_matrix,
+            commands::export_chat,
+            commands::export_attachments,
+            commands::export_contact,
+            commands::get_chats_for_contact,
+            commands::get_common_chats,
+            commands::get_introductions,
+            commands::get_contact_ranking,
+            commands::get_person_timeline,
+            commands::link_handles,
+            commands::unlink_handle,
+            commands::get_handle_aliases,
+            commands::get_suggested_merges,
+            commands::get_first_messages,
+            commands::compare_periods,
+            commands::compare_chats,
+            commands::get_trend_decomposition,
+            commands::get_year_end_projection,
+            commands::get_chat_archetypes,
+            commands::import_csv,
+            commands::import_sms_backup,
+            commands::sync_archive,
+            commands::get_archive_stats,
+            commands::compact_archive,
+            commands::start_api_server,
+            commands::stop_api_server,
+            commands::get_api_server_status,
+            commands::export_obsidian_vault,
+            commands::push_summary_to_webhook,
+            commands::get_digests,
+            commands::list_triggers,
+            commands::set_trigger,
+            commands::delete_trigger,
+            open_chat_window,
+            commands::list_export_templates,
+            commands::validate_template,
+            commands::get_message_by_guid,
+            commands::get_context,
+            commands::get_messages_around_date,
+            commands::pin_message,
+            commands::unpin_message,
+            commands::get_pinned,
+            commands::set_chat_note,
+            commands::set_contact_note,
+            commands::get_export_history,
+            commands::rerun_export,
+            commands::list_jobs,
+            commands::save_job,
+            commands::delete_job,
+            run_job_now,
+            set_launch_at_login,
+            get_launch_at_login,
+            get_active_tasks,
+            cancel_task,
+            commands::export_logs,
+            commands::get_permission_report,
+            commands::generate_demo_data,
+            enable_demo_mode,
+            disable_demo_mode,
+            commands::is_demo_mode,
+            commands::get_language_stats,
+            commands::get_word_frequencies,
+            commands::get_keyword_trends,
+            commands::get_inside_vocabulary,
+            commands::get_phrase_counts,
+            commands::get_style_fingerprints,
+            commands::get_abbreviation_timeline,
+            commands::get_correction_counts,
+            commands::get_personal_data_report,
+            commands::forget_contact,
+            commands::get_encryption_status,
+            commands::set_app_data_encryption,
+            commands::set_secret,
+            commands::has_secret,
+            commands::get_privacy_status,
+            commands::get_storage_usage,
+            commands::clear_caches,
+            commands::open_system_preferences,
+            commands::open_contacts_preferences,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
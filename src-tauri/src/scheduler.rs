@@ -0,0 +1,39 @@
+use chrono::{Local, NaiveDateTime, Timelike};
+use insights::scheduler::{self, JobRun, ScheduledJob};
+use insights::{encryption, tasks::TaskRegistry};
+use tauri::{AppHandle, Emitter, Manager};
+
+// Checked twice a minute so no scheduled minute is missed
+const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Run a job now, emitting `job-finished` with its outcome
+pub(crate) fn run(app: &AppHandle, job: &ScheduledJob) -> Result<JobRun, String> {
+    let run = scheduler::run(&app.state::<TaskRegistry>(), job, &|title, body| crate::notify(app, title, body))?;
+    let _ = app.emit("job-finished", &run);
+    Ok(run)
+}
+
+/// Start due jobs for as long as the app is open
+pub(crate) fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_minute: Option<NaiveDateTime> = None;
+        loop {
+            let minute = Local::now().naive_local().with_second(0).and_then(|t| t.with_nanosecond(0));
+            if let Some(now) = minute.filter(|_| minute != last_minute) {
+                last_minute = minute;
+                // Each job gets its own thread so a long backup doesn't hold up the clock;
+                // scheduler::run keeps a job from overlapping itself
+                // Jobs would only fail while the app databases are being rewritten
+                if !encryption::is_migrating() {
+                    for job in scheduler::due_jobs(now) {
+                        let app = app.clone();
+                        std::thread::spawn(move || {
+                            let _ = run(&app, &job);
+                        });
+                    }
+                }
+            }
+            std::thread::sleep(TICK_INTERVAL);
+        }
+    });
+}
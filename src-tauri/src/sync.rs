@@ -0,0 +1,60 @@
+use insights::triggers;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter};
+
+// chat.db is only touched when Messages writes; polling its mtime is cheap
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Latest modification time across chat.db and its WAL, where new messages land first
+fn database_modified() -> Option<SystemTime> {
+    let path = insights::get_imessage_db_path()?;
+    let wal = path.with_file_name("chat.db-wal");
+    [path, wal]
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// Work done each time chat.db changes
+fn on_change(app: &AppHandle) {
+    let _span = tracing::info_span!("chat_db_changed").entered();
+    // Demo data must never fire real notifications or webhooks
+    if !insights::fixtures::is_demo_mode() {
+        if let Err(e) = triggers::evaluate(&|title, body| crate::notify(app, title, body)) {
+            tracing::warn!("Trigger evaluation failed: {}", e);
+        }
+    }
+    crate::tray::update_today(app);
+    let _ = app.emit("messages-changed", ());
+}
+
+/// Watch chat.db in the background and react to new messages
+pub(crate) fn spawn_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_seen = database_modified();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            // Re-encryption locks the app databases; the change is picked up on a later poll
+            if PAUSED.load(Ordering::Relaxed) || insights::encryption::is_migrating() {
+                continue;
+            }
+            let modified = database_modified();
+            if modified.is_some() && modified != last_seen {
+                last_seen = modified;
+                on_change(&app);
+            }
+        }
+    });
+}
+
+/// Pause or resume reacting to chat.db changes
+pub(crate) fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::Relaxed);
+}
+
+pub(crate) fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,83 @@
+use crate::sync;
+use insights::settings;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{App, AppHandle, Emitter, Manager, Wry};
+
+/// Menu items that change after the tray is built
+struct TrayState {
+    today: MenuItem<Wry>,
+}
+
+fn today_label() -> String {
+    match insights::count_messages_today() {
+        Ok(count) => format!("Today: {} messages", settings::format_count(count, &settings::load_settings())),
+        Err(_) => "Today: unavailable".to_string(),
+    }
+}
+
+/// Refresh the tray's message count (called when chat.db changes)
+pub(crate) fn update_today(app: &AppHandle) {
+    if let Some(state) = app.try_state::<TrayState>() {
+        let _ = state.today.set_text(today_label());
+    }
+}
+
+fn open_last_export_folder() {
+    let folder = settings::load_settings().last_export_dir;
+    match folder {
+        Some(dir) => {
+            if let Err(e) = std::process::Command::new("open").arg(&dir).spawn() {
+                tracing::warn!("Cannot open {}: {}", dir, e);
+            }
+        }
+        None => tracing::info!("No export folder yet"),
+    }
+}
+
+/// Add the menu bar icon with today's count and quick actions
+pub(crate) fn setup(app: &App) -> tauri::Result<()> {
+    let today = MenuItem::with_id(app, "today", today_label(), false, None::<&str>)?;
+    let show = MenuItem::with_id(app, "show_window", "Open Message Insights", true, None::<&str>)?;
+    let refresh = MenuItem::with_id(app, "refresh", "Refresh", true, None::<&str>)?;
+    let open_exports = MenuItem::with_id(app, "open_exports", "Open Last Export Folder", true, None::<&str>)?;
+    let pause = CheckMenuItem::with_id(app, "pause_watcher", "Pause Watcher", true, sync::is_paused(), None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &today,
+            &PredefinedMenuItem::separator(app)?,
+            &show,
+            &refresh,
+            &open_exports,
+            &pause,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::quit(app, None)?,
+        ],
+    )?;
+
+    let mut tray = TrayIconBuilder::with_id("main")
+        .tooltip("Message Insights")
+        .menu(&menu)
+        .on_menu_event(move |app, event| match event.id().as_ref() {
+            "show_window" => crate::background::show_main_window(app),
+            "refresh" => {
+                update_today(app);
+                let _ = app.emit("refresh-requested", ());
+            }
+            "open_exports" => open_last_export_folder(),
+            "pause_watcher" => {
+                let paused = !sync::is_paused();
+                sync::set_paused(paused);
+                let _ = pause.set_checked(paused);
+            }
+            _ => {}
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    app.manage(TrayState { today });
+    Ok(())
+}